@@ -1,12 +1,13 @@
-use std::cmp::Ordering;
 use sorted_vec::sorted_vec::{BucketConfiguration, MaxBucketCapacity, SortedVec};
+use sorted_vec::total_order::OrderedF64;
 
-#[derive(Debug)]
+// Fields compare in declaration order, so flights sort by price first.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
 struct Flight {
+    price: OrderedF64,
     id: u64,
     origin: String,
     destination: String,
-    price: f64,
 }
 
 impl Flight {
@@ -15,31 +16,11 @@ impl Flight {
             id,
             origin,
             destination,
-            price,
+            price: price.into(),
         }
     }
 }
 
-impl Eq for Flight {}
-
-impl Ord for Flight {
-    fn cmp(&self, other: &Self) -> Ordering {
-        self.price.partial_cmp(&other.price).unwrap()
-    }
-}
-
-impl PartialEq<Self> for Flight {
-    fn eq(&self, other: &Self) -> bool {
-        self.price == other.price
-    }
-}
-
-impl PartialOrd for Flight {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        self.price.partial_cmp(&other.price)
-    }
-}
-
 fn main() {
     let flights = vec![
         Flight::new(5, "LAX".to_string(), "JFK".to_string(), 500.0),
@@ -53,4 +34,8 @@ fn main() {
 
     println!("{:?}", sorted_vec.first());
     println!("{:?}", sorted_vec.last());
+
+    for flight in sorted_vec.iter() {
+        println!("#{} {} -> {}: {}", flight.id, flight.origin, flight.destination, flight.price);
+    }
 }
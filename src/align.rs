@@ -0,0 +1,153 @@
+use std::cmp::Ordering;
+use std::iter::{FusedIterator, Peekable};
+
+use crate::sorted_vec::SortedVec;
+use crate::storage::BucketStorage;
+
+/// One step of walking two sorted sequences side by side. Yielded by
+/// [`SortedVec::align_with`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aligned<L, R> {
+    /// Only on the left side.
+    Left(L),
+    /// Only on the right side.
+    Right(R),
+    /// On both sides, as told by the alignment comparator.
+    Both(L, R),
+}
+
+/// Walks two sorted iterators in lockstep. Returned by
+/// [`SortedVec::align_with`].
+pub struct Align<L: Iterator, R: Iterator, F> {
+    left: Peekable<L>,
+    right: Peekable<R>,
+    align: F,
+}
+
+impl<L: Iterator, R: Iterator, F: FnMut(&L::Item, &R::Item) -> Ordering> Align<L, R, F> {
+    pub(crate) fn new(left: L, right: R, align: F) -> Self {
+        Align {
+            left: left.peekable(),
+            right: right.peekable(),
+            align,
+        }
+    }
+}
+
+impl<L: Iterator, R: Iterator, F: FnMut(&L::Item, &R::Item) -> Ordering> FusedIterator for Align<L, R, F> {}
+
+impl<L: Iterator, R: Iterator, F: FnMut(&L::Item, &R::Item) -> Ordering> Iterator for Align<L, R, F> {
+    type Item = Aligned<L::Item, R::Item>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let ordering = match (self.left.peek(), self.right.peek()) {
+            (Some(left), Some(right)) => (self.align)(left, right),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => return None,
+        };
+
+        match ordering {
+            Ordering::Less => self.left.next().map(Aligned::Left),
+            Ordering::Greater => self.right.next().map(Aligned::Right),
+            Ordering::Equal => Some(Aligned::Both(self.left.next()?, self.right.next()?)),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (left_min, left_max) = self.left.size_hint();
+        let (right_min, right_max) = self.right.size_hint();
+        let max = left_max.and_then(|left| right_max.and_then(|right| left.checked_add(right)));
+        (left_min.max(right_min), max)
+    }
+}
+
+impl<T: PartialOrd + Ord, S: BucketStorage<T>> SortedVec<T, S> {
+    /// Walks `self` and `other` in lockstep. `align` compares an element of
+    /// each and must agree with the order of both containers: `Less` means
+    /// the left one comes first. Matching elements are paired one to one, so
+    /// with duplicates the surplus on either side comes out on its own.
+    pub fn align_with<'a, U: PartialOrd + Ord, S2: BucketStorage<U>, F: FnMut(&&'a T, &&'a U) -> Ordering>(
+        &'a self,
+        other: &'a SortedVec<U, S2>,
+        align: F,
+    ) -> Align<impl Iterator<Item = &'a T>, impl Iterator<Item = &'a U>, F> {
+        Align::new(self.iter(), other.iter(), align)
+    }
+
+    /// Calls `f` for every step of [`align_with`](Self::align_with).
+    pub fn zip_with<U: PartialOrd + Ord, S2: BucketStorage<U>>(
+        &self,
+        other: &SortedVec<U, S2>,
+        mut align: impl FnMut(&T, &U) -> Ordering,
+        f: impl FnMut(Aligned<&T, &U>),
+    ) {
+        self.align_with(other, |left, right| align(left, right)).for_each(f);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sorted_vec::{BucketConfiguration, DuplicatePolicy, Order};
+
+    fn configuration() -> BucketConfiguration {
+        BucketConfiguration::builder().max_bucket_capacity(4).build()
+    }
+
+    #[test]
+    fn zip_with_pairs_matching_keys() {
+        let orders = SortedVec::from_vec(vec![(1, "a"), (3, "b"), (4, "c"), (7, "d"), (9, "e")], configuration());
+        let customers = SortedVec::from_vec(vec![1u64, 2, 4, 9, 10], configuration());
+
+        let mut steps = Vec::new();
+        orders.zip_with(&customers, |(id, _), customer| (*id as u64).cmp(customer), |step| {
+            steps.push(match step {
+                Aligned::Left(order) => Aligned::Left(*order),
+                Aligned::Right(customer) => Aligned::Right(*customer),
+                Aligned::Both(order, customer) => Aligned::Both(*order, *customer),
+            });
+        });
+
+        assert_eq!(steps, vec![
+            Aligned::Both((1, "a"), 1),
+            Aligned::Right(2),
+            Aligned::Left((3, "b")),
+            Aligned::Both((4, "c"), 4),
+            Aligned::Left((7, "d")),
+            Aligned::Both((9, "e"), 9),
+            Aligned::Right(10),
+        ]);
+    }
+
+    #[test]
+    fn align_with_pairs_duplicates_one_to_one() {
+        let configuration = BucketConfiguration::builder().max_bucket_capacity(4).duplicate_policy(DuplicatePolicy::Allow).build();
+        let left = SortedVec::from_vec(vec![1, 1, 1, 2], configuration.clone());
+        let right = SortedVec::from_vec(vec![1, 2, 2], configuration);
+
+        let steps: Vec<_> = left.align_with(&right, |left, right| left.cmp(right)).collect();
+        assert_eq!(steps, vec![
+            Aligned::Both(&1, &1),
+            Aligned::Left(&1),
+            Aligned::Left(&1),
+            Aligned::Both(&2, &2),
+            Aligned::Right(&2),
+        ]);
+    }
+
+    #[test]
+    fn align_with_follows_the_comparator_order() {
+        let configuration = BucketConfiguration::builder().max_bucket_capacity(4).order(Order::Descending).build();
+        let left = SortedVec::from_vec((0..20).step_by(2).collect(), configuration.clone());
+        let right = SortedVec::from_vec((0..20).step_by(3).collect(), configuration);
+
+        let mut align = left.align_with(&right, |left, right| right.cmp(left));
+        assert_eq!(align.size_hint(), (10, Some(17)));
+        assert_eq!(align.next(), Some(Aligned::Both(&18, &18)));
+        assert_eq!(align.next(), Some(Aligned::Left(&16)));
+        assert_eq!(align.next(), Some(Aligned::Right(&15)));
+        assert_eq!(align.by_ref().filter(|step| matches!(step, Aligned::Both(..))).count(), 3);
+        assert_eq!(align.next(), None);
+    }
+}
@@ -0,0 +1,63 @@
+use arbitrary::{Arbitrary, Result, Unstructured};
+
+use crate::sorted_vec::{BucketConfiguration, BucketSearch, DuplicatePolicy, Order, SortedVec, TieBreak};
+
+/// Upper bound on the elements generated for one container, so fuzz inputs
+/// stay cheap to run.
+const MAX_LEN: usize = 1024;
+
+impl<'a> Arbitrary<'a> for BucketConfiguration {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let duplicate_policy = if u.arbitrary()? { DuplicatePolicy::Allow } else { DuplicatePolicy::Reject };
+        let tie_break = if u.arbitrary()? { TieBreak::Lifo } else { TieBreak::Fifo };
+        let order = if u.arbitrary()? { Order::Descending } else { Order::Ascending };
+        let bucket_search = if u.arbitrary()? { BucketSearch::Galloping } else { BucketSearch::Binary };
+
+        Ok(BucketConfiguration::builder()
+            .max_bucket_capacity(u.int_in_range(1..=64)?)
+            .initial_buckets(u.int_in_range(0..=8)?)
+            .duplicate_policy(duplicate_policy)
+            .tie_break(tie_break)
+            .order(order)
+            .split_ratio(u.int_in_range(1..=99)?)
+            .split_hysteresis(u.int_in_range(0..=4)?)
+            .bucket_search(bucket_search)
+            .build())
+    }
+}
+
+impl<'a, T: PartialOrd + Ord + Arbitrary<'a>> Arbitrary<'a> for SortedVec<T> {
+    /// Picks a configuration first so small bucket capacities, both orders
+    /// and both duplicate policies all get exercised.
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let mut result = SortedVec::new(BucketConfiguration::arbitrary(u)?);
+        for item in u.arbitrary_iter::<T>()?.take(MAX_LEN) {
+            result.insert(item?);
+        }
+
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arbitrary_sorted_vec_is_sorted() {
+        let bytes: Vec<u8> = (0..4096u32).map(|i| (i.wrapping_mul(2_654_435_761) >> 13) as u8).collect();
+        for chunk in bytes.chunks(512) {
+            let sorted_vec = SortedVec::<i16>::arbitrary(&mut Unstructured::new(chunk)).unwrap();
+            let order = sorted_vec.configuration.order();
+            let items: Vec<_> = sorted_vec.iter().collect();
+            assert_eq!(items.len(), sorted_vec.size);
+            assert!(items.windows(2).all(|pair| order.compare(pair[0], pair[1]).is_le()));
+        }
+    }
+
+    #[test]
+    fn arbitrary_from_empty_input() {
+        let sorted_vec = SortedVec::<u32>::arbitrary(&mut Unstructured::new(&[])).unwrap();
+        assert_eq!(sorted_vec.size, 0);
+    }
+}
@@ -0,0 +1,811 @@
+use std::cmp::Ordering;
+
+use crate::bucket::Bucket;
+use crate::sorted_vec::{DuplicatePolicy, Order, SortedVec, TieBreak};
+use crate::storage::BucketStorage;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BatchOperation<T> {
+    Insert(T),
+    Remove(T),
+}
+
+impl<T> BatchOperation<T> {
+    pub fn item(&self) -> &T {
+        match self {
+            BatchOperation::Insert(item) | BatchOperation::Remove(item) => item,
+        }
+    }
+}
+
+/// What a bulk insert did with its input.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct InsertSummary {
+    pub added: usize,
+    /// Inputs dropped because an equal element was already stored or came
+    /// earlier in the same batch.
+    pub duplicates: usize,
+}
+
+impl<T: PartialOrd + Ord, S: BucketStorage<T>> SortedVec<T, S> {
+    /// Sorts the incoming items once and merges them into the existing buckets
+    /// in a single pass, instead of searching and shifting for every item.
+    /// Buckets that overflow are split evenly.
+    pub fn insert_many(&mut self, items: impl IntoIterator<Item = T>) -> InsertSummary {
+        #[cfg(feature = "metrics")]
+        let _scope = self.metrics.scope();
+        let order = self.configuration.order();
+        let mut batch: Vec<T> = items.into_iter().collect();
+        batch.sort_by(|a, b| order.compare(a, b));
+        self.insert_sorted_batch(batch)
+    }
+
+    /// Like [`insert_many`](Self::insert_many) for borrowed elements: the
+    /// slice is cloned into one batch, sorted and merged.
+    pub fn extend_from_slice(&mut self, items: &[T]) -> InsertSummary
+    where
+        T: Clone,
+    {
+        self.insert_many(items.to_vec())
+    }
+
+    /// Like [`insert_many`](Self::insert_many), but moves the inputs rejected
+    /// as duplicates into `rejected`, in order, instead of dropping them, e.g.
+    /// to route them to a dead-letter queue.
+    pub fn insert_many_into(&mut self, items: impl IntoIterator<Item = T>, rejected: &mut Vec<T>) -> InsertSummary {
+        #[cfg(feature = "metrics")]
+        let _scope = self.metrics.scope();
+        let order = self.configuration.order();
+        let mut batch: Vec<T> = items.into_iter().collect();
+        batch.sort_by(|a, b| order.compare(a, b));
+        let start = rejected.len();
+        let summary = self.insert_sorted_batch_into(batch, Some(rejected));
+        // Duplicates within the batch are collected before those of stored
+        // elements; both runs are sorted, so the stable sort merges them.
+        rejected[start..].sort_by(|a, b| order.compare(a, b));
+
+        summary
+    }
+
+    /// Merges a batch that is already sorted by the container's order.
+    pub(crate) fn insert_sorted_batch(&mut self, batch: Vec<T>) -> InsertSummary {
+        self.insert_sorted_batch_into(batch, None)
+    }
+
+    fn insert_sorted_batch_into(&mut self, mut batch: Vec<T>, mut rejected: Option<&mut Vec<T>>) -> InsertSummary {
+        let order = self.configuration.order();
+        let duplicate_policy = self.configuration.duplicate_policy();
+        let tie_break = self.configuration.tie_break();
+        let incoming = batch.len();
+        if duplicate_policy == DuplicatePolicy::Reject {
+            match rejected.as_deref_mut() {
+                Some(rejected) => {
+                    let mut kept: Vec<T> = Vec::with_capacity(batch.len());
+                    for item in batch {
+                        if kept.last().is_some_and(|last| order.compare(last, &item) == Ordering::Equal) {
+                            rejected.push(item);
+                        } else {
+                            kept.push(item);
+                        }
+                    }
+                    batch = kept;
+                },
+                None => batch.dedup_by(|a, b| order.compare(a, b) == Ordering::Equal),
+            }
+        }
+
+        let mut added = 0;
+        self.merge_sweep(batch, |item| item, |bucket, routed| {
+            added += bucket.merge(routed, order, duplicate_policy, tie_break, rejected.as_deref_mut());
+        });
+
+        InsertSummary {
+            added,
+            duplicates: incoming - added,
+        }
+    }
+
+    /// Replaces every element with `f(element)`. If the mapping kept the
+    /// elements in order, the buckets are reused as they are; otherwise the
+    /// elements are sorted once and merged back, dropping rejected
+    /// duplicates.
+    pub fn transform(&mut self, mut f: impl FnMut(T) -> T) {
+        self.retain_map(|item| Some(f(item)));
+    }
+
+    /// Like [`transform`](Self::transform), but elements mapped to `None`
+    /// are dropped. If `f` or the comparator panics before the new order is
+    /// known, the container is left empty.
+    pub fn retain_map(&mut self, mut f: impl FnMut(T) -> Option<T>) {
+        #[cfg(feature = "metrics")]
+        let _scope = self.metrics.scope();
+        // Until the order is checked, a panic may leave mapped elements out
+        // of order.
+        let sorted = self.clear_on_unwind(|this| {
+            for bucket in &mut this.buckets {
+                let items = bucket.data.take().into_iter().filter_map(&mut f).collect();
+                bucket.data.replace(items);
+            }
+            this.buckets.retain(|bucket| bucket.len() > 0);
+            if this.buckets.is_empty() {
+                this.buckets.push(Bucket::default());
+            }
+            this.size = this.buckets.iter().map(|bucket| bucket.len()).sum();
+            this.bump_generation();
+            this.is_in_order()
+        });
+        if sorted {
+            self.rebuild_indexes();
+        } else {
+            self.sort_all();
+        }
+    }
+
+    /// Whether the elements are in the configured order, without duplicates
+    /// when those are rejected.
+    pub(crate) fn is_in_order(&self) -> bool {
+        let order = self.configuration.order();
+        let ordered = match self.configuration.duplicate_policy() {
+            DuplicatePolicy::Reject => Ordering::Less,
+            DuplicatePolicy::Allow => Ordering::Equal,
+        };
+        let mut items = self.iter();
+        let mut prev = items.next();
+        items.all(|item| {
+            let in_order = prev.is_none_or(|prev| order.compare(prev, item) <= ordered);
+            prev = Some(item);
+            in_order
+        })
+    }
+
+    /// Sorts every element again from scratch, for when the buckets are out
+    /// of order.
+    pub(crate) fn sort_all(&mut self) {
+        let order = self.configuration.order();
+        let mut batch: Vec<T> = std::mem::take(&mut self.buckets).into_iter().flat_map(|bucket| bucket.data).collect();
+        self.buckets.push(Bucket::default());
+        self.size = 0;
+        batch.sort_by(|a, b| order.compare(a, b));
+        self.insert_sorted_batch(batch);
+    }
+
+    /// Keeps the elements for which `f` returns `true` and appends the others
+    /// to `removed` in order, so their ownership is not lost.
+    pub fn retain_into(&mut self, mut f: impl FnMut(&T) -> bool, removed: &mut Vec<T>) {
+        #[cfg(feature = "metrics")]
+        let _scope = self.metrics.scope();
+        self.repair_on_unwind(|this| {
+            for bucket in &mut this.buckets {
+                let (kept, dropped): (Vec<T>, Vec<T>) = bucket.data.take().into_iter().partition(&mut f);
+                bucket.data.replace(kept);
+                if !dropped.is_empty() {
+                    bucket.drop_bloom();
+                }
+                removed.extend(dropped);
+            }
+        });
+        self.buckets.retain(|bucket| bucket.len() > 0);
+        if self.buckets.is_empty() {
+            self.buckets.push(Bucket::default());
+        }
+        self.size = self.buckets.iter().map(|bucket| bucket.len()).sum();
+        self.bump_generation();
+        self.refresh_indexes();
+    }
+
+    /// Consumes the container and splits it into the elements for which `f`
+    /// returns `true` and the others, both still in order. A bucket whose
+    /// elements all land on one side is moved over with its allocation. Both
+    /// halves share the configuration but start without the optional indexes
+    /// such as the fence index.
+    pub fn partition(self, mut f: impl FnMut(&T) -> bool) -> (Self, Self) {
+        let mut matching = Self::with_storage(self.configuration.clone());
+        let mut rest = Self::with_storage(self.configuration.clone());
+        matching.buckets.clear();
+        rest.buckets.clear();
+
+        for mut bucket in self.buckets {
+            let sides: Vec<bool> = bucket.data.iter().map(&mut f).collect();
+            if sides.iter().all(|&side| side) {
+                matching.buckets.push(bucket);
+            } else if !sides.contains(&true) {
+                rest.buckets.push(bucket);
+            } else {
+                let mut sides = sides.into_iter();
+                let (kept, dropped): (Vec<T>, Vec<T>) = bucket.data.take().into_iter().partition(|_| sides.next().unwrap_or(false));
+                matching.buckets.push(Bucket::from_vec(kept));
+                rest.buckets.push(Bucket::from_vec(dropped));
+            }
+        }
+        for half in [&mut matching, &mut rest] {
+            half.buckets.retain(|bucket| bucket.len() > 0);
+            if half.buckets.is_empty() {
+                half.buckets.push(Bucket::default());
+            }
+            half.size = half.buckets.iter().map(|bucket| bucket.len()).sum();
+        }
+
+        (matching, rest)
+    }
+
+    /// Removes and returns the `n` smallest elements by `T`'s own `Ord`,
+    /// smallest first, like popping a priority queue `n` times. Buckets taken
+    /// whole are moved out without touching the others.
+    pub fn take_smallest(&mut self, n: usize) -> Vec<T> {
+        match self.configuration.order() {
+            Order::Ascending => self.take_front(n),
+            Order::Descending => {
+                let mut taken = self.take_back(n);
+                taken.reverse();
+                taken
+            },
+        }
+    }
+
+    /// Like [`take_smallest`](Self::take_smallest) for the `n` largest
+    /// elements, largest first.
+    pub fn take_largest(&mut self, n: usize) -> Vec<T> {
+        match self.configuration.order() {
+            Order::Ascending => {
+                let mut taken = self.take_back(n);
+                taken.reverse();
+                taken
+            },
+            Order::Descending => self.take_front(n),
+        }
+    }
+
+    /// Removes the first `n` elements, in order.
+    fn take_front(&mut self, n: usize) -> Vec<T> {
+        let n = n.min(self.size);
+        if n == 0 {
+            return Vec::new();
+        }
+
+        let (whole, counted) = whole_buckets(self.buckets.iter().map(|bucket| bucket.len()), n);
+        let mut taken = Vec::with_capacity(n);
+        for mut bucket in self.buckets.drain(0..whole) {
+            taken.append(&mut bucket.data.take());
+        }
+        if counted < n {
+            let bucket = &mut self.buckets[0];
+            let mut items = bucket.data.take();
+            taken.extend(items.drain(..n - counted));
+            bucket.data.replace(items);
+            bucket.drop_bloom();
+        }
+
+        self.finish_take(n);
+        taken
+    }
+
+    /// Removes the last `n` elements, in order.
+    fn take_back(&mut self, n: usize) -> Vec<T> {
+        let n = n.min(self.size);
+        if n == 0 {
+            return Vec::new();
+        }
+
+        let (whole, counted) = whole_buckets(self.buckets.iter().rev().map(|bucket| bucket.len()), n);
+        let first_whole = self.buckets.len() - whole;
+        let buckets = self.buckets.drain(first_whole..self.buckets.len());
+        let mut taken = Vec::with_capacity(n);
+        if counted < n {
+            let bucket = &mut self.buckets[first_whole - 1];
+            let mut items = bucket.data.take();
+            taken.extend(items.drain(items.len() - (n - counted)..));
+            bucket.data.replace(items);
+            bucket.drop_bloom();
+        }
+        for mut bucket in buckets {
+            taken.append(&mut bucket.data.take());
+        }
+
+        self.finish_take(n);
+        taken
+    }
+
+    fn finish_take(&mut self, taken: usize) {
+        if self.buckets.is_empty() {
+            self.buckets.push(Bucket::default());
+        }
+        self.size -= taken;
+        self.bump_generation();
+        self.refresh_indexes();
+    }
+
+    /// Applies a batch of inserts and removals in one sweep over the buckets.
+    /// Operations on equal elements are applied in the order they were given.
+    pub fn apply_batch(&mut self, operations: impl IntoIterator<Item = BatchOperation<T>>) {
+        #[cfg(feature = "metrics")]
+        let _scope = self.metrics.scope();
+        let order = self.configuration.order();
+        let duplicate_policy = self.configuration.duplicate_policy();
+        let lifo = duplicate_policy == DuplicatePolicy::Allow && self.configuration.tie_break() == TieBreak::Lifo;
+
+        let mut batch: Vec<BatchOperation<T>> = operations.into_iter().collect();
+        batch.sort_by(|a, b| order.compare(a.item(), b.item()));
+
+        self.merge_sweep(batch, BatchOperation::item, |bucket, routed| {
+            let existing = bucket.data.take();
+            let mut merged = Vec::with_capacity(existing.len() + routed.len());
+            let mut existing = existing.into_iter().peekable();
+            let mut routed = routed.into_iter().peekable();
+
+            while let Some(first) = routed.next() {
+                let mut group = vec![first];
+                while let Some(next) = routed.next_if(|next| order.compare(next.item(), group[0].item()) == Ordering::Equal) {
+                    group.push(next);
+                }
+
+                let key = group[0].item();
+                while let Some(current) = existing.next_if(|current| order.compare(current, key) == Ordering::Less) {
+                    merged.push(current);
+                }
+                let mut run = Vec::new();
+                while let Some(current) = existing.next_if(|current| order.compare(current, key) == Ordering::Equal) {
+                    run.push(current);
+                }
+
+                for operation in group {
+                    match operation {
+                        BatchOperation::Insert(item) => {
+                            if lifo {
+                                run.insert(0, item);
+                            } else if run.is_empty() || duplicate_policy == DuplicatePolicy::Allow {
+                                run.push(item);
+                            }
+                        },
+                        BatchOperation::Remove(_) => {
+                            if !run.is_empty() {
+                                run.remove(0);
+                            }
+                        },
+                    }
+                }
+                merged.extend(run);
+            }
+            merged.extend(existing);
+            bucket.data.replace(merged);
+        });
+    }
+
+    /// Removes one matching element per given item with a single sweep over
+    /// the buckets and returns how many elements were actually removed.
+    pub fn remove_all<'a>(&mut self, items: impl IntoIterator<Item = &'a T>) -> usize
+    where
+        T: 'a,
+    {
+        #[cfg(feature = "metrics")]
+        let _scope = self.metrics.scope();
+        let order = self.configuration.order();
+        let mut batch: Vec<&T> = items.into_iter().collect();
+        batch.sort_by(|a, b| order.compare(*a, *b));
+
+        // Walks the removal set and the buckets together. Equal runs may span
+        // several buckets, so unmatched items carry over to the next bucket.
+        let mut removals = batch.into_iter().peekable();
+        let removed = self.repair_on_unwind(|this| {
+            let mut removed = 0;
+            for bucket in &mut this.buckets {
+                let (Some(first), Some(last)) = (bucket.data.first(), bucket.data.last()) else {
+                    continue;
+                };
+                while removals.next_if(|item| order.compare(*item, first) == Ordering::Less).is_some() {}
+                if removals.peek().is_none_or(|item| order.compare(*item, last) == Ordering::Greater) {
+                    continue;
+                }
+
+                let len = bucket.len();
+                bucket.data.retain(|current| {
+                    while removals.next_if(|item| order.compare(*item, current) == Ordering::Less).is_some() {}
+                    removals.next_if(|item| order.compare(*item, current) == Ordering::Equal).is_none()
+                });
+                if bucket.len() < len {
+                    bucket.drop_bloom();
+                }
+                removed += len - bucket.len();
+            }
+
+            removed
+        });
+
+        self.buckets.retain(|bucket| bucket.len() > 0);
+        if self.buckets.is_empty() {
+            self.buckets.push(Bucket::default());
+        }
+        self.refresh_indexes();
+        self.size -= removed;
+        self.bump_generation();
+
+        removed
+    }
+
+    /// Routes an already sorted batch to the buckets it belongs to and hands
+    /// each bucket its share. Afterwards empty buckets are dropped, overflowing
+    /// ones are split evenly and `size` is brought up to date. A panic while
+    /// routing or in `apply` is repaired with
+    /// [`repair_on_unwind`](Self::repair_on_unwind).
+    fn merge_sweep<U>(&mut self, batch: Vec<U>, key: impl Fn(&U) -> &T, apply: impl FnMut(&mut Bucket<T, S>, Vec<U>)) {
+        self.repair_on_unwind(|this| this.sweep_buckets(batch, key, apply));
+    }
+
+    fn sweep_buckets<U>(
+        &mut self,
+        batch: Vec<U>,
+        key: impl Fn(&U) -> &T,
+        mut apply: impl FnMut(&mut Bucket<T, S>, Vec<U>),
+    ) {
+        #[cfg(feature = "metrics")]
+        let _scope = self.metrics.scope();
+        if batch.is_empty() {
+            return;
+        }
+
+        if self.buckets.is_empty() {
+            self.buckets.push(Bucket::default());
+        }
+
+        let order = self.configuration.order();
+        // Inserting ahead of equal runs means an item equal to the next
+        // bucket's first element may still belong before it.
+        let lifo = self.configuration.duplicate_policy() == DuplicatePolicy::Allow && self.configuration.tie_break() == TieBreak::Lifo;
+        let stays_left = if lifo { Ordering::Equal } else { Ordering::Less };
+        let mut incoming = batch.into_iter().peekable();
+        let mut size = 0;
+
+        // The buckets stay in place while they are merged, so a panic only
+        // loses the elements of the bucket being merged.
+        for bucket_idx in 0..self.buckets.len() {
+            // An item belongs to this bucket unless it reaches the next bucket's
+            // first element, so equal runs keep growing at their end.
+            let next_first = self.buckets.get(bucket_idx + 1).and_then(|next| next.data.first());
+            let mut routed = Vec::new();
+            while let Some(item) = incoming.next_if(|item| {
+                next_first.is_none_or(|first| order.compare(key(item), first) <= stays_left)
+            }) {
+                routed.push(item);
+            }
+
+            let bucket = &mut self.buckets[bucket_idx];
+            if !routed.is_empty() {
+                #[cfg(feature = "metrics")]
+                crate::metrics::count(|stats| stats.merges += 1);
+                apply(bucket, routed);
+                bucket.drop_bloom();
+            }
+            size += bucket.len();
+        }
+
+        let capacity = self.configuration.bucket_capacity(size);
+        for mut bucket in std::mem::take(&mut self.buckets).into_iter().filter(|bucket| bucket.len() > 0) {
+            if bucket.len() > capacity {
+                let rest = bucket.split_evenly(capacity);
+                #[cfg(feature = "metrics")]
+                crate::metrics::count(|stats| stats.splits += rest.len() as u64);
+                self.buckets.push(bucket);
+                self.buckets.extend(rest);
+            } else {
+                self.buckets.push(bucket);
+            }
+        }
+        if self.buckets.is_empty() {
+            self.buckets.push(Bucket::default());
+        }
+        self.refresh_indexes();
+        self.size = size;
+        self.bump_generation();
+    }
+}
+
+/// How many of the buckets with `lens` fit whole into `n` elements, and how
+/// many elements they hold.
+fn whole_buckets(lens: impl Iterator<Item = usize>, n: usize) -> (usize, usize) {
+    let mut whole = 0;
+    let mut counted = 0;
+    for len in lens {
+        if counted + len > n {
+            break;
+        }
+        counted += len;
+        whole += 1;
+    }
+
+    (whole, counted)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cmp::Ordering;
+
+    use crate::batch::{BatchOperation, InsertSummary};
+    use crate::container::SortedContainer;
+    use crate::sorted_vec::{BucketConfiguration, DuplicatePolicy, Order, SortedVec, TieBreak};
+
+    #[test]
+    fn insert_many_into_empty() {
+        let config = BucketConfiguration::builder().max_bucket_capacity(4).build();
+        let mut sorted_vec = SortedVec::new(config);
+        let summary = sorted_vec.insert_many(vec![5, 3, 9, 1, 7, 3, 2, 8, 6, 4, 0]);
+
+        assert_eq!(summary, InsertSummary { added: 10, duplicates: 1 });
+        assert_eq!(sorted_vec.size, 10);
+        assert!(sorted_vec.buckets.iter().all(|bucket| bucket.len() <= 4));
+        assert_eq!(sorted_vec.iter().copied().collect::<Vec<_>>(), (0..10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn insert_many_merges_into_existing_buckets() {
+        let config = BucketConfiguration::builder().max_bucket_capacity(3).build();
+        let mut sorted_vec = SortedVec::from_vec((0..20).step_by(2).collect(), config);
+        let summary = sorted_vec.insert_many((1..20).step_by(2).chain([4, 21, -1]));
+
+        assert_eq!(summary, InsertSummary { added: 12, duplicates: 1 });
+        assert_eq!(sorted_vec.size, 22);
+        assert!(sorted_vec.buckets.iter().all(|bucket| bucket.len() <= 3));
+        assert_eq!(sorted_vec.iter().copied().collect::<Vec<_>>(), (-1..20).chain([21]).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn insert_many_keeps_duplicates_when_allowed() {
+        let config = BucketConfiguration::builder()
+            .max_bucket_capacity(2)
+            .duplicate_policy(DuplicatePolicy::Allow)
+            .build();
+        let mut sorted_vec = SortedVec::from_vec(vec![1, 2, 2, 3], config);
+        let summary = sorted_vec.insert_many(vec![2, 2, 0]);
+
+        assert_eq!(summary, InsertSummary { added: 3, duplicates: 0 });
+        assert_eq!(sorted_vec.size, 7);
+        assert_eq!(sorted_vec.iter().copied().collect::<Vec<_>>(), vec![0, 1, 2, 2, 2, 2, 3]);
+    }
+
+    #[test]
+    fn insert_many_with_empty_batch() {
+        let mut sorted_vec = SortedVec::from_vec(vec![1, 2], Default::default());
+        sorted_vec.insert_many(Vec::new());
+
+        assert_eq!(sorted_vec.size, 2);
+    }
+
+    #[test]
+    fn apply_batch_inserts_and_removes() {
+        let config = BucketConfiguration::builder().max_bucket_capacity(3).build();
+        let mut sorted_vec = SortedVec::from_vec((0..10).collect(), config);
+        sorted_vec.apply_batch(vec![
+            BatchOperation::Remove(3),
+            BatchOperation::Insert(12),
+            BatchOperation::Remove(0),
+            BatchOperation::Insert(11),
+            BatchOperation::Remove(42),
+            BatchOperation::Insert(5),
+        ]);
+
+        assert_eq!(sorted_vec.size, 10);
+        assert!(sorted_vec.buckets.iter().all(|bucket| bucket.len() <= 3));
+        assert_eq!(sorted_vec.iter().copied().collect::<Vec<_>>(), vec![1, 2, 4, 5, 6, 7, 8, 9, 11, 12]);
+    }
+
+    #[test]
+    fn apply_batch_keeps_operation_order_for_equal_items() {
+        let mut sorted_vec = SortedVec::from_vec(vec![1, 2], Default::default());
+        sorted_vec.apply_batch(vec![
+            BatchOperation::Remove(2),
+            BatchOperation::Insert(2),
+            BatchOperation::Insert(3),
+            BatchOperation::Remove(3),
+        ]);
+
+        assert_eq!(sorted_vec.iter().copied().collect::<Vec<_>>(), vec![1, 2]);
+    }
+
+    #[test]
+    fn apply_batch_puts_lifo_inserts_before_their_equals() {
+        let config = BucketConfiguration::builder()
+            .max_bucket_capacity(2)
+            .duplicate_policy(DuplicatePolicy::Allow)
+            .tie_break(TieBreak::Lifo)
+            .build();
+        let mut batched = SortedVec::from_vec(vec![Keyed(0, "stored"), Keyed(1, "stored")], config.clone());
+        batched.apply_batch(vec![BatchOperation::Insert(Keyed(0, "first")), BatchOperation::Insert(Keyed(0, "second"))]);
+
+        let mut inserted = SortedVec::from_vec(vec![Keyed(0, "stored"), Keyed(1, "stored")], config);
+        inserted.insert(Keyed(0, "first"));
+        inserted.insert(Keyed(0, "second"));
+
+        let payloads = |sorted_vec: &SortedVec<Keyed>| sorted_vec.iter().map(|item| item.1).collect::<Vec<_>>();
+        assert_eq!(payloads(&batched), vec!["second", "first", "stored", "stored"]);
+        assert_eq!(payloads(&batched), payloads(&inserted));
+    }
+
+    #[test]
+    fn apply_batch_removes_everything() {
+        let config = BucketConfiguration::builder().max_bucket_capacity(2).build();
+        let mut sorted_vec = SortedVec::from_vec(vec![1, 2, 3, 4], config);
+        sorted_vec.apply_batch((1..5).map(BatchOperation::Remove));
+
+        assert_eq!(sorted_vec.size, 0);
+        assert_eq!(sorted_vec.buckets.len(), 1);
+        sorted_vec.insert(7);
+        assert_eq!(sorted_vec.first(), Some(&7));
+    }
+
+    #[test]
+    fn remove_all_returns_removed_count() {
+        let config = BucketConfiguration::builder().max_bucket_capacity(3).build();
+        let mut sorted_vec = SortedVec::from_vec((0..10).collect(), config);
+        let removed = sorted_vec.remove_all(&[8, 1, 42, 3, 3, 9, 0]);
+
+        assert_eq!(removed, 5);
+        assert_eq!(sorted_vec.size, 5);
+        assert_eq!(sorted_vec.iter().copied().collect::<Vec<_>>(), vec![2, 4, 5, 6, 7]);
+    }
+
+    #[test]
+    fn remove_all_removes_one_duplicate_per_item() {
+        let config = BucketConfiguration::builder()
+            .max_bucket_capacity(2)
+            .duplicate_policy(DuplicatePolicy::Allow)
+            .build();
+        let mut sorted_vec = SortedVec::from_vec(vec![1, 2, 2, 2, 3], config);
+        let removed = sorted_vec.remove_all(&[2, 2]);
+
+        assert_eq!(removed, 2);
+        assert_eq!(sorted_vec.iter().copied().collect::<Vec<_>>(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn transform_keeps_buckets_when_order_is_preserved() {
+        let config = BucketConfiguration::builder().max_bucket_capacity(2).build();
+        let mut sorted_vec = SortedVec::from_vec(vec![1, 2, 3, 4, 5], config);
+        let buckets = sorted_vec.buckets.len();
+        sorted_vec.transform(|item| item * 10);
+
+        assert_eq!(sorted_vec.buckets.len(), buckets);
+        assert_eq!(sorted_vec.iter().copied().collect::<Vec<_>>(), vec![10, 20, 30, 40, 50]);
+        assert!(sorted_vec.find_index(&30).is_some());
+    }
+
+    #[test]
+    fn transform_resorts_and_drops_rejected_duplicates() {
+        let config = BucketConfiguration::builder().max_bucket_capacity(2).build();
+        let mut sorted_vec = SortedVec::from_vec(vec![1, 2, 3, 4, 5, 6], config);
+        sorted_vec.transform(|item| (item * 7) % 4);
+
+        assert_eq!(sorted_vec.size, 4);
+        assert_eq!(sorted_vec.iter().copied().collect::<Vec<_>>(), vec![0, 1, 2, 3]);
+        assert!(sorted_vec.find_index(&2).is_some());
+    }
+
+    #[test]
+    fn retain_map_drops_and_maps() {
+        let config = BucketConfiguration::builder().max_bucket_capacity(2).build();
+        let mut sorted_vec = SortedVec::from_vec((0..10).collect(), config);
+        sorted_vec.retain_map(|item| (item % 3 == 0).then_some(item / 3));
+
+        assert_eq!(sorted_vec.size, 4);
+        assert!(sorted_vec.buckets.iter().all(|bucket| bucket.len() > 0));
+        assert_eq!(sorted_vec.iter().copied().collect::<Vec<_>>(), vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn retain_into_hands_back_removed_elements() {
+        let config = BucketConfiguration::builder().max_bucket_capacity(2).build();
+        let mut sorted_vec = SortedVec::from_vec((0..10).map(|item| item.to_string()).collect(), config);
+        let mut removed = vec!["x".to_string()];
+        sorted_vec.retain_into(|item| item.parse::<u32>().unwrap() >= 6, &mut removed);
+
+        assert_eq!(sorted_vec.size, 4);
+        assert!(sorted_vec.buckets.iter().all(|bucket| bucket.len() > 0));
+        assert!(sorted_vec.iter().eq(["6", "7", "8", "9"].iter()));
+        assert_eq!(removed, ["x", "0", "1", "2", "3", "4", "5"]);
+        assert!(sorted_vec.find_index(&"7".to_string()).is_some());
+    }
+
+    #[test]
+    fn partition_keeps_order_and_moves_one_sided_buckets() {
+        let config = BucketConfiguration::builder().max_bucket_capacity(4).build();
+        let sorted_vec = SortedVec::from_vec((0..30).rev().collect(), config);
+        let pointers: Vec<*const i32> = sorted_vec.buckets.iter().map(|bucket| bucket.data.as_ptr()).collect();
+
+        let (small, large) = sorted_vec.partition(|&item| item < 100);
+        assert!(small.buckets.iter().map(|bucket| bucket.data.as_ptr()).eq(pointers));
+        assert_eq!((small.size, large.size), (30, 0));
+        assert_eq!(large.buckets.len(), 1);
+
+        let mut calls = 0;
+        let (even, odd) = small.partition(|&item| {
+            calls += 1;
+            item % 2 == 0 || item > 20
+        });
+        assert_eq!(calls, 30);
+        assert!(even.iter().copied().eq((0..21).step_by(2).chain(21..30)));
+        assert!(odd.iter().copied().eq((1..21).step_by(2)));
+        assert_eq!((even.size, odd.size), (20, 10));
+        assert_eq!(even.check_integrity(), Ok(()));
+        assert_eq!(odd.check_integrity(), Ok(()));
+    }
+
+    #[derive(Debug, PartialEq, Eq)]
+    struct Keyed(u32, &'static str);
+
+    impl PartialOrd for Keyed {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Keyed {
+        fn cmp(&self, other: &Self) -> Ordering {
+            self.0.cmp(&other.0)
+        }
+    }
+
+    #[test]
+    fn insert_many_into_collects_rejected_duplicates() {
+        let config = BucketConfiguration::builder().max_bucket_capacity(2).build();
+        let mut sorted_vec = SortedVec::from_vec((0..10).map(|key| Keyed(key * 2, "stored")).collect(), config);
+        let mut rejected = Vec::new();
+        let summary = sorted_vec.insert_many_into(
+            [Keyed(5, "first"), Keyed(4, "late"), Keyed(5, "second"), Keyed(18, "late"), Keyed(19, "new")],
+            &mut rejected,
+        );
+
+        assert_eq!(summary, InsertSummary { added: 2, duplicates: 3 });
+        assert_eq!(rejected, vec![Keyed(4, "late"), Keyed(5, "second"), Keyed(18, "late")]);
+        assert_eq!(sorted_vec.size, 12);
+        assert!(sorted_vec.iter().any(|item| *item == Keyed(5, "first") && item.1 == "first"));
+        assert!(sorted_vec.iter().filter(|item| item.0 % 2 == 0).all(|item| item.1 == "stored"));
+    }
+
+    #[test]
+    fn extend_from_slices_and_references() {
+        let config = BucketConfiguration::builder().max_bucket_capacity(4).build();
+        let mut sorted_vec: SortedVec<u64> = SortedVec::new(config);
+        let items: Vec<u64> = (0..20).rev().collect();
+
+        assert_eq!(sorted_vec.extend_from_slice(&items[..10]), InsertSummary { added: 10, duplicates: 0 });
+        sorted_vec.extend(&items);
+        sorted_vec.extend([&25, &20]);
+
+        assert_eq!(sorted_vec.size, 22);
+        assert!(sorted_vec.iter().copied().eq((0..21).chain([25])));
+    }
+
+    #[test]
+    fn take_smallest_and_largest_drain_both_ends() {
+        let configuration = BucketConfiguration::builder().max_bucket_capacity(4).build();
+        let mut sorted_vec = SortedVec::from_vec((0..30).collect(), configuration).with_fence_index();
+        let buckets = sorted_vec.buckets.len();
+
+        assert_eq!(sorted_vec.take_smallest(0), Vec::<i32>::new());
+        assert_eq!(sorted_vec.take_smallest(9), (0..9).collect::<Vec<_>>());
+        assert_eq!(sorted_vec.take_largest(5), vec![29, 28, 27, 26, 25]);
+        assert!(sorted_vec.buckets.len() < buckets);
+        assert_eq!(sorted_vec.len(), 16);
+        assert!(sorted_vec.iter().copied().eq(9..25));
+        assert!(sorted_vec.find_index(&12).is_some());
+        assert_eq!(sorted_vec.check_integrity(), Ok(()));
+
+        assert_eq!(sorted_vec.take_largest(100), (9..25).rev().collect::<Vec<_>>());
+        assert!(sorted_vec.is_empty());
+        assert_eq!(sorted_vec.take_smallest(1), Vec::<i32>::new());
+        sorted_vec.insert(7);
+        assert_eq!(sorted_vec.check_integrity(), Ok(()));
+    }
+
+    #[test]
+    fn take_smallest_follows_the_element_order_when_descending() {
+        let configuration = BucketConfiguration::builder()
+            .max_bucket_capacity(4)
+            .order(Order::Descending)
+            .duplicate_policy(DuplicatePolicy::Allow)
+            .build();
+        let mut sorted_vec = SortedVec::from_vec(vec![5, 3, 3, 8, 1, 9, 3, 2, 7, 4], configuration);
+
+        assert_eq!(sorted_vec.take_smallest(4), vec![1, 2, 3, 3]);
+        assert_eq!(sorted_vec.take_largest(2), vec![9, 8]);
+        assert_eq!(sorted_vec.iter().copied().collect::<Vec<_>>(), vec![7, 5, 4, 3]);
+        assert_eq!(sorted_vec.check_integrity(), Ok(()));
+    }
+}
@@ -0,0 +1,258 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use crate::sorted_vec::SortedVec;
+use crate::storage::BucketStorage;
+
+/// Filters never get fewer bits than this, so small buckets still get a
+/// useful false positive rate.
+const MIN_BITS: usize = 256;
+
+/// The parameters of the bloom filters kept per bucket, so lookups for
+/// absent elements can stop before searching the bucket they are routed to.
+pub(crate) struct BloomFilter<T> {
+    bits_per_element: usize,
+    hashes: u32,
+    element_hash: fn(&T) -> u64,
+}
+
+/// The bloom filter of one bucket. Removed elements keep their bits until
+/// the next rebuild, which happens once the filter has seen twice the
+/// elements it was sized for or as many removals as it was sized for. Bulk
+/// operations drop the filters of the buckets they change and rebuild only
+/// those.
+#[derive(Debug)]
+pub(crate) struct BucketBloom {
+    words: Vec<u64>,
+    /// The element count the filter was sized for.
+    sized_for: usize,
+    added: usize,
+    removed: usize,
+}
+
+fn element_hash<T: Hash>(item: &T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    item.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl<T> BloomFilter<T> {
+    /// The bit positions for `item` in a filter of `words`, by double
+    /// hashing.
+    fn positions(&self, words: usize, item: &T) -> impl Iterator<Item = (usize, u64)> {
+        let hash = (self.element_hash)(item);
+        let step = hash.rotate_left(32) | 1;
+        let bits = words as u64 * 64;
+        (0..u64::from(self.hashes)).map(move |i| {
+            let position = hash.wrapping_add(i.wrapping_mul(step)) % bits;
+            ((position / 64) as usize, 1 << (position % 64))
+        })
+    }
+
+    fn add(&self, filter: &mut BucketBloom, item: &T) {
+        for (word, mask) in self.positions(filter.words.len(), item) {
+            filter.words[word] |= mask;
+        }
+        filter.added += 1;
+    }
+
+    fn might_contain(&self, filter: &BucketBloom, item: &T) -> bool {
+        self.positions(filter.words.len(), item).all(|(word, mask)| filter.words[word] & mask != 0)
+    }
+
+    /// A filter sized for and holding the `len` elements of `items`.
+    fn build<'a>(&self, len: usize, items: impl Iterator<Item = &'a T>) -> BucketBloom
+    where
+        T: 'a,
+    {
+        let mut filter = BucketBloom {
+            words: vec![0; (len * self.bits_per_element).max(MIN_BITS).div_ceil(64)],
+            sized_for: len,
+            added: 0,
+            removed: 0,
+        };
+        for item in items {
+            self.add(&mut filter, item);
+        }
+
+        filter
+    }
+}
+
+impl BucketBloom {
+    fn needs_rebuild(&self) -> bool {
+        self.added > 2 * self.sized_for || self.removed > self.sized_for
+    }
+}
+
+impl<T: PartialOrd + Ord, S: BucketStorage<T>> SortedVec<T, S> {
+    /// Keeps a bloom filter of the elements of every bucket with about
+    /// `bits_per_element` bits each, so looking up an absent element usually
+    /// skips the search inside its bucket. Ten bits give roughly a 1% false
+    /// positive rate. Elements that compare equal must hash the same.
+    pub fn with_bloom_filter(mut self, bits_per_element: usize) -> Self
+    where
+        T: Hash,
+    {
+        let bits_per_element = bits_per_element.max(1);
+        self.bloom = Some(BloomFilter {
+            bits_per_element,
+            hashes: ((bits_per_element as f64 * std::f64::consts::LN_2).round() as u32).clamp(1, 16),
+            element_hash: element_hash::<T>,
+        });
+        self.rebuild_bloom();
+
+        self
+    }
+
+    /// Whether `item` may be stored: `false` means it definitely is not.
+    /// Always `true` without a filter from
+    /// [`with_bloom_filter`](Self::with_bloom_filter).
+    pub fn might_contain(&self, item: &T) -> bool {
+        self.bloom.is_none() || self.bucket_might_contain(self.find_bucket_index(item), item)
+    }
+
+    /// Whether `item` may be stored in `bucket_idx`, the bucket it is routed
+    /// to.
+    pub(crate) fn bucket_might_contain(&self, bucket_idx: usize, item: &T) -> bool {
+        let Some(bloom) = &self.bloom else {
+            return true;
+        };
+
+        self.buckets[bucket_idx].bloom.as_ref().is_none_or(|filter| bloom.might_contain(filter, item))
+    }
+
+    pub(crate) fn bloom_added(&mut self, (bucket_idx, item_idx): (usize, usize)) {
+        let Some(bloom) = &self.bloom else {
+            return;
+        };
+
+        let bucket = &mut self.buckets[bucket_idx];
+        let Some(filter) = &mut bucket.bloom else {
+            return self.rebuild_bucket_bloom(bucket_idx);
+        };
+        let item = bucket.data.get(item_idx).expect("the added element is stored at its position");
+        bloom.add(filter, item);
+        if filter.needs_rebuild() {
+            self.rebuild_bucket_bloom(bucket_idx);
+        }
+    }
+
+    /// Counts a removal from `bucket_idx`, which may since have been dropped
+    /// if the removal emptied it.
+    pub(crate) fn bloom_removed(&mut self, bucket_idx: usize) {
+        if self.bloom.is_none() {
+            return;
+        }
+        let Some(filter) = self.buckets.get_mut(bucket_idx).and_then(|bucket| bucket.bloom.as_mut()) else {
+            return;
+        };
+
+        filter.removed += 1;
+        if filter.needs_rebuild() {
+            self.rebuild_bucket_bloom(bucket_idx);
+        }
+    }
+
+    /// Builds the filter of `bucket_idx` from its elements.
+    pub(crate) fn rebuild_bucket_bloom(&mut self, bucket_idx: usize) {
+        let Some(bloom) = &self.bloom else {
+            return;
+        };
+
+        let bucket = &mut self.buckets[bucket_idx];
+        bucket.bloom = Some(bloom.build(bucket.len(), bucket.data.iter()));
+    }
+
+    /// Builds the filters of the buckets that have none, after a bulk
+    /// operation dropped the filters of the buckets it changed.
+    pub(crate) fn rebuild_stale_blooms(&mut self) {
+        if self.bloom.is_none() {
+            return;
+        }
+
+        for bucket_idx in 0..self.buckets.len() {
+            if self.buckets[bucket_idx].bloom.is_none() {
+                self.rebuild_bucket_bloom(bucket_idx);
+            }
+        }
+    }
+
+    /// Rebuilds the filter of every bucket, after bulk operations that
+    /// changed elements without dropping the filters of their buckets.
+    pub(crate) fn rebuild_bloom(&mut self) {
+        if self.bloom.is_none() {
+            return;
+        }
+
+        for bucket_idx in 0..self.buckets.len() {
+            self.rebuild_bucket_bloom(bucket_idx);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use crate::sorted_vec::{BucketConfiguration, DuplicatePolicy, SortedVec};
+
+    fn filtered(items: impl IntoIterator<Item = u64>) -> SortedVec<u64> {
+        let configuration = BucketConfiguration::builder()
+            .max_bucket_capacity(8)
+            .duplicate_policy(DuplicatePolicy::Allow)
+            .build();
+        let mut sorted_vec = SortedVec::new(configuration).with_bloom_filter(10);
+        sorted_vec.extend(items);
+        sorted_vec
+    }
+
+    #[test]
+    fn bloom_filter_has_no_false_negatives() {
+        let mut sorted_vec = filtered((0..2_000).map(|item| item * 3));
+        for item in 2_000..4_000 {
+            sorted_vec.insert(item * 3);
+        }
+        sorted_vec.remove(&30);
+        sorted_vec.update(&33, |item| *item = 1);
+
+        let stored: BTreeSet<u64> = sorted_vec.iter().copied().collect();
+        for item in 0..12_000 {
+            let stored = stored.contains(&item);
+            assert_eq!(sorted_vec.contains(&item), stored);
+            assert!(!stored || sorted_vec.might_contain(&item));
+        }
+    }
+
+    #[test]
+    fn bloom_filter_rejects_most_misses() {
+        let mut sorted_vec = filtered((0..5_000).map(|item| item * 2));
+        for item in 0..2_500 {
+            sorted_vec.remove(&(item * 2));
+        }
+        sorted_vec.insert_many((10_000..12_000).map(|item| item * 2));
+
+        let false_positives = (0..20_000).map(|item| item * 2 + 1).filter(|item| sorted_vec.might_contain(item)).count();
+        assert!(false_positives < 600, "{false_positives} false positives");
+        assert!(SortedVec::<u64>::new(Default::default()).might_contain(&7));
+    }
+
+    #[test]
+    fn bloom_filter_rebuilds_only_changed_buckets() {
+        let mut sorted_vec = filtered((0..1_000).map(|item| item * 4));
+        let words = |sorted_vec: &SortedVec<u64>, bucket_idx: usize| sorted_vec.buckets[bucket_idx].bloom.as_ref().unwrap().words.as_ptr();
+        let first = words(&sorted_vec, 0);
+        sorted_vec.insert_many([3_001, 3_002, 3_999]);
+        sorted_vec.remove_range(400..800);
+        sorted_vec.retain_into(|item| *item < 3_500 || item % 3 != 0, &mut Vec::new());
+        sorted_vec.remove_all(&[3_001]);
+        sorted_vec.take_largest(5);
+        sorted_vec.shrink_buckets_to(50);
+
+        assert_eq!(words(&sorted_vec, 0), first);
+        assert!(sorted_vec.buckets.iter().all(|bucket| bucket.bloom.is_some()));
+        assert!(sorted_vec.iter().all(|item| sorted_vec.might_contain(item)));
+        assert!(sorted_vec.contains(&3_002));
+        assert!(!sorted_vec.contains(&3_001));
+        assert!(!sorted_vec.contains(&400));
+    }
+}
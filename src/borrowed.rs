@@ -0,0 +1,87 @@
+use std::borrow::Cow;
+
+use crate::builder::SortedVecBuilder;
+use crate::sorted_vec::{BucketConfiguration, SortedVec};
+
+impl<'a, T: PartialOrd + Ord> SortedVec<&'a T> {
+    /// Builds a sorted index of references into `items`, which stays in its
+    /// own order, e.g. arrival order.
+    pub fn from_slice_refs(items: &'a [T], configuration: BucketConfiguration) -> Self {
+        Self::from_vec_with_summary(items.iter().collect(), configuration).0
+    }
+
+    /// Clones the referenced elements into an owned container with the same
+    /// configuration.
+    pub fn cloned(&self) -> SortedVec<T>
+    where
+        T: Clone,
+    {
+        let mut builder = SortedVecBuilder::new(self.configuration.clone());
+        for item in self.iter() {
+            if builder.push((*item).clone()).is_err() {
+                unreachable!("references order like their targets");
+            }
+        }
+
+        builder.finish()
+    }
+}
+
+impl<'a, B: PartialOrd + Ord + ToOwned + ?Sized> SortedVec<Cow<'a, B>> {
+    /// Builds a container that borrows every element until it is replaced
+    /// with an owned one.
+    pub fn from_borrowed(items: impl IntoIterator<Item = &'a B>, configuration: BucketConfiguration) -> Self {
+        Self::from_vec_with_summary(items.into_iter().map(Cow::Borrowed).collect(), configuration).0
+    }
+
+    /// Converts every element into its owned form. The owned type is only
+    /// expected to order like the borrowed one, so the elements are sorted
+    /// again rather than trusted.
+    pub fn into_owned(self) -> SortedVec<B::Owned>
+    where
+        B::Owned: PartialOrd + Ord,
+    {
+        let configuration = self.configuration.clone();
+        let items = self.buckets.into_iter().flat_map(|bucket| bucket.data).map(Cow::into_owned).collect();
+
+        SortedVec::from_vec_with_summary(items, configuration).0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use crate::sorted_vec::{BucketConfiguration, DuplicatePolicy, Order, SortedVec};
+
+    #[test]
+    fn from_slice_refs_indexes_data_owned_elsewhere() {
+        let arrivals = vec![7, 3, 9, 3, 1];
+        let configuration = BucketConfiguration::builder()
+            .max_bucket_capacity(2)
+            .duplicate_policy(DuplicatePolicy::Allow)
+            .build();
+        let index = SortedVec::from_slice_refs(&arrivals, configuration);
+
+        assert!(index.iter().copied().eq(&[1, 3, 3, 7, 9]));
+        assert!(index.find_index(&&9).is_some());
+        assert!(std::ptr::eq(*index.first().unwrap(), &arrivals[4]));
+        assert!(index.cloned().iter().eq(&[1, 3, 3, 7, 9]));
+        assert_eq!(arrivals, vec![7, 3, 9, 3, 1]);
+    }
+
+    #[test]
+    fn cow_elements_borrow_until_modified() {
+        let words = ["pear", "apple", "fig"];
+        let configuration = BucketConfiguration::builder().order(Order::Descending).build();
+        let mut sorted_vec = SortedVec::from_borrowed(words, configuration);
+        sorted_vec.insert(Cow::Owned("kiwi".to_string()));
+
+        assert!(sorted_vec.iter().all(|word| matches!(word, Cow::Borrowed(_)) == (word != "kiwi")));
+        assert!(sorted_vec.find_index(&Cow::Borrowed("fig")).is_some());
+
+        let owned: SortedVec<String> = sorted_vec.into_owned();
+        assert!(owned.iter().eq(["pear", "kiwi", "fig", "apple"]));
+        assert_eq!(owned.configuration.order(), Order::Descending);
+    }
+}
@@ -0,0 +1,98 @@
+use std::cmp::Ordering;
+use std::ops::Deref;
+
+use crate::sorted_vec::{BucketConfiguration, SortedVec};
+
+/// Keeps only the first `capacity` elements in the configured order, i.e.
+/// the smallest ones, or the largest with
+/// [`Order::Descending`](crate::sorted_vec::Order::Descending). Once full,
+/// inserting an element that sorts before the current last one evicts it,
+/// while one equal to the last element is turned away and the element
+/// already kept stays. Reads go through `Deref`.
+#[derive(Debug)]
+pub struct BoundedSortedVec<T: PartialOrd + Ord> {
+    sorted_vec: SortedVec<T>,
+    capacity: usize,
+}
+
+impl<T: PartialOrd + Ord> BoundedSortedVec<T> {
+    pub fn new(capacity: usize, configuration: BucketConfiguration) -> Self {
+        BoundedSortedVec {
+            sorted_vec: SortedVec::new(configuration),
+            capacity,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.sorted_vec.size >= self.capacity
+    }
+
+    /// Inserts `item` and returns the element that no longer fits: the
+    /// evicted last element, or `item` itself if it does not sort before the
+    /// last one, which includes being equal to it.
+    /// Returns `None` if nothing was dropped, including when `item` is
+    /// rejected as a duplicate.
+    pub fn insert(&mut self, item: T) -> Option<T> {
+        if self.is_full() {
+            let order = self.sorted_vec.configuration.order();
+            match self.sorted_vec.last() {
+                Some(last) if order.compare(&item, last) == Ordering::Less => {},
+                _ => return Some(item),
+            }
+        }
+
+        self.sorted_vec.insert_position(item)?;
+        if self.sorted_vec.size > self.capacity {
+            return self.sorted_vec.pop_last();
+        }
+
+        None
+    }
+
+    pub fn into_inner(self) -> SortedVec<T> {
+        self.sorted_vec
+    }
+}
+
+impl<T: PartialOrd + Ord> Deref for BoundedSortedVec<T> {
+    type Target = SortedVec<T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.sorted_vec
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sorted_vec::Order;
+
+    #[test]
+    fn bounded_sorted_vec_keeps_smallest() {
+        let configuration = BucketConfiguration::builder().max_bucket_capacity(2).build();
+        let mut bounded = BoundedSortedVec::new(3, configuration);
+        let evicted: Vec<_> = [5, 8, 1, 9, 3, 2, 1].into_iter().filter_map(|item| bounded.insert(item)).collect();
+
+        assert!(bounded.is_full());
+        assert_eq!(bounded.iter().copied().collect::<Vec<_>>(), vec![1, 2, 3]);
+        assert_eq!(evicted, vec![9, 8, 5]);
+    }
+
+    #[test]
+    fn bounded_sorted_vec_keeps_largest_when_descending() {
+        let configuration = BucketConfiguration::builder().order(Order::Descending).build();
+        let mut bounded = BoundedSortedVec::new(2, configuration);
+        for item in [4, 7, 1, 9, 6] {
+            bounded.insert(item);
+        }
+
+        assert_eq!(bounded.iter().copied().collect::<Vec<_>>(), vec![9, 7]);
+        assert_eq!(bounded.insert(0), Some(0));
+        assert_eq!(bounded.insert(7), Some(7));
+        assert_eq!(BoundedSortedVec::new(0, Default::default()).insert(1), Some(1));
+    }
+}
@@ -1,47 +1,337 @@
 use std::cmp::Ordering;
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, OnceLock};
 use crate::AddResult;
+#[cfg(feature = "bloom-filter")]
+use crate::bloom::BucketBloom;
+use crate::builder::OutOfOrder;
+use crate::directory::Weighted;
+use crate::sorted_vec::{DuplicatePolicy, Order, TieBreak};
+use crate::storage::BucketStorage;
 
-#[derive(Default, Debug)]
-pub(crate) struct Bucket<T: PartialOrd> {
-    pub(crate) data: Vec<T>,
+/// The elements of a bucket, reference counted so snapshots can share them.
+/// Mutable access copies the elements only while a snapshot still holds
+/// them; an unshared bucket is written in place.
+#[derive(Debug)]
+pub(crate) struct BucketData<T, S = Vec<T>> {
+    items: Arc<SharedItems<S>>,
+    _elements: PhantomData<T>,
 }
 
+/// Bucket storage handed out to snapshots. `copy` is set by the first
+/// [`BucketData::share`], which is the only place `T: Clone` is known, and is
+/// used by the write path to detach from a snapshot.
+#[derive(Debug)]
+pub(crate) struct SharedItems<S> {
+    items: S,
+    copy: OnceLock<fn(&S) -> S>,
+}
+
+impl<S> SharedItems<S> {
+    pub(crate) fn new(items: S) -> Self {
+        SharedItems {
+            items,
+            copy: OnceLock::new(),
+        }
+    }
+}
+
+impl<S> Deref for SharedItems<S> {
+    type Target = S;
+
+    fn deref(&self) -> &Self::Target {
+        &self.items
+    }
+}
+
+fn copy_storage<T: Clone, S: BucketStorage<T>>(items: &S) -> S {
+    items.new_like(items.iter().cloned().collect())
+}
+
+impl<T, S: BucketStorage<T>> BucketData<T, S> {
+    fn from_storage(items: S) -> Self {
+        BucketData {
+            items: Arc::new(SharedItems::new(items)),
+            _elements: PhantomData,
+        }
+    }
+
+    /// Moves the elements out, leaving an empty storage of the same kind.
+    pub(crate) fn take(&mut self) -> Vec<T> {
+        let empty = self.items.new_like(Vec::new());
+        std::mem::replace(self, Self::from_storage(empty)).into_vec()
+    }
+
+    pub(crate) fn replace(&mut self, items: Vec<T>) {
+        let items = self.items.new_like(items);
+        *self = Self::from_storage(items);
+    }
+
+    /// Shares the elements without copying them.
+    pub(crate) fn share(&self) -> Arc<SharedItems<S>>
+    where
+        T: Clone,
+    {
+        self.items.copy.get_or_init(|| copy_storage::<T, S>);
+        Arc::clone(&self.items)
+    }
+
+    fn into_vec(self) -> Vec<T> {
+        match Arc::try_unwrap(self.items) {
+            Ok(shared) => shared.items.into_vec(),
+            Err(items) => detach(&items).into_vec(),
+        }
+    }
+}
+
+/// Copies storage still held by a snapshot. The hook is always set by then,
+/// since `share` is the only way to hand out a second reference.
+fn detach<S>(items: &SharedItems<S>) -> S {
+    let copy = items.copy.get().expect("shared bucket has a copy hook");
+    copy(&items.items)
+}
+
+impl<T, S: BucketStorage<T>> Default for BucketData<T, S> {
+    fn default() -> Self {
+        Vec::new().into()
+    }
+}
+
+impl<T, S> Deref for BucketData<T, S> {
+    type Target = S;
+
+    fn deref(&self) -> &Self::Target {
+        &self.items.items
+    }
+}
+
+impl<T, S> DerefMut for BucketData<T, S> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        if Arc::get_mut(&mut self.items).is_none() {
+            self.items = Arc::new(SharedItems::new(detach(&self.items)));
+        }
+
+        &mut Arc::get_mut(&mut self.items).expect("bucket storage is unshared").items
+    }
+}
+
+impl<T, S: BucketStorage<T>> From<Vec<T>> for BucketData<T, S> {
+    fn from(items: Vec<T>) -> Self {
+        Self::from_storage(S::from_vec(items))
+    }
+}
+
+impl<T, S: BucketStorage<T>> IntoIterator for BucketData<T, S> {
+    type Item = T;
+    type IntoIter = std::vec::IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.into_vec().into_iter()
+    }
+}
+
+impl<T: PartialEq, S: BucketStorage<T>> PartialEq<Vec<T>> for BucketData<T, S> {
+    fn eq(&self, other: &Vec<T>) -> bool {
+        self.iter().eq(other.iter())
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Bucket<T: PartialOrd, S = Vec<T>> {
+    pub(crate) data: BucketData<T, S>,
+    /// The bloom filter of the elements, `None` until it is built or after a
+    /// bulk change dropped it. A bucket without one may hold any element.
+    #[cfg(feature = "bloom-filter")]
+    pub(crate) bloom: Option<BucketBloom>,
+}
+
+#[cfg(test)]
 impl<T: PartialOrd + Ord> Bucket<T> {
     pub fn new(data: Vec<T>) -> Self {
-        Bucket { data }
+        Bucket::from_vec(data)
     }
 
     pub fn empty() -> Self {
-        Bucket { data: Vec::new() }
+        Bucket::default()
+    }
+}
+
+impl<T: PartialOrd + Ord, S: BucketStorage<T>> Default for Bucket<T, S> {
+    fn default() -> Self {
+        Bucket::from_vec(Vec::new())
+    }
+}
+
+impl<T: PartialOrd + Ord, S: BucketStorage<T>> Weighted for Bucket<T, S> {
+    fn weight(&self) -> usize {
+        self.len()
+    }
+}
+
+impl<T: PartialOrd + Ord, S: BucketStorage<T>> Bucket<T, S> {
+    pub(crate) fn from_vec(data: Vec<T>) -> Self {
+        Bucket {
+            data: data.into(),
+            #[cfg(feature = "bloom-filter")]
+            bloom: None,
+        }
     }
 
-    pub fn insert(&mut self, value: T) {
-        self.data.push(value);
+    /// Appends `item`, which must not sort before the last element. The
+    /// fast path for loaders that already know the element is the largest.
+    pub(crate) fn push_max(&mut self, item: T, order: Order) -> Result<(), OutOfOrder<T>> {
+        if self.data.last().is_some_and(|last| order.compare(last, &item) == Ordering::Greater) {
+            return Err(OutOfOrder(item));
+        }
+
+        self.data.push(item);
+        Ok(())
     }
 
     pub fn len(&self) -> usize {
         self.data.len()
     }
 
-    pub(crate) fn split(&mut self) -> Bucket<T> {
-        let curr_len = self.data.len();
-        let at = curr_len / 2;
+    /// Forgets the bloom filter after a bulk change, so the next index
+    /// refresh rebuilds it from the elements.
+    pub(crate) fn drop_bloom(&mut self) {
+        #[cfg(feature = "bloom-filter")]
+        {
+            self.bloom = None;
+        }
+    }
+
+    pub(crate) fn split_at(&mut self, at: usize) -> Bucket<T, S> {
         let other = self.data.split_off(at);
 
-        Bucket { data: other }
+        Bucket {
+            data: BucketData::from_storage(other),
+            #[cfg(feature = "bloom-filter")]
+            bloom: None,
+        }
     }
 
-    pub fn add(&mut self, item: T) -> AddResult {
-        match self.data.binary_search(&item) {
-            Ok(idx) => AddResult::Duplicated(idx),
-            Err(idx) => {
+    /// Like [`split_at`](Self::split_at), but the lower part moves to a new
+    /// allocation and the returned upper part keeps this one.
+    pub(crate) fn split_at_moving_lower(&mut self, at: usize) -> Bucket<T, S> {
+        let mut items = self.data.take();
+        let lower = items.drain(..at).collect();
+        self.data.replace(lower);
+
+        Bucket {
+            data: BucketData::from_storage(self.data.new_like(items)),
+            #[cfg(feature = "bloom-filter")]
+            bloom: None,
+        }
+    }
+
+    /// Splits the bucket into as few evenly sized pieces as fit in `capacity`,
+    /// keeping the first piece and returning the rest in order.
+    pub(crate) fn split_evenly(&mut self, capacity: usize) -> Vec<Bucket<T, S>> {
+        let len = self.data.len();
+        let pieces = len.div_ceil(capacity.max(1));
+        let mut rest = Vec::with_capacity(pieces.saturating_sub(1));
+        for piece in (1..pieces).rev() {
+            rest.push(self.split_at(len * piece / pieces));
+        }
+        rest.reverse();
+
+        rest
+    }
+
+    /// Merges an already sorted run into the bucket in one linear pass and
+    /// returns how many items were added. Equal elements are placed according
+    /// to `tie_break`, as if the incoming ones were inserted one by one.
+    /// Rejected duplicates are moved into `rejected` when given.
+    pub(crate) fn merge(
+        &mut self,
+        mut incoming: Vec<T>,
+        order: Order,
+        duplicate_policy: DuplicatePolicy,
+        tie_break: TieBreak,
+        mut rejected: Option<&mut Vec<T>>,
+    ) -> usize {
+        let lifo = duplicate_policy == DuplicatePolicy::Allow && tie_break == TieBreak::Lifo;
+        if lifo {
+            for run in incoming.chunk_by_mut(|a, b| order.compare(a, b) == Ordering::Equal) {
+                run.reverse();
+            }
+        }
+
+        let existing = self.data.take();
+        let mut merged = Vec::with_capacity(existing.len() + incoming.len());
+        let mut existing = existing.into_iter().peekable();
+        let mut added = 0;
+        let stays_ahead = if lifo { Ordering::Less } else { Ordering::Equal };
+
+        for item in incoming {
+            while let Some(current) = existing.next_if(|current| order.compare(current, &item) <= stays_ahead) {
+                merged.push(current);
+            }
+
+            let duplicated = merged
+                .last()
+                .is_some_and(|last| order.compare(last, &item) == Ordering::Equal);
+            if duplicated && duplicate_policy == DuplicatePolicy::Reject {
+                if let Some(rejected) = rejected.as_deref_mut() {
+                    rejected.push(item);
+                }
+                continue;
+            }
+
+            merged.push(item);
+            added += 1;
+        }
+        merged.extend(existing);
+        self.data.replace(merged);
+
+        added
+    }
+
+    pub fn search(&self, item: &T, order: Order) -> Result<usize, usize> {
+        self.data.binary_search_by(|probe| order.compare(probe, item))
+    }
+
+    pub fn add(&mut self, item: T, order: Order, duplicate_policy: DuplicatePolicy, tie_break: TieBreak) -> AddResult {
+        let appends = match self.data.last() {
+            Some(last) => matches!(
+                (order.compare(last, &item), duplicate_policy, tie_break),
+                (Ordering::Less, ..) | (Ordering::Equal, DuplicatePolicy::Allow, TieBreak::Fifo)
+            ),
+            None => true,
+        };
+        if appends {
+            self.data.push(item);
+            return AddResult::Added(self.data.len() - 1);
+        }
+
+        match (self.search(&item, order), duplicate_policy) {
+            (Ok(idx), DuplicatePolicy::Reject) => AddResult::Duplicated(idx),
+            (Ok(_), DuplicatePolicy::Allow) => {
+                let idx = match tie_break {
+                    TieBreak::Fifo => self.data.partition_point(|probe| order.compare(probe, &item) != Ordering::Greater),
+                    TieBreak::Lifo => self.data.partition_point(|probe| order.compare(probe, &item) == Ordering::Less),
+                };
+                #[cfg(feature = "metrics")]
+                crate::metrics::count(|stats| stats.shifts += (self.data.len() - idx) as u64);
+                self.data.insert(idx, item);
+                AddResult::Added(idx)
+            },
+            (Err(idx), _) => {
+                #[cfg(feature = "metrics")]
+                crate::metrics::count(|stats| stats.shifts += (self.data.len() - idx) as u64);
                 self.data.insert(idx, item);
                 AddResult::Added(idx)
             },
         }
     }
 
-    pub fn item_compare(&self, item: &T) -> Ordering {
+    /// Where `item` lies relative to the bucket's range: `Equal` inside it,
+    /// `Greater` if the bucket comes after the item and `Less` if it comes
+    /// before. An item in the gap between two buckets compares `Less` to the
+    /// left one and `Greater` to the right one; callers route it to the left.
+    pub fn item_compare(&self, item: &T, order: Order) -> Ordering {
         let first_item = match self.data.first() {
             Some(f) => f,
             None => return Ordering::Equal,
@@ -52,11 +342,11 @@ impl<T: PartialOrd + Ord> Bucket<T> {
             None => return Ordering::Equal,
         };
 
-        if item < first_item {
+        if order.compare(item, first_item) == Ordering::Less {
             return Ordering::Greater;
         }
 
-        if last_item < item {
+        if order.compare(last_item, item) == Ordering::Less {
             return Ordering::Less;
         }
 
@@ -69,26 +359,81 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_bucket_insert() {
+    fn test_bucket_push_max() {
         let mut bucket = Bucket::empty();
-        bucket.insert(1);
-        bucket.insert(2);
-        bucket.insert(3);
-        bucket.insert(4);
-        bucket.insert(5);
+        for item in [1, 2, 3, 3, 5] {
+            assert_eq!(bucket.push_max(item, Order::Ascending), Ok(()));
+        }
 
-        assert_eq!(bucket.data, vec![1, 2, 3, 4, 5]);
+        assert_eq!(bucket.push_max(4, Order::Ascending), Err(OutOfOrder(4)));
+        assert_eq!(bucket.push_max(6, Order::Descending), Err(OutOfOrder(6)));
+        assert_eq!(bucket.data, vec![1, 2, 3, 3, 5]);
     }
 
     #[test]
     fn test_bucket_split() {
         let mut bucket = Bucket::new(vec![1, 2, 3, 4, 5]);
-        let new_bucket = bucket.split();
+        let new_bucket = bucket.split_at(2);
 
         assert_eq!(bucket.data, vec![1, 2]);
         assert_eq!(new_bucket.data, vec![3, 4, 5]);
     }
 
+    #[test]
+    fn test_bucket_split_at() {
+        let mut bucket = Bucket::new(vec![1, 2, 3, 4, 5]);
+        let new_bucket = bucket.split_at(4);
+
+        assert_eq!(bucket.data, vec![1, 2, 3, 4]);
+        assert_eq!(new_bucket.data, vec![5]);
+    }
+
+    #[test]
+    fn bucket_split_evenly() {
+        let mut bucket = Bucket::new((0..10).collect());
+        let rest = bucket.split_evenly(3);
+
+        assert_eq!(bucket.data, vec![0, 1]);
+        assert_eq!(rest.iter().map(|b| b.data.to_vec()).collect::<Vec<_>>(), vec![vec![2, 3, 4], vec![5, 6], vec![7, 8, 9]]);
+    }
+
+    #[test]
+    fn bucket_merge_rejects_duplicates() {
+        let mut bucket = Bucket::new(vec![1, 3, 5]);
+        let added = bucket.merge(vec![0, 3, 4, 6], Order::Ascending, DuplicatePolicy::Reject, TieBreak::Fifo, None);
+
+        assert_eq!(added, 3);
+        assert_eq!(bucket.data, vec![0, 1, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn bucket_merge_allows_duplicates() {
+        let mut bucket = Bucket::new(vec![1, 3]);
+        let added = bucket.merge(vec![1, 3, 3], Order::Ascending, DuplicatePolicy::Allow, TieBreak::Fifo, None);
+
+        assert_eq!(added, 3);
+        assert_eq!(bucket.data, vec![1, 1, 3, 3, 3]);
+    }
+
+    #[test]
+    fn bucket_data_copies_only_while_shared() {
+        let mut bucket = Bucket::new(vec![1, 2, 3]);
+        let shared = bucket.data.share();
+        assert!(Arc::ptr_eq(&shared, &bucket.data.share()));
+
+        bucket.add(4, Order::Ascending, DuplicatePolicy::Reject, TieBreak::Fifo);
+        let updated = bucket.data.share();
+        assert!(!Arc::ptr_eq(&shared, &updated));
+        assert_eq!(**shared, vec![1, 2, 3]);
+        assert_eq!(**updated, vec![1, 2, 3, 4]);
+
+        drop(shared);
+        drop(updated);
+        let before = Arc::as_ptr(&bucket.data.items);
+        bucket.add(5, Order::Ascending, DuplicatePolicy::Reject, TieBreak::Fifo);
+        assert_eq!(Arc::as_ptr(&bucket.data.items), before);
+    }
+
     #[test]
     fn bucket_empty_has_no_elements() {
         let bucket = Bucket::<i32>::empty();
@@ -104,23 +449,57 @@ mod tests {
     #[test]
     fn bucket_add_inserts_in_sorted_order() {
         let mut bucket = Bucket::empty();
-        bucket.add(3);
-        bucket.add(1);
-        bucket.add(2);
+        bucket.add(3, Order::Ascending, DuplicatePolicy::Reject, TieBreak::Fifo);
+        bucket.add(1, Order::Ascending, DuplicatePolicy::Reject, TieBreak::Fifo);
+        bucket.add(2, Order::Ascending, DuplicatePolicy::Reject, TieBreak::Fifo);
         assert_eq!(bucket.data, vec![1, 2, 3]);
     }
 
     #[test]
     fn bucket_add_returns_correct_result() {
         let mut bucket = Bucket::empty();
-        assert_eq!(bucket.add(1), AddResult::Added(0));
-        assert_eq!(bucket.add(1), AddResult::Duplicated(0));
+        assert_eq!(bucket.add(1, Order::Ascending, DuplicatePolicy::Reject, TieBreak::Fifo), AddResult::Added(0));
+        assert_eq!(bucket.add(1, Order::Ascending, DuplicatePolicy::Reject, TieBreak::Fifo), AddResult::Duplicated(0));
+    }
+
+    #[test]
+    fn bucket_add_allows_duplicates() {
+        let mut bucket = Bucket::empty();
+        assert_eq!(bucket.add(1, Order::Ascending, DuplicatePolicy::Allow, TieBreak::Fifo), AddResult::Added(0));
+        assert_eq!(bucket.add(1, Order::Ascending, DuplicatePolicy::Allow, TieBreak::Fifo), AddResult::Added(1));
+        assert_eq!(bucket.data, vec![1, 1]);
+    }
+
+    #[test]
+    fn bucket_add_lifo_goes_before_equals() {
+        let mut bucket = Bucket::new(vec![1, 1, 2]);
+        assert_eq!(bucket.add(1, Order::Ascending, DuplicatePolicy::Allow, TieBreak::Lifo), AddResult::Added(0));
+        assert_eq!(bucket.add(1, Order::Ascending, DuplicatePolicy::Allow, TieBreak::Fifo), AddResult::Added(3));
+        assert_eq!(bucket.add(2, Order::Ascending, DuplicatePolicy::Allow, TieBreak::Lifo), AddResult::Added(4));
+    }
+
+    #[test]
+    fn bucket_merge_lifo_reverses_equal_runs() {
+        let mut bucket = Bucket::new(vec![1, 2]);
+        let added = bucket.merge(vec![2, 2, 3], Order::Ascending, DuplicatePolicy::Allow, TieBreak::Lifo, None);
+
+        assert_eq!(added, 3);
+        assert_eq!(bucket.data, vec![1, 2, 2, 2, 3]);
+    }
+
+    #[test]
+    fn bucket_add_descending_order() {
+        let mut bucket = Bucket::empty();
+        bucket.add(1, Order::Descending, DuplicatePolicy::Reject, TieBreak::Fifo);
+        bucket.add(3, Order::Descending, DuplicatePolicy::Reject, TieBreak::Fifo);
+        bucket.add(2, Order::Descending, DuplicatePolicy::Reject, TieBreak::Fifo);
+        assert_eq!(bucket.data, vec![3, 2, 1]);
     }
 
     #[test]
     fn bucket_split_on_empty_bucket() {
         let mut bucket = Bucket::<i32>::empty();
-        let new_bucket = bucket.split();
+        let new_bucket = bucket.split_at(0);
         assert_eq!(bucket.data, vec![]);
         assert_eq!(new_bucket.data, vec![]);
     }
@@ -128,18 +507,18 @@ mod tests {
     #[test]
     fn bucket_item_compare_less_than_first() {
         let bucket = Bucket::new(vec![2, 3, 4]);
-        assert_eq!(bucket.item_compare(&1), Ordering::Greater);
+        assert_eq!(bucket.item_compare(&1, Order::Ascending), Ordering::Greater);
     }
 
     #[test]
     fn bucket_item_compare_greater_than_last() {
         let bucket = Bucket::new(vec![2, 3, 4]);
-        assert_eq!(bucket.item_compare(&5), Ordering::Less);
+        assert_eq!(bucket.item_compare(&5, Order::Ascending), Ordering::Less);
     }
 
     #[test]
     fn bucket_item_compare_within_range() {
         let bucket = Bucket::new(vec![2, 3, 4]);
-        assert_eq!(bucket.item_compare(&3), Ordering::Equal);
+        assert_eq!(bucket.item_compare(&3, Order::Ascending), Ordering::Equal);
     }
 }
\ No newline at end of file
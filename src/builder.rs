@@ -0,0 +1,107 @@
+use std::cmp::Ordering;
+use std::error::Error;
+use std::fmt::{self, Debug, Display, Formatter};
+
+use crate::bucket::Bucket;
+use crate::sorted_vec::{BucketConfiguration, DuplicatePolicy, SortedVec};
+
+/// Returned by [`SortedVecBuilder::push`] with the element that sorted
+/// before the previously pushed one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutOfOrder<T>(pub T);
+
+impl<T> Display for OutOfOrder<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("element pushed out of order")
+    }
+}
+
+impl<T: Debug> Error for OutOfOrder<T> {}
+
+/// Builds a [`SortedVec`] from elements that already arrive in the
+/// configured order. Each element is appended to the last bucket, which is
+/// closed once full, so nothing is searched or shifted.
+#[derive(Debug)]
+pub struct SortedVecBuilder<T: PartialOrd + Ord> {
+    sorted_vec: SortedVec<T>,
+}
+
+impl<T: PartialOrd + Ord> SortedVecBuilder<T> {
+    pub fn new(configuration: BucketConfiguration) -> Self {
+        SortedVecBuilder {
+            sorted_vec: SortedVec::new(configuration),
+        }
+    }
+
+    /// Appends `item`, which must not sort before the previous element.
+    /// Elements equal to the previous one are dropped under
+    /// [`DuplicatePolicy::Reject`].
+    pub fn push(&mut self, item: T) -> Result<(), OutOfOrder<T>> {
+        let configuration = &self.sorted_vec.configuration;
+        if let Some(last) = self.sorted_vec.last() {
+            match configuration.order().compare(last, &item) {
+                Ordering::Greater => return Err(OutOfOrder(item)),
+                Ordering::Equal if configuration.duplicate_policy() == DuplicatePolicy::Reject => return Ok(()),
+                _ => {},
+            }
+        }
+
+        let capacity = configuration.bucket_capacity(self.sorted_vec.size + 1);
+        if self.sorted_vec.buckets.last().is_some_and(|bucket| bucket.len() >= capacity) {
+            self.sorted_vec.buckets.push(Bucket::default());
+        }
+        if let Some(bucket) = self.sorted_vec.buckets.last_mut() {
+            bucket.push_max(item, configuration.order())?;
+        }
+        self.sorted_vec.size += 1;
+
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.sorted_vec.size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn finish(mut self) -> SortedVec<T> {
+        self.sorted_vec.rebuild_indexes();
+        self.sorted_vec
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sorted_vec::Order;
+
+    #[test]
+    fn builder_fills_buckets_sequentially() {
+        let configuration = BucketConfiguration::builder().max_bucket_capacity(4).build();
+        let mut builder = SortedVecBuilder::new(configuration);
+        for item in [1, 2, 2, 3, 5, 8, 13, 21, 34] {
+            builder.push(item).unwrap();
+        }
+        assert_eq!(builder.push(4), Err(OutOfOrder(4)));
+        assert_eq!(builder.len(), 8);
+
+        let mut sorted_vec = builder.finish();
+        assert_eq!(sorted_vec.buckets.iter().map(|bucket| bucket.len()).collect::<Vec<_>>(), vec![4, 4]);
+        assert!(sorted_vec.find_index(&13).is_some());
+        sorted_vec.insert(4);
+        assert_eq!(sorted_vec.iter().copied().collect::<Vec<_>>(), vec![1, 2, 3, 4, 5, 8, 13, 21, 34]);
+    }
+
+    #[test]
+    fn builder_follows_configured_order() {
+        let configuration = BucketConfiguration::builder().order(Order::Descending).build();
+        let mut builder = SortedVecBuilder::new(configuration);
+        builder.push(3).unwrap();
+        builder.push(1).unwrap();
+
+        assert!(builder.push(2).is_err());
+        assert_eq!(builder.finish().first(), Some(&3));
+    }
+}
@@ -0,0 +1,229 @@
+use std::cmp::Ordering;
+use std::io::{self, Error, ErrorKind, Read, Write};
+
+use crate::bucket::Bucket;
+use crate::sorted_vec::{BucketConfiguration, DuplicatePolicy, SortedVec};
+use crate::storage::BucketStorage;
+
+const MAGIC: &[u8; 4] = b"SVEC";
+
+/// Upper bound on the elements reserved up front while reading. The element
+/// count comes from the stream and can't be trusted, so buckets grow as
+/// elements actually arrive past this point.
+const MAX_PREALLOCATED: usize = 1024;
+
+/// Minimal binary encoding used by [`SortedVec::write_to`] and
+/// [`SortedVec::read_from`]. Variable sized values are length-prefixed.
+pub trait Codec: Sized {
+    fn encode<W: Write>(&self, out: &mut W) -> io::Result<()>;
+
+    fn decode<R: Read>(input: &mut R) -> io::Result<Self>;
+}
+
+macro_rules! impl_codec {
+    ($($ty:ty),*) => {
+        $(
+            impl Codec for $ty {
+                fn encode<W: Write>(&self, out: &mut W) -> io::Result<()> {
+                    out.write_all(&self.to_le_bytes())
+                }
+
+                fn decode<R: Read>(input: &mut R) -> io::Result<Self> {
+                    let mut raw = [0; size_of::<$ty>()];
+                    input.read_exact(&mut raw)?;
+                    Ok(<$ty>::from_le_bytes(raw))
+                }
+            }
+        )*
+    };
+}
+
+impl_codec!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
+
+impl Codec for bool {
+    fn encode<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        (*self as u8).encode(out)
+    }
+
+    fn decode<R: Read>(input: &mut R) -> io::Result<Self> {
+        match u8::decode(input)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(invalid("invalid bool")),
+        }
+    }
+}
+
+impl Codec for String {
+    fn encode<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        (self.len() as u64).encode(out)?;
+        out.write_all(self.as_bytes())
+    }
+
+    fn decode<R: Read>(input: &mut R) -> io::Result<Self> {
+        let len = u64::decode(input)?;
+        let mut bytes = Vec::new();
+        input.take(len).read_to_end(&mut bytes)?;
+        if bytes.len() as u64 != len {
+            return Err(Error::from(ErrorKind::UnexpectedEof));
+        }
+
+        String::from_utf8(bytes).map_err(|_| invalid("invalid utf-8"))
+    }
+}
+
+impl<A: Codec, B: Codec> Codec for (A, B) {
+    fn encode<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        self.0.encode(out)?;
+        self.1.encode(out)
+    }
+
+    fn decode<R: Read>(input: &mut R) -> io::Result<Self> {
+        Ok((A::decode(input)?, B::decode(input)?))
+    }
+}
+
+fn invalid(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
+
+impl<T: PartialOrd + Ord + Codec, S: BucketStorage<T>> SortedVec<T, S> {
+    /// Streams the elements in order, prefixed by a magic tag and the element
+    /// count. The configuration is not stored.
+    pub fn write_to<W: Write>(&self, mut out: W) -> io::Result<()> {
+        out.write_all(MAGIC)?;
+        (self.size as u64).encode(&mut out)?;
+        for bucket in &self.buckets {
+            for item in bucket.data.iter() {
+                item.encode(&mut out)?;
+            }
+        }
+
+        out.flush()
+    }
+
+    /// Reads what [`write_to`](Self::write_to) wrote, filling buckets in
+    /// order without searching or sorting. Fails with `InvalidData` if the
+    /// elements are not sorted for `configuration`.
+    pub fn read_from<R: Read>(mut input: R, configuration: BucketConfiguration) -> io::Result<Self> {
+        let mut magic = [0; 4];
+        input.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid("not a sorted vec stream"));
+        }
+
+        let len = usize::try_from(u64::decode(&mut input)?).map_err(|_| invalid("element count does not fit in memory"))?;
+        let order = configuration.order();
+        let duplicate_policy = configuration.duplicate_policy();
+        let capacity = configuration.bucket_capacity(len);
+        let preallocated = capacity.min(len).min(MAX_PREALLOCATED);
+        let mut result = Self::with_storage(configuration);
+        result.buckets.clear();
+
+        let mut current: Vec<T> = Vec::with_capacity(preallocated);
+        for _ in 0..len {
+            let item = T::decode(&mut input)?;
+            let last = current
+                .last()
+                .or_else(|| result.buckets.last().and_then(|bucket| bucket.data.last()));
+            match last.map(|last| order.compare(last, &item)) {
+                Some(Ordering::Greater) => return Err(invalid("elements are not sorted")),
+                Some(Ordering::Equal) if duplicate_policy == DuplicatePolicy::Reject => {
+                    return Err(invalid("duplicated element"));
+                },
+                _ => {},
+            }
+
+            current.push(item);
+            if current.len() == capacity {
+                let full = std::mem::replace(&mut current, Vec::with_capacity(preallocated));
+                result.buckets.push(Bucket::from_vec(full));
+            }
+        }
+
+        if !current.is_empty() || result.buckets.is_empty() {
+            result.buckets.push(Bucket::from_vec(current));
+        }
+        result.size = len;
+
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn codec_round_trip() {
+        let mut bytes = Vec::new();
+        (42u32, "LAX".to_string()).encode(&mut bytes).unwrap();
+        true.encode(&mut bytes).unwrap();
+        (-7i64).encode(&mut bytes).unwrap();
+
+        let mut input = Cursor::new(bytes);
+        assert_eq!(<(u32, String)>::decode(&mut input).unwrap(), (42, "LAX".to_string()));
+        assert!(bool::decode(&mut input).unwrap());
+        assert_eq!(i64::decode(&mut input).unwrap(), -7);
+    }
+
+    #[test]
+    fn sorted_vec_write_and_read() {
+        let config = BucketConfiguration::builder().max_bucket_capacity(4).build();
+        let sorted_vec = SortedVec::from_vec((0..25u64).rev().collect(), config.clone());
+        let mut bytes = Vec::new();
+        sorted_vec.write_to(&mut bytes).unwrap();
+
+        let read: SortedVec<u64> = SortedVec::read_from(bytes.as_slice(), config).unwrap();
+        assert_eq!(read.size, 25);
+        assert!(read.buckets.iter().all(|bucket| bucket.len() <= 4));
+        assert!(read.iter().eq(sorted_vec.iter()));
+    }
+
+    #[test]
+    fn sorted_vec_read_empty() {
+        let sorted_vec: SortedVec<String> = SortedVec::new(Default::default());
+        let mut bytes = Vec::new();
+        sorted_vec.write_to(&mut bytes).unwrap();
+
+        let mut read: SortedVec<String> = SortedVec::read_from(bytes.as_slice(), Default::default()).unwrap();
+        assert_eq!(read.size, 0);
+        read.insert("JFK".to_string());
+        assert_eq!(read.first().map(String::as_str), Some("JFK"));
+    }
+
+    #[test]
+    fn sorted_vec_read_rejects_unsorted_input() {
+        let mut bytes = MAGIC.to_vec();
+        for value in [3u64, 1, 3, 2] {
+            value.encode(&mut bytes).unwrap();
+        }
+
+        let error = SortedVec::<u64>::read_from(bytes.as_slice(), Default::default()).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn sorted_vec_read_truncated_input() {
+        let sorted_vec = SortedVec::from_vec(vec![1u32, 2, 3], Default::default());
+        let mut bytes = Vec::new();
+        sorted_vec.write_to(&mut bytes).unwrap();
+        bytes.truncate(bytes.len() - 1);
+
+        let error = SortedVec::<u32>::read_from(bytes.as_slice(), Default::default()).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn sorted_vec_read_does_not_trust_the_element_count() {
+        let config = BucketConfiguration::builder().max_bucket_capacity(usize::MAX).build();
+        let mut bytes = MAGIC.to_vec();
+        (u64::MAX >> 8).encode(&mut bytes).unwrap();
+        1u64.encode(&mut bytes).unwrap();
+
+        let error = SortedVec::<u64>::read_from(bytes.as_slice(), config).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+    }
+}
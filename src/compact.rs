@@ -0,0 +1,128 @@
+use std::cmp::min;
+use std::mem::size_of;
+
+use crate::bucket::Bucket;
+use crate::sorted_vec::SortedVec;
+use crate::storage::BucketStorage;
+
+/// What [`shrink_buckets_to`](SortedVec::shrink_buckets_to) reclaimed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Compaction {
+    pub buckets_merged: usize,
+    /// Bucket headers plus unused element slots freed, going by
+    /// [`BucketStorage::capacity`].
+    pub bytes_reclaimed: usize,
+}
+
+impl<T: PartialOrd + Ord, S: BucketStorage<T>> SortedVec<T, S> {
+    /// Merges every bucket holding less than `min_fill_percent` of the bucket
+    /// capacity into its smaller neighbour, as long as the result still fits
+    /// the capacity. Only underfilled buckets and their neighbours are
+    /// touched, so it is cheap enough for routine maintenance.
+    pub fn shrink_buckets_to(&mut self, min_fill_percent: u8) -> Compaction {
+        let capacity = self.configuration.bucket_capacity(self.size);
+        let threshold = capacity * usize::from(min_fill_percent.min(100)) / 100;
+        let mut compaction = Compaction::default();
+
+        let mut bucket_idx = 0;
+        while bucket_idx < self.buckets.len() {
+            let len = self.buckets[bucket_idx].len();
+            let fits = |neighbour: usize| self.buckets.get(neighbour).is_some_and(|bucket| bucket.len() + len <= capacity);
+            let prev = bucket_idx.checked_sub(1).filter(|&prev| fits(prev));
+            let next = Some(bucket_idx + 1).filter(|&next| fits(next));
+            let target = match (prev, next) {
+                _ if len >= threshold => None,
+                (Some(prev), Some(next)) if self.buckets[next].len() < self.buckets[prev].len() => Some(next),
+                (prev, next) => prev.or(next),
+            };
+            let Some(target) = target else {
+                bucket_idx += 1;
+                continue;
+            };
+
+            // The merged bucket is looked at again, in case it is still
+            // underfilled.
+            bucket_idx = min(bucket_idx, target);
+            compaction.bytes_reclaimed += self.merge_with_next(bucket_idx);
+            compaction.buckets_merged += 1;
+        }
+
+        if compaction.buckets_merged > 0 {
+            self.bump_generation();
+            self.rebuild_fences();
+            #[cfg(feature = "bloom-filter")]
+            self.rebuild_stale_blooms();
+        }
+
+        compaction
+    }
+
+    /// Moves the elements of the bucket after `bucket_idx` into it and
+    /// returns the bytes freed.
+    fn merge_with_next(&mut self, bucket_idx: usize) -> usize {
+        let next = self.buckets.remove(bucket_idx + 1);
+        let bucket = &mut self.buckets[bucket_idx];
+        let before = bucket.data.capacity() + next.data.capacity();
+
+        let mut items = Vec::with_capacity(bucket.len() + next.len());
+        items.append(&mut bucket.data.take());
+        items.extend(next.data);
+        bucket.data.replace(items);
+        bucket.drop_bloom();
+
+        before.saturating_sub(bucket.data.capacity()) * size_of::<T>() + size_of::<Bucket<T, S>>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sorted_vec::BucketConfiguration;
+
+    fn buckets(sorted_vec: &SortedVec<i32>) -> Vec<Vec<i32>> {
+        sorted_vec.buckets.iter().map(|bucket| bucket.data.to_vec()).collect()
+    }
+
+    #[test]
+    fn shrink_buckets_to_merges_underfilled_buckets() {
+        let configuration = BucketConfiguration::builder().max_bucket_capacity(8).build();
+        let mut sorted_vec = SortedVec::new(configuration);
+        sorted_vec.buckets = vec![
+            Bucket::from_vec(vec![1]),
+            Bucket::from_vec(vec![2, 3]),
+            Bucket::from_vec((4..12).collect()),
+            Bucket::from_vec(vec![12, 13, 14, 15, 16]),
+            Bucket::from_vec(vec![17]),
+            Bucket::from_vec(vec![18, 19, 20, 21, 22, 23, 24]),
+        ]
+        .into();
+        sorted_vec.size = 24;
+
+        let compaction = sorted_vec.shrink_buckets_to(50);
+        assert_eq!(compaction.buckets_merged, 2);
+        assert!(compaction.bytes_reclaimed >= 2 * size_of::<Bucket<i32>>());
+        assert_eq!(
+            buckets(&sorted_vec),
+            vec![vec![1, 2, 3], (4..12).collect(), vec![12, 13, 14, 15, 16, 17], (18..25).collect()]
+        );
+        assert_eq!(sorted_vec.check_integrity(), Ok(()));
+
+        assert_eq!(sorted_vec.shrink_buckets_to(50), Compaction::default());
+        assert_eq!(SortedVec::<i32>::new(Default::default()).shrink_buckets_to(100), Compaction::default());
+    }
+
+    #[test]
+    fn shrink_buckets_to_after_mass_removal() {
+        let configuration = BucketConfiguration::builder().max_bucket_capacity(16).build();
+        let mut sorted_vec = SortedVec::from_vec((0..1_000).collect(), configuration).with_fence_index();
+        sorted_vec.retain_map(|item| (item % 10 == 0).then_some(item));
+        let before = sorted_vec.buckets.len();
+
+        let compaction = sorted_vec.shrink_buckets_to(75);
+        assert_eq!(sorted_vec.buckets.len(), before - compaction.buckets_merged);
+        assert!(sorted_vec.buckets.len() < before / 4);
+        assert!(sorted_vec.iter().copied().eq((0..1_000).step_by(10)));
+        assert_eq!(sorted_vec.find_index(&500).map(|found| sorted_vec.get_found(&found).copied()), Some(Some(500)));
+        assert_eq!(sorted_vec.check_integrity(), Ok(()));
+    }
+}
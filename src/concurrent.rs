@@ -0,0 +1,234 @@
+use std::cmp::Ordering;
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use crate::sorted_vec::{BucketConfiguration, SortedVec};
+
+type Shard<T> = Arc<RwLock<SortedVec<T>>>;
+
+/// A sorted container that can be shared between threads. Elements are split
+/// into shards covering contiguous key ranges, each behind its own `RwLock`,
+/// so writers touching different ranges don't block each other.
+///
+/// Lock ordering: the shard directory is always locked before any shard, and
+/// shards are locked in ascending order. Splitting or dropping a shard needs
+/// the directory write lock, which is only taken after every shard lock has
+/// been released.
+#[derive(Debug)]
+pub struct ShardedSortedVec<T: PartialOrd + Ord> {
+    directory: RwLock<Vec<(Option<T>, Shard<T>)>>,
+    configuration: BucketConfiguration,
+    max_shard_len: usize,
+}
+
+impl<T: PartialOrd + Ord + Clone> ShardedSortedVec<T> {
+    /// `max_shard_len` is the element count above which a shard is split in two.
+    pub fn new(configuration: BucketConfiguration, max_shard_len: usize) -> Self {
+        let shard = Arc::new(RwLock::new(SortedVec::new(configuration.clone())));
+
+        ShardedSortedVec {
+            directory: RwLock::new(vec![(None, shard)]),
+            configuration,
+            max_shard_len: max_shard_len.max(2),
+        }
+    }
+
+    pub fn insert(&self, item: T) {
+        let needs_split = {
+            let directory = read(&self.directory);
+            let idx = self.shard_index(&directory, &item);
+            let mut shard = write(&directory[idx].1);
+            shard.insert(item);
+            shard.size > self.max_shard_len
+        };
+
+        if needs_split {
+            self.split_oversized_shards();
+        }
+    }
+
+    pub fn remove(&self, item: &T) {
+        let emptied = {
+            let directory = read(&self.directory);
+            let idx = self.shard_index(&directory, item);
+            let mut shard = write(&directory[idx].1);
+            shard.remove(item);
+            shard.size == 0 && directory.len() > 1
+        };
+
+        if emptied {
+            self.drop_empty_shards();
+        }
+    }
+
+    pub fn contains(&self, item: &T) -> bool {
+        let directory = read(&self.directory);
+        let idx = self.shard_index(&directory, item);
+        let shard = read(&directory[idx].1);
+        shard.find_index(item).is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        let directory = read(&self.directory);
+        directory.iter().map(|(_, shard)| read(shard).size).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn shard_count(&self) -> usize {
+        read(&self.directory).len()
+    }
+
+    /// Copies out a consistent view of every element in order. All shards are
+    /// read-locked together, so concurrent writers can't tear the snapshot.
+    pub fn snapshot(&self) -> Vec<T> {
+        let directory = read(&self.directory);
+        let shards: Vec<_> = directory.iter().map(|(_, shard)| read(shard)).collect();
+        let mut result = Vec::with_capacity(shards.iter().map(|shard| shard.size).sum());
+        for shard in &shards {
+            result.extend(shard.iter().cloned());
+        }
+
+        result
+    }
+
+    /// Shard `i` holds the elements from its fence (inclusive) up to the next
+    /// shard's fence (exclusive). The first shard has no lower fence.
+    fn shard_index(&self, directory: &[(Option<T>, Shard<T>)], item: &T) -> usize {
+        let order = self.configuration.order();
+        let idx = directory.partition_point(|(fence, _)| {
+            fence.as_ref().is_none_or(|fence| order.compare(fence, item) != Ordering::Greater)
+        });
+
+        idx.saturating_sub(1)
+    }
+
+    fn split_oversized_shards(&self) {
+        let mut directory = write(&self.directory);
+        let mut idx = 0;
+        while idx < directory.len() {
+            let upper = {
+                let mut shard = write(&directory[idx].1);
+                if shard.size <= self.max_shard_len {
+                    None
+                } else {
+                    split_upper_half(&mut shard)
+                }
+            };
+
+            match upper {
+                Some(upper) => {
+                    let fence = upper.first().cloned();
+                    directory.insert(idx + 1, (fence, Arc::new(RwLock::new(upper))));
+                },
+                None => idx += 1,
+            }
+        }
+    }
+
+    fn drop_empty_shards(&self) {
+        let mut directory = write(&self.directory);
+        let mut idx = 0;
+        while idx < directory.len() && directory.len() > 1 {
+            if read(&directory[idx].1).size == 0 {
+                let (fence, _) = directory.remove(idx);
+                // The next shard takes over the dropped range.
+                if let Some(next) = directory.get_mut(idx) {
+                    next.0 = fence;
+                }
+            } else {
+                idx += 1;
+            }
+        }
+    }
+}
+
+/// Moves the upper half of the elements into a new container.
+fn split_upper_half<T: PartialOrd + Ord>(sorted_vec: &mut SortedVec<T>) -> Option<SortedVec<T>> {
+    if sorted_vec.buckets.len() < 2 {
+        let half = sorted_vec.buckets[0].len() / 2;
+        let upper_bucket = sorted_vec.buckets[0].split_at(half);
+        sorted_vec.buckets.push(upper_bucket);
+    }
+
+    let mut upper = SortedVec::new(sorted_vec.configuration.clone());
+    upper.buckets = sorted_vec.buckets.split_off(sorted_vec.buckets.len() / 2);
+    upper.size = upper.buckets.iter().map(|bucket| bucket.len()).sum();
+    sorted_vec.size -= upper.size;
+    sorted_vec.refresh_indexes();
+    sorted_vec.bump_generation();
+
+    (upper.size > 0).then_some(upper)
+}
+
+/// A panic while a lock is held can't leave a shard half-mutated in a way the
+/// other operations care about, so poisoning is ignored.
+fn read<L>(lock: &RwLock<L>) -> RwLockReadGuard<'_, L> {
+    lock.read().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn write<L>(lock: &RwLock<L>) -> RwLockWriteGuard<'_, L> {
+    lock.write().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn sharded_sorted_vec_insert_and_contains() {
+        let sharded = ShardedSortedVec::new(BucketConfiguration::default(), 4);
+        for item in [5, 3, 9, 1, 7, 3, 2, 8] {
+            sharded.insert(item);
+        }
+
+        assert_eq!(sharded.len(), 7);
+        assert!(sharded.shard_count() > 1);
+        assert!(sharded.contains(&9));
+        assert!(!sharded.contains(&4));
+        assert_eq!(sharded.snapshot(), vec![1, 2, 3, 5, 7, 8, 9]);
+    }
+
+    #[test]
+    fn sharded_sorted_vec_remove_drops_empty_shards() {
+        let sharded = ShardedSortedVec::new(BucketConfiguration::default(), 2);
+        for item in 0..10 {
+            sharded.insert(item);
+        }
+        for item in 0..8 {
+            sharded.remove(&item);
+        }
+
+        assert_eq!(sharded.snapshot(), vec![8, 9]);
+        sharded.insert(0);
+        assert_eq!(sharded.snapshot(), vec![0, 8, 9]);
+        assert!(sharded.contains(&0));
+    }
+
+    #[test]
+    fn sharded_sorted_vec_concurrent_writers() {
+        let sharded = ShardedSortedVec::new(BucketConfiguration::default(), 64);
+        thread::scope(|scope| {
+            for writer in 0..4 {
+                let sharded = &sharded;
+                scope.spawn(move || {
+                    for item in 0..1000 {
+                        sharded.insert(item * 4 + writer);
+                    }
+                });
+            }
+            scope.spawn(|| {
+                for _ in 0..10 {
+                    let snapshot = sharded.snapshot();
+                    assert!(snapshot.windows(2).all(|pair| pair[0] < pair[1]));
+                }
+            });
+        });
+
+        assert_eq!(sharded.len(), 4000);
+        assert_eq!(sharded.snapshot(), (0..4000).collect::<Vec<_>>());
+    }
+}
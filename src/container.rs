@@ -0,0 +1,136 @@
+use std::ops::RangeBounds;
+
+use crate::iter::SortedVecIter;
+use crate::sorted_vec::SortedVec;
+use crate::storage::BucketStorage;
+
+/// The operations every sorted container in the crate supports, so code can
+/// be written once and benchmarked against each backend.
+pub trait SortedContainer<T> {
+    type Range<'a>: Iterator<Item = &'a T>
+    where
+        Self: 'a,
+        T: 'a;
+
+    /// Adds `item` and returns whether it was stored. A duplicate rejected by
+    /// the container is dropped and `false` is returned.
+    fn insert(&mut self, item: T) -> bool;
+
+    /// Removes one element equal to `item` and returns whether there was one.
+    fn remove(&mut self, item: &T) -> bool;
+
+    fn contains(&self, item: &T) -> bool;
+
+    /// The elements inside `range`, in order.
+    fn range<R: RangeBounds<T>>(&self, range: R) -> Self::Range<'_>;
+
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T: PartialOrd + Ord, S: BucketStorage<T>> SortedContainer<T> for SortedVec<T, S> {
+    type Range<'a>
+        = SortedVecIter<'a, T, S>
+    where
+        Self: 'a,
+        T: 'a;
+
+    fn insert(&mut self, item: T) -> bool {
+        self.insert_position(item).is_some()
+    }
+
+    fn remove(&mut self, item: &T) -> bool {
+        self.remove_position(item).is_some()
+    }
+
+    fn contains(&self, item: &T) -> bool {
+        self.find_index(item).is_some()
+    }
+
+    fn range<R: RangeBounds<T>>(&self, range: R) -> Self::Range<'_> {
+        SortedVec::range(self, range)
+    }
+
+    fn len(&self) -> usize {
+        self.size
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::sorted_vec::BucketConfiguration;
+
+    /// The pseudo-random generator behind the model tests, so runs are
+    /// reproducible without a dependency.
+    pub(crate) struct Lcg(u32);
+
+    impl Lcg {
+        pub(crate) fn new(seed: u32) -> Self {
+            Lcg(seed)
+        }
+
+        /// The next value below `bound`.
+        pub(crate) fn below(&mut self, bound: usize) -> usize {
+            self.0 = self.0.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            (self.0 >> 8) as usize % bound
+        }
+    }
+
+    /// A step of a workload checking a positional container against `Vec`.
+    #[derive(Debug, Clone, Copy)]
+    pub(crate) enum VecStep {
+        Insert(usize, i32),
+        Remove(usize),
+    }
+
+    /// `steps` inserts and removals at random positions of a sequence that
+    /// starts empty, every `remove_every`th step a removal while there is
+    /// something to remove. Inserted values are the step numbers.
+    pub(crate) fn vec_steps(seed: u32, steps: i32, remove_every: i32) -> impl Iterator<Item = VecStep> {
+        let mut rng = Lcg::new(seed);
+        let mut len = 0;
+        (0..steps).map(move |step| {
+            let at = rng.below(len + 1);
+            if step % remove_every == remove_every - 1 && len > 0 {
+                len -= 1;
+                VecStep::Remove(at.min(len))
+            } else {
+                len += 1;
+                VecStep::Insert(at, step)
+            }
+        })
+    }
+
+    /// Runs the same workload against `container` and checks it behaves like a
+    /// `BTreeSet`. Shared with the other backends' tests.
+    pub(crate) fn behaves_like_a_set(mut container: impl SortedContainer<u32>) {
+        let mut model = std::collections::BTreeSet::new();
+        let mut rng = Lcg::new(29);
+        for step in 0..500 {
+            let item = rng.below(100) as u32;
+            if step % 3 == 2 {
+                assert_eq!(container.remove(&item), model.remove(&item));
+            } else {
+                assert_eq!(container.insert(item), model.insert(item));
+            }
+            assert_eq!(container.contains(&item), model.contains(&item));
+            assert_eq!(container.len(), model.len());
+        }
+
+        assert!(container.range(..).eq(model.range(..)));
+        assert!(container.range(20..60).eq(model.range(20..60)));
+        assert!(container.range(40..=40).eq(model.range(40..=40)));
+        assert!(container.range(95..).eq(model.range(95..)));
+        assert_eq!(container.is_empty(), model.is_empty());
+    }
+
+    #[test]
+    fn sorted_vec_is_a_sorted_container() {
+        let config = BucketConfiguration::builder().max_bucket_capacity(4).build();
+        behaves_like_a_set(SortedVec::new(config));
+    }
+}
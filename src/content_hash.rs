@@ -0,0 +1,196 @@
+use std::hash::{Hash, Hasher};
+
+use crate::iter::SortedVecIter;
+use crate::sorted_vec::SortedVec;
+use crate::storage::BucketStorage;
+
+/// The wrapping sum of a hash of every element, so an insert or removal
+/// updates it with one element hash and the result does not depend on how
+/// the elements are split into buckets.
+pub(crate) struct ContentHash<T> {
+    sum: u64,
+    element_hash: fn(&T) -> u64,
+}
+
+fn element_hash<T: Hash>(item: &T) -> u64 {
+    let mut hasher = Fnv1a::default();
+    item.hash(&mut hasher);
+    hasher.finish()
+}
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// 64-bit FNV-1a over the bytes fed by `Hash`, starting from the standard
+/// offset basis. Integers are fed little-endian and `usize`/`isize` as 64-bit
+/// values, so the result is the same on every platform and Rust release.
+struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Fnv1a(FNV_OFFSET_BASIS)
+    }
+}
+
+macro_rules! fnv_write_le {
+    ($($method:ident: $ty:ty),*) => {
+        $(
+            fn $method(&mut self, value: $ty) {
+                self.write(&value.to_le_bytes());
+            }
+        )*
+    };
+}
+
+impl Hasher for Fnv1a {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ u64::from(byte)).wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    fnv_write_le!(
+        write_u16: u16, write_u32: u32, write_u64: u64, write_u128: u128,
+        write_i16: i16, write_i32: i32, write_i64: i64, write_i128: i128
+    );
+
+    fn write_usize(&mut self, value: usize) {
+        self.write_u64(value as u64);
+    }
+
+    fn write_isize(&mut self, value: isize) {
+        self.write_i64(value as i64);
+    }
+}
+
+impl<T: PartialOrd + Ord, S: BucketStorage<T>> SortedVec<T, S> {
+    /// Keeps a hash of the contents up to date, so two replicas can compare
+    /// [`content_hash`](Self::content_hash) before running a full diff.
+    /// Equal contents give equal hashes whatever their bucket layout. Element
+    /// hashes use 64-bit FNV-1a with a fixed seed, so the value is stable
+    /// across processes, platforms and Rust releases as long as `T`'s `Hash`
+    /// implementation feeds the same data.
+    pub fn with_content_hash(mut self) -> Self
+    where
+        T: Hash,
+    {
+        self.content_hash = Some(ContentHash {
+            sum: 0,
+            element_hash: element_hash::<T>,
+        });
+        self.rehash();
+
+        self
+    }
+
+    /// The hash of the contents, when enabled with
+    /// [`with_content_hash`](Self::with_content_hash).
+    pub fn content_hash(&self) -> Option<u64> {
+        self.content_hash.as_ref().map(|content_hash| content_hash.sum)
+    }
+
+    pub(crate) fn hash_added(&mut self, (bucket_idx, item_idx): (usize, usize)) {
+        if let Some(content_hash) = &mut self.content_hash {
+            let item = self.buckets[bucket_idx].data.get(item_idx).expect("the added element is stored at its position");
+            content_hash.sum = content_hash.sum.wrapping_add((content_hash.element_hash)(item));
+        }
+    }
+
+    pub(crate) fn hash_removed(&mut self, item: &T) {
+        if let Some(content_hash) = &mut self.content_hash {
+            content_hash.sum = content_hash.sum.wrapping_sub((content_hash.element_hash)(item));
+        }
+    }
+
+    /// Takes the elements from position `start` up to `end` out of the hash,
+    /// before a range removal drops them.
+    pub(crate) fn hash_range_removed(&mut self, start: (usize, usize), end: (usize, usize)) {
+        let Some(content_hash) = &self.content_hash else {
+            return;
+        };
+
+        let element_hash = content_hash.element_hash;
+        let removed = SortedVecIter::between(self, start, end).fold(0, |sum: u64, item| sum.wrapping_add(element_hash(item)));
+        if let Some(content_hash) = &mut self.content_hash {
+            content_hash.sum = content_hash.sum.wrapping_sub(removed);
+        }
+    }
+
+    /// Recomputes the hash from every element, after bulk operations.
+    pub(crate) fn rehash(&mut self) {
+        let Some(content_hash) = &mut self.content_hash else {
+            return;
+        };
+
+        let element_hash = content_hash.element_hash;
+        content_hash.sum = self
+            .buckets
+            .iter()
+            .flat_map(|bucket| bucket.data.iter())
+            .fold(0, |sum, item| sum.wrapping_add(element_hash(item)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sorted_vec::{BucketConfiguration, DuplicatePolicy, SortedVec};
+
+    fn hashed(items: impl IntoIterator<Item = i32>, max_bucket_capacity: usize) -> SortedVec<i32> {
+        let configuration = BucketConfiguration::builder()
+            .max_bucket_capacity(max_bucket_capacity)
+            .duplicate_policy(DuplicatePolicy::Allow)
+            .build();
+        let mut sorted_vec = SortedVec::new(configuration).with_content_hash();
+        sorted_vec.extend(items);
+        sorted_vec
+    }
+
+    #[test]
+    fn content_hash_ignores_bucket_layout() {
+        let a = hashed((0..50).rev(), 3);
+        let b = hashed(0..50, 16);
+
+        assert!(a.content_hash().is_some());
+        assert_eq!(a.content_hash(), b.content_hash());
+        assert_ne!(a.content_hash(), hashed(1..50, 3).content_hash());
+        assert_eq!(SortedVec::<i32>::new(Default::default()).content_hash(), None);
+    }
+
+    #[test]
+    fn content_hash_follows_mutations() {
+        let mut sorted_vec = hashed(0..20, 3);
+        sorted_vec.remove(&4);
+        sorted_vec.update(&7, |item| *item = 30);
+        sorted_vec.update(&8, |item| *item = 9);
+        sorted_vec.insert_many([40, 41, 5]);
+        sorted_vec.remove_range(10..13);
+        sorted_vec.remove_all(&[0, 1]);
+        sorted_vec.transform(|item| item + 1);
+        sorted_vec.remove_any_in_range(15..16);
+
+        let rebuilt = hashed(sorted_vec.iter().copied(), 5);
+        assert_eq!(sorted_vec.content_hash(), rebuilt.content_hash());
+    }
+
+    #[test]
+    fn content_hash_follows_reshaping_with_fence_index() {
+        let mut sorted_vec = hashed(0..40, 4).with_fence_index();
+        sorted_vec.remove_range(3..30);
+        sorted_vec.shrink_buckets_to(100);
+        sorted_vec.insert(2);
+        sorted_vec.remove(&35);
+
+        let rebuilt = hashed(sorted_vec.iter().copied(), 5);
+        assert_eq!(sorted_vec.content_hash(), rebuilt.content_hash());
+    }
+
+    #[test]
+    fn content_hash_is_stable() {
+        assert_eq!(super::element_hash(&1u32), 0xad2a_ca77_4798_5764);
+        assert_eq!(hashed([1, 2, 3], 2).content_hash(), hashed([3, 2, 1], 8).content_hash());
+    }
+}
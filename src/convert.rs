@@ -0,0 +1,72 @@
+use std::collections::BTreeSet;
+
+use crate::sorted_vec::SortedVec;
+use crate::storage::BucketStorage;
+
+/// Sorts the elements and bulk builds the buckets with the default
+/// configuration.
+impl<T: PartialOrd + Ord, S: BucketStorage<T>> From<Vec<T>> for SortedVec<T, S> {
+    fn from(mut items: Vec<T>) -> Self {
+        let mut result = SortedVec::with_storage(Default::default());
+        let order = result.configuration.order();
+        items.sort_by(|a, b| order.compare(a, b));
+        result.insert_sorted_batch(items);
+
+        result
+    }
+}
+
+impl<T: PartialOrd + Ord + Clone, S: BucketStorage<T>> From<&[T]> for SortedVec<T, S> {
+    fn from(items: &[T]) -> Self {
+        items.to_vec().into()
+    }
+}
+
+/// The set is already sorted and unique, so the buckets are filled in one
+/// linear pass.
+impl<T: PartialOrd + Ord, S: BucketStorage<T>> From<BTreeSet<T>> for SortedVec<T, S> {
+    fn from(set: BTreeSet<T>) -> Self {
+        let mut result = SortedVec::with_storage(Default::default());
+        result.insert_sorted_batch(set.into_iter().collect());
+
+        result
+    }
+}
+
+impl<T: PartialOrd + Ord, S: BucketStorage<T>> From<SortedVec<T, S>> for BTreeSet<T> {
+    fn from(sorted_vec: SortedVec<T, S>) -> Self {
+        sorted_vec.buckets.into_iter().flat_map(|bucket| bucket.data).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use crate::sorted_vec::SortedVec;
+
+    #[test]
+    fn sorted_vec_from_vec_sorts_and_dedups() {
+        let sorted_vec: SortedVec<i32> = vec![3, 1, 2, 3].into();
+        assert_eq!(sorted_vec.iter().copied().collect::<Vec<_>>(), vec![1, 2, 3]);
+        assert_eq!(sorted_vec.size, 3);
+    }
+
+    #[test]
+    fn sorted_vec_from_slice() {
+        let items = [5, 4, 6];
+        let sorted_vec = SortedVec::<i32>::from(&items[..]);
+        assert_eq!(sorted_vec.iter().copied().collect::<Vec<_>>(), vec![4, 5, 6]);
+    }
+
+    #[test]
+    fn sorted_vec_btree_set_round_trip() {
+        let set: BTreeSet<i32> = (0..10_000).rev().collect();
+        let sorted_vec = SortedVec::<i32>::from(set.clone());
+        assert_eq!(sorted_vec.size, 10_000);
+        assert!(sorted_vec.buckets.len() > 1);
+        assert!(sorted_vec.iter().eq(set.iter()));
+
+        assert_eq!(BTreeSet::from(sorted_vec), set);
+    }
+}
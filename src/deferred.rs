@@ -0,0 +1,140 @@
+use crate::sorted_vec::SortedVec;
+
+/// Mutable access to every element of a [`SortedVec`], handed out by
+/// [`iter_mut_deferred`](SortedVec::iter_mut_deferred). The container cannot
+/// be queried while the guard lives; [`resort`](Self::resort), or dropping
+/// the guard, puts the elements back in order.
+///
+/// Should the guard be dropped by a panic, the container is emptied instead,
+/// since sorting would call into `T` again.
+#[derive(Debug)]
+pub struct DeferredMut<'a, T: PartialOrd + Ord> {
+    sorted_vec: &'a mut SortedVec<T>,
+}
+
+impl<T: PartialOrd + Ord> SortedVec<T> {
+    /// Lets every element be changed in place, in any way, with the order
+    /// repaired once when the returned guard is done.
+    pub fn iter_mut_deferred(&mut self) -> DeferredMut<'_, T> {
+        DeferredMut { sorted_vec: self }
+    }
+
+    /// Sorts each bucket on its own, and everything again only when the
+    /// buckets now overlap or, with duplicates rejected, share an element.
+    fn resort_buckets(&mut self) {
+        let order = self.configuration.order();
+        let sorted = self.clear_on_unwind(|this| {
+            for bucket in &mut this.buckets {
+                bucket.data.sort_by(|a, b| order.compare(a, b));
+            }
+            this.is_in_order()
+        });
+        if sorted {
+            self.bump_generation();
+            self.rebuild_indexes();
+        } else {
+            self.sort_all();
+        }
+    }
+}
+
+impl<T: PartialOrd + Ord> DeferredMut<'_, T> {
+    pub fn len(&self) -> usize {
+        self.sorted_vec.size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sorted_vec.size == 0
+    }
+
+    /// The elements, in the order they were in before the guard was taken.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut T> + '_ {
+        self.sorted_vec.buckets.iter_mut().flat_map(|bucket| bucket.data.iter_mut())
+    }
+
+    /// Restores the order. Elements that became duplicates are dropped when
+    /// the container rejects duplicates.
+    pub fn resort(self) {}
+}
+
+impl<T: PartialOrd + Ord> Drop for DeferredMut<'_, T> {
+    fn drop(&mut self) {
+        if std::thread::panicking() {
+            self.sorted_vec.clear_after_panic();
+        } else {
+            self.sorted_vec.resort_buckets();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    use crate::container::SortedContainer;
+    use crate::sorted_vec::{BucketConfiguration, DuplicatePolicy, Order, SortedVec};
+
+    fn configuration() -> BucketConfiguration {
+        BucketConfiguration::builder().max_bucket_capacity(8).build()
+    }
+
+    #[test]
+    fn resort_keeps_bucket_local_changes() {
+        let mut sorted_vec = SortedVec::from_vec((0..100).map(|item| item * 10).collect(), configuration()).with_fence_index();
+        let buckets = sorted_vec.buckets.len();
+
+        let mut deferred = sorted_vec.iter_mut_deferred();
+        assert_eq!(deferred.len(), 100);
+        for item in deferred.iter_mut() {
+            // Swaps neighbours without leaving the gap to the next pair.
+            *item = if *item % 20 == 0 { *item + 11 } else { *item - 9 };
+        }
+        deferred.resort();
+
+        assert_eq!(sorted_vec.buckets.len(), buckets);
+        assert!(sorted_vec.iter().copied().eq((0..50).flat_map(|pair| [pair * 20 + 1, pair * 20 + 11])));
+        assert!(sorted_vec.find_index(&791).is_some());
+        assert_eq!(sorted_vec.check_integrity(), Ok(()));
+    }
+
+    #[test]
+    fn resort_falls_back_to_a_full_sort() {
+        let configuration = BucketConfiguration::builder().max_bucket_capacity(8).order(Order::Descending).build();
+        let mut sorted_vec = SortedVec::from_vec((0..100).collect(), configuration);
+
+        for item in sorted_vec.iter_mut_deferred().iter_mut() {
+            *item = (*item * 37) % 100;
+        }
+
+        assert!(sorted_vec.iter().copied().eq((0..100).rev()));
+        assert_eq!(sorted_vec.check_integrity(), Ok(()));
+    }
+
+    #[test]
+    fn resort_drops_new_duplicates() {
+        let configuration = BucketConfiguration::builder().max_bucket_capacity(8).duplicate_policy(DuplicatePolicy::Reject).build();
+        let mut sorted_vec = SortedVec::from_vec((0..50).collect(), configuration);
+
+        sorted_vec.iter_mut_deferred().iter_mut().for_each(|item| *item /= 2);
+
+        assert!(sorted_vec.iter().copied().eq(0..25));
+        assert_eq!(sorted_vec.len(), 25);
+        assert_eq!(sorted_vec.check_integrity(), Ok(()));
+    }
+
+    #[test]
+    fn panic_while_mutating_empties_the_container() {
+        let mut sorted_vec = SortedVec::from_vec((0..50).collect(), configuration());
+
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            for item in sorted_vec.iter_mut_deferred().iter_mut() {
+                assert!(*item < 30, "mutation failed");
+                *item = 100 - *item;
+            }
+        }));
+
+        assert!(result.is_err());
+        assert!(sorted_vec.is_empty());
+        assert_eq!(sorted_vec.check_integrity(), Ok(()));
+    }
+}
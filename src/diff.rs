@@ -0,0 +1,126 @@
+use std::cmp::Ordering;
+use std::iter::{FusedIterator, Peekable};
+
+use crate::batch::BatchOperation;
+use crate::iter::SortedVecIter;
+use crate::sorted_vec::{Order, SortedVec};
+use crate::storage::BucketStorage;
+
+/// One step of turning a container into another. Returned by
+/// [`SortedVec::diff`] and consumed by [`SortedVec::apply_diff`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiffEntry<T> {
+    /// Only in the target.
+    Added(T),
+    /// Only in the source.
+    Removed(T),
+    /// In both, compared with the source's order.
+    Unchanged(T),
+}
+
+impl<T> DiffEntry<&T> {
+    pub fn cloned(self) -> DiffEntry<T>
+    where
+        T: Clone,
+    {
+        match self {
+            DiffEntry::Added(item) => DiffEntry::Added(item.clone()),
+            DiffEntry::Removed(item) => DiffEntry::Removed(item.clone()),
+            DiffEntry::Unchanged(item) => DiffEntry::Unchanged(item.clone()),
+        }
+    }
+}
+
+/// Walks two containers side by side. Returned by [`SortedVec::diff`].
+pub struct Diff<'a, T: PartialOrd + Ord, S: BucketStorage<T> = Vec<T>> {
+    order: Order,
+    source: Peekable<SortedVecIter<'a, T, S>>,
+    target: Peekable<SortedVecIter<'a, T, S>>,
+}
+
+impl<T: PartialOrd + Ord, S: BucketStorage<T>> FusedIterator for Diff<'_, T, S> {}
+
+impl<'a, T: PartialOrd + Ord, S: BucketStorage<T>> Iterator for Diff<'a, T, S> {
+    type Item = DiffEntry<&'a T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let ordering = match (self.source.peek(), self.target.peek()) {
+            (Some(source), Some(target)) => self.order.compare(source, target),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => return None,
+        };
+
+        match ordering {
+            Ordering::Less => self.source.next().map(DiffEntry::Removed),
+            Ordering::Greater => self.target.next().map(DiffEntry::Added),
+            Ordering::Equal => {
+                self.target.next();
+                self.source.next().map(DiffEntry::Unchanged)
+            },
+        }
+    }
+}
+
+impl<T: PartialOrd + Ord, S: BucketStorage<T>> SortedVec<T, S> {
+    /// Lists what it takes to turn `self` into `other` in a single merge walk.
+    /// Equal elements are paired up one to one, so with duplicates the
+    /// surplus on either side shows up as added or removed.
+    pub fn diff<'a>(&'a self, other: &'a SortedVec<T, S>) -> Diff<'a, T, S> {
+        Diff {
+            order: self.configuration.order(),
+            source: self.iter().peekable(),
+            target: other.iter().peekable(),
+        }
+    }
+
+    /// Applies the added and removed entries of a diff in one sweep.
+    pub fn apply_diff(&mut self, diff: impl IntoIterator<Item = DiffEntry<T>>) {
+        self.apply_batch(diff.into_iter().filter_map(|entry| match entry {
+            DiffEntry::Added(item) => Some(BatchOperation::Insert(item)),
+            DiffEntry::Removed(item) => Some(BatchOperation::Remove(item)),
+            DiffEntry::Unchanged(_) => None,
+        }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sorted_vec::{BucketConfiguration, DuplicatePolicy};
+
+    #[test]
+    fn diff_walks_both_containers() {
+        let configuration = BucketConfiguration::builder().max_bucket_capacity(2).build();
+        let old = SortedVec::from_vec(vec![1, 2, 4, 6], configuration.clone());
+        let new = SortedVec::from_vec(vec![2, 3, 4, 7], configuration);
+
+        let diff: Vec<_> = old.diff(&new).collect();
+        assert_eq!(
+            diff,
+            vec![
+                DiffEntry::Removed(&1),
+                DiffEntry::Unchanged(&2),
+                DiffEntry::Added(&3),
+                DiffEntry::Unchanged(&4),
+                DiffEntry::Removed(&6),
+                DiffEntry::Added(&7),
+            ]
+        );
+    }
+
+    #[test]
+    fn apply_diff_patches_to_target() {
+        let configuration = BucketConfiguration::builder()
+            .max_bucket_capacity(2)
+            .duplicate_policy(DuplicatePolicy::Allow)
+            .build();
+        let mut old = SortedVec::from_vec(vec![1, 1, 3, 5, 8], configuration.clone());
+        let new = SortedVec::from_vec(vec![1, 3, 3, 4, 9], configuration);
+
+        let diff: Vec<_> = old.diff(&new).map(DiffEntry::cloned).collect();
+        old.apply_diff(diff);
+        assert_eq!(old.iter().collect::<Vec<_>>(), new.iter().collect::<Vec<_>>());
+        assert!(old.diff(&new).all(|entry| matches!(entry, DiffEntry::Unchanged(_))));
+    }
+}
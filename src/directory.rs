@@ -0,0 +1,748 @@
+use std::collections::TryReserveError;
+use std::fmt::{self, Debug, Formatter};
+use std::iter::Flatten;
+use std::ops::{Index, IndexMut, Range};
+use std::{mem, slice, vec};
+
+use crate::index::{narrow, widen, Idx};
+
+/// Chunks never shrink below this capacity, so small containers keep a
+/// single chunk.
+const MIN_CHUNK_CAPACITY: usize = 32;
+
+/// How many elements a directory entry holds, so the directory can answer
+/// rank queries.
+pub(crate) trait Weighted {
+    fn weight(&self) -> usize;
+}
+
+/// The bucket table, kept as a list of chunks of about `sqrt(len)` buckets.
+/// Inserting or removing a bucket shifts one chunk plus the chunk start
+/// offsets, so splits in the middle of a huge container cost O(sqrt(#buckets))
+/// instead of moving every bucket after the split. Indexing costs a binary
+/// search over the chunk starts. It offers the `Vec` methods the container
+/// uses, under the same names.
+///
+/// Element counts are kept in the same layout: the elements before every
+/// bucket within its chunk and the elements before every chunk, so ranking
+/// and selecting an element are two binary searches. A chunk lent out
+/// mutably is only recounted on the next structural change or
+/// [`refresh_counts`](Self::refresh_counts); until then queries count its
+/// buckets directly.
+pub(crate) struct BucketDirectory<B> {
+    /// Only the last chunk may be empty, e.g. after reserving space.
+    chunks: Vec<Vec<B>>,
+    /// The index of the first bucket of each chunk.
+    starts: Vec<usize>,
+    /// Per chunk, the elements before each of its buckets counted from the
+    /// start of the chunk, followed by the chunk total. Stored as [`Idx`],
+    /// as there is one per bucket.
+    offsets: Vec<Vec<Idx>>,
+    /// The elements before each chunk. They agree with the totals in
+    /// `offsets`, even for a dirty chunk.
+    element_starts: Vec<usize>,
+    dirty: Dirty,
+    len: usize,
+}
+
+/// Which chunks may have changed size since `offsets` was last counted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Dirty {
+    Clean,
+    Chunk(usize),
+    All,
+}
+
+impl<B> BucketDirectory<B> {
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// How many buckets fit before a new chunk has to be allocated.
+    #[cfg(test)]
+    pub(crate) fn capacity(&self) -> usize {
+        self.chunks.last().map_or(0, |last| self.len - last.len() + last.capacity())
+    }
+
+    pub(crate) fn get(&self, idx: usize) -> Option<&B> {
+        let (chunk, offset) = self.locate(idx)?;
+        Some(&self.chunks[chunk][offset])
+    }
+
+    pub(crate) fn first(&self) -> Option<&B> {
+        self.get(0)
+    }
+
+    pub(crate) fn last(&self) -> Option<&B> {
+        self.get(self.len.checked_sub(1)?)
+    }
+
+    /// Like `slice::partition_point`, looking at one bucket per chunk before
+    /// searching inside a single chunk.
+    pub(crate) fn partition_point(&self, mut pred: impl FnMut(&B) -> bool) -> usize {
+        let chunk = self.chunks.partition_point(|chunk| chunk.last().is_none_or(&mut pred));
+        match self.chunks.get(chunk) {
+            Some(buckets) => self.starts[chunk] + buckets.partition_point(pred),
+            None => self.len,
+        }
+    }
+
+    pub(crate) fn iter(&self) -> Iter<'_, B> {
+        Iter {
+            inner: self.chunks.iter().flatten(),
+            remaining: self.len,
+        }
+    }
+
+    /// The chunk and the offset inside it holding bucket `idx`.
+    fn locate(&self, idx: usize) -> Option<(usize, usize)> {
+        if idx >= self.len {
+            return None;
+        }
+
+        let chunk = self.starts.partition_point(|&start| start <= idx) - 1;
+        Some((chunk, idx - self.starts[chunk]))
+    }
+}
+
+impl<B: Weighted> BucketDirectory<B> {
+    pub(crate) fn new() -> Self {
+        BucketDirectory {
+            chunks: Vec::new(),
+            starts: Vec::new(),
+            offsets: Vec::new(),
+            element_starts: Vec::new(),
+            dirty: Dirty::Clean,
+            len: 0,
+        }
+    }
+
+    pub(crate) fn with_capacity(capacity: usize) -> Self {
+        BucketDirectory {
+            chunks: vec![Vec::with_capacity(capacity)],
+            starts: vec![0],
+            offsets: vec![vec![0]],
+            element_starts: vec![0],
+            dirty: Dirty::Clean,
+            len: 0,
+        }
+    }
+
+    /// Reserves room for `additional` more buckets pushed at the end.
+    pub(crate) fn try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        self.last_chunk().try_reserve(additional)
+    }
+
+    pub(crate) fn get_mut(&mut self, idx: usize) -> Option<&mut B> {
+        let (chunk, offset) = self.locate(idx)?;
+        self.mark(chunk);
+        Some(&mut self.chunks[chunk][offset])
+    }
+
+    pub(crate) fn last_mut(&mut self) -> Option<&mut B> {
+        self.get_mut(self.len.checked_sub(1)?)
+    }
+
+    pub(crate) fn push(&mut self, bucket: B) {
+        self.refresh_counts();
+        let weight = bucket.weight();
+        let capacity = self.chunk_capacity();
+        match self.chunks.last_mut() {
+            Some(last) if last.len() < capacity.max(last.capacity()) => {
+                last.push(bucket);
+                let offsets = self.offsets.last_mut().expect("every chunk has offsets");
+                offsets.push(narrow(widen(offsets[offsets.len() - 1]) + weight));
+            },
+            _ => {
+                self.element_starts.push(self.total());
+                self.chunks.push(vec![bucket]);
+                self.starts.push(self.len);
+                self.offsets.push(vec![0, narrow(weight)]);
+            },
+        }
+        self.len += 1;
+    }
+
+    pub(crate) fn insert(&mut self, idx: usize, bucket: B) {
+        assert!(idx <= self.len, "insertion index {idx} out of bounds");
+        let Some((chunk, offset)) = self.locate(idx) else {
+            return self.push(bucket);
+        };
+
+        self.refresh_counts();
+        self.chunks[chunk].insert(offset, bucket);
+        self.len += 1;
+        for start in &mut self.starts[chunk + 1..] {
+            *start += 1;
+        }
+        self.recount(chunk);
+        if self.chunks[chunk].len() > 2 * self.chunk_capacity() {
+            let at = self.chunks[chunk].len() / 2;
+            let tail = self.chunks[chunk].split_off(at);
+            self.chunks.insert(chunk + 1, tail);
+            self.starts.insert(chunk + 1, self.starts[chunk] + at);
+            self.offsets.insert(chunk + 1, Vec::new());
+            self.element_starts.insert(chunk + 1, 0);
+            self.recount(chunk);
+            self.recount(chunk + 1);
+        }
+        self.restart_elements(chunk + 1);
+    }
+
+    pub(crate) fn remove(&mut self, idx: usize) -> B {
+        let (chunk, offset) = self.locate(idx).unwrap_or_else(|| panic!("removal index {idx} out of bounds"));
+        self.refresh_counts();
+        let bucket = self.chunks[chunk].remove(offset);
+        self.len -= 1;
+        for start in &mut self.starts[chunk + 1..] {
+            *start -= 1;
+        }
+        if self.chunks[chunk].is_empty() {
+            self.chunks.remove(chunk);
+            self.starts.remove(chunk);
+            self.offsets.remove(chunk);
+            self.element_starts.remove(chunk);
+            self.restart_elements(chunk);
+        } else {
+            self.recount(chunk);
+            self.restart_elements(chunk + 1);
+        }
+
+        bucket
+    }
+
+    /// Keeps the buckets for which `f` is true, filtering every chunk in
+    /// place.
+    pub(crate) fn retain(&mut self, mut f: impl FnMut(&B) -> bool) {
+        for chunk in &mut self.chunks {
+            chunk.retain(&mut f);
+        }
+        self.chunks.retain(|chunk| !chunk.is_empty());
+        self.offsets = self.chunks.iter().map(|chunk| Self::count(chunk)).collect();
+        self.dirty = Dirty::Clean;
+        self.reindex();
+    }
+
+    pub(crate) fn clear(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Removes the buckets in `range` and returns them in order.
+    pub(crate) fn drain(&mut self, range: Range<usize>) -> vec::IntoIter<B> {
+        let mut drained = Vec::with_capacity(range.len());
+        self.splice_out(range, |buckets: vec::Drain<'_, B>| drained.extend(buckets));
+
+        drained.into_iter()
+    }
+
+    /// Drops the buckets in `range` without collecting them.
+    pub(crate) fn remove_range(&mut self, range: Range<usize>) {
+        self.splice_out(range, |buckets: vec::Drain<'_, B>| drop(buckets));
+    }
+
+    /// Hands the buckets in `range` to `removed` in order. Chunks entirely
+    /// inside the range are taken whole and only the two chunks at its ends
+    /// are touched.
+    fn splice_out(&mut self, range: Range<usize>, mut removed: impl FnMut(vec::Drain<'_, B>)) {
+        assert!(range.start <= range.end && range.end <= self.len, "drain range {range:?} out of bounds");
+        if range.is_empty() {
+            return;
+        }
+
+        self.refresh_counts();
+        let (first, from) = self.locate(range.start).expect("the range is in bounds");
+        let (last, to) = self.locate(range.end - 1).expect("the range is in bounds");
+        if first == last {
+            removed(self.chunks[first].drain(from..=to));
+        } else {
+            removed(self.chunks[first].drain(from..));
+            self.offsets.drain(first + 1..last);
+            for mut chunk in self.chunks.drain(first + 1..last) {
+                removed(chunk.drain(..));
+            }
+            removed(self.chunks[first + 1].drain(..=to));
+            self.recount(first + 1);
+        }
+        self.recount(first);
+        self.join_chunks(first);
+        self.reindex();
+    }
+
+    /// Moves the buckets from `at` onwards into a new directory, splitting a
+    /// single chunk.
+    pub(crate) fn split_off(&mut self, at: usize) -> Self {
+        assert!(at <= self.len, "split index {at} out of bounds");
+        let Some((chunk, offset)) = self.locate(at) else {
+            return Self::new();
+        };
+
+        self.refresh_counts();
+        let mut tail = Self::new();
+        tail.chunks.push(self.chunks[chunk].split_off(offset));
+        tail.chunks.extend(self.chunks.drain(chunk + 1..));
+        tail.offsets.push(Vec::new());
+        tail.offsets.extend(self.offsets.drain(chunk + 1..));
+        tail.recount(0);
+        tail.reindex();
+
+        self.recount(chunk);
+        if self.chunks[chunk].is_empty() {
+            self.chunks.pop();
+            self.offsets.pop();
+        }
+        self.reindex();
+
+        tail
+    }
+
+    /// The number of elements in all buckets.
+    pub(crate) fn total(&self) -> usize {
+        match self.chunks.len() {
+            0 => 0,
+            chunks => self.chunk_start(chunks - 1) + self.chunk_total(chunks - 1),
+        }
+    }
+
+    /// The number of elements in the buckets before `bucket_idx`, which may
+    /// be `len` to count them all.
+    pub(crate) fn rank(&self, bucket_idx: usize) -> usize {
+        match self.locate(bucket_idx) {
+            Some((chunk, offset)) => self.chunk_start(chunk) + self.offset_in_chunk(chunk, offset),
+            None => self.total(),
+        }
+    }
+
+    /// The bucket holding the element ranked `rank` over all buckets, and
+    /// the element's index inside it.
+    pub(crate) fn select(&self, rank: usize) -> Option<(usize, usize)> {
+        let (mut lo, mut hi) = (0, self.chunks.len());
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            if self.chunk_start(mid) + self.chunk_total(mid) <= rank {
+                lo = mid + 1;
+            } else {
+                hi = mid;
+            }
+        }
+        if lo == self.chunks.len() {
+            return None;
+        }
+
+        let local = rank - self.chunk_start(lo);
+        if self.is_dirty(lo) {
+            let mut remaining = local;
+            for (offset, bucket) in self.chunks[lo].iter().enumerate() {
+                if remaining < bucket.weight() {
+                    return Some((self.starts[lo] + offset, remaining));
+                }
+                remaining -= bucket.weight();
+            }
+            return None;
+        }
+
+        let offsets = &self.offsets[lo];
+        let offset = offsets.partition_point(|&before| widen(before) <= local) - 1;
+        Some((self.starts[lo] + offset, local - widen(offsets[offset])))
+    }
+
+    /// Recounts the chunks lent out mutably, so queries no longer count
+    /// their buckets one by one.
+    pub(crate) fn refresh_counts(&mut self) {
+        match mem::replace(&mut self.dirty, Dirty::Clean) {
+            Dirty::Clean => {},
+            Dirty::Chunk(chunk) => {
+                self.recount(chunk);
+                self.restart_elements(chunk + 1);
+            },
+            Dirty::All => {
+                for chunk in 0..self.chunks.len() {
+                    self.recount(chunk);
+                }
+                self.restart_elements(0);
+            },
+        }
+    }
+
+    pub(crate) fn iter_mut(&mut self) -> IterMut<'_, B> {
+        self.dirty = Dirty::All;
+        IterMut {
+            inner: self.chunks.iter_mut().flatten(),
+            remaining: self.len,
+        }
+    }
+
+    fn chunk_capacity(&self) -> usize {
+        self.len.isqrt().max(MIN_CHUNK_CAPACITY)
+    }
+
+    fn last_chunk(&mut self) -> &mut Vec<B> {
+        if self.chunks.is_empty() {
+            self.chunks.push(Vec::new());
+            self.starts.push(0);
+            self.offsets.push(vec![0]);
+            self.element_starts.push(0);
+        }
+
+        self.chunks.last_mut().expect("a chunk was just ensured")
+    }
+
+    /// Records that a bucket of `chunk` is about to be lent out mutably.
+    /// Only one chunk is tracked at a time, so the previous one is recounted
+    /// first.
+    fn mark(&mut self, chunk: usize) {
+        if !self.is_dirty(chunk) {
+            self.refresh_counts();
+            self.dirty = Dirty::Chunk(chunk);
+        }
+    }
+
+    fn is_dirty(&self, chunk: usize) -> bool {
+        match self.dirty {
+            Dirty::Clean => false,
+            Dirty::Chunk(dirty) => dirty == chunk,
+            Dirty::All => true,
+        }
+    }
+
+    /// The elements before `chunk`, correcting for a dirty chunk before it.
+    fn chunk_start(&self, chunk: usize) -> usize {
+        match self.dirty {
+            Dirty::Chunk(dirty) if dirty < chunk => {
+                self.element_starts[chunk] - self.recorded_total(dirty) + self.chunk_total(dirty)
+            },
+            Dirty::All => self.chunks[..chunk].iter().flatten().map(Weighted::weight).sum(),
+            _ => self.element_starts[chunk],
+        }
+    }
+
+    fn chunk_total(&self, chunk: usize) -> usize {
+        if self.is_dirty(chunk) {
+            self.chunks[chunk].iter().map(Weighted::weight).sum()
+        } else {
+            self.recorded_total(chunk)
+        }
+    }
+
+    fn recorded_total(&self, chunk: usize) -> usize {
+        widen(self.offsets[chunk][self.offsets[chunk].len() - 1])
+    }
+
+    fn offset_in_chunk(&self, chunk: usize, offset: usize) -> usize {
+        if self.is_dirty(chunk) {
+            self.chunks[chunk][..offset].iter().map(Weighted::weight).sum()
+        } else {
+            widen(self.offsets[chunk][offset])
+        }
+    }
+
+    /// Cumulative element counts of the buckets of `chunk`, followed by the
+    /// chunk total.
+    fn count(chunk: &[B]) -> Vec<Idx> {
+        let mut offsets = Vec::with_capacity(chunk.len() + 1);
+        let mut total = 0;
+        offsets.push(0);
+        for bucket in chunk {
+            total += bucket.weight();
+            offsets.push(narrow(total));
+        }
+
+        offsets
+    }
+
+    fn recount(&mut self, chunk: usize) {
+        self.offsets[chunk] = Self::count(&self.chunks[chunk]);
+    }
+
+    /// Recomputes the element starts of the chunks from `chunk` onwards.
+    fn restart_elements(&mut self, chunk: usize) {
+        for idx in chunk.max(1)..self.chunks.len() {
+            self.element_starts[idx] = self.element_starts[idx - 1] + self.recorded_total(idx - 1);
+        }
+        if chunk == 0 && !self.chunks.is_empty() {
+            self.element_starts[0] = 0;
+        }
+    }
+
+    /// Drops `chunk` and the one after it if they ended up empty, and merges
+    /// them when both are small.
+    fn join_chunks(&mut self, chunk: usize) {
+        for idx in [chunk + 1, chunk] {
+            if self.chunks.get(idx).is_some_and(Vec::is_empty) {
+                self.chunks.remove(idx);
+                self.offsets.remove(idx);
+            }
+        }
+        if chunk + 1 < self.chunks.len() && self.chunks[chunk].len() + self.chunks[chunk + 1].len() <= self.chunk_capacity() {
+            let next = self.chunks.remove(chunk + 1);
+            self.offsets.remove(chunk + 1);
+            self.chunks[chunk].extend(next);
+            self.recount(chunk);
+        }
+    }
+
+    /// Recomputes the bucket and element starts of every chunk from the
+    /// chunk lengths and totals, after chunks were added or removed.
+    fn reindex(&mut self) {
+        self.starts.clear();
+        self.element_starts.clear();
+        let (mut buckets, mut elements) = (0, 0);
+        for (chunk, offsets) in self.chunks.iter().zip(&self.offsets) {
+            self.starts.push(buckets);
+            self.element_starts.push(elements);
+            buckets += chunk.len();
+            elements += widen(offsets[offsets.len() - 1]);
+        }
+        self.len = buckets;
+    }
+}
+
+impl<B: Weighted> Default for BucketDirectory<B> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<B: Clone> Clone for BucketDirectory<B> {
+    fn clone(&self) -> Self {
+        BucketDirectory {
+            chunks: self.chunks.clone(),
+            starts: self.starts.clone(),
+            offsets: self.offsets.clone(),
+            element_starts: self.element_starts.clone(),
+            dirty: self.dirty,
+            len: self.len,
+        }
+    }
+}
+
+impl<B: Debug> Debug for BucketDirectory<B> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<B> Index<usize> for BucketDirectory<B> {
+    type Output = B;
+
+    fn index(&self, idx: usize) -> &Self::Output {
+        let len = self.len;
+        self.get(idx).unwrap_or_else(|| panic!("bucket index {idx} out of bounds for {len} buckets"))
+    }
+}
+
+impl<B: Weighted> IndexMut<usize> for BucketDirectory<B> {
+    fn index_mut(&mut self, idx: usize) -> &mut Self::Output {
+        let len = self.len;
+        self.get_mut(idx).unwrap_or_else(|| panic!("bucket index {idx} out of bounds for {len} buckets"))
+    }
+}
+
+impl<B: Weighted> FromIterator<B> for BucketDirectory<B> {
+    fn from_iter<I: IntoIterator<Item = B>>(iter: I) -> Self {
+        let mut result = Self::new();
+        result.extend(iter);
+        result
+    }
+}
+
+impl<B: Weighted> Extend<B> for BucketDirectory<B> {
+    fn extend<I: IntoIterator<Item = B>>(&mut self, iter: I) {
+        for bucket in iter {
+            self.push(bucket);
+        }
+    }
+}
+
+impl<B: Weighted> From<Vec<B>> for BucketDirectory<B> {
+    fn from(buckets: Vec<B>) -> Self {
+        buckets.into_iter().collect()
+    }
+}
+
+impl<B> IntoIterator for BucketDirectory<B> {
+    type Item = B;
+    type IntoIter = IntoIter<B>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter {
+            inner: self.chunks.into_iter().flatten(),
+            remaining: self.len,
+        }
+    }
+}
+
+impl<'a, B> IntoIterator for &'a BucketDirectory<B> {
+    type Item = &'a B;
+    type IntoIter = Iter<'a, B>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, B: Weighted> IntoIterator for &'a mut BucketDirectory<B> {
+    type Item = &'a mut B;
+    type IntoIter = IterMut<'a, B>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+/// Defines an iterator over the buckets of all chunks that knows its exact
+/// length, as the ones over a `Vec` do.
+macro_rules! impl_directory_iter {
+    ($name:ident<$($lifetime:lifetime,)? $bucket:ident>, $chunks:ty, $item:ty) => {
+        pub(crate) struct $name<$($lifetime,)? $bucket> {
+            inner: Flatten<$chunks>,
+            remaining: usize,
+        }
+
+        impl<$($lifetime,)? $bucket> Iterator for $name<$($lifetime,)? $bucket> {
+            type Item = $item;
+
+            fn next(&mut self) -> Option<Self::Item> {
+                let bucket = self.inner.next()?;
+                self.remaining -= 1;
+                Some(bucket)
+            }
+
+            fn size_hint(&self) -> (usize, Option<usize>) {
+                (self.remaining, Some(self.remaining))
+            }
+        }
+
+        impl<$($lifetime,)? $bucket> DoubleEndedIterator for $name<$($lifetime,)? $bucket> {
+            fn next_back(&mut self) -> Option<Self::Item> {
+                let bucket = self.inner.next_back()?;
+                self.remaining -= 1;
+                Some(bucket)
+            }
+        }
+
+        impl<$($lifetime,)? $bucket> ExactSizeIterator for $name<$($lifetime,)? $bucket> {}
+    };
+}
+
+impl_directory_iter!(Iter<'a, B>, slice::Iter<'a, Vec<B>>, &'a B);
+impl_directory_iter!(IterMut<'a, B>, slice::IterMut<'a, Vec<B>>, &'a mut B);
+impl_directory_iter!(IntoIter<B>, vec::IntoIter<Vec<B>>, B);
+
+impl<B> Clone for Iter<'_, B> {
+    fn clone(&self) -> Self {
+        Iter {
+            inner: self.inner.clone(),
+            remaining: self.remaining,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::container::tests::{vec_steps, VecStep};
+
+    impl Weighted for i32 {
+        fn weight(&self) -> usize {
+            self.unsigned_abs() as usize % 5
+        }
+    }
+
+    /// Checks `rank` and `select` of every bucket against counting the
+    /// buckets of `model` one by one.
+    fn assert_counts_match(directory: &BucketDirectory<i32>, model: &[i32]) {
+        let mut rank = 0;
+        for (bucket_idx, bucket) in model.iter().enumerate() {
+            assert_eq!(directory.rank(bucket_idx), rank);
+            for item_idx in 0..bucket.weight() {
+                assert_eq!(directory.select(rank + item_idx), Some((bucket_idx, item_idx)));
+            }
+            rank += bucket.weight();
+        }
+        assert_eq!(directory.rank(model.len()), rank);
+        assert_eq!(directory.total(), rank);
+        assert_eq!(directory.select(rank), None);
+    }
+
+    #[test]
+    fn bucket_directory_matches_vec() {
+        let mut directory = BucketDirectory::new();
+        let mut model = Vec::new();
+        for step in vec_steps(5, 5_000, 4) {
+            match step {
+                VecStep::Insert(at, item) => {
+                    directory.insert(at, item);
+                    model.insert(at, item);
+                },
+                VecStep::Remove(at) => assert_eq!(directory.remove(at), model.remove(at)),
+            }
+        }
+
+        assert!(directory.chunks.len() > 1);
+        assert_eq!(directory.len(), model.len());
+        assert!(directory.iter().eq(model.iter()));
+        assert!(directory.iter().rev().eq(model.iter().rev()));
+        assert_eq!(directory[model.len() / 2], model[model.len() / 2]);
+        assert_eq!(directory.last(), model.last());
+
+        model.sort();
+        let mut directory: BucketDirectory<_> = model.clone().into();
+        assert_eq!(directory.partition_point(|&bucket| bucket < 1_000), model.partition_point(|&bucket| bucket < 1_000));
+        assert_eq!(directory.partition_point(|_| true), model.len());
+
+        let tail = directory.split_off(100);
+        let model_tail = model.split_off(100);
+        assert!(tail.into_iter().eq(model_tail));
+        directory.retain(|bucket| bucket % 3 == 0);
+        model.retain(|bucket| bucket % 3 == 0);
+        assert!(directory.into_iter().eq(model));
+    }
+
+    #[test]
+    fn bucket_directory_counts_elements() {
+        let mut directory = BucketDirectory::new();
+        let mut model = Vec::new();
+        for step in 0..2_000 {
+            let at = (step * 7) as usize % (model.len() + 1);
+            directory.insert(at, step);
+            model.insert(at, step);
+        }
+        assert!(directory.chunks.len() > 1);
+        assert_counts_match(&directory, &model);
+
+        *directory.get_mut(10).unwrap() += 1;
+        model[10] += 1;
+        assert_counts_match(&directory, &model);
+        *directory.get_mut(1_500).unwrap() += 3;
+        model[1_500] += 3;
+        assert_counts_match(&directory, &model);
+        for (bucket, modelled) in directory.iter_mut().zip(&mut model).step_by(3) {
+            *bucket += 2;
+            *modelled += 2;
+        }
+        assert_counts_match(&directory, &model);
+        directory.refresh_counts();
+        assert_counts_match(&directory, &model);
+
+        directory.remove(700);
+        model.remove(700);
+        assert_counts_match(&directory, &model);
+        let drained: Vec<_> = directory.drain(100..1_200).collect();
+        assert!(drained.iter().eq(model.drain(100..1_200).as_slice()));
+        assert_counts_match(&directory, &model);
+        directory.remove_range(40..90);
+        model.drain(40..90);
+        assert_counts_match(&directory, &model);
+        let tail = directory.split_off(300);
+        let model_tail = model.split_off(300);
+        assert_counts_match(&directory, &model);
+        assert_counts_match(&tail, &model_tail);
+        directory.retain(|bucket| bucket % 2 == 0);
+        model.retain(|bucket| bucket % 2 == 0);
+        assert_counts_match(&directory, &model);
+    }
+}
@@ -0,0 +1,44 @@
+use std::collections::TryReserveError;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+
+/// Misuse reported by the `try_*` methods instead of panicking or silently
+/// ignoring it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SortedVecError {
+    /// A bucket capacity of 0 was requested.
+    InvalidCapacity(usize),
+    /// A split ratio outside `1..=99` was requested.
+    InvalidSplitRatio(u8),
+    /// The element at `index` sorts before the one preceding it.
+    UnsortedInput { index: usize },
+    /// `index` is not below the container's length `len`.
+    IndexOutOfBounds { index: usize, len: usize },
+    /// The element at `index` compares inconsistently with itself or its
+    /// neighbours, which means its `Ord` implementation is not a total order.
+    InconsistentOrder { index: usize },
+    /// Room for the requested elements could not be allocated.
+    AllocationFailed(TryReserveError),
+}
+
+impl Display for SortedVecError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            SortedVecError::InvalidCapacity(capacity) => write!(f, "bucket capacity must be greater than 0, got {capacity}"),
+            SortedVecError::InvalidSplitRatio(percent) => write!(f, "split ratio must be between 1 and 99, got {percent}"),
+            SortedVecError::UnsortedInput { index } => write!(f, "input is not sorted at index {index}"),
+            SortedVecError::IndexOutOfBounds { index, len } => write!(f, "index {index} out of bounds for length {len}"),
+            SortedVecError::InconsistentOrder { index } => write!(f, "inconsistent ordering detected at index {index}"),
+            SortedVecError::AllocationFailed(error) => write!(f, "cannot allocate the requested capacity: {error}"),
+        }
+    }
+}
+
+impl Error for SortedVecError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SortedVecError::AllocationFailed(error) => Some(error),
+            _ => None,
+        }
+    }
+}
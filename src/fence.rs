@@ -0,0 +1,384 @@
+use std::cmp::Ordering;
+use std::ops::Range;
+
+use crate::directory::{BucketDirectory, Weighted};
+use crate::sorted_vec::SortedVec;
+use crate::storage::BucketStorage;
+
+/// Copies of the first element of every non-empty bucket, kept in dense
+/// chunks so locating a bucket binary searches contiguous keys instead of
+/// following a pointer into each bucket. They share the chunked layout of
+/// the bucket table, so a split or merge shifts one chunk instead of every
+/// fence after it.
+pub(crate) struct FenceIndex<T> {
+    firsts: BucketDirectory<Fence<T>>,
+    clone: fn(&T) -> T,
+}
+
+/// Compact keys of the first and last element of every non-empty bucket,
+/// projected by a user function, so bucket lookups compare integers and only
+/// touch the elements when two keys tie. Chunked like the fences.
+pub(crate) struct BoundaryKeys<T> {
+    keys: BucketDirectory<Fence<(u64, u64)>>,
+    project: fn(&T) -> u64,
+}
+
+/// An entry of the fence or boundary key directory. It stands for a bucket
+/// but holds no elements.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Fence<K>(K);
+
+impl<K> Weighted for Fence<K> {
+    fn weight(&self) -> usize {
+        0
+    }
+}
+
+impl<T: PartialOrd + Ord, S: BucketStorage<T>> SortedVec<T, S> {
+    /// Caches `project` of each bucket's first and last element next to the
+    /// bucket list. `project` must never decrease along `Ord`: `a <= b` has
+    /// to imply `project(a) <= project(b)`, e.g. a numeric id field or the
+    /// first bytes of a string key.
+    pub fn with_boundary_keys(mut self, project: fn(&T) -> u64) -> Self {
+        self.boundaries = Some(BoundaryKeys {
+            keys: BucketDirectory::new(),
+            project,
+        });
+        self.rebuild_fences();
+
+        self
+    }
+
+    /// How the first (or the last, if `last`) element of `bucket_idx`
+    /// compares to `item`, settled by the boundary keys when they differ.
+    /// `None` for an empty bucket.
+    pub(crate) fn compare_bucket_end(&self, bucket_idx: usize, last: bool, item: &T) -> Option<Ordering> {
+        let order = self.configuration.order();
+        if let Some(boundaries) = self.boundaries.as_ref().filter(|boundaries| boundaries.keys.len() == self.buckets.len()) {
+            let Fence((first_key, last_key)) = boundaries.keys[bucket_idx];
+            let key = if last { last_key } else { first_key };
+            match order.compare(&key, &(boundaries.project)(item)) {
+                Ordering::Equal => {},
+                decided => return Some(decided),
+            }
+        }
+
+        let data = &self.buckets[bucket_idx].data;
+        let end = if last { data.last() } else { data.first() }?;
+        Some(order.compare(end, item))
+    }
+
+    /// Like [`fence_bucket_index`](Self::fence_bucket_index), searching the
+    /// boundary keys instead.
+    pub(crate) fn boundary_bucket_index(&self, item: &T, strict: bool) -> Option<usize> {
+        let boundaries = self.boundaries.as_ref()?;
+        if boundaries.keys.len() != self.buckets.len() {
+            return None;
+        }
+
+        let (mut lo, mut hi) = (0, self.buckets.len());
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            let before = match self.compare_bucket_end(mid, false, item) {
+                Some(Ordering::Less) => true,
+                Some(Ordering::Equal) => !strict,
+                _ => false,
+            };
+            if before {
+                lo = mid + 1;
+            } else {
+                hi = mid;
+            }
+        }
+        Some(lo.saturating_sub(1))
+    }
+
+    /// Re-reads the boundary keys of `bucket_idx` after its first or last
+    /// element may have changed.
+    pub(crate) fn refresh_boundary(&mut self, bucket_idx: usize) {
+        let Some(boundaries) = &mut self.boundaries else {
+            return;
+        };
+
+        let bucket = self.buckets.get(bucket_idx).map(|bucket| (bucket.data.first(), bucket.data.last()));
+        match bucket {
+            Some((Some(first), Some(last))) if boundaries.keys.len() == self.buckets.len() => {
+                boundaries.keys[bucket_idx] = Fence(((boundaries.project)(first), (boundaries.project)(last)))
+            },
+            Some(_) => self.rebuild_boundaries(),
+            None => {},
+        }
+    }
+
+    fn rebuild_boundaries(&mut self) {
+        let Some(boundaries) = &mut self.boundaries else {
+            return;
+        };
+
+        let project = boundaries.project;
+        boundaries.keys = self
+            .buckets
+            .iter()
+            .filter_map(|bucket| Some(Fence((project(bucket.data.first()?), project(bucket.data.last()?)))))
+            .collect();
+    }
+
+    /// Keeps a fence index next to the buckets. Lookups get cheaper for large
+    /// elements, at the cost of a clone whenever a bucket's first element
+    /// changes.
+    pub fn with_fence_index(self) -> Self
+    where
+        T: Clone,
+    {
+        self.with_fence_clone(T::clone)
+    }
+
+    pub(crate) fn with_fence_clone(mut self, clone: fn(&T) -> T) -> Self {
+        self.fences = Some(FenceIndex {
+            firsts: BucketDirectory::new(),
+            clone,
+        });
+        self.rebuild_fences();
+
+        self
+    }
+
+    /// How the fence index copies elements, when it is enabled.
+    pub(crate) fn fence_clone(&self) -> Option<fn(&T) -> T> {
+        self.fences.as_ref().map(|fences| fences.clone)
+    }
+
+    pub fn has_fence_index(&self) -> bool {
+        self.fences.is_some()
+    }
+
+    /// The index of the last bucket whose first element is ordered before
+    /// `item` (or not after it, unless `strict`), when the fence index is
+    /// enabled.
+    pub(crate) fn fence_bucket_index(&self, item: &T, strict: bool) -> Option<usize> {
+        let fences = self.fences.as_ref()?;
+        if fences.firsts.len() != self.buckets.len() {
+            return None;
+        }
+
+        let order = self.configuration.order();
+        let idx = fences.firsts.partition_point(|Fence(first)| match order.compare(first, item) {
+            Ordering::Less => true,
+            Ordering::Equal => !strict,
+            Ordering::Greater => false,
+        });
+        Some(idx.saturating_sub(1))
+    }
+
+    /// Re-reads the first element of `bucket_idx` after it may have changed.
+    pub(crate) fn refresh_fence(&mut self, bucket_idx: usize) {
+        self.refresh_boundary(bucket_idx);
+        let Some(fences) = &mut self.fences else {
+            return;
+        };
+
+        match self.buckets[bucket_idx].data.first() {
+            Some(first) if fences.firsts.len() == self.buckets.len() => fences.firsts[bucket_idx] = Fence((fences.clone)(first)),
+            _ => self.rebuild_fences(),
+        }
+    }
+
+    /// Records a bucket newly inserted at `bucket_idx`.
+    pub(crate) fn insert_fence(&mut self, bucket_idx: usize) {
+        if let Some(boundaries) = &mut self.boundaries {
+            if boundaries.keys.len() + 1 == self.buckets.len() {
+                boundaries.keys.insert(bucket_idx, Fence((0, 0)));
+            }
+            self.refresh_boundary(bucket_idx);
+        }
+        let Some(fences) = &mut self.fences else {
+            return;
+        };
+
+        match self.buckets[bucket_idx].data.first() {
+            Some(first) if fences.firsts.len() + 1 == self.buckets.len() => {
+                fences.firsts.insert(bucket_idx, Fence((fences.clone)(first)))
+            },
+            _ => self.rebuild_fences(),
+        }
+    }
+
+    /// Forgets the bucket that was removed from `bucket_idx`.
+    pub(crate) fn remove_fence(&mut self, bucket_idx: usize) {
+        if let Some(boundaries) = &mut self.boundaries {
+            if boundaries.keys.len() == self.buckets.len() + 1 {
+                boundaries.keys.remove(bucket_idx);
+            } else {
+                self.rebuild_boundaries();
+            }
+        }
+        let Some(fences) = &mut self.fences else {
+            return;
+        };
+
+        if fences.firsts.len() == self.buckets.len() + 1 {
+            fences.firsts.remove(bucket_idx);
+        } else {
+            self.rebuild_fences();
+        }
+    }
+
+    /// Forgets the buckets that were removed from `buckets`.
+    pub(crate) fn remove_fences(&mut self, buckets: Range<usize>) {
+        if let Some(boundaries) = &mut self.boundaries {
+            if boundaries.keys.len() == self.buckets.len() + buckets.len() {
+                boundaries.keys.remove_range(buckets.clone());
+            } else {
+                self.rebuild_boundaries();
+            }
+        }
+        let Some(fences) = &mut self.fences else {
+            return;
+        };
+
+        if fences.firsts.len() == self.buckets.len() + buckets.len() {
+            fences.firsts.remove_range(buckets);
+        } else {
+            self.rebuild_fences();
+        }
+    }
+
+    /// Recomputes the fences and boundary keys, after bulk operations that
+    /// reshape many buckets at once. Operations that also change the
+    /// elements use [`rebuild_indexes`](SortedVec::rebuild_indexes).
+    pub(crate) fn rebuild_fences(&mut self) {
+        self.rebuild_boundaries();
+        let Some(fences) = &mut self.fences else {
+            return;
+        };
+
+        let clone = fences.clone;
+        fences.firsts = self
+            .buckets
+            .iter()
+            .filter_map(|bucket| bucket.data.first().map(|first| Fence(clone(first))))
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sorted_vec::{BucketConfiguration, BucketSearch, DuplicatePolicy, SortedVec};
+
+    fn assert_fences_match(sorted_vec: &SortedVec<i32>) {
+        let firsts: Vec<i32> = sorted_vec.buckets.iter().filter_map(|bucket| bucket.data.first().copied()).collect();
+        assert!(sorted_vec.fences.as_ref().unwrap().firsts.iter().map(|fence| fence.0).eq(firsts));
+    }
+
+    #[test]
+    fn fence_index_spans_several_chunks() {
+        let configuration = BucketConfiguration::builder().max_bucket_capacity(2).build();
+        let mut sorted_vec = SortedVec::new(configuration).with_fence_index().with_boundary_keys(|item| *item as u64 / 4);
+        for item in (0..400).map(|item| item * 7 % 400) {
+            sorted_vec.insert(item);
+        }
+        for item in (0..400).step_by(3) {
+            sorted_vec.remove(&item);
+        }
+
+        assert!(sorted_vec.buckets.len() > 64);
+        assert_fences_match(&sorted_vec);
+        assert_boundaries_match(&sorted_vec, |item| *item as u64 / 4);
+        assert!((0..400).all(|item| sorted_vec.contains(&item) == (item % 3 != 0)));
+
+        sorted_vec.remove_range(41..357);
+        assert_fences_match(&sorted_vec);
+        assert_boundaries_match(&sorted_vec, |item| *item as u64 / 4);
+        assert!((0..400).all(|item| sorted_vec.contains(&item) == (item % 3 != 0 && !(41..357).contains(&item))));
+    }
+
+    #[test]
+    fn fence_index_follows_inserts_and_removes() {
+        let configuration = BucketConfiguration::builder().max_bucket_capacity(3).build();
+        let mut sorted_vec = SortedVec::new(configuration).with_fence_index();
+        for item in [50, 10, 40, 20, 30, 5, 60, 35, 45] {
+            sorted_vec.insert(item);
+            assert_fences_match(&sorted_vec);
+        }
+        for item in [5, 10, 35, 60, 20] {
+            sorted_vec.remove(&item);
+            assert_fences_match(&sorted_vec);
+            assert!(sorted_vec.find_index(&item).is_none());
+        }
+
+        assert!(sorted_vec.find_index(&40).is_some());
+        assert_eq!(sorted_vec.iter().copied().collect::<Vec<_>>(), vec![30, 40, 45, 50]);
+    }
+
+    #[test]
+    fn fence_index_follows_bulk_operations() {
+        let configuration = BucketConfiguration::builder()
+            .max_bucket_capacity(2)
+            .duplicate_policy(DuplicatePolicy::Allow)
+            .build();
+        let mut sorted_vec = SortedVec::from_vec((0..10).collect(), configuration).with_fence_index();
+        assert_fences_match(&sorted_vec);
+
+        sorted_vec.insert_many([3, 3, 11]);
+        assert_fences_match(&sorted_vec);
+        sorted_vec.remove_range(2..6);
+        assert_fences_match(&sorted_vec);
+        sorted_vec.remove_all(&[0, 1]);
+        assert_fences_match(&sorted_vec);
+        sorted_vec.update(&6, |item| *item = 20);
+        assert_fences_match(&sorted_vec);
+
+        assert_eq!(sorted_vec.iter().copied().collect::<Vec<_>>(), vec![7, 8, 9, 11, 20]);
+    }
+
+    fn assert_boundaries_match(sorted_vec: &SortedVec<i32>, project: fn(&i32) -> u64) {
+        let keys: Vec<(u64, u64)> = sorted_vec
+            .buckets
+            .iter()
+            .filter_map(|bucket| Some((project(bucket.data.first()?), project(bucket.data.last()?))))
+            .collect();
+        assert!(sorted_vec.boundaries.as_ref().unwrap().keys.iter().map(|keys| keys.0).eq(keys));
+    }
+
+    #[test]
+    fn boundary_keys_follow_mutations() {
+        // Coarse on purpose, so ties fall back to the elements.
+        let tens: fn(&i32) -> u64 = |item| (*item / 10) as u64;
+        for bucket_search in [BucketSearch::Binary, BucketSearch::Galloping] {
+            let configuration = BucketConfiguration::builder()
+                .max_bucket_capacity(3)
+                .duplicate_policy(DuplicatePolicy::Allow)
+                .bucket_search(bucket_search)
+                .build();
+            let mut sorted_vec = SortedVec::new(configuration).with_boundary_keys(tens);
+            for item in [50, 10, 40, 20, 30, 5, 60, 35, 45, 45, 12, 99] {
+                sorted_vec.insert(item);
+                assert_boundaries_match(&sorted_vec, tens);
+            }
+            for item in [5, 99, 35, 60, 20] {
+                sorted_vec.remove(&item);
+                assert_boundaries_match(&sorted_vec, tens);
+                assert!(sorted_vec.find_index(&item).is_none());
+            }
+            sorted_vec.update(&45, |item| *item = 70);
+            assert_boundaries_match(&sorted_vec, tens);
+            sorted_vec.insert_many([1, 2, 3]);
+            assert_boundaries_match(&sorted_vec, tens);
+
+            for item in [1, 2, 3, 10, 12, 30, 40, 45, 50, 70] {
+                assert!(sorted_vec.find_index(&item).is_some(), "{item} not found");
+            }
+            assert_eq!(sorted_vec.iter().copied().collect::<Vec<_>>(), vec![1, 2, 3, 10, 12, 30, 40, 45, 50, 70]);
+        }
+    }
+
+    #[test]
+    fn fence_index_empty_container() {
+        let mut sorted_vec = SortedVec::new(Default::default()).with_fence_index();
+        assert!(sorted_vec.has_fence_index());
+        sorted_vec.insert(1);
+        sorted_vec.remove(&1);
+        assert_fences_match(&sorted_vec);
+        assert!(sorted_vec.find_index(&1).is_none());
+    }
+}
@@ -0,0 +1,191 @@
+use std::cmp::Ordering;
+use std::ops::{Bound, RangeBounds};
+
+use crate::builder::SortedVecBuilder;
+use crate::sorted_vec::{BucketConfiguration, SortedVec};
+use crate::storage::BucketStorage;
+
+/// Every this many elements one is copied into the fences of a frozen
+/// container.
+const FENCE_STRIDE: usize = 64;
+
+/// An immutable container holding all elements in one boxed slice, built
+/// with [`freeze`](SortedVec::freeze). Lookups binary search the slice
+/// directly and ranges come back as subslices, so a build-once index pays
+/// no per-bucket overhead. [`thaw`](Self::thaw) turns it back into a
+/// [`SortedVec`].
+#[derive(Debug)]
+pub struct FrozenSortedVec<T> {
+    items: Box<[T]>,
+    /// Copies of every `FENCE_STRIDE`-th element, kept when the container had
+    /// a fence index, so the first search steps stay in one small array.
+    fences: Option<Box<[T]>>,
+    fence_clone: Option<fn(&T) -> T>,
+    configuration: BucketConfiguration,
+}
+
+impl<T: PartialOrd + Ord, S: BucketStorage<T>> SortedVec<T, S> {
+    /// Moves every element into a [`FrozenSortedVec`].
+    pub fn freeze(self) -> FrozenSortedVec<T> {
+        let fence_clone = self.fence_clone();
+        let mut items = Vec::with_capacity(self.size);
+        for mut bucket in self.buckets {
+            items.append(&mut bucket.data.take());
+        }
+        let items = items.into_boxed_slice();
+        let fences = fence_clone.map(|clone| items.iter().step_by(FENCE_STRIDE).map(clone).collect());
+
+        FrozenSortedVec {
+            items,
+            fences,
+            fence_clone,
+            configuration: self.configuration,
+        }
+    }
+}
+
+impl<T: PartialOrd + Ord> FrozenSortedVec<T> {
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// The elements in order.
+    pub fn as_slice(&self) -> &[T] {
+        &self.items
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, T> {
+        self.items.iter()
+    }
+
+    pub fn at(&self, idx: usize) -> Option<&T> {
+        self.items.get(idx)
+    }
+
+    pub fn first(&self) -> Option<&T> {
+        self.items.first()
+    }
+
+    pub fn last(&self) -> Option<&T> {
+        self.items.last()
+    }
+
+    pub fn contains(&self, item: &T) -> bool {
+        self.find_index(item).is_some()
+    }
+
+    /// The index of the first element equal to `item`.
+    pub fn find_index(&self, item: &T) -> Option<usize> {
+        let idx = self.lower_bound(item);
+        let order = self.configuration.order();
+        self.items
+            .get(idx)
+            .filter(|probe| order.compare(*probe, item) == Ordering::Equal)
+            .map(|_| idx)
+    }
+
+    /// The elements inside `range`, as a subslice.
+    pub fn range<R: RangeBounds<T>>(&self, range: R) -> &[T] {
+        let order = self.configuration.order();
+        let start = match range.start_bound() {
+            Bound::Included(start) => self.lower_bound(start),
+            Bound::Excluded(start) => self.partition_point(|probe| order.compare(probe, start) != Ordering::Greater),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(end) => self.partition_point(|probe| order.compare(probe, end) != Ordering::Greater),
+            Bound::Excluded(end) => self.lower_bound(end),
+            Bound::Unbounded => self.items.len(),
+        };
+
+        &self.items[start..end.max(start)]
+    }
+
+    /// Builds a [`SortedVec`] with the configuration the container was frozen
+    /// with, re-enabling the fence index if it had one.
+    pub fn thaw(self) -> SortedVec<T> {
+        let mut builder = SortedVecBuilder::new(self.configuration);
+        for item in self.items {
+            if builder.push(item).is_err() {
+                unreachable!("frozen elements are in order");
+            }
+        }
+        let sorted_vec = builder.finish();
+
+        match self.fence_clone {
+            Some(clone) => sorted_vec.with_fence_clone(clone),
+            None => sorted_vec,
+        }
+    }
+
+    fn lower_bound(&self, item: &T) -> usize {
+        let order = self.configuration.order();
+        self.partition_point(|probe| order.compare(probe, item) == Ordering::Less)
+    }
+
+    /// Like `slice::partition_point`, narrowed down by the fences first.
+    fn partition_point(&self, pred: impl Fn(&T) -> bool) -> usize {
+        let Some(fences) = &self.fences else {
+            return self.items.partition_point(pred);
+        };
+
+        let fence = fences.partition_point(&pred);
+        let start = fence.saturating_sub(1) * FENCE_STRIDE;
+        let end = (fence * FENCE_STRIDE).min(self.items.len());
+        start + self.items[start..end].partition_point(pred)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sorted_vec::{DuplicatePolicy, Order};
+
+    #[test]
+    fn frozen_sorted_vec_lookups_match_the_container() {
+        let configuration = BucketConfiguration::builder()
+            .max_bucket_capacity(16)
+            .duplicate_policy(DuplicatePolicy::Allow)
+            .build();
+        let items: Vec<i32> = (0..1_000).map(|i| (i * 7) % 500).collect();
+        for fenced in [false, true] {
+            let mut sorted_vec = SortedVec::from_vec(items.clone(), configuration.clone());
+            if fenced {
+                sorted_vec = sorted_vec.with_fence_index();
+            }
+            let expected: Vec<i32> = sorted_vec.iter().copied().collect();
+            let frozen = sorted_vec.freeze();
+
+            assert_eq!(frozen.fences.is_some(), fenced);
+            assert_eq!(frozen.as_slice(), &expected[..]);
+            for item in -1..=500 {
+                assert_eq!(frozen.contains(&item), expected.contains(&item));
+                assert_eq!(frozen.find_index(&item), expected.iter().position(|probe| *probe == item));
+            }
+            assert_eq!(frozen.range(10..13), &expected[20..26]);
+            assert_eq!(frozen.range((Bound::Excluded(497), Bound::Unbounded)), &[498, 498, 499, 499]);
+            assert!(frozen.range((Bound::Included(300), Bound::Included(299))).is_empty());
+
+            let thawed = frozen.thaw();
+            assert_eq!(thawed.has_fence_index(), fenced);
+            assert!(thawed.iter().eq(expected.iter()));
+        }
+    }
+
+    #[test]
+    fn frozen_sorted_vec_keeps_descending_order() {
+        let configuration = BucketConfiguration::builder().order(Order::Descending).build();
+        let sorted_vec = SortedVec::from_vec(vec![3, 9, 1, 5], configuration);
+        let expected: Vec<i32> = sorted_vec.range((Bound::Included(8), Bound::Included(2))).copied().collect();
+        let frozen = sorted_vec.freeze();
+
+        assert_eq!(frozen.as_slice(), &[9, 5, 3, 1]);
+        assert_eq!(frozen.find_index(&3), Some(2));
+        assert_eq!(frozen.range((Bound::Included(8), Bound::Included(2))), &expected[..]);
+        assert_eq!(expected, vec![5, 3]);
+    }
+}
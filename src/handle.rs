@@ -0,0 +1,147 @@
+use std::cmp::Ordering;
+
+use crate::indexed::IndexedSortedVec;
+use crate::sorted_vec::BucketConfiguration;
+
+/// A stable reference to an element of a [`HandleSortedVec`]. It stays
+/// valid while the element moves between positions and buckets, and stops
+/// resolving once the element is removed, even if its slot is reused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Handle {
+    slot: u32,
+    generation: u32,
+}
+
+#[derive(Debug)]
+struct Tagged<T> {
+    item: T,
+    handle: Handle,
+}
+
+impl<T: PartialEq> PartialEq for Tagged<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.item == other.item
+    }
+}
+
+impl<T: Eq> Eq for Tagged<T> {}
+
+impl<T: Ord> PartialOrd for Tagged<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: Ord> Ord for Tagged<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.item.cmp(&other.item)
+    }
+}
+
+type Indexed<T> = IndexedSortedVec<Tagged<T>, Handle, fn(&Tagged<T>) -> Handle>;
+
+/// A sorted container handing out a [`Handle`] per inserted element, so
+/// outside structures can refer to entries without searching for them again.
+/// Handles are generational ids resolved through a slot map that follows
+/// the elements across shifts and splits.
+#[derive(Debug)]
+pub struct HandleSortedVec<T: PartialOrd + Ord> {
+    indexed: Indexed<T>,
+    generations: Vec<u32>,
+    free_slots: Vec<u32>,
+}
+
+impl<T: PartialOrd + Ord> HandleSortedVec<T> {
+    pub fn new(configuration: BucketConfiguration) -> Self {
+        HandleSortedVec {
+            indexed: IndexedSortedVec::new(configuration, |tagged| tagged.handle),
+            generations: Vec::new(),
+            free_slots: Vec::new(),
+        }
+    }
+
+    /// Inserts `item` and returns its handle, or `None` if it was rejected
+    /// as a duplicate.
+    pub fn insert_with_handle(&mut self, item: T) -> Option<Handle> {
+        let handle = match self.free_slots.pop() {
+            Some(slot) => Handle { slot, generation: self.generations[slot as usize] },
+            None => {
+                self.generations.push(0);
+                Handle { slot: self.generations.len() as u32 - 1, generation: 0 }
+            },
+        };
+
+        self.indexed.insert(Tagged { item, handle });
+        if self.indexed.contains_key(&handle) {
+            return Some(handle);
+        }
+
+        self.release(handle);
+        None
+    }
+
+    pub fn get_by_handle(&self, handle: Handle) -> Option<&T> {
+        self.indexed.get(&handle).map(|tagged| &tagged.item)
+    }
+
+    pub fn remove_by_handle(&mut self, handle: Handle) -> Option<T> {
+        let removed = self.indexed.remove(&handle)?;
+        self.release(handle);
+        Some(removed.item)
+    }
+
+    /// Bumps the slot's generation so outstanding handles to it go stale.
+    fn release(&mut self, handle: Handle) {
+        let generation = &mut self.generations[handle.slot as usize];
+        *generation = generation.wrapping_add(1);
+        self.free_slots.push(handle.slot);
+    }
+
+    pub fn len(&self) -> usize {
+        self.indexed.size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &T> + ExactSizeIterator {
+        self.indexed.iter().map(|tagged| &tagged.item)
+    }
+
+    /// Yields the elements in order together with their handles.
+    pub fn iter_with_handles(&self) -> impl DoubleEndedIterator<Item = (Handle, &T)> + ExactSizeIterator {
+        self.indexed.iter().map(|tagged| (tagged.handle, &tagged.item))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn handles_survive_splits_and_shifts() {
+        let configuration = BucketConfiguration::builder().max_bucket_capacity(2).build();
+        let mut sorted_vec = HandleSortedVec::new(configuration);
+        let handles: Vec<_> = (0..20).rev().map(|item| (item, sorted_vec.insert_with_handle(item).unwrap())).collect();
+
+        for (item, handle) in &handles {
+            assert_eq!(sorted_vec.get_by_handle(*handle), Some(item));
+        }
+        assert_eq!(sorted_vec.insert_with_handle(5), None);
+        assert_eq!(sorted_vec.iter().copied().collect::<Vec<_>>(), (0..20).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn removed_handles_go_stale_when_slots_are_reused() {
+        let mut sorted_vec = HandleSortedVec::new(Default::default());
+        let first = sorted_vec.insert_with_handle(1).unwrap();
+        assert_eq!(sorted_vec.remove_by_handle(first), Some(1));
+
+        let second = sorted_vec.insert_with_handle(2).unwrap();
+        assert_eq!(first.slot, second.slot);
+        assert_eq!(sorted_vec.get_by_handle(first), None);
+        assert_eq!(sorted_vec.remove_by_handle(first), None);
+        assert_eq!(sorted_vec.iter_with_handles().collect::<Vec<_>>(), vec![(second, &2)]);
+    }
+}
@@ -0,0 +1,183 @@
+use std::ops::Deref;
+
+use crate::sorted_vec::SortedVec;
+
+#[derive(Debug, Clone)]
+enum Delta<T> {
+    Inserted(T),
+    Removed(T),
+}
+
+/// Wraps a [`SortedVec`] and records every effective insert and remove so they
+/// can be rolled back and replayed. Each recorded mutation bumps the version
+/// by one; version `0` is the state the wrapper was created with. Making a new
+/// change after undoing drops the undone changes.
+///
+/// Undoing an insert removes an element equal to the inserted one, which with
+/// [`DuplicatePolicy::Allow`](crate::sorted_vec::DuplicatePolicy::Allow) may
+/// be a different but equal element.
+#[derive(Debug)]
+pub struct HistorySortedVec<T: PartialOrd + Ord> {
+    sorted_vec: SortedVec<T>,
+    deltas: Vec<Delta<T>>,
+    version: usize,
+}
+
+impl<T: PartialOrd + Ord + Clone> HistorySortedVec<T> {
+    pub fn new(sorted_vec: SortedVec<T>) -> Self {
+        HistorySortedVec {
+            sorted_vec,
+            deltas: Vec::new(),
+            version: 0,
+        }
+    }
+
+    pub fn insert(&mut self, item: T) {
+        if self.sorted_vec.insert_position(item.clone()).is_some() {
+            self.record(Delta::Inserted(item));
+        }
+    }
+
+    pub fn remove(&mut self, item: &T) {
+        if let Some((removed, _)) = self.sorted_vec.remove_position(item) {
+            self.record(Delta::Removed(removed));
+        }
+    }
+
+    fn record(&mut self, delta: Delta<T>) {
+        self.deltas.truncate(self.version);
+        self.deltas.push(delta);
+        self.version += 1;
+    }
+
+    pub fn version(&self) -> usize {
+        self.version
+    }
+
+    /// The newest version reachable with [`redo`](Self::redo).
+    pub fn latest_version(&self) -> usize {
+        self.deltas.len()
+    }
+
+    /// Rolls back the last change. Returns `false` when there is nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        if self.version == 0 {
+            return false;
+        }
+
+        self.version -= 1;
+        match &self.deltas[self.version] {
+            Delta::Inserted(item) => self.sorted_vec.remove(item),
+            Delta::Removed(item) => self.sorted_vec.insert(item.clone()),
+        }
+        true
+    }
+
+    /// Reapplies the last undone change. Returns `false` when there is nothing
+    /// to redo.
+    pub fn redo(&mut self) -> bool {
+        let Some(delta) = self.deltas.get(self.version) else {
+            return false;
+        };
+
+        match delta {
+            Delta::Inserted(item) => self.sorted_vec.insert(item.clone()),
+            Delta::Removed(item) => self.sorted_vec.remove(item),
+        }
+        self.version += 1;
+        true
+    }
+
+    /// Undoes or redoes changes until `version` is reached. Returns `false`,
+    /// leaving the contents untouched, if `version` is newer than
+    /// [`latest_version`](Self::latest_version).
+    pub fn revert_to(&mut self, version: usize) -> bool {
+        if version > self.deltas.len() {
+            return false;
+        }
+
+        while self.version > version {
+            self.undo();
+        }
+        while self.version < version {
+            self.redo();
+        }
+        true
+    }
+
+    /// Forgets the recorded changes, making the current state version `0`.
+    pub fn clear_history(&mut self) {
+        self.deltas.clear();
+        self.version = 0;
+    }
+
+    pub fn into_inner(self) -> SortedVec<T> {
+        self.sorted_vec
+    }
+}
+
+impl<T: PartialOrd + Ord> Deref for HistorySortedVec<T> {
+    type Target = SortedVec<T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.sorted_vec
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contents(history: &HistorySortedVec<i32>) -> Vec<i32> {
+        history.iter().copied().collect()
+    }
+
+    #[test]
+    fn history_undo_and_redo() {
+        let mut history = HistorySortedVec::new(SortedVec::from_vec(vec![1, 5], Default::default()));
+        history.insert(3);
+        history.remove(&1);
+        history.insert(3);
+        assert_eq!(history.version(), 2);
+
+        assert!(history.undo());
+        assert_eq!(contents(&history), vec![1, 3, 5]);
+        assert!(history.undo());
+        assert_eq!(contents(&history), vec![1, 5]);
+        assert!(!history.undo());
+
+        assert!(history.redo());
+        assert!(history.redo());
+        assert!(!history.redo());
+        assert_eq!(contents(&history), vec![3, 5]);
+    }
+
+    #[test]
+    fn history_new_change_drops_redo() {
+        let mut history = HistorySortedVec::new(SortedVec::new(Default::default()));
+        history.insert(1);
+        history.insert(2);
+        history.undo();
+        history.insert(7);
+
+        assert_eq!(history.latest_version(), 2);
+        assert!(!history.redo());
+        assert_eq!(contents(&history), vec![1, 7]);
+    }
+
+    #[test]
+    fn history_revert_to_version() {
+        let mut history = HistorySortedVec::new(SortedVec::new(Default::default()));
+        for item in [4, 2, 8, 6] {
+            history.insert(item);
+        }
+        history.remove(&2);
+
+        assert!(history.revert_to(2));
+        assert_eq!(contents(&history), vec![2, 4]);
+        assert!(history.revert_to(5));
+        assert_eq!(contents(&history), vec![4, 6, 8]);
+        assert!(!history.revert_to(6));
+        assert_eq!(history.version(), 5);
+    }
+}
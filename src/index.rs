@@ -0,0 +1,50 @@
+/// The integer type positions and counts are kept as where the crate stores
+/// one per element or per bucket: the locations and bucket ids of
+/// [`IndexedSortedVec`](crate::indexed::IndexedSortedVec) and the element
+/// counts of the bucket table. It is `usize` unless the `compact-index`
+/// feature makes it `u32`, which halves that bookkeeping but limits each
+/// bucket, the number of buckets, and the elements in a chunk of the bucket
+/// table to `u32::MAX`. [`Handle`](crate::handle::Handle)s always use `u32`
+/// slots.
+#[cfg(not(feature = "compact-index"))]
+pub type Idx = usize;
+#[cfg(feature = "compact-index")]
+pub type Idx = u32;
+
+#[cfg(not(feature = "compact-index"))]
+pub(crate) fn narrow(idx: usize) -> Idx {
+    idx
+}
+
+/// Panics if `idx` does not fit, rather than wrapping to another position.
+#[cfg(feature = "compact-index")]
+pub(crate) fn narrow(idx: usize) -> Idx {
+    Idx::try_from(idx).unwrap_or_else(|_| panic!("index {idx} does not fit the compact index type"))
+}
+
+#[cfg(not(feature = "compact-index"))]
+pub(crate) fn widen(idx: Idx) -> usize {
+    idx
+}
+
+#[cfg(feature = "compact-index")]
+pub(crate) fn widen(idx: Idx) -> usize {
+    idx as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn narrow_and_widen_round_trip() {
+        assert_eq!(widen(narrow(70_000)), 70_000);
+    }
+
+    #[cfg(feature = "compact-index")]
+    #[test]
+    #[should_panic(expected = "does not fit")]
+    fn narrow_rejects_large_indices() {
+        narrow(u32::MAX as usize + 1);
+    }
+}
@@ -0,0 +1,173 @@
+use std::collections::HashMap;
+use std::fmt::{self, Debug, Formatter};
+use std::hash::Hash;
+use std::ops::Deref;
+
+use crate::index::{narrow, widen, Idx};
+use crate::sorted_vec::{BucketConfiguration, SortedVec};
+use crate::storage::BucketStorage;
+
+/// A [`SortedVec`] with a hash index from an identity key, e.g. a flight id,
+/// to where each element sits, so elements can be looked up and removed by
+/// key in O(1) while iteration stays in sorted order. Reads go through
+/// `Deref`.
+///
+/// Locations are kept as a stable bucket id plus the offset inside that
+/// bucket, both stored as [`Idx`]. Inserts and removals only re-index the
+/// elements of the bucket they shift, and splits only the two halves.
+pub struct IndexedSortedVec<T: PartialOrd + Ord, K, F> {
+    sorted_vec: SortedVec<T>,
+    key: F,
+    locations: HashMap<K, (Idx, Idx)>,
+    /// Stable id of each bucket, by bucket index.
+    bucket_ids: Vec<Idx>,
+    /// Current index of each bucket, by bucket id.
+    bucket_indices: Vec<Idx>,
+    free_ids: Vec<Idx>,
+}
+
+impl<T: PartialOrd + Ord, K: Hash + Eq, F: Fn(&T) -> K> IndexedSortedVec<T, K, F> {
+    pub fn new(configuration: BucketConfiguration, key: F) -> Self {
+        IndexedSortedVec {
+            sorted_vec: SortedVec::new(configuration),
+            key,
+            locations: HashMap::new(),
+            bucket_ids: vec![0],
+            bucket_indices: vec![0],
+            free_ids: Vec::new(),
+        }
+    }
+
+    /// Inserts `item`, replacing and returning the element with the same key.
+    /// If `item` is rejected as a duplicate under the container's order, the
+    /// replaced element is still removed.
+    pub fn insert(&mut self, item: T) -> Option<T> {
+        let replaced = self.remove(&(self.key)(&item));
+        let Some(((bucket_idx, item_idx), split)) = self.sorted_vec.insert_tracked(item) else {
+            return replaced;
+        };
+
+        match split {
+            Some(split_idx) => {
+                let id = self.free_ids.pop().unwrap_or(narrow(self.bucket_indices.len()));
+                self.bucket_ids.insert(split_idx + 1, id);
+                self.reindex_buckets();
+                self.reindex(split_idx, 0);
+                self.reindex(split_idx + 1, 0);
+            },
+            None => self.reindex(bucket_idx, item_idx),
+        }
+
+        replaced
+    }
+
+    pub fn get(&self, key: &K) -> Option<&T> {
+        let &(id, item_idx) = self.locations.get(key)?;
+        self.sorted_vec.buckets[widen(self.bucket_indices[widen(id)])].data.get(widen(item_idx))
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.locations.contains_key(key)
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<T> {
+        let (id, item_idx) = self.locations.remove(key)?;
+        let (bucket_idx, item_idx) = (widen(self.bucket_indices[widen(id)]), widen(item_idx));
+        let buckets = self.sorted_vec.buckets.len();
+        let removed = self.sorted_vec.remove_at((bucket_idx, item_idx));
+
+        if self.sorted_vec.buckets.len() < buckets {
+            self.free_ids.push(self.bucket_ids.remove(bucket_idx));
+            self.reindex_buckets();
+        } else {
+            self.reindex(bucket_idx, item_idx);
+        }
+
+        Some(removed)
+    }
+
+    pub fn into_inner(self) -> SortedVec<T> {
+        self.sorted_vec
+    }
+
+    /// Records the location of every element of a bucket from `from` on.
+    fn reindex(&mut self, bucket_idx: usize, from: usize) {
+        let id = self.bucket_ids[bucket_idx];
+        let bucket = &self.sorted_vec.buckets[bucket_idx];
+        for (item_idx, item) in bucket.data.iter().enumerate().skip(from) {
+            self.locations.insert((self.key)(item), (id, narrow(item_idx)));
+        }
+    }
+
+    fn reindex_buckets(&mut self) {
+        for (bucket_idx, &id) in self.bucket_ids.iter().enumerate() {
+            if widen(id) == self.bucket_indices.len() {
+                self.bucket_indices.push(narrow(bucket_idx));
+            } else {
+                self.bucket_indices[widen(id)] = narrow(bucket_idx);
+            }
+        }
+    }
+}
+
+impl<T: PartialOrd + Ord, K, F> Deref for IndexedSortedVec<T, K, F> {
+    type Target = SortedVec<T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.sorted_vec
+    }
+}
+
+impl<T: PartialOrd + Ord + Debug, K, F> Debug for IndexedSortedVec<T, K, F> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IndexedSortedVec").field("sorted_vec", &self.sorted_vec).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sorted_vec::DuplicatePolicy;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+    struct Flight {
+        price: u32,
+        id: u32,
+    }
+
+    fn flight(id: u32, price: u32) -> Flight {
+        Flight { price, id }
+    }
+
+    #[test]
+    fn indexed_sorted_vec_lookup_survives_splits_and_removals() {
+        let configuration = BucketConfiguration::builder().max_bucket_capacity(3).build();
+        let mut indexed = IndexedSortedVec::new(configuration, |flight: &Flight| flight.id);
+        for id in 0..40 {
+            indexed.insert(flight(id, (id * 37) % 100));
+        }
+        for id in (0..40).step_by(3) {
+            assert_eq!(indexed.remove(&id).map(|flight| flight.id), Some(id));
+        }
+
+        for id in 0..40 {
+            let expected = (id % 3 != 0).then(|| flight(id, (id * 37) % 100));
+            assert_eq!(indexed.get(&id), expected.as_ref());
+        }
+        assert!(indexed.iter().is_sorted());
+        assert_eq!(indexed.size, 26);
+    }
+
+    #[test]
+    fn indexed_sorted_vec_insert_replaces_same_key() {
+        let configuration = BucketConfiguration::builder().duplicate_policy(DuplicatePolicy::Allow).build();
+        let mut indexed = IndexedSortedVec::new(configuration, |flight: &Flight| flight.id);
+        indexed.insert(flight(1, 300));
+        indexed.insert(flight(2, 200));
+
+        assert_eq!(indexed.insert(flight(1, 100)), Some(flight(1, 300)));
+        assert_eq!(indexed.first(), Some(&flight(1, 100)));
+        assert_eq!(indexed.get(&2), Some(&flight(2, 200)));
+        assert!(!indexed.contains_key(&3));
+    }
+}
@@ -0,0 +1,180 @@
+use std::cmp::Ordering;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+
+use crate::sorted_vec::{DuplicatePolicy, SortedVec};
+use crate::storage::BucketStorage;
+
+/// One broken invariant. Elements are located by `(bucket_idx, item_idx)`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Violation {
+    /// Comparing the element with itself did not return `Equal`.
+    NotReflexive { position: (usize, usize) },
+    /// Comparing the two neighbours gave results that are not each other's
+    /// reverse.
+    NotAntisymmetric { previous: (usize, usize), position: (usize, usize) },
+    /// The element sorts before its predecessor.
+    OutOfOrder { previous: (usize, usize), position: (usize, usize) },
+    /// The element equals its predecessor although duplicates are rejected.
+    Duplicate { previous: (usize, usize), position: (usize, usize) },
+    /// An empty bucket next to other buckets.
+    EmptyBucket { bucket_idx: usize },
+    /// The stored length does not match the elements in the buckets.
+    SizeMismatch { recorded: usize, actual: usize },
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::NotReflexive { position } => write!(f, "element {position:?} does not compare equal to itself"),
+            Violation::NotAntisymmetric { previous, position } => {
+                write!(f, "elements {previous:?} and {position:?} compare inconsistently in the two directions")
+            },
+            Violation::OutOfOrder { previous, position } => write!(f, "element {position:?} sorts before element {previous:?}"),
+            Violation::Duplicate { previous, position } => write!(f, "element {position:?} duplicates element {previous:?}"),
+            Violation::EmptyBucket { bucket_idx } => write!(f, "bucket {bucket_idx} is empty"),
+            Violation::SizeMismatch { recorded, actual } => write!(f, "length is {recorded} but the buckets hold {actual} elements"),
+        }
+    }
+}
+
+/// Every violation found by [`check_integrity`](SortedVec::check_integrity),
+/// in bucket order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IntegrityReport {
+    pub violations: Vec<Violation>,
+}
+
+impl Display for IntegrityReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} integrity violation(s)", self.violations.len())?;
+        for violation in &self.violations {
+            write!(f, "\n  {violation}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Error for IntegrityReport {}
+
+impl<T: PartialOrd + Ord, S: BucketStorage<T>> SortedVec<T, S> {
+    /// Walks every element and reports each place where the layout disagrees
+    /// with the comparator, e.g. because the element type's `Ord` is not a
+    /// total order. Costs two comparisons per element, so it is meant for
+    /// debugging and tests.
+    pub fn check_integrity(&self) -> Result<(), IntegrityReport> {
+        let order = self.configuration.order();
+        let strict = self.configuration.duplicate_policy() == DuplicatePolicy::Reject;
+        let mut violations = Vec::new();
+        let mut previous: Option<((usize, usize), &T)> = None;
+        let mut actual = 0;
+
+        for (bucket_idx, bucket) in self.buckets.iter().enumerate() {
+            if bucket.len() == 0 && self.buckets.len() > 1 {
+                violations.push(Violation::EmptyBucket { bucket_idx });
+            }
+            for (item_idx, item) in bucket.data.iter().enumerate() {
+                let position = (bucket_idx, item_idx);
+                if order.compare(item, item) != Ordering::Equal {
+                    violations.push(Violation::NotReflexive { position });
+                }
+                if let Some((previous, before)) = previous {
+                    let forward = order.compare(before, item);
+                    if forward != order.compare(item, before).reverse() {
+                        violations.push(Violation::NotAntisymmetric { previous, position });
+                    } else if forward == Ordering::Greater {
+                        violations.push(Violation::OutOfOrder { previous, position });
+                    } else if forward == Ordering::Equal && strict {
+                        violations.push(Violation::Duplicate { previous, position });
+                    }
+                }
+                previous = Some((position, item));
+            }
+            actual += bucket.len();
+        }
+        if actual != self.size {
+            violations.push(Violation::SizeMismatch { recorded: self.size, actual });
+        }
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(IntegrityReport { violations })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bucket::Bucket;
+    use crate::sorted_vec::BucketConfiguration;
+
+    #[test]
+    fn check_integrity_passes_on_a_healthy_container() {
+        let config = BucketConfiguration::builder().max_bucket_capacity(3).build();
+        let sorted_vec = SortedVec::from_vec((0..20).rev().collect(), config);
+
+        assert_eq!(sorted_vec.check_integrity(), Ok(()));
+        assert_eq!(SortedVec::<i32>::new(Default::default()).check_integrity(), Ok(()));
+    }
+
+    #[test]
+    fn check_integrity_reports_each_violation() {
+        let config = BucketConfiguration::builder().max_bucket_capacity(3).build();
+        let mut sorted_vec = SortedVec::new(config);
+        sorted_vec.buckets = vec![
+            Bucket::from_vec(vec![0, 1, 2]),
+            Bucket::from_vec(vec![5, 4, 3]),
+            Bucket::from_vec(vec![6, 6]),
+            Bucket::default(),
+        ]
+        .into();
+        sorted_vec.size = 10;
+
+        let report = sorted_vec.check_integrity().unwrap_err();
+        assert_eq!(
+            report.violations,
+            vec![
+                Violation::OutOfOrder { previous: (1, 0), position: (1, 1) },
+                Violation::OutOfOrder { previous: (1, 1), position: (1, 2) },
+                Violation::Duplicate { previous: (2, 0), position: (2, 1) },
+                Violation::EmptyBucket { bucket_idx: 3 },
+                Violation::SizeMismatch { recorded: 10, actual: 8 },
+            ]
+        );
+        assert!(report.to_string().starts_with("5 integrity violation(s)\n  element (1, 1) sorts before element (1, 0)"));
+    }
+
+    #[derive(Debug, PartialEq, Eq)]
+    struct AlwaysLess(u8);
+
+    impl PartialOrd for AlwaysLess {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for AlwaysLess {
+        fn cmp(&self, _: &Self) -> Ordering {
+            Ordering::Less
+        }
+    }
+
+    #[test]
+    fn check_integrity_reports_a_broken_ord() {
+        let mut sorted_vec = SortedVec::new(Default::default());
+        sorted_vec.buckets[0].data.extend([AlwaysLess(1), AlwaysLess(2)]);
+        sorted_vec.size = 2;
+
+        assert_eq!(
+            sorted_vec.check_integrity().unwrap_err().violations,
+            vec![
+                Violation::NotReflexive { position: (0, 0) },
+                Violation::NotReflexive { position: (0, 1) },
+                Violation::NotAntisymmetric { previous: (0, 0), position: (0, 1) },
+            ]
+        );
+    }
+}
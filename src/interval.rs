@@ -0,0 +1,179 @@
+use std::cmp::Ordering;
+use std::iter::successors;
+use std::ops::{Deref, Range};
+
+use crate::sorted_vec::{BucketConfiguration, SortedVec};
+
+/// A half-open interval `[start, end)`. Intervals are ordered by their start
+/// only, which is unique among the non-overlapping intervals of a
+/// [`SortedIntervalVec`].
+#[derive(Debug, Clone)]
+pub struct Interval<T> {
+    pub start: T,
+    pub end: T,
+}
+
+impl<T: Clone> Interval<T> {
+    pub fn to_range(&self) -> Range<T> {
+        self.start.clone()..self.end.clone()
+    }
+
+    fn at(point: &T) -> Self {
+        Interval { start: point.clone(), end: point.clone() }
+    }
+}
+
+impl<T: Ord> PartialEq for Interval<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.start == other.start
+    }
+}
+
+impl<T: Ord> Eq for Interval<T> {}
+
+impl<T: Ord> PartialOrd for Interval<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: Ord> Ord for Interval<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.start.cmp(&other.start)
+    }
+}
+
+/// A set of points stored as non-overlapping intervals sorted by start.
+/// Overlapping and touching intervals are coalesced on insert, and removing
+/// the middle of an interval splits it. Reads of the stored intervals go
+/// through `Deref`.
+#[derive(Debug)]
+pub struct SortedIntervalVec<T: Ord> {
+    intervals: SortedVec<Interval<T>>,
+}
+
+impl<T: Ord + Clone> SortedIntervalVec<T> {
+    pub fn new(configuration: BucketConfiguration) -> Self {
+        SortedIntervalVec {
+            intervals: SortedVec::new(configuration),
+        }
+    }
+
+    /// Adds every point of `range`, merging it with the intervals it overlaps
+    /// or touches. Empty ranges are ignored.
+    pub fn insert_interval(&mut self, range: Range<T>) {
+        if range.start >= range.end {
+            return;
+        }
+
+        let (mut start, mut end) = (range.start, range.end);
+        if let Some(before) = self.intervals.lt(&Interval::at(&start)).filter(|before| before.end >= start) {
+            start = before.start.clone();
+        }
+        if let Some(last) = self.intervals.le(&Interval::at(&end)).filter(|last| last.end > end) {
+            end = last.end.clone();
+        }
+
+        self.intervals.remove_range(Interval::at(&start)..=Interval::at(&end));
+        self.intervals.insert(Interval { start, end });
+    }
+
+    /// Removes every point of `range`, trimming or splitting the intervals it
+    /// cuts through.
+    pub fn remove_interval(&mut self, range: Range<T>) {
+        if range.start >= range.end {
+            return;
+        }
+
+        let (start, end) = (Interval::at(&range.start), Interval::at(&range.end));
+        let head = self.intervals.lt(&start).filter(|before| before.end > range.start).map(|before| before.start.clone());
+        let tail = self.intervals.lt(&end).filter(|last| last.end > range.end).map(|last| last.end.clone());
+
+        self.intervals.remove_range(head.as_ref().map_or(start, Interval::at)..end);
+        if let Some(head) = head {
+            self.intervals.insert(Interval { start: head, end: range.start });
+        }
+        if let Some(tail) = tail {
+            self.intervals.insert(Interval { start: range.end, end: tail });
+        }
+    }
+
+    pub fn contains_point(&self, point: &T) -> bool {
+        self.intervals.le(&Interval::at(point)).is_some_and(|interval| *point < interval.end)
+    }
+
+    /// The intervals sharing at least one point with `range`, in order.
+    pub fn overlapping(&self, range: Range<T>) -> impl Iterator<Item = &Interval<T>> {
+        let mut first = self.intervals.lower_bound_position(&Interval::at(&range.start));
+        if let Some(before) = self.intervals.step_back(first) {
+            if self.intervals.get_position(before).is_some_and(|interval| interval.end > range.start) {
+                first = before;
+            }
+        }
+
+        successors(Some(first), |&position| self.intervals.step_forward(position))
+            .map_while(|position| self.intervals.get_position(position))
+            .take_while(move |interval| interval.start < range.end)
+    }
+}
+
+impl<T: Ord> Deref for SortedIntervalVec<T> {
+    type Target = SortedVec<Interval<T>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.intervals
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ranges(intervals: &SortedIntervalVec<i32>) -> Vec<Range<i32>> {
+        intervals.iter().map(Interval::to_range).collect()
+    }
+
+    #[test]
+    fn interval_insert_coalesces() {
+        let configuration = BucketConfiguration::builder().max_bucket_capacity(2).build();
+        let mut intervals = SortedIntervalVec::new(configuration);
+        for range in [10..20, 30..40, 50..60, 70..80, 0..5] {
+            intervals.insert_interval(range);
+        }
+        intervals.insert_interval(15..30);
+        intervals.insert_interval(55..72);
+        intervals.insert_interval(5..5);
+
+        assert_eq!(ranges(&intervals), vec![0..5, 10..40, 50..80]);
+        assert!(intervals.contains_point(&39));
+        assert!(!intervals.contains_point(&40));
+        assert!(!intervals.contains_point(&7));
+    }
+
+    #[test]
+    fn interval_remove_splits_and_trims() {
+        let configuration = BucketConfiguration::builder().max_bucket_capacity(2).build();
+        let mut intervals = SortedIntervalVec::new(configuration);
+        for range in [0..10, 20..30, 40..50] {
+            intervals.insert_interval(range);
+        }
+        intervals.remove_interval(3..5);
+        intervals.remove_interval(25..45);
+
+        assert_eq!(ranges(&intervals), vec![0..3, 5..10, 20..25, 45..50]);
+        intervals.remove_interval(0..100);
+        assert_eq!(intervals.size, 0);
+    }
+
+    #[test]
+    fn interval_overlapping_query() {
+        let mut intervals = SortedIntervalVec::new(Default::default());
+        for range in [0..10, 20..30, 40..50] {
+            intervals.insert_interval(range);
+        }
+
+        let overlapping: Vec<_> = intervals.overlapping(5..40).map(Interval::to_range).collect();
+        assert_eq!(overlapping, vec![0..10, 20..30]);
+        assert_eq!(intervals.overlapping(10..20).count(), 0);
+    }
+}
@@ -0,0 +1,192 @@
+use std::io::{self, Error, ErrorKind, Read, Write};
+use std::ops::Deref;
+
+use crate::codec::Codec;
+use crate::sorted_vec::{BucketConfiguration, SortedVec};
+
+const INSERT: u8 = 1;
+const REMOVE: u8 = 2;
+
+/// Wraps a [`SortedVec`] so every mutation is appended to a write-ahead log
+/// before it is applied. On startup, [`recover`](Self::recover) loads the last
+/// compacted snapshot and replays the log on top of it.
+///
+/// Each log record is a one byte tag (insert or remove) followed by the
+/// [`Codec`] encoding of the element.
+#[derive(Debug)]
+pub struct JournaledSortedVec<T: PartialOrd + Ord + Codec, W: Write> {
+    sorted_vec: SortedVec<T>,
+    wal: W,
+    entries: usize,
+    compaction_threshold: usize,
+}
+
+impl<T: PartialOrd + Ord + Codec, W: Write> JournaledSortedVec<T, W> {
+    /// `wal` should already hold every entry applied to `sorted_vec` since its
+    /// last snapshot, typically by opening the log file in append mode.
+    pub fn new(sorted_vec: SortedVec<T>, wal: W) -> Self {
+        JournaledSortedVec {
+            sorted_vec,
+            wal,
+            entries: 0,
+            compaction_threshold: usize::MAX,
+        }
+    }
+
+    /// After this many logged entries [`needs_compaction`](Self::needs_compaction)
+    /// starts returning `true`.
+    pub fn with_compaction_threshold(mut self, compaction_threshold: usize) -> Self {
+        self.compaction_threshold = compaction_threshold;
+        self
+    }
+
+    pub fn insert(&mut self, item: T) -> io::Result<()> {
+        self.log(INSERT, &item)?;
+        self.sorted_vec.insert(item);
+
+        Ok(())
+    }
+
+    pub fn remove(&mut self, item: &T) -> io::Result<()> {
+        self.log(REMOVE, item)?;
+        self.sorted_vec.remove(item);
+
+        Ok(())
+    }
+
+    fn log(&mut self, tag: u8, item: &T) -> io::Result<()> {
+        tag.encode(&mut self.wal)?;
+        item.encode(&mut self.wal)?;
+        self.wal.flush()?;
+        self.entries += 1;
+
+        Ok(())
+    }
+
+    /// Entries logged since the last compaction.
+    pub fn entries(&self) -> usize {
+        self.entries
+    }
+
+    pub fn needs_compaction(&self) -> bool {
+        self.entries >= self.compaction_threshold
+    }
+
+    /// Writes the current contents as a snapshot and switches to `new_wal`,
+    /// returning the old log so the caller can discard it.
+    pub fn compact<S: Write>(&mut self, snapshot: S, new_wal: W) -> io::Result<W> {
+        self.sorted_vec.write_to(snapshot)?;
+        self.entries = 0;
+
+        Ok(std::mem::replace(&mut self.wal, new_wal))
+    }
+
+    pub fn into_parts(self) -> (SortedVec<T>, W) {
+        (self.sorted_vec, self.wal)
+    }
+
+    /// Rebuilds the container from an optional snapshot plus the log written
+    /// after it.
+    pub fn recover<S: Read, L: Read>(snapshot: Option<S>, wal: L, configuration: BucketConfiguration) -> io::Result<SortedVec<T>> {
+        let mut sorted_vec = match snapshot {
+            Some(snapshot) => SortedVec::read_from(snapshot, configuration)?,
+            None => SortedVec::new(configuration),
+        };
+        Self::replay(&mut sorted_vec, wal)?;
+
+        Ok(sorted_vec)
+    }
+
+    /// Applies every record in `wal` and returns how many were applied. A
+    /// record cut short at the end of the log, as left by a crash mid-write,
+    /// is ignored.
+    pub fn replay<L: Read>(sorted_vec: &mut SortedVec<T>, mut wal: L) -> io::Result<usize> {
+        let mut applied = 0;
+        loop {
+            let mut tag = [0; 1];
+            if wal.read(&mut tag)? == 0 {
+                return Ok(applied);
+            }
+
+            let item = match T::decode(&mut wal) {
+                Ok(item) => item,
+                Err(error) if error.kind() == ErrorKind::UnexpectedEof => return Ok(applied),
+                Err(error) => return Err(error),
+            };
+            match tag[0] {
+                INSERT => sorted_vec.insert(item),
+                REMOVE => sorted_vec.remove(&item),
+                _ => return Err(Error::new(ErrorKind::InvalidData, "invalid journal record")),
+            }
+            applied += 1;
+        }
+    }
+}
+
+impl<T: PartialOrd + Ord + Codec, W: Write> Deref for JournaledSortedVec<T, W> {
+    type Target = SortedVec<T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.sorted_vec
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn journal_logs_and_replays() {
+        let mut journal = JournaledSortedVec::new(SortedVec::new(Default::default()), Vec::new());
+        journal.insert(3u32).unwrap();
+        journal.insert(1).unwrap();
+        journal.remove(&3).unwrap();
+        journal.insert(2).unwrap();
+        assert_eq!(journal.entries(), 4);
+
+        let (sorted_vec, wal) = journal.into_parts();
+        let recovered = JournaledSortedVec::<u32, Vec<u8>>::recover(None::<&[u8]>, wal.as_slice(), Default::default()).unwrap();
+        assert!(recovered.iter().eq(sorted_vec.iter()));
+    }
+
+    #[test]
+    fn journal_compaction_starts_a_new_log() {
+        let mut journal = JournaledSortedVec::new(SortedVec::new(Default::default()), Vec::new())
+            .with_compaction_threshold(2);
+        journal.insert(5u64).unwrap();
+        journal.insert(7).unwrap();
+        assert!(journal.needs_compaction());
+
+        let mut snapshot = Vec::new();
+        let old_wal = journal.compact(&mut snapshot, Vec::new()).unwrap();
+        assert!(!old_wal.is_empty());
+        assert!(!journal.needs_compaction());
+        journal.remove(&5).unwrap();
+
+        let (_, wal) = journal.into_parts();
+        let recovered = JournaledSortedVec::<u64, Vec<u8>>::recover(Some(snapshot.as_slice()), wal.as_slice(), Default::default()).unwrap();
+        assert_eq!(recovered.iter().copied().collect::<Vec<_>>(), vec![7]);
+    }
+
+    #[test]
+    fn journal_replay_ignores_torn_tail() {
+        let mut journal = JournaledSortedVec::new(SortedVec::new(Default::default()), Vec::new());
+        journal.insert(10u32).unwrap();
+        journal.insert(20).unwrap();
+        let (_, mut wal) = journal.into_parts();
+        wal.truncate(wal.len() - 2);
+
+        let mut sorted_vec = SortedVec::new(Default::default());
+        let applied = JournaledSortedVec::<u32, Vec<u8>>::replay(&mut sorted_vec, wal.as_slice()).unwrap();
+        assert_eq!(applied, 1);
+        assert_eq!(sorted_vec.first(), Some(&10));
+    }
+
+    #[test]
+    fn journal_replay_rejects_unknown_records() {
+        let wal = [9u8, 0, 0, 0, 0];
+        let mut sorted_vec = SortedVec::<u32>::new(Default::default());
+        let error = JournaledSortedVec::<u32, Vec<u8>>::replay(&mut sorted_vec, wal.as_slice()).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+    }
+}
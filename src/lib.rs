@@ -1,9 +1,69 @@
+pub mod align;
+#[cfg(feature = "arbitrary")]
+mod arbitrary;
+pub mod batch;
+#[cfg(feature = "bloom-filter")]
+mod bloom;
+mod borrowed;
+pub mod bounded;
 mod bucket;
+pub mod builder;
+pub mod codec;
+pub mod compact;
+pub mod concurrent;
+pub mod container;
+#[cfg(feature = "content-hash")]
+mod content_hash;
+mod convert;
+pub mod deferred;
+pub mod diff;
+mod directory;
+pub mod error;
+mod fence;
+pub mod frozen;
+pub mod handle;
+pub mod history;
+pub mod index;
+pub mod indexed;
+pub mod integrity;
+pub mod interval;
+pub mod journal;
+pub mod map;
+pub mod measure;
+mod merge;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+#[cfg(all(feature = "mmap", unix))]
+pub mod mmap;
+mod nearest;
+pub mod observe;
+pub mod page;
+mod parallel;
+pub mod persistent;
+mod prefix;
+#[cfg(feature = "proptest")]
+pub mod proptest;
+#[cfg(feature = "rand")]
+pub mod sample;
+pub mod set;
+#[cfg(feature = "simd-search")]
+pub mod simd;
+#[cfg(feature = "skip-list")]
+pub mod skip_list;
+pub mod snapshot;
 pub mod sorted_vec;
 mod sorted_vec_iter;
+pub mod storage;
+#[cfg(feature = "futures")]
+pub mod stream;
+pub mod tombstone;
+pub mod total_order;
+mod unwind;
 
 pub mod iter {
-    pub use crate::sorted_vec_iter::SortedVecIter;
+    #[cfg(feature = "rayon")]
+    pub use crate::parallel::{IntoParIter, ParBuckets, ParIter};
+    pub use crate::sorted_vec_iter::{BucketView, Buckets, GroupByEqual, SortedVecIter};
 }
 
 #[derive(Debug, PartialEq)]
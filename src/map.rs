@@ -0,0 +1,559 @@
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::fmt::{self, Debug, Formatter};
+use std::ops::{Bound, RangeBounds};
+
+use crate::align::Aligned;
+use crate::iter::SortedVecIter;
+use crate::sorted_vec::{BucketConfiguration, DuplicatePolicy, SortedVec};
+
+/// A key with its value, ordered by the key only.
+#[derive(Debug, Clone)]
+pub(crate) struct KeyValue<K, V> {
+    pub(crate) key: K,
+    pub(crate) value: V,
+}
+
+impl<K: Ord, V> PartialEq for KeyValue<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+
+impl<K: Ord, V> Eq for KeyValue<K, V> {}
+
+impl<K: Ord, V> PartialOrd for KeyValue<K, V> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<K: Ord, V> Ord for KeyValue<K, V> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key.cmp(&other.key)
+    }
+}
+
+/// A map kept as key-value pairs in a [`SortedVec`], ordered by key. Keys are
+/// unique, whatever the configuration says.
+pub struct SortedVecMap<K: PartialOrd + Ord, V> {
+    pub(crate) sorted_vec: SortedVec<KeyValue<K, V>>,
+}
+
+impl<K: PartialOrd + Ord, V> SortedVecMap<K, V> {
+    pub fn new() -> Self {
+        Self::with_configuration(Default::default())
+    }
+
+    pub fn with_configuration(configuration: BucketConfiguration) -> Self {
+        SortedVecMap {
+            sorted_vec: SortedVec::new(configuration.with_duplicate_policy(DuplicatePolicy::Reject)),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.sorted_vec.size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sorted_vec.size == 0
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.search(key).is_ok()
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        let position = self.search(key).ok()?;
+        Some(&self.pair(position).value)
+    }
+
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        let position = self.search(key).ok()?;
+        Some(&mut self.pair_mut(position).value)
+    }
+
+    pub fn get_key_value(&self, key: &K) -> Option<(&K, &V)> {
+        let pair = self.pair(self.search(key).ok()?);
+        Some((&pair.key, &pair.value))
+    }
+
+    /// Sets the value of `key` and returns the previous one, if any. The
+    /// stored key is kept.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        match self.entry(key) {
+            Entry::Occupied(mut entry) => Some(entry.insert(value)),
+            Entry::Vacant(entry) => {
+                entry.insert(value);
+                None
+            },
+        }
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        self.remove_entry(key).map(|(_, value)| value)
+    }
+
+    pub fn remove_entry(&mut self, key: &K) -> Option<(K, V)> {
+        let position = self.search(key).ok()?;
+        let KeyValue { key, value } = self.sorted_vec.remove_at(position);
+        Some((key, value))
+    }
+
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (&K, &V)> + ExactSizeIterator {
+        self.sorted_vec.iter().map(|pair| (&pair.key, &pair.value))
+    }
+
+    pub fn keys(&self) -> impl DoubleEndedIterator<Item = &K> + ExactSizeIterator {
+        self.sorted_vec.iter().map(|pair| &pair.key)
+    }
+
+    pub fn values(&self) -> impl DoubleEndedIterator<Item = &V> + ExactSizeIterator {
+        self.sorted_vec.iter().map(|pair| &pair.value)
+    }
+
+    /// Consumes the map, yielding the keys in order. Buckets are moved out
+    /// one at a time.
+    pub fn into_keys(self) -> impl DoubleEndedIterator<Item = K> {
+        self.into_pairs().map(|pair| pair.key)
+    }
+
+    /// Consumes the map, yielding the values in key order.
+    pub fn into_values(self) -> impl DoubleEndedIterator<Item = V> {
+        self.into_pairs().map(|pair| pair.value)
+    }
+
+    /// Like [`iter`](Self::iter) with mutable values. Keys can't be changed,
+    /// so the order is never at risk.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (&K, &mut V)> {
+        self.range_mut(..)
+    }
+
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut V> {
+        self.iter_mut().map(|(_, value)| value)
+    }
+
+    /// The pairs whose keys fall in `range`, in order.
+    pub fn range<R: RangeBounds<K>>(&self, range: R) -> impl DoubleEndedIterator<Item = (&K, &V)> {
+        let (start, end) = self.key_range_positions(&range);
+        SortedVecIter::between(&self.sorted_vec, start, end.max(start)).map(|pair| (&pair.key, &pair.value))
+    }
+
+    /// Like [`range`](Self::range) with mutable values.
+    pub fn range_mut<R: RangeBounds<K>>(&mut self, range: R) -> impl Iterator<Item = (&K, &mut V)> {
+        let (start, end) = self.key_range_positions(&range);
+        let end = end.max(start);
+        self.sorted_vec
+            .buckets
+            .iter_mut()
+            .enumerate()
+            .skip(start.0)
+            .take(end.0 + 1 - start.0)
+            .flat_map(move |(bucket_idx, bucket)| {
+                let from = if bucket_idx == start.0 { start.1 } else { 0 };
+                let to = if bucket_idx == end.0 { end.1 } else { bucket.len() };
+                bucket.data[from..to].iter_mut().map(|pair| (&pair.key, &mut pair.value))
+            })
+    }
+
+    /// The entry for `key`, found with a single search.
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V> {
+        match self.search(&key) {
+            Ok(position) => Entry::Occupied(OccupiedEntry { map: self, position }),
+            Err(position) => Entry::Vacant(VacantEntry { map: self, key, position }),
+        }
+    }
+
+    /// The keys in both maps with both values, in key order. Both maps must
+    /// be configured with the same order.
+    pub fn inner_join<'a, V2>(&'a self, other: &'a SortedVecMap<K, V2>) -> impl Iterator<Item = (&'a K, &'a V, &'a V2)> {
+        self.align(other).filter_map(|step| match step {
+            Aligned::Both(left, right) => Some((&left.key, &left.value, &right.value)),
+            _ => None,
+        })
+    }
+
+    /// Every key of `self` with its value in `other`, if any.
+    pub fn left_join<'a, V2>(&'a self, other: &'a SortedVecMap<K, V2>) -> impl Iterator<Item = (&'a K, &'a V, Option<&'a V2>)> {
+        self.align(other).filter_map(|step| match step {
+            Aligned::Left(left) => Some((&left.key, &left.value, None)),
+            Aligned::Both(left, right) => Some((&left.key, &left.value, Some(&right.value))),
+            Aligned::Right(_) => None,
+        })
+    }
+
+    /// Every key of either map with its value in each.
+    pub fn outer_join<'a, V2>(&'a self, other: &'a SortedVecMap<K, V2>) -> impl Iterator<Item = (&'a K, Option<&'a V>, Option<&'a V2>)> {
+        self.align(other).map(|step| match step {
+            Aligned::Left(left) => (&left.key, Some(&left.value), None),
+            Aligned::Right(right) => (&right.key, None, Some(&right.value)),
+            Aligned::Both(left, right) => (&left.key, Some(&left.value), Some(&right.value)),
+        })
+    }
+
+    /// Walks the pairs of both maps side by side, matched by key.
+    fn align<'a, V2>(
+        &'a self,
+        other: &'a SortedVecMap<K, V2>,
+    ) -> impl Iterator<Item = Aligned<&'a KeyValue<K, V>, &'a KeyValue<K, V2>>> {
+        let order = self.sorted_vec.configuration.order();
+        self.sorted_vec.align_with(&other.sorted_vec, move |left, right| order.compare(&left.key, &right.key))
+    }
+
+    /// Where `key` is stored, or where it would be inserted.
+    fn search(&self, key: &K) -> Result<(usize, usize), (usize, usize)> {
+        let order = self.sorted_vec.configuration.order();
+        let (bucket_idx, item_idx) = self.lower_bound(key);
+        match self.sorted_vec.get_position((bucket_idx, item_idx)) {
+            Some(pair) if order.compare(&pair.key, key) == Ordering::Equal => Ok((bucket_idx, item_idx)),
+            Some(_) => Err((bucket_idx, item_idx)),
+            None => {
+                let bucket_idx = bucket_idx.min(self.sorted_vec.buckets.len() - 1);
+                Err((bucket_idx, self.sorted_vec.buckets[bucket_idx].len()))
+            },
+        }
+    }
+
+    fn into_pairs(self) -> impl DoubleEndedIterator<Item = KeyValue<K, V>> {
+        self.sorted_vec.buckets.into_iter().flat_map(|bucket| bucket.data)
+    }
+
+    /// Position of the first pair whose key is not ordered before `key`.
+    fn lower_bound<Q: Ord + ?Sized>(&self, key: &Q) -> (usize, usize)
+    where
+        K: Borrow<Q>,
+    {
+        let order = self.sorted_vec.configuration.order();
+        self.sorted_vec.partition_position(|pair| order.compare(pair.key.borrow(), key) == Ordering::Less)
+    }
+
+    /// Position of the first pair whose key is ordered after `key`.
+    fn upper_bound<Q: Ord + ?Sized>(&self, key: &Q) -> (usize, usize)
+    where
+        K: Borrow<Q>,
+    {
+        let order = self.sorted_vec.configuration.order();
+        self.sorted_vec.partition_position(|pair| order.compare(pair.key.borrow(), key) != Ordering::Greater)
+    }
+
+    /// Start and exclusive end positions of the pairs with keys in `range`.
+    pub(crate) fn key_range_positions<Q, R>(&self, range: &R) -> ((usize, usize), (usize, usize))
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q> + ?Sized,
+    {
+        let start = match range.start_bound() {
+            Bound::Included(key) => self.lower_bound(key),
+            Bound::Excluded(key) => self.upper_bound(key),
+            Bound::Unbounded => (0, 0),
+        };
+        let end = match range.end_bound() {
+            Bound::Included(key) => self.upper_bound(key),
+            Bound::Excluded(key) => self.lower_bound(key),
+            Bound::Unbounded => (self.sorted_vec.buckets.len(), 0),
+        };
+
+        (start, end)
+    }
+
+    fn pair(&self, (bucket_idx, item_idx): (usize, usize)) -> &KeyValue<K, V> {
+        &self.sorted_vec.buckets[bucket_idx].data[item_idx]
+    }
+
+    fn pair_mut(&mut self, (bucket_idx, item_idx): (usize, usize)) -> &mut KeyValue<K, V> {
+        &mut self.sorted_vec.buckets[bucket_idx].data[item_idx]
+    }
+}
+
+impl<K: PartialOrd + Ord, V> Default for SortedVecMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: PartialOrd + Ord + Debug, V: Debug> Debug for SortedVecMap<K, V> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K: PartialOrd + Ord, V> FromIterator<(K, V)> for SortedVecMap<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut result = Self::new();
+        result.extend(iter);
+        result
+    }
+}
+
+impl<K: PartialOrd + Ord, V> Extend<(K, V)> for SortedVecMap<K, V> {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+/// A view into a single key of a [`SortedVecMap`], returned by
+/// [`SortedVecMap::entry`].
+pub enum Entry<'a, K: PartialOrd + Ord, V> {
+    Occupied(OccupiedEntry<'a, K, V>),
+    Vacant(VacantEntry<'a, K, V>),
+}
+
+impl<'a, K: PartialOrd + Ord, V> Entry<'a, K, V> {
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        self.or_insert_with(|| default)
+    }
+
+    pub fn or_insert_with(self, default: impl FnOnce() -> V) -> &'a mut V {
+        self.or_insert_with_key(|_| default())
+    }
+
+    pub fn or_insert_with_key(self, default: impl FnOnce(&K) -> V) -> &'a mut V {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let value = default(&entry.key);
+                entry.insert(value)
+            },
+        }
+    }
+
+    pub fn or_default(self) -> &'a mut V
+    where
+        V: Default,
+    {
+        self.or_insert_with(V::default)
+    }
+
+    /// Lets `f` modify the value if the key is present.
+    pub fn and_modify(mut self, f: impl FnOnce(&mut V)) -> Self {
+        if let Entry::Occupied(entry) = &mut self {
+            f(entry.get_mut());
+        }
+        self
+    }
+}
+
+pub struct OccupiedEntry<'a, K: PartialOrd + Ord, V> {
+    map: &'a mut SortedVecMap<K, V>,
+    position: (usize, usize),
+}
+
+impl<'a, K: PartialOrd + Ord, V> OccupiedEntry<'a, K, V> {
+    pub fn key(&self) -> &K {
+        &self.map.pair(self.position).key
+    }
+
+    pub fn get(&self) -> &V {
+        &self.map.pair(self.position).value
+    }
+
+    pub fn get_mut(&mut self) -> &mut V {
+        &mut self.map.pair_mut(self.position).value
+    }
+
+    pub fn into_mut(self) -> &'a mut V {
+        &mut self.map.pair_mut(self.position).value
+    }
+
+    /// Replaces the value and returns the old one.
+    pub fn insert(&mut self, value: V) -> V {
+        std::mem::replace(self.get_mut(), value)
+    }
+
+    pub fn remove(self) -> V {
+        self.remove_entry().1
+    }
+
+    pub fn remove_entry(self) -> (K, V) {
+        let KeyValue { key, value } = self.map.sorted_vec.remove_at(self.position);
+        (key, value)
+    }
+}
+
+pub struct VacantEntry<'a, K: PartialOrd + Ord, V> {
+    map: &'a mut SortedVecMap<K, V>,
+    key: K,
+    position: (usize, usize),
+}
+
+impl<'a, K: PartialOrd + Ord, V> VacantEntry<'a, K, V> {
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    pub fn into_key(self) -> K {
+        self.key
+    }
+
+    /// Inserts the value at the position found by the entry search.
+    pub fn insert(self, value: V) -> &'a mut V {
+        let (position, _) = self.map.sorted_vec.insert_at(self.position, KeyValue { key: self.key, value });
+        &mut self.map.pair_mut(position).value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::container::tests::Lcg;
+    use crate::sorted_vec::Order;
+
+    fn small_buckets<V>() -> SortedVecMap<u32, V> {
+        SortedVecMap::with_configuration(BucketConfiguration::builder().max_bucket_capacity(4).build())
+    }
+
+    #[test]
+    fn sorted_vec_map_matches_btree_map() {
+        let mut map = small_buckets();
+        let mut model = BTreeMap::new();
+        let mut rng = Lcg::new(3);
+        for step in 0..500 {
+            let key = rng.below(60) as u32;
+            if step % 3 == 2 {
+                assert_eq!(map.remove(&key), model.remove(&key));
+            } else {
+                assert_eq!(map.insert(key, step), model.insert(key, step));
+            }
+            assert_eq!(map.len(), model.len());
+        }
+
+        assert!(map.iter().eq(model.iter()));
+        assert_eq!(map.get(&7), model.get(&7));
+        assert!(map.sorted_vec.buckets.len() > 1);
+    }
+
+    #[test]
+    fn sorted_vec_map_entry() {
+        let mut map = small_buckets();
+        for word in ["b", "a", "c", "a", "b", "a"] {
+            *map.entry(word.len() as u32 * word.as_bytes()[0] as u32).or_insert(0) += 1;
+        }
+        assert_eq!(map.iter().map(|(_, count)| *count).collect::<Vec<_>>(), vec![3, 2, 1]);
+
+        map.entry(98).and_modify(|count| *count *= 10).or_insert(0);
+        map.entry(1).and_modify(|count| *count *= 10).or_insert_with(|| 7);
+        assert_eq!(map.get(&98), Some(&20));
+        assert_eq!(map.get(&1), Some(&7));
+        assert_eq!(*map.entry(2).or_insert_with_key(|key| *key * 100), 200);
+
+        match map.entry(97) {
+            Entry::Occupied(entry) => assert_eq!(entry.remove_entry(), (97, 3)),
+            Entry::Vacant(_) => panic!("97 is present"),
+        }
+        assert!(!map.contains_key(&97));
+        assert_eq!(map.entry(50).key(), &50);
+    }
+
+    #[test]
+    fn sorted_vec_map_entry_fills_many_buckets() {
+        let mut map = small_buckets::<u32>();
+        for key in (0..100u32).rev().chain(0..100).map(|key| key * 7 % 100) {
+            *map.entry(key).or_default() += 1;
+        }
+
+        assert_eq!(map.len(), 100);
+        assert!(map.iter().all(|(_, count)| *count == 2));
+        assert!(map.iter().map(|(key, _)| *key).eq(0..100));
+    }
+
+    #[test]
+    fn sorted_vec_map_mutable_values() {
+        let mut map = small_buckets();
+        map.extend((0..20).map(|key| (key, key)));
+        for value in map.values_mut() {
+            *value *= 2;
+        }
+        for (key, value) in map.range_mut(5..=8) {
+            *value += *key;
+        }
+        *map.get_mut(&19).unwrap() = 0;
+
+        assert_eq!(map.range(4..10).map(|(_, value)| *value).collect::<Vec<_>>(), vec![8, 15, 18, 21, 24, 18]);
+        assert_eq!(map.range(3..=3).next_back(), Some((&3, &6)));
+        assert_eq!(map.get(&19), Some(&0));
+        assert_eq!(map.get_mut(&20), None);
+        assert_eq!(map.range_mut((Bound::Included(10), Bound::Excluded(5))).count(), 0);
+        assert_eq!(map.range_mut((Bound::Excluded(17), Bound::Unbounded)).count(), 2);
+    }
+
+    #[test]
+    fn sorted_vec_map_projections() {
+        let mut map = small_buckets();
+        map.extend((0..10).map(|key| (key, key.to_string())));
+
+        assert!(map.keys().copied().eq(0..10));
+        assert_eq!(map.values().len(), 10);
+        assert_eq!(map.values().next_back().map(String::as_str), Some("9"));
+        assert_eq!(map.iter().next_back(), Some((&9, &"9".to_string())));
+
+        let values: Vec<String> = small_buckets::<String>().into_values().collect();
+        assert!(values.is_empty());
+        assert_eq!(map.into_values().rev().take(2).collect::<Vec<_>>(), vec!["9", "8"]);
+
+        let map: SortedVecMap<u32, ()> = (0..5).rev().map(|key| (key, ())).collect();
+        assert!(map.into_keys().eq(0..5));
+    }
+
+    #[test]
+    fn sorted_vec_map_descending() {
+        let configuration = BucketConfiguration::builder().order(Order::Descending).max_bucket_capacity(2).build();
+        let mut map = SortedVecMap::with_configuration(configuration);
+        map.extend([(1, "one"), (3, "three"), (2, "two")]);
+        map.entry(4).or_insert("four");
+
+        assert_eq!(map.iter().map(|(key, _)| *key).collect::<Vec<_>>(), vec![4, 3, 2, 1]);
+        assert_eq!(map.get(&2), Some(&"two"));
+    }
+
+    #[test]
+    fn joins_match_keys_lazily() {
+        let mut prices = small_buckets();
+        prices.extend([(1, 10), (3, 30), (4, 40), (7, 70), (9, 90)]);
+        let mut names = small_buckets();
+        names.extend([(1, "one"), (2, "two"), (4, "four"), (9, "nine"), (10, "ten")]);
+
+        let inner: Vec<_> = prices.inner_join(&names).collect();
+        assert_eq!(inner, vec![(&1, &10, &"one"), (&4, &40, &"four"), (&9, &90, &"nine")]);
+
+        let left: Vec<_> = prices.left_join(&names).map(|(key, _, name)| (*key, name.copied())).collect();
+        assert_eq!(left, vec![(1, Some("one")), (3, None), (4, Some("four")), (7, None), (9, Some("nine"))]);
+
+        let outer: Vec<_> = prices.outer_join(&names).map(|(key, price, name)| (*key, price.copied(), name.copied())).collect();
+        assert_eq!(outer.len(), 7);
+        assert_eq!(outer[1], (2, None, Some("two")));
+        assert_eq!(outer[2], (3, Some(30), None));
+        assert_eq!(outer[6], (10, None, Some("ten")));
+
+        let mut joined = prices.outer_join(&names);
+        assert_eq!(joined.next(), Some((&1, Some(&10), Some(&"one"))));
+        assert!(prices.inner_join(&SortedVecMap::<u32, ()>::new()).next().is_none());
+    }
+
+    #[test]
+    fn joins_follow_descending_maps() {
+        let configuration = BucketConfiguration::builder().max_bucket_capacity(4).order(Order::Descending).build();
+        let left: SortedVecMap<u32, u32> = {
+            let mut map = SortedVecMap::with_configuration(configuration.clone());
+            map.extend((0..20).step_by(2).map(|key| (key, key)));
+            map
+        };
+        let mut right = SortedVecMap::with_configuration(configuration);
+        right.extend((0..20).step_by(3).map(|key| (key, key * 100)));
+
+        let keys: Vec<_> = left.inner_join(&right).map(|(key, _, value)| (*key, *value)).collect();
+        assert_eq!(keys, vec![(18, 1800), (12, 1200), (6, 600), (0, 0)]);
+        assert_eq!(left.outer_join(&right).count(), 13);
+    }
+}
@@ -0,0 +1,287 @@
+use std::ops::{Add, Bound, Deref, RangeBounds};
+
+use crate::bucket::Bucket;
+use crate::sorted_vec::SortedVec;
+use crate::storage::BucketStorage;
+
+/// A monoid summarising elements: `combine` must be associative and
+/// `identity` neutral for it. Each bucket of a [`MeasuredSortedVec`] caches
+/// the combination of its elements' measures.
+pub trait Measure<T>: Clone {
+    fn identity() -> Self;
+
+    fn measure(item: &T) -> Self;
+
+    fn combine(&self, other: &Self) -> Self;
+}
+
+/// Elements carrying a weight, e.g. a quota or a sampling frequency.
+pub trait Weighted {
+    fn weight(&self) -> u64;
+}
+
+/// The [`Measure`] adding up element weights, which enables the weighted
+/// queries on [`MeasuredSortedVec`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Weight(pub u64);
+
+impl<T: Weighted> Measure<T> for Weight {
+    fn identity() -> Self {
+        Weight(0)
+    }
+
+    fn measure(item: &T) -> Self {
+        Weight(item.weight())
+    }
+
+    fn combine(&self, other: &Self) -> Self {
+        Weight(self.0 + other.0)
+    }
+}
+
+/// Wraps a [`SortedVec`] and keeps a [`Measure`] summary per bucket, so
+/// aggregates over a range combine whole buckets from their summaries and
+/// only fold elements one by one in the two buckets at the range's ends.
+/// Reads go through `Deref`.
+#[derive(Debug)]
+pub struct MeasuredSortedVec<T: PartialOrd + Ord, M> {
+    sorted_vec: SortedVec<T>,
+    summaries: Vec<M>,
+}
+
+impl<T: PartialOrd + Ord, M: Measure<T>> MeasuredSortedVec<T, M> {
+    pub fn new(sorted_vec: SortedVec<T>) -> Self {
+        let summaries = sorted_vec.buckets.iter().map(summarize).collect();
+        MeasuredSortedVec { sorted_vec, summaries }
+    }
+
+    pub fn insert(&mut self, item: T) {
+        let Some((bucket_idx, _)) = self.sorted_vec.insert_position(item) else {
+            return;
+        };
+
+        if self.summaries.len() == self.sorted_vec.buckets.len() {
+            self.summaries[bucket_idx] = summarize(&self.sorted_vec.buckets[bucket_idx]);
+            return;
+        }
+
+        // The bucket split into `bucket_idx` and one of its neighbours.
+        self.summaries.insert(bucket_idx, M::identity());
+        let last = self.summaries.len() - 1;
+        for idx in bucket_idx.saturating_sub(1)..=(bucket_idx + 1).min(last) {
+            self.summaries[idx] = summarize(&self.sorted_vec.buckets[idx]);
+        }
+    }
+
+    pub fn remove(&mut self, item: &T) -> Option<T> {
+        let (removed, (bucket_idx, _)) = self.sorted_vec.remove_position(item)?;
+        if self.summaries.len() == self.sorted_vec.buckets.len() {
+            self.summaries[bucket_idx] = summarize(&self.sorted_vec.buckets[bucket_idx]);
+        } else {
+            self.summaries.remove(bucket_idx);
+        }
+
+        Some(removed)
+    }
+
+    /// The summary of every element.
+    pub fn total(&self) -> M {
+        self.summaries.iter().fold(M::identity(), |acc, summary| acc.combine(summary))
+    }
+
+    /// The summary of the elements inside `range`.
+    pub fn measure_range<R: RangeBounds<T>>(&self, range: R) -> M {
+        self.range_fold(range, M::identity(), |acc, summary| acc.combine(summary))
+    }
+
+    /// Folds the summaries covering `range` in order: whole buckets pass
+    /// their cached summary and the elements of partially covered buckets
+    /// pass their own measure.
+    pub fn range_fold<R: RangeBounds<T>, B>(&self, range: R, init: B, mut op: impl FnMut(B, &M) -> B) -> B {
+        let (start, end) = self.sorted_vec.range_positions(&range);
+        let mut acc = init;
+        if start >= end {
+            return acc;
+        }
+
+        let buckets = self.sorted_vec.buckets.iter().zip(&self.summaries).enumerate();
+        for (bucket_idx, (bucket, summary)) in buckets.take(end.0 + 1).skip(start.0) {
+            let from = if bucket_idx == start.0 { start.1 } else { 0 };
+            let to = if bucket_idx == end.0 { end.1 } else { bucket.len() };
+            if from == 0 && to == bucket.len() {
+                acc = op(acc, summary);
+            } else {
+                acc = bucket.data.iter().take(to).skip(from).fold(acc, |acc, item| op(acc, &M::measure(item)));
+            }
+        }
+
+        acc
+    }
+
+    /// Adds up the number `value` reads from each summary covering `range`.
+    /// `value` has to be additive, i.e. `value(a.combine(b))` must equal
+    /// `value(a) + value(b)`, as with counts or sums.
+    pub fn range_sum<R: RangeBounds<T>, N: Add<Output = N> + Default>(&self, range: R, value: impl Fn(&M) -> N) -> N {
+        self.range_fold(range, N::default(), |acc, summary| acc + value(summary))
+    }
+
+    pub fn into_inner(self) -> SortedVec<T> {
+        self.sorted_vec
+    }
+}
+
+impl<T: PartialOrd + Ord + Weighted> MeasuredSortedVec<T, Weight> {
+    /// The total weight of the elements ordered before `item`.
+    pub fn weight_before(&self, item: &T) -> u64 {
+        self.total_weight_in_range((Bound::Unbounded, Bound::Excluded(item)))
+    }
+
+    pub fn total_weight_in_range<R: RangeBounds<T>>(&self, range: R) -> u64 {
+        self.range_sum(range, |summary| summary.0)
+    }
+
+    /// The element whose weight covers offset `weight` when the weights are
+    /// laid end to end in order, i.e. the first one where the running total
+    /// exceeds `weight`. `None` once `weight` reaches the total. Feeding it a
+    /// uniform offset below the total gives weighted sampling.
+    pub fn find_by_cumulative_weight(&self, mut weight: u64) -> Option<&T> {
+        let buckets = self.sorted_vec.buckets.iter().zip(&self.summaries);
+        for (bucket, summary) in buckets {
+            if weight >= summary.0 {
+                weight -= summary.0;
+                continue;
+            }
+            for item in bucket.data.iter() {
+                match weight.checked_sub(item.weight()) {
+                    Some(rest) => weight = rest,
+                    None => return Some(item),
+                }
+            }
+        }
+
+        None
+    }
+}
+
+fn summarize<T: PartialOrd + Ord, M: Measure<T>>(bucket: &Bucket<T>) -> M {
+    bucket.data.iter().fold(M::identity(), |acc, item| acc.combine(&M::measure(item)))
+}
+
+impl<T: PartialOrd + Ord, M> Deref for MeasuredSortedVec<T, M> {
+    type Target = SortedVec<T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.sorted_vec
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sorted_vec::BucketConfiguration;
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Sum(i64);
+
+    impl Measure<i64> for Sum {
+        fn identity() -> Self {
+            Sum(0)
+        }
+
+        fn measure(item: &i64) -> Self {
+            Sum(*item)
+        }
+
+        fn combine(&self, other: &Self) -> Self {
+            Sum(self.0 + other.0)
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct CountAndMax(usize, Option<i64>);
+
+    impl Measure<i64> for CountAndMax {
+        fn identity() -> Self {
+            CountAndMax(0, None)
+        }
+
+        fn measure(item: &i64) -> Self {
+            CountAndMax(1, Some(*item))
+        }
+
+        fn combine(&self, other: &Self) -> Self {
+            CountAndMax(self.0 + other.0, self.1.max(other.1))
+        }
+    }
+
+    #[test]
+    fn measured_sorted_vec_range_sums_across_splits_and_removals() {
+        let configuration = BucketConfiguration::builder().max_bucket_capacity(4).build();
+        let mut measured: MeasuredSortedVec<i64, Sum> = MeasuredSortedVec::new(SortedVec::new(configuration));
+        for item in [7, 3, 9, 1, 5, 2, 8, 6, 4, 0] {
+            measured.insert(item);
+        }
+        measured.remove(&5);
+        measured.remove(&0);
+        measured.remove(&1);
+
+        assert_eq!(measured.total(), Sum(2 + 3 + 4 + 6 + 7 + 8 + 9));
+        assert_eq!(measured.measure_range(3..8), Sum(3 + 4 + 6 + 7));
+        assert_eq!(measured.measure_range(..=4), Sum(2 + 3 + 4));
+        assert_eq!(measured.measure_range(10..), Sum(0));
+        for start in 0..10 {
+            for end in start..11 {
+                let expected = measured.iter().filter(|item| (start..end).contains(*item)).sum::<i64>();
+                assert_eq!(measured.measure_range(start..end), Sum(expected));
+            }
+        }
+    }
+
+    #[test]
+    fn measured_sorted_vec_count_and_max() {
+        let sorted_vec = SortedVec::from_vec(vec![10, 40, 20, 30], BucketConfiguration::builder().max_bucket_capacity(2).build());
+        let measured: MeasuredSortedVec<i64, CountAndMax> = MeasuredSortedVec::new(sorted_vec);
+
+        assert_eq!(measured.measure_range(15..35), CountAndMax(2, Some(30)));
+        assert_eq!(measured.total(), CountAndMax(4, Some(40)));
+    }
+
+    #[test]
+    fn measured_sorted_vec_range_fold_and_sum() {
+        let configuration = BucketConfiguration::builder().max_bucket_capacity(2).build();
+        let measured: MeasuredSortedVec<i64, CountAndMax> = MeasuredSortedVec::new(SortedVec::from_vec((1..=9).collect(), configuration));
+
+        assert_eq!(measured.range_sum(2..=7, |summary| summary.0), 6);
+        assert_eq!(measured.range_sum(.., |summary| summary.0), 9);
+        let max = measured.range_fold(2..=7, None, |acc, summary| acc.max(summary.1));
+        assert_eq!(max, Some(7));
+    }
+
+    #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+    struct Task {
+        id: u32,
+        quota: u64,
+    }
+
+    impl Weighted for Task {
+        fn weight(&self) -> u64 {
+            self.quota
+        }
+    }
+
+    #[test]
+    fn measured_sorted_vec_weighted_queries() {
+        let configuration = BucketConfiguration::builder().max_bucket_capacity(2).build();
+        let mut measured: MeasuredSortedVec<Task, Weight> = MeasuredSortedVec::new(SortedVec::new(configuration));
+        for (id, quota) in [(4, 1), (1, 5), (3, 0), (2, 2), (5, 3)] {
+            measured.insert(Task { id, quota });
+        }
+
+        assert_eq!(measured.total(), Weight(11));
+        assert_eq!(measured.weight_before(&Task { id: 1, quota: 5 }), 0);
+        assert_eq!(measured.weight_before(&Task { id: 4, quota: 1 }), 7);
+        assert_eq!(measured.total_weight_in_range(Task { id: 2, quota: 2 }..Task { id: 5, quota: 3 }), 3);
+        let ids: Vec<u32> = (0..12).map(|weight| measured.find_by_cumulative_weight(weight).map_or(0, |task| task.id)).collect();
+        assert_eq!(ids, vec![1, 1, 1, 1, 1, 2, 2, 4, 5, 5, 5, 0]);
+    }
+}
@@ -0,0 +1,134 @@
+use std::cmp::Ordering;
+use std::iter::FusedIterator;
+
+use crate::iter::SortedVecIter;
+use crate::sorted_vec::{Order, SortedVec};
+use crate::storage::BucketStorage;
+
+/// Lazily merges several containers into one sorted stream, using the order
+/// of the first one. All of them are expected to share that order. Equal
+/// elements come out in the order their containers were given.
+pub fn merge_iter<'a, T: PartialOrd + Ord, S: BucketStorage<T>>(
+    sources: impl IntoIterator<Item = &'a SortedVec<T, S>>,
+) -> MergeIter<'a, T, S> {
+    MergeIter::new(sources.into_iter().collect())
+}
+
+/// K-way merge over a loser tree: every step replays a single leaf-to-root
+/// path, so it costs O(log k) comparisons. Returned by [`merge_iter`].
+pub struct MergeIter<'a, T: PartialOrd + Ord, S = Vec<T>> {
+    order: Order,
+    sources: Vec<SortedVecIter<'a, T, S>>,
+    heads: Vec<Option<&'a T>>,
+    /// `tree[0]` holds the current winner, `tree[1..]` the loser of each
+    /// internal node. Leaf `i` sits at node `k + i`.
+    tree: Vec<usize>,
+    remaining: usize,
+}
+
+impl<'a, T: PartialOrd + Ord, S: BucketStorage<T>> MergeIter<'a, T, S> {
+    fn new(sources: Vec<&'a SortedVec<T, S>>) -> Self {
+        let order = sources.first().map(|source| source.configuration.order()).unwrap_or_default();
+        let remaining = sources.iter().map(|source| source.size).sum();
+        let mut sources: Vec<_> = sources.into_iter().map(SortedVec::iter).collect();
+        let heads = sources.iter_mut().map(Iterator::next).collect();
+        let k = sources.len();
+        let mut merge = MergeIter {
+            order,
+            sources,
+            heads,
+            tree: vec![0; k.max(1)],
+            remaining,
+        };
+
+        let mut winners = vec![0; k];
+        for node in (1..k).rev() {
+            let [left, right] = [2 * node, 2 * node + 1].map(|child| if child >= k { child - k } else { winners[child] });
+            let (winner, loser) = if merge.beats(left, right) { (left, right) } else { (right, left) };
+            winners[node] = winner;
+            merge.tree[node] = loser;
+        }
+        if k > 1 {
+            merge.tree[0] = winners[1];
+        }
+
+        merge
+    }
+
+    fn beats(&self, a: usize, b: usize) -> bool {
+        match (self.heads[a], self.heads[b]) {
+            (Some(x), Some(y)) => match self.order.compare(x, y) {
+                Ordering::Less => true,
+                Ordering::Equal => a < b,
+                Ordering::Greater => false,
+            },
+            (Some(_), None) => true,
+            (None, _) => false,
+        }
+    }
+}
+
+impl<T: PartialOrd + Ord, S: BucketStorage<T>> ExactSizeIterator for MergeIter<'_, T, S> {
+    fn len(&self) -> usize {
+        self.remaining
+    }
+}
+
+impl<T: PartialOrd + Ord, S: BucketStorage<T>> FusedIterator for MergeIter<'_, T, S> {}
+
+impl<'a, T: PartialOrd + Ord, S: BucketStorage<T>> Iterator for MergeIter<'a, T, S> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut winner = *self.tree.first()?;
+        let item = self.heads.get(winner).copied().flatten()?;
+        self.heads[winner] = self.sources[winner].next();
+        self.remaining -= 1;
+
+        let k = self.sources.len();
+        let mut node = (winner + k) / 2;
+        while node > 0 {
+            if self.beats(self.tree[node], winner) {
+                std::mem::swap(&mut self.tree[node], &mut winner);
+            }
+            node /= 2;
+        }
+        self.tree[0] = winner;
+
+        Some(item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sorted_vec::{BucketConfiguration, DuplicatePolicy};
+
+    #[test]
+    fn merge_iter_interleaves_many_sources() {
+        let configuration = BucketConfiguration::builder().max_bucket_capacity(2).build();
+        let shards: Vec<SortedVec<i32>> = (0..5)
+            .map(|shard| SortedVec::from_vec((0..20).filter(|item| item % 5 == shard).collect(), configuration.clone()))
+            .collect();
+
+        let merged = merge_iter(&shards);
+        assert_eq!(merged.len(), 20);
+        assert_eq!(merged.copied().collect::<Vec<_>>(), (0..20).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn merge_iter_keeps_duplicates_and_handles_empty_sources() {
+        let configuration = BucketConfiguration::builder().duplicate_policy(DuplicatePolicy::Allow).build();
+        let a = SortedVec::from_vec(vec![1, 3, 3], configuration.clone());
+        let b = SortedVec::new(configuration.clone());
+        let c = SortedVec::from_vec(vec![2, 3], configuration);
+
+        assert_eq!(merge_iter([&a, &b, &c]).copied().collect::<Vec<_>>(), vec![1, 2, 3, 3, 3]);
+        assert_eq!(merge_iter([&b]).next(), None);
+        assert_eq!(merge_iter(Vec::<&SortedVec<i32>>::new()).next(), None);
+    }
+}
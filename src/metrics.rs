@@ -0,0 +1,147 @@
+use std::cell::Cell;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Operation counts collected by a [`SortedVec`](crate::sorted_vec::SortedVec)
+/// since it was created or last reset.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Stats {
+    /// Element comparisons, including those made while sorting batches.
+    pub comparisons: u64,
+    /// Buckets created by splitting an overflowing bucket.
+    pub splits: u64,
+    /// Sorted runs merged into a bucket by the batch operations.
+    pub merges: u64,
+    /// Elements moved inside a bucket to open or close a gap.
+    pub shifts: u64,
+}
+
+impl Stats {
+    fn since(self, start: Stats) -> Stats {
+        Stats {
+            comparisons: self.comparisons.wrapping_sub(start.comparisons),
+            splits: self.splits.wrapping_sub(start.splits),
+            merges: self.merges.wrapping_sub(start.merges),
+            shifts: self.shifts.wrapping_sub(start.shifts),
+        }
+    }
+}
+
+thread_local! {
+    // Bucket code and `Order::compare` have no handle on the container, so
+    // they count into the current thread and the outermost `Scope` moves the
+    // difference into the instance.
+    static COUNTERS: Cell<Stats> = const { Cell::new(Stats { comparisons: 0, splits: 0, merges: 0, shifts: 0 }) };
+    static DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+pub(crate) fn count(f: impl FnOnce(&mut Stats)) {
+    COUNTERS.with(|counters| {
+        let mut stats = counters.get();
+        f(&mut stats);
+        counters.set(stats);
+    });
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Metrics {
+    comparisons: AtomicU64,
+    splits: AtomicU64,
+    merges: AtomicU64,
+    shifts: AtomicU64,
+}
+
+impl Metrics {
+    /// Attributes everything counted on this thread until the returned guard
+    /// is dropped to this instance. Nested scopes defer to the outermost one.
+    pub(crate) fn scope(self: &Arc<Self>) -> Scope {
+        let depth = DEPTH.with(|depth| depth.replace(depth.get() + 1));
+        let start = (depth == 0).then(|| COUNTERS.with(Cell::get));
+
+        Scope { metrics: self.clone(), start }
+    }
+
+    pub(crate) fn stats(&self) -> Stats {
+        Stats {
+            comparisons: self.comparisons.load(Ordering::Relaxed),
+            splits: self.splits.load(Ordering::Relaxed),
+            merges: self.merges.load(Ordering::Relaxed),
+            shifts: self.shifts.load(Ordering::Relaxed),
+        }
+    }
+
+    pub(crate) fn reset(&self) {
+        self.comparisons.store(0, Ordering::Relaxed);
+        self.splits.store(0, Ordering::Relaxed);
+        self.merges.store(0, Ordering::Relaxed);
+        self.shifts.store(0, Ordering::Relaxed);
+    }
+}
+
+/// Holds its own handle on the counters so the container stays free to be
+/// mutated while the scope is alive.
+pub(crate) struct Scope {
+    metrics: Arc<Metrics>,
+    start: Option<Stats>,
+}
+
+impl Drop for Scope {
+    fn drop(&mut self) {
+        DEPTH.with(|depth| depth.set(depth.get() - 1));
+        let Some(start) = self.start else {
+            return;
+        };
+
+        let delta = COUNTERS.with(Cell::get).since(start);
+        self.metrics.comparisons.fetch_add(delta.comparisons, Ordering::Relaxed);
+        self.metrics.splits.fetch_add(delta.splits, Ordering::Relaxed);
+        self.metrics.merges.fetch_add(delta.merges, Ordering::Relaxed);
+        self.metrics.shifts.fetch_add(delta.shifts, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::batch::BatchOperation;
+    use crate::metrics::Stats;
+    use crate::sorted_vec::{BucketConfiguration, SortedVec};
+
+    #[test]
+    fn metrics_count_splits_and_shifts() {
+        let configuration = BucketConfiguration::builder().max_bucket_capacity(4).build();
+        let mut sorted_vec = SortedVec::new(configuration);
+        for item in (0..8).rev() {
+            sorted_vec.insert(item);
+        }
+
+        let stats = sorted_vec.stats();
+        assert!(stats.comparisons > 0);
+        assert_eq!(stats.splits as usize, sorted_vec.buckets.len() - 1);
+        assert!(stats.shifts > 0);
+        assert_eq!(stats.merges, 0);
+    }
+
+    #[test]
+    fn metrics_are_per_instance_and_resettable() {
+        let mut first = SortedVec::new(Default::default());
+        let second = SortedVec::<i32>::new(Default::default());
+        first.insert(1);
+        first.insert(2);
+        first.find_index(&2);
+        assert!(first.stats().comparisons > 0);
+        assert_eq!(second.stats(), Stats::default());
+
+        first.reset_stats();
+        assert_eq!(first.stats(), Stats::default());
+    }
+
+    #[test]
+    fn metrics_count_batch_merges() {
+        let mut sorted_vec = SortedVec::from_vec(vec![1, 5], Default::default());
+        sorted_vec.reset_stats();
+        sorted_vec.insert_many([3, 4]);
+        sorted_vec.apply_batch([BatchOperation::Remove(3)]);
+
+        assert_eq!(sorted_vec.stats().merges, 2);
+    }
+}
@@ -0,0 +1,321 @@
+use std::cmp::Ordering;
+use std::fs::File;
+use std::io::{self, BufWriter, Error, ErrorKind, Write};
+use std::os::fd::AsRawFd;
+use std::path::Path;
+use std::{ptr, slice};
+
+use crate::sorted_vec::{Order, SortedVec};
+use crate::storage::BucketStorage;
+
+const MAGIC: &[u8; 4] = b"SVMM";
+const HEADER_LEN: usize = 24;
+
+/// A type stored in a fixed number of bytes, so elements of a mapped file can
+/// be located by position without an offset table.
+pub trait FixedCodec: Sized {
+    const SIZE: usize;
+
+    fn encode(&self, out: &mut [u8]);
+
+    fn decode(bytes: &[u8]) -> Self;
+}
+
+macro_rules! impl_fixed_codec {
+    ($($ty:ty),*) => {
+        $(
+            impl FixedCodec for $ty {
+                const SIZE: usize = size_of::<$ty>();
+
+                fn encode(&self, out: &mut [u8]) {
+                    out.copy_from_slice(&self.to_le_bytes());
+                }
+
+                fn decode(bytes: &[u8]) -> Self {
+                    let mut raw = [0; size_of::<$ty>()];
+                    raw.copy_from_slice(bytes);
+                    <$ty>::from_le_bytes(raw)
+                }
+            }
+        )*
+    };
+}
+
+impl_fixed_codec!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
+
+/// A read-only sorted container whose elements live in a memory-mapped file.
+/// Only a fence index holding the first element of every block stays in RAM;
+/// lookups binary search the fences and then decode a single block, so the
+/// data set can be far larger than memory.
+///
+/// File layout: a 24 byte header (magic, element size, order, block length,
+/// element count) followed by the encoded elements in order.
+///
+/// The file is mapped privately, which does not protect against other
+/// processes changing it: see the safety contract of [`open`](Self::open).
+pub struct MappedSortedVec<T: PartialOrd + Ord + FixedCodec> {
+    map: Mapping,
+    fences: Vec<T>,
+    order: Order,
+    block_len: usize,
+    len: usize,
+}
+
+impl<T: PartialOrd + Ord + FixedCodec> MappedSortedVec<T> {
+    /// Writes `sorted_vec` to `path` and maps the result. Blocks hold as many
+    /// elements as the container's maximum bucket capacity.
+    ///
+    /// # Safety
+    ///
+    /// Same as [`open`](Self::open): the file must not be modified or
+    /// truncated while the returned value is alive.
+    pub unsafe fn create<S: BucketStorage<T>>(path: impl AsRef<Path>, sorted_vec: &SortedVec<T, S>) -> io::Result<Self> {
+        let mut out = BufWriter::new(File::create(&path)?);
+        let order = sorted_vec.configuration.order();
+        let block_len = *sorted_vec.configuration.max_bucket_capacity();
+
+        out.write_all(MAGIC)?;
+        out.write_all(&(T::SIZE as u32).to_le_bytes())?;
+        out.write_all(&[(order == Order::Descending) as u8, 0, 0, 0])?;
+        out.write_all(&(block_len as u32).to_le_bytes())?;
+        out.write_all(&(sorted_vec.size as u64).to_le_bytes())?;
+
+        let mut buffer = vec![0; T::SIZE];
+        for item in sorted_vec.buckets.iter().flat_map(|bucket| bucket.data.iter()) {
+            item.encode(&mut buffer);
+            out.write_all(&buffer)?;
+        }
+        out.into_inner().map_err(|error| error.into_error())?.sync_all()?;
+
+        // SAFETY: forwarded to the caller.
+        unsafe { Self::open(path) }
+    }
+
+    /// Maps the file at `path`, validating its header and length.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified or truncated, by this or any other
+    /// process, while the returned value is alive. Changes show through the
+    /// mapping and would break the invariants the reads rely on, and a
+    /// truncated file makes reads fault.
+    pub unsafe fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let map = Mapping::new(&file)?;
+        let header = map.bytes().get(..HEADER_LEN).ok_or_else(|| invalid("file is too short"))?;
+        if &header[..4] != MAGIC {
+            return Err(invalid("not a mapped sorted vec file"));
+        }
+
+        let element_size = u32::from_le_bytes(header[4..8].try_into().unwrap()) as usize;
+        if element_size != T::SIZE {
+            return Err(invalid("element size does not match"));
+        }
+        let order = if header[8] == 1 { Order::Descending } else { Order::Ascending };
+        let block_len = (u32::from_le_bytes(header[12..16].try_into().unwrap()) as usize).max(1);
+        let len = usize::try_from(u64::from_le_bytes(header[16..24].try_into().unwrap()))
+            .map_err(|_| invalid("element count does not fit in memory"))?;
+        let expected = len.checked_mul(T::SIZE).and_then(|bytes| bytes.checked_add(HEADER_LEN));
+        if expected != Some(map.bytes().len()) {
+            return Err(invalid("file length does not match the element count"));
+        }
+
+        let mut result = MappedSortedVec {
+            map,
+            fences: Vec::new(),
+            order,
+            block_len,
+            len,
+        };
+        result.fences = (0..len).step_by(block_len).map(|idx| result.decode(idx)).collect();
+
+        Ok(result)
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self, idx: usize) -> Option<T> {
+        (idx < self.len).then(|| self.decode(idx))
+    }
+
+    pub fn first(&self) -> Option<T> {
+        self.get(0)
+    }
+
+    pub fn last(&self) -> Option<T> {
+        self.get(self.len.checked_sub(1)?)
+    }
+
+    pub fn contains(&self, item: &T) -> bool {
+        self.position(item).is_ok()
+    }
+
+    /// `Ok` with the position of an equal element, or `Err` with the position
+    /// where it would be inserted, like `slice::binary_search`.
+    pub fn position(&self, item: &T) -> Result<usize, usize> {
+        let block = self
+            .fences
+            .partition_point(|fence| self.order.compare(fence, item) != Ordering::Greater)
+            .saturating_sub(1);
+        let start = block * self.block_len;
+        let end = (start + self.block_len).min(self.len);
+
+        let (mut lo, mut hi) = (start, end);
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            match self.order.compare(&self.decode(mid), item) {
+                Ordering::Less => lo = mid + 1,
+                Ordering::Greater => hi = mid,
+                Ordering::Equal => return Ok(mid),
+            }
+        }
+
+        Err(lo)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = T> + '_ {
+        (0..self.len).map(|idx| self.decode(idx))
+    }
+
+    fn decode(&self, idx: usize) -> T {
+        let offset = HEADER_LEN + idx * T::SIZE;
+        T::decode(&self.map.bytes()[offset..offset + T::SIZE])
+    }
+}
+
+fn invalid(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
+
+/// A read-only private mapping of a whole file, unmapped on drop.
+struct Mapping {
+    ptr: *mut libc::c_void,
+    len: usize,
+}
+
+// The mapping is read-only and owned exclusively by this value.
+unsafe impl Send for Mapping {}
+unsafe impl Sync for Mapping {}
+
+impl Mapping {
+    fn new(file: &File) -> io::Result<Self> {
+        let len = usize::try_from(file.metadata()?.len()).map_err(|_| invalid("file does not fit in memory"))?;
+        if len == 0 {
+            return Ok(Mapping { ptr: ptr::null_mut(), len: 0 });
+        }
+
+        // SAFETY: a fresh read-only private mapping of an open file descriptor;
+        // the kernel picks the address.
+        let ptr = unsafe { libc::mmap(ptr::null_mut(), len, libc::PROT_READ, libc::MAP_PRIVATE, file.as_raw_fd(), 0) };
+        if ptr == libc::MAP_FAILED {
+            return Err(Error::last_os_error());
+        }
+
+        Ok(Mapping { ptr, len })
+    }
+
+    fn bytes(&self) -> &[u8] {
+        if self.len == 0 {
+            return &[];
+        }
+
+        // SAFETY: `ptr` points to `len` readable bytes for as long as the
+        // mapping lives, and callers of `MappedSortedVec::open` promise the
+        // file is not changed underneath it.
+        unsafe { slice::from_raw_parts(self.ptr as *const u8, self.len) }
+    }
+}
+
+impl Drop for Mapping {
+    fn drop(&mut self) {
+        if self.len > 0 {
+            // SAFETY: unmaps exactly the region returned by `mmap`.
+            unsafe {
+                libc::munmap(self.ptr, self.len);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::sorted_vec::BucketConfiguration;
+
+    fn temp_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("sorted-vec-{}-{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn mapped_sorted_vec_round_trip() {
+        let path = temp_path("round-trip");
+        let config = BucketConfiguration::builder().max_bucket_capacity(8).build();
+        let sorted_vec = SortedVec::from_vec((0..1000u64).map(|i| i * 3).collect(), config);
+        // SAFETY: the file is private to this test.
+        let mapped = unsafe { MappedSortedVec::create(&path, &sorted_vec) }.unwrap();
+
+        assert_eq!(mapped.len(), 1000);
+        assert_eq!(mapped.fences.len(), 125);
+        assert_eq!(mapped.first(), Some(0));
+        assert_eq!(mapped.last(), Some(2997));
+        assert_eq!(mapped.get(10), Some(30));
+        assert!(mapped.contains(&2997));
+        assert!(!mapped.contains(&2998));
+        assert_eq!(mapped.position(&31), Err(11));
+        assert!(mapped.iter().eq(sorted_vec.iter().copied()));
+
+        // SAFETY: the file is private to this test.
+        let reopened = unsafe { MappedSortedVec::<u64>::open(&path) }.unwrap();
+        assert!(reopened.contains(&300));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn mapped_sorted_vec_empty() {
+        let path = temp_path("empty");
+        let sorted_vec: SortedVec<i32> = SortedVec::new(Default::default());
+        // SAFETY: the file is private to this test.
+        let mapped = unsafe { MappedSortedVec::create(&path, &sorted_vec) }.unwrap();
+
+        assert!(mapped.is_empty());
+        assert_eq!(mapped.first(), None);
+        assert!(!mapped.contains(&1));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn mapped_sorted_vec_rejects_wrong_element_size() {
+        let path = temp_path("wrong-size");
+        let sorted_vec = SortedVec::from_vec(vec![1u32, 2, 3], Default::default());
+        // SAFETY: the file is private to this test.
+        unsafe { MappedSortedVec::create(&path, &sorted_vec) }.unwrap();
+
+        assert!(unsafe { MappedSortedVec::<u64>::open(&path) }.is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn mapped_sorted_vec_rejects_overflowing_element_count() {
+        let path = temp_path("overflow");
+        let sorted_vec = SortedVec::from_vec(vec![1u64, 2, 3], Default::default());
+        // SAFETY: the file is private to this test.
+        unsafe { MappedSortedVec::create(&path, &sorted_vec) }.unwrap();
+
+        let mut bytes = std::fs::read(&path).unwrap();
+        bytes[16..24].copy_from_slice(&(u64::MAX / 4).to_le_bytes());
+        std::fs::write(&path, bytes).unwrap();
+
+        let error = unsafe { MappedSortedVec::<u64>::open(&path) }.err().unwrap();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+        std::fs::remove_file(path).unwrap();
+    }
+}
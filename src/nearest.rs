@@ -0,0 +1,139 @@
+use std::ops::Sub;
+
+use crate::sorted_vec::SortedVec;
+use crate::storage::BucketStorage;
+
+impl<T: PartialOrd + Ord, S: BucketStorage<T>> SortedVec<T, S> {
+    /// The element closest to `item` by absolute difference. Ties go to the
+    /// element that comes first in the container's order.
+    pub fn closest<D: PartialOrd>(&self, item: &T) -> Option<&T>
+    where
+        for<'a> &'a T: Sub<&'a T, Output = D>,
+    {
+        self.closest_by(item, difference)
+    }
+
+    /// The `n` elements closest to `item` by absolute difference, in sorted
+    /// order.
+    pub fn closest_n<D: PartialOrd>(&self, item: &T, n: usize) -> Vec<&T>
+    where
+        for<'a> &'a T: Sub<&'a T, Output = D>,
+    {
+        self.closest_n_by(item, n, difference)
+    }
+
+    /// Like [`closest`](Self::closest) with a custom distance. Returning a
+    /// tuple such as `(distance, tiebreak)` picks between equally distant
+    /// elements.
+    pub fn closest_by<D: PartialOrd>(&self, item: &T, distance: impl Fn(&T, &T) -> D) -> Option<&T> {
+        self.closest_n_by(item, 1, distance).pop()
+    }
+
+    /// Like [`closest_n`](Self::closest_n) with a custom distance. Walks
+    /// outwards from the insertion point of `item`, so only `n` elements on
+    /// each side are looked at.
+    pub fn closest_n_by<D: PartialOrd>(&self, item: &T, n: usize, distance: impl Fn(&T, &T) -> D) -> Vec<&T> {
+        let start = self.lower_bound_position(item);
+        let mut before = self.step_back(start);
+        let mut after = self.get_position(start).map(|_| start);
+        let mut taken_before = Vec::new();
+        let mut taken_after = Vec::new();
+
+        while taken_before.len() + taken_after.len() < n {
+            let candidates = (before.and_then(|at| self.get_position(at)), after.and_then(|at| self.get_position(at)));
+            let take_before = match candidates {
+                (Some(lower), Some(upper)) => distance(lower, item) <= distance(upper, item),
+                (Some(_), None) => true,
+                (None, Some(_)) => false,
+                (None, None) => break,
+            };
+
+            if take_before {
+                taken_before.push(candidates.0.unwrap());
+                before = before.and_then(|at| self.step_back(at));
+            } else {
+                taken_after.push(candidates.1.unwrap());
+                after = after.and_then(|at| self.step_forward(at));
+            }
+        }
+
+        taken_before.reverse();
+        taken_before.extend(taken_after);
+        taken_before
+    }
+
+    pub(crate) fn step_back(&self, (bucket_idx, item_idx): (usize, usize)) -> Option<(usize, usize)> {
+        match item_idx {
+            0 => {
+                let bucket_idx = bucket_idx.checked_sub(1)?;
+                Some((bucket_idx, self.buckets[bucket_idx].len().checked_sub(1)?))
+            },
+            _ => Some((bucket_idx, item_idx - 1)),
+        }
+    }
+
+    pub(crate) fn step_forward(&self, (bucket_idx, item_idx): (usize, usize)) -> Option<(usize, usize)> {
+        if item_idx + 1 < self.buckets[bucket_idx].len() {
+            Some((bucket_idx, item_idx + 1))
+        } else if bucket_idx + 1 < self.buckets.len() {
+            Some((bucket_idx + 1, 0))
+        } else {
+            None
+        }
+    }
+}
+
+/// Subtracts the smaller value from the larger one, so unsigned types don't
+/// underflow.
+fn difference<T: Ord, D>(a: &T, b: &T) -> D
+where
+    for<'a> &'a T: Sub<&'a T, Output = D>,
+{
+    if a < b { b - a } else { a - b }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sorted_vec::{BucketConfiguration, Order, SortedVec};
+
+    #[test]
+    fn sorted_vec_closest() {
+        let configuration = BucketConfiguration::builder().max_bucket_capacity(2).build();
+        let sorted_vec = SortedVec::from_vec(vec![10u32, 20, 30, 40, 50], configuration);
+
+        assert_eq!(sorted_vec.closest(&24), Some(&20));
+        assert_eq!(sorted_vec.closest(&26), Some(&30));
+        assert_eq!(sorted_vec.closest(&25), Some(&20));
+        assert_eq!(sorted_vec.closest(&0), Some(&10));
+        assert_eq!(sorted_vec.closest(&99), Some(&50));
+        assert_eq!(SortedVec::<u32>::new(Default::default()).closest(&1), None);
+    }
+
+    #[test]
+    fn sorted_vec_closest_n() {
+        let configuration = BucketConfiguration::builder().max_bucket_capacity(2).build();
+        let sorted_vec = SortedVec::from_vec(vec![1, 4, 6, 7, 12, 20], configuration);
+
+        assert_eq!(sorted_vec.closest_n(&6, 3), vec![&4, &6, &7]);
+        assert_eq!(sorted_vec.closest_n(&15, 2), vec![&12, &20]);
+        assert_eq!(sorted_vec.closest_n(&0, 10).len(), 6);
+        assert!(sorted_vec.closest_n(&0, 0).is_empty());
+    }
+
+    #[test]
+    fn sorted_vec_closest_by_with_tiebreak() {
+        let sorted_vec = SortedVec::from_vec(vec![10, 20, 30], Default::default());
+        let prefer_larger = |a: &i32, b: &i32| ((a - b).abs(), -a);
+
+        assert_eq!(sorted_vec.closest_by(&25, prefer_larger), Some(&30));
+    }
+
+    #[test]
+    fn sorted_vec_closest_descending() {
+        let configuration = BucketConfiguration::builder().order(Order::Descending).build();
+        let sorted_vec = SortedVec::from_vec(vec![10, 20, 30], configuration);
+
+        assert_eq!(sorted_vec.closest(&24), Some(&20));
+        assert_eq!(sorted_vec.closest_n(&24, 2), vec![&30, &20]);
+    }
+}
@@ -0,0 +1,343 @@
+use std::fmt::{self, Debug, Formatter};
+use std::ops::{Bound, Deref, RangeBounds};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Weak};
+
+use crate::sorted_vec::SortedVec;
+
+/// A mutation reported to subscribers, with the element's global position.
+/// For removals the position is where the element was before it was removed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change<T> {
+    Inserted(T, usize),
+    Removed(T, usize),
+}
+
+/// A bucket that overflowed on insert and was split in two. The upper part
+/// became the bucket right after it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SplitEvent {
+    pub bucket: usize,
+    pub lower_len: usize,
+    pub upper_len: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Insert,
+    Remove,
+}
+
+type Callback<T> = Box<dyn FnMut(&T, usize) + Send>;
+type SplitCallback = Box<dyn FnMut(&SplitEvent) + Send>;
+
+enum Target<T> {
+    Callback(Callback<T>),
+    Channel(Sender<Change<T>>),
+}
+
+struct Observer<T> {
+    kinds: &'static [Kind],
+    range: (Bound<T>, Bound<T>),
+    target: Target<T>,
+    /// Set for [`Watch`] subscriptions; dead once the `Watch` is dropped.
+    watch: Option<Weak<()>>,
+}
+
+/// The receiving end of [`watch`](ObservedSortedVec::watch), reading like a
+/// `Receiver` through `Deref`. Dropping it unsubscribes: the subscription is
+/// cleaned up at the next change to the container, even one outside the
+/// watched range.
+#[derive(Debug)]
+pub struct Watch<T> {
+    receiver: Receiver<Change<T>>,
+    _alive: Arc<()>,
+}
+
+impl<T> Deref for Watch<T> {
+    type Target = Receiver<Change<T>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.receiver
+    }
+}
+
+/// Wraps a [`SortedVec`] and notifies registered callbacks and channels about
+/// every insert and remove, so caches and views can follow the container
+/// without diffing it. Reads go through `Deref`.
+pub struct ObservedSortedVec<T: PartialOrd + Ord> {
+    sorted_vec: SortedVec<T>,
+    observers: Vec<Observer<T>>,
+    split_observers: Vec<SplitCallback>,
+}
+
+impl<T: PartialOrd + Ord> ObservedSortedVec<T> {
+    pub fn new(sorted_vec: SortedVec<T>) -> Self {
+        ObservedSortedVec {
+            sorted_vec,
+            observers: Vec::new(),
+            split_observers: Vec::new(),
+        }
+    }
+
+    pub fn on_insert(&mut self, f: impl FnMut(&T, usize) + Send + 'static) {
+        self.observe(&[Kind::Insert], (Bound::Unbounded, Bound::Unbounded), Target::Callback(Box::new(f)));
+    }
+
+    pub fn on_remove(&mut self, f: impl FnMut(&T, usize) + Send + 'static) {
+        self.observe(&[Kind::Remove], (Bound::Unbounded, Bound::Unbounded), Target::Callback(Box::new(f)));
+    }
+
+    /// Calls `f` whenever an insert splits a bucket, e.g. to tune a
+    /// [`SplitStrategy`](crate::sorted_vec::SplitStrategy).
+    pub fn on_split(&mut self, f: impl FnMut(&SplitEvent) + Send + 'static) {
+        self.split_observers.push(Box::new(f));
+    }
+
+    /// Like [`on_insert`](Self::on_insert), but only for elements inside `range`.
+    pub fn on_insert_in<R: RangeBounds<T>>(&mut self, range: R, f: impl FnMut(&T, usize) + Send + 'static)
+    where
+        T: Clone,
+    {
+        self.observe(&[Kind::Insert], owned_bounds(range), Target::Callback(Box::new(f)));
+    }
+
+    /// Like [`on_remove`](Self::on_remove), but only for elements inside `range`.
+    pub fn on_remove_in<R: RangeBounds<T>>(&mut self, range: R, f: impl FnMut(&T, usize) + Send + 'static)
+    where
+        T: Clone,
+    {
+        self.observe(&[Kind::Remove], owned_bounds(range), Target::Callback(Box::new(f)));
+    }
+
+    /// Returns a channel receiving every change. Dropping the receiver
+    /// unsubscribes.
+    pub fn subscribe(&mut self) -> Receiver<Change<T>>
+    where
+        T: Clone,
+    {
+        self.subscribe_in(..)
+    }
+
+    /// Like [`subscribe`](Self::subscribe), but only for elements inside `range`.
+    pub fn subscribe_in<R: RangeBounds<T>>(&mut self, range: R) -> Receiver<Change<T>>
+    where
+        T: Clone,
+    {
+        let (sender, receiver) = mpsc::channel();
+        self.observe(&[Kind::Insert, Kind::Remove], owned_bounds(range), Target::Channel(sender));
+        receiver
+    }
+
+    /// Delivers the inserts and removals of elements inside `range`, e.g. to
+    /// keep a materialized view of that range up to date.
+    pub fn watch<R: RangeBounds<T>>(&mut self, range: R) -> Watch<T>
+    where
+        T: Clone,
+    {
+        let (sender, receiver) = mpsc::channel();
+        let alive = Arc::new(());
+        self.observers.push(Observer {
+            kinds: &[Kind::Insert, Kind::Remove],
+            range: owned_bounds(range),
+            target: Target::Channel(sender),
+            watch: Some(Arc::downgrade(&alive)),
+        });
+
+        Watch { receiver, _alive: alive }
+    }
+
+    fn observe(&mut self, kinds: &'static [Kind], range: (Bound<T>, Bound<T>), target: Target<T>) {
+        self.observers.push(Observer {
+            kinds,
+            range,
+            target,
+            watch: None,
+        });
+    }
+
+    pub fn insert(&mut self, item: T)
+    where
+        T: Clone,
+    {
+        if let Some((position, split)) = self.sorted_vec.insert_tracked(item) {
+            if let Some(bucket) = split.filter(|_| !self.split_observers.is_empty()) {
+                let event = SplitEvent {
+                    bucket,
+                    lower_len: self.sorted_vec.buckets[bucket].len(),
+                    upper_len: self.sorted_vec.buckets[bucket + 1].len(),
+                };
+                self.split_observers.iter_mut().for_each(|f| f(&event));
+            }
+            if self.observers.is_empty() {
+                return;
+            }
+            let idx = self.sorted_vec.global_index(position);
+            let item = &self.sorted_vec.buckets[position.0].data[position.1];
+            notify(&mut self.observers, Kind::Insert, item, idx);
+        }
+    }
+
+    pub fn remove(&mut self, item: &T)
+    where
+        T: Clone,
+    {
+        if let Some((removed, position)) = self.sorted_vec.remove_position(item) {
+            if self.observers.is_empty() {
+                return;
+            }
+            let idx = self.sorted_vec.global_index(position);
+            notify(&mut self.observers, Kind::Remove, &removed, idx);
+        }
+    }
+
+    pub fn into_inner(self) -> SortedVec<T> {
+        self.sorted_vec
+    }
+}
+
+fn notify<T: PartialOrd + Ord + Clone>(observers: &mut Vec<Observer<T>>, kind: Kind, item: &T, idx: usize) {
+    observers.retain_mut(|observer| {
+        if observer.watch.as_ref().is_some_and(|watch| watch.strong_count() == 0) {
+            return false;
+        }
+        if !observer.kinds.contains(&kind) || !observer.range.contains(item) {
+            return true;
+        }
+
+        match &mut observer.target {
+            Target::Callback(f) => {
+                f(item, idx);
+                true
+            },
+            Target::Channel(sender) => {
+                let change = match kind {
+                    Kind::Insert => Change::Inserted(item.clone(), idx),
+                    Kind::Remove => Change::Removed(item.clone(), idx),
+                };
+                sender.send(change).is_ok()
+            },
+        }
+    });
+}
+
+fn owned_bounds<T: Clone, R: RangeBounds<T>>(range: R) -> (Bound<T>, Bound<T>) {
+    (range.start_bound().cloned(), range.end_bound().cloned())
+}
+
+impl<T: PartialOrd + Ord> Deref for ObservedSortedVec<T> {
+    type Target = SortedVec<T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.sorted_vec
+    }
+}
+
+impl<T: PartialOrd + Ord + Debug> Debug for ObservedSortedVec<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ObservedSortedVec")
+            .field("sorted_vec", &self.sorted_vec)
+            .field("observers", &self.observers.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::sorted_vec::BucketConfiguration;
+
+    #[test]
+    fn observed_sorted_vec_reports_global_positions() {
+        let configuration = BucketConfiguration::builder().max_bucket_capacity(2).build();
+        let mut observed = ObservedSortedVec::new(SortedVec::new(configuration));
+        let inserted = Arc::new(Mutex::new(Vec::new()));
+        let removed = Arc::new(Mutex::new(Vec::new()));
+        let sink = inserted.clone();
+        observed.on_insert(move |item, idx| sink.lock().unwrap().push((*item, idx)));
+        let sink = removed.clone();
+        observed.on_remove(move |item, idx| sink.lock().unwrap().push((*item, idx)));
+
+        for item in [5, 1, 3, 4, 2, 3] {
+            observed.insert(item);
+        }
+        observed.remove(&4);
+        observed.remove(&9);
+
+        assert_eq!(*inserted.lock().unwrap(), vec![(5, 0), (1, 0), (3, 1), (4, 2), (2, 1)]);
+        assert_eq!(*removed.lock().unwrap(), vec![(4, 3)]);
+        assert_eq!(observed.iter().copied().collect::<Vec<_>>(), vec![1, 2, 3, 5]);
+    }
+
+    #[test]
+    fn observed_sorted_vec_range_restricted_callbacks() {
+        let mut observed = ObservedSortedVec::new(SortedVec::new(Default::default()));
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = seen.clone();
+        observed.on_insert_in(10..20, move |item, _| sink.lock().unwrap().push(*item));
+
+        for item in [5, 10, 15, 20, 25] {
+            observed.insert(item);
+        }
+
+        assert_eq!(*seen.lock().unwrap(), vec![10, 15]);
+    }
+
+    #[test]
+    fn observed_sorted_vec_channel_subscription() {
+        let mut observed = ObservedSortedVec::new(SortedVec::new(Default::default()));
+        let changes = observed.subscribe();
+        observed.insert(2);
+        observed.insert(1);
+        observed.remove(&2);
+
+        assert_eq!(
+            changes.try_iter().collect::<Vec<_>>(),
+            vec![Change::Inserted(2, 0), Change::Inserted(1, 0), Change::Removed(2, 1)]
+        );
+
+        drop(changes);
+        observed.insert(3);
+        assert_eq!(observed.observers.len(), 0);
+    }
+
+    #[test]
+    fn observed_sorted_vec_watch_filters_and_cleans_up() {
+        let mut observed = ObservedSortedVec::new(SortedVec::new(Default::default()));
+        let low = observed.watch(..10);
+        let high = observed.watch(10..);
+        for item in [3, 12, 7] {
+            observed.insert(item);
+        }
+        observed.remove(&12);
+
+        assert_eq!(low.try_iter().collect::<Vec<_>>(), vec![Change::Inserted(3, 0), Change::Inserted(7, 1)]);
+        assert_eq!(high.try_iter().collect::<Vec<_>>(), vec![Change::Inserted(12, 1), Change::Removed(12, 2)]);
+
+        drop(high);
+        observed.insert(1);
+        assert_eq!(observed.observers.len(), 1);
+        assert_eq!(low.try_recv(), Ok(Change::Inserted(1, 0)));
+    }
+
+    #[test]
+    fn observed_sorted_vec_reports_splits() {
+        let configuration = BucketConfiguration::builder().max_bucket_capacity(4).build();
+        let mut observed = ObservedSortedVec::new(SortedVec::new(configuration));
+        let splits = Arc::new(Mutex::new(Vec::new()));
+        let sink = splits.clone();
+        observed.on_split(move |event| sink.lock().unwrap().push(*event));
+
+        for item in 0..10 {
+            observed.insert(item);
+        }
+
+        assert_eq!(*splits.lock().unwrap(), vec![
+            SplitEvent { bucket: 0, lower_len: 2, upper_len: 3 },
+            SplitEvent { bucket: 1, lower_len: 2, upper_len: 3 },
+            SplitEvent { bucket: 2, lower_len: 2, upper_len: 3 },
+        ]);
+    }
+}
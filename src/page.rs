@@ -0,0 +1,168 @@
+use std::cmp::Ordering;
+use std::io::{self, Read, Write};
+
+use crate::codec::Codec;
+use crate::iter::SortedVecIter;
+use crate::sorted_vec::SortedVec;
+use crate::storage::BucketStorage;
+
+/// Where a listing stopped: the last element handed out and how many
+/// elements equal to it were handed out. It holds no position, so resuming
+/// stays correct when elements are inserted or removed between fetches.
+/// Encode it with [`Codec`] to hand it to a client.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageToken<T> {
+    last: T,
+    offset: usize,
+}
+
+impl<T: Codec> Codec for PageToken<T> {
+    fn encode<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        self.last.encode(out)?;
+        (self.offset as u64).encode(out)
+    }
+
+    fn decode<R: Read>(input: &mut R) -> io::Result<Self> {
+        Ok(PageToken {
+            last: T::decode(input)?,
+            offset: u64::decode(input)? as usize,
+        })
+    }
+}
+
+/// One page of a listing. Returned by [`SortedVec::page_after`].
+#[derive(Debug)]
+pub struct Page<'a, T> {
+    pub items: Vec<&'a T>,
+    /// Where the next page starts, `None` once the listing reached the end.
+    pub next: Option<PageToken<T>>,
+}
+
+impl<T: PartialOrd + Ord, S: BucketStorage<T>> SortedVec<T, S> {
+    /// The elements ranked `page_idx * page_size` up to the next page. Pages
+    /// shift when elements are inserted before them; listings that must not
+    /// skip or repeat elements should use [`page_after`](Self::page_after).
+    /// Both ends are selected through the bucket directory's element counts.
+    pub fn page(&self, page_size: usize, page_idx: usize) -> SortedVecIter<'_, T, S> {
+        let start = page_size.saturating_mul(page_idx).min(self.size);
+        let end = start.saturating_add(page_size).min(self.size);
+
+        SortedVecIter::between_counted(self, self.position_of_rank(start), self.position_of_rank(end), end - start)
+    }
+
+    /// The first `page_size` elements after `token`, or from the start
+    /// without one, together with the token for the following page.
+    /// Elements inserted behind the token are not listed and elements
+    /// inserted ahead of it are. Within a run of equal elements this relies
+    /// on new elements going to the end of the run, as with
+    /// [`TieBreak::Fifo`](crate::sorted_vec::TieBreak::Fifo).
+    pub fn page_after(&self, token: Option<&PageToken<T>>, page_size: usize) -> Page<'_, T>
+    where
+        T: Clone,
+    {
+        let order = self.configuration.order();
+        let mut items = self.iter();
+        let mut skipped = 0;
+        if let Some(token) = token {
+            items = self.iter_from(&token.last);
+            while skipped < token.offset && items.clone().next().is_some_and(|item| order.compare(item, &token.last) == Ordering::Equal) {
+                items.next();
+                skipped += 1;
+            }
+        }
+
+        let page: Vec<&T> = items.by_ref().take(page_size).collect();
+        let next = match page.last() {
+            Some(&last) if page.len() == page_size && items.len() > 0 => {
+                let run = page.iter().rev().take_while(|&&item| order.compare(item, last) == Ordering::Equal).count();
+                let resumed_run = token.is_some_and(|token| order.compare(&token.last, last) == Ordering::Equal);
+                let carried = if run == page.len() && resumed_run { skipped } else { 0 };
+                Some(PageToken {
+                    last: last.clone(),
+                    offset: carried + run,
+                })
+            },
+            _ => None,
+        };
+
+        Page { items: page, next }
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sorted_vec::{BucketConfiguration, DuplicatePolicy};
+
+    fn configuration() -> BucketConfiguration {
+        BucketConfiguration::builder()
+            .max_bucket_capacity(3)
+            .duplicate_policy(DuplicatePolicy::Allow)
+            .build()
+    }
+
+    #[test]
+    fn page_counts_by_rank() {
+        let sorted_vec = SortedVec::from_vec((0..10).collect(), configuration());
+
+        assert!(sorted_vec.page(4, 0).copied().eq(0..4));
+        assert!(sorted_vec.page(4, 2).copied().eq(8..10));
+        assert_eq!(sorted_vec.page(4, 3).count(), 0);
+        assert_eq!(sorted_vec.page(4, usize::MAX).count(), 0);
+    }
+
+    #[test]
+    fn page_counts_by_rank_after_removals() {
+        let mut sorted_vec = SortedVec::from_vec((0..300).collect(), configuration());
+        for item in (0..300).step_by(4) {
+            sorted_vec.remove(&item);
+        }
+
+        let items: Vec<_> = sorted_vec.iter().copied().collect();
+        for (page_idx, expected) in items.chunks(7).enumerate() {
+            let page = sorted_vec.page(7, page_idx);
+            assert_eq!(page.len(), expected.len());
+            assert!(page.eq(expected));
+        }
+    }
+
+    #[test]
+    fn page_after_survives_concurrent_inserts() {
+        let mut sorted_vec = SortedVec::from_vec(vec![1, 2, 5, 5, 5, 5, 5, 8, 9], configuration());
+        let mut listed = Vec::new();
+        let mut token = None;
+        loop {
+            let page = sorted_vec.page_after(token.as_ref(), 2);
+            listed.extend(page.items.into_iter().copied());
+            let Some(next) = page.next else {
+                break;
+            };
+
+            let mut encoded = Vec::new();
+            next.encode(&mut encoded).unwrap();
+            token = Some(PageToken::decode(&mut &encoded[..]).unwrap());
+            assert_eq!(token.as_ref(), Some(&next));
+
+            if listed.len() == 4 {
+                sorted_vec.insert(0);
+                sorted_vec.insert(5);
+                sorted_vec.insert(7);
+            }
+        }
+
+        assert_eq!(listed, vec![1, 2, 5, 5, 5, 5, 5, 5, 7, 8, 9]);
+    }
+
+    #[test]
+    fn page_after_stops_at_the_end() {
+        let sorted_vec = SortedVec::from_vec(vec![3, 3, 3, 3], configuration());
+        let first = sorted_vec.page_after(None, 2);
+        assert_eq!(first.next, Some(PageToken { last: 3, offset: 2 }));
+
+        let second = sorted_vec.page_after(first.next.as_ref(), 2);
+        assert_eq!(second.items, vec![&3, &3]);
+        assert_eq!(second.next, None);
+        assert!(sorted_vec.page_after(Some(&PageToken { last: 3, offset: 4 }), 2).items.is_empty());
+    }
+}
@@ -0,0 +1,304 @@
+use std::ops::Range;
+
+#[cfg(feature = "rayon")]
+use rayon::iter::plumbing::UnindexedConsumer;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use crate::iter::SortedVecIter;
+#[cfg(feature = "rayon")]
+use crate::sorted_vec::BucketConfiguration;
+use crate::sorted_vec::SortedVec;
+use crate::storage::BucketStorage;
+
+/// Parallel iterator over the elements, handing each bucket to a worker as
+/// one chunk. Returned by `par_iter()` through [`IntoParallelIterator`].
+#[cfg(feature = "rayon")]
+pub struct ParIter<'a, T> {
+    buckets: Vec<&'a [T]>,
+}
+
+#[cfg(feature = "rayon")]
+impl<'a, T: Sync> ParallelIterator for ParIter<'a, T> {
+    type Item = &'a T;
+
+    fn drive_unindexed<C: UnindexedConsumer<Self::Item>>(self, consumer: C) -> C::Result {
+        self.buckets.into_par_iter().flat_map_iter(|bucket| bucket.iter()).drive_unindexed(consumer)
+    }
+}
+
+/// Parallel iterator moving the elements out, one bucket per chunk.
+#[cfg(feature = "rayon")]
+pub struct IntoParIter<T> {
+    buckets: Vec<Vec<T>>,
+}
+
+#[cfg(feature = "rayon")]
+impl<T: Send> ParallelIterator for IntoParIter<T> {
+    type Item = T;
+
+    fn drive_unindexed<C: UnindexedConsumer<Self::Item>>(self, consumer: C) -> C::Result {
+        self.buckets.into_par_iter().flat_map_iter(Vec::into_iter).drive_unindexed(consumer)
+    }
+}
+
+/// Parallel iterator over the buckets as slices, in bucket order when
+/// collected. Returned by [`SortedVec::par_buckets`].
+#[cfg(feature = "rayon")]
+pub struct ParBuckets<'a, T> {
+    buckets: Vec<&'a [T]>,
+}
+
+#[cfg(feature = "rayon")]
+impl<'a, T: Sync> ParallelIterator for ParBuckets<'a, T> {
+    type Item = &'a [T];
+
+    fn drive_unindexed<C: UnindexedConsumer<Self::Item>>(self, consumer: C) -> C::Result {
+        self.buckets.into_par_iter().drive_unindexed(consumer)
+    }
+
+    fn opt_len(&self) -> Option<usize> {
+        Some(self.buckets.len())
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<'a, T: PartialOrd + Ord + Sync> IntoParallelIterator for &'a SortedVec<T> {
+    type Iter = ParIter<'a, T>;
+    type Item = &'a T;
+
+    fn into_par_iter(self) -> Self::Iter {
+        ParIter { buckets: self.bucket_slices() }
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<T: PartialOrd + Ord + Send> IntoParallelIterator for SortedVec<T> {
+    type Iter = IntoParIter<T>;
+    type Item = T;
+
+    fn into_par_iter(self) -> Self::Iter {
+        IntoParIter {
+            buckets: self.buckets.into_iter().map(|mut bucket| bucket.data.take()).collect(),
+        }
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<T: PartialOrd + Ord + Send> ParallelExtend<T> for SortedVec<T> {
+    /// Collects and sorts the items on the rayon pool, then merges them into
+    /// the buckets in a single pass like [`insert_many`](SortedVec::insert_many).
+    fn par_extend<I: IntoParallelIterator<Item = T>>(&mut self, items: I) {
+        let order = self.configuration.order();
+        let mut batch: Vec<T> = items.into_par_iter().collect();
+        batch.par_sort_by(|a, b| order.compare(a, b));
+        self.insert_sorted_batch(batch);
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<T: PartialOrd + Ord + Send> FromParallelIterator<T> for SortedVec<T> {
+    fn from_par_iter<I: IntoParallelIterator<Item = T>>(items: I) -> Self {
+        let mut result = SortedVec::new(Default::default());
+        result.par_extend(items);
+        result
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<T: PartialOrd + Ord + Sync> SortedVec<T> {
+    /// The buckets as slices, each a natural chunk of parallel work.
+    pub fn par_buckets(&self) -> ParBuckets<'_, T> {
+        ParBuckets { buckets: self.bucket_slices() }
+    }
+
+    fn bucket_slices(&self) -> Vec<&[T]> {
+        self.buckets.iter().map(|bucket| bucket.data.as_slice()).filter(|bucket| !bucket.is_empty()).collect()
+    }
+}
+
+impl<T: PartialOrd + Ord, S: BucketStorage<T>> SortedVec<T, S> {
+    /// Cuts the container into at most `n` contiguous runs of whole buckets
+    /// holding roughly the same number of elements, so work can be handed to
+    /// any thread pool. The runs are returned in order and none is empty.
+    pub fn chunks_for_parallelism(&self, n: usize) -> Vec<SortedVecIter<'_, T, S>> {
+        self.balanced_runs(n)
+            .into_iter()
+            .map(|run| SortedVecIter::between(self, (run.start, 0), (run.end, 0)))
+            .collect()
+    }
+
+    /// Consumes the container and moves its buckets, without cloning any
+    /// element, into at most `n` containers cut like
+    /// [`chunks_for_parallelism`](Self::chunks_for_parallelism), e.g. to
+    /// re-shard data across workers. The pieces share the configuration but
+    /// start without the optional indexes such as the fence index.
+    pub fn split_into(self, n: usize) -> Vec<Self> {
+        let runs = self.balanced_runs(n);
+        let mut buckets = self.buckets.into_iter();
+        runs.into_iter()
+            .map(|run| {
+                let mut piece = Self::with_storage(self.configuration.clone());
+                piece.buckets.clear();
+                piece.buckets.extend(buckets.by_ref().take(run.len()));
+                piece.size = piece.buckets.iter().map(|bucket| bucket.len()).sum();
+                piece
+            })
+            .collect()
+    }
+
+    /// The first element of every piece [`split_into`](Self::split_into)
+    /// would produce except the first one, i.e. the keys where the shards
+    /// after the first begin.
+    pub fn split_keys(&self, n: usize) -> Vec<&T> {
+        self.balanced_runs(n)
+            .into_iter()
+            .skip(1)
+            .filter_map(|run| self.buckets[run.start].data.first())
+            .collect()
+    }
+
+    /// Bucket index ranges of at most `n` contiguous, non-empty runs holding
+    /// roughly the same number of elements.
+    fn balanced_runs(&self, n: usize) -> Vec<Range<usize>> {
+        let n = n.max(1);
+        let mut runs = Vec::with_capacity(n);
+        let mut start = 0;
+        let mut seen = 0;
+        for (bucket_idx, bucket) in self.buckets.iter().enumerate() {
+            seen += bucket.len();
+            if seen > 0 && seen * n >= self.size * (runs.len() + 1) {
+                runs.push(start..bucket_idx + 1);
+                start = bucket_idx + 1;
+            }
+        }
+
+        runs
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<T: PartialOrd + Ord + Send> SortedVec<T> {
+    /// Builds a container from unsorted data by sorting it on the rayon pool,
+    /// dropping duplicates according to the configuration and cutting the
+    /// result into buckets. Much faster than [`from_vec`](Self::from_vec) for
+    /// large inputs.
+    pub fn from_vec_par(mut data: Vec<T>, configuration: BucketConfiguration) -> Self {
+        let mut result = Self::new(configuration);
+        let order = result.configuration.order();
+        data.par_sort_by(|a, b| order.compare(a, b));
+        result.insert_sorted_batch(data);
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "rayon")]
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[cfg(feature = "rayon")]
+    use rayon::prelude::*;
+
+    use crate::sorted_vec::{BucketConfiguration, SortedVec};
+
+    #[test]
+    fn chunks_for_parallelism_cover_everything_once() {
+        let config = BucketConfiguration::builder().max_bucket_capacity(10).build();
+        let sorted_vec = SortedVec::from_vec((0..1000).collect(), config);
+
+        let chunks = sorted_vec.chunks_for_parallelism(4);
+        assert_eq!(chunks.len(), 4);
+        assert!(chunks.iter().all(|chunk| chunk.len() > 150 && chunk.len() < 350));
+        let items: Vec<i32> = chunks.into_iter().flatten().copied().collect();
+        assert_eq!(items, (0..1000).collect::<Vec<_>>());
+
+        assert_eq!(sorted_vec.chunks_for_parallelism(10_000).len(), sorted_vec.buckets.len());
+        assert!(SortedVec::<i32>::new(Default::default()).chunks_for_parallelism(3).is_empty());
+    }
+
+    #[test]
+    fn split_into_moves_balanced_pieces() {
+        let config = BucketConfiguration::builder().max_bucket_capacity(10).build();
+        let sorted_vec = SortedVec::from_vec((0..1000).collect(), config);
+        let keys: Vec<i32> = sorted_vec.split_keys(4).into_iter().copied().collect();
+
+        let pieces = sorted_vec.split_into(4);
+        assert_eq!(pieces.len(), 4);
+        assert!(pieces.iter().all(|piece| piece.size > 150 && piece.size < 350));
+        assert_eq!(pieces.iter().skip(1).map(|piece| *piece.first().unwrap()).collect::<Vec<_>>(), keys);
+        assert!(pieces.iter().flat_map(|piece| piece.iter()).copied().eq(0..1000));
+
+        let mut piece = pieces.into_iter().nth(1).unwrap();
+        piece.insert(-1);
+        assert!(piece.find_index(&-1).is_some());
+        assert!(SortedVec::<i32>::new(Default::default()).split_into(3).is_empty());
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn par_buckets_keep_bucket_order() {
+        let config = BucketConfiguration::builder().max_bucket_capacity(100).build();
+        let sorted_vec = SortedVec::from_vec((0..50_000).collect(), config);
+        let firsts: Vec<i32> = sorted_vec.par_buckets().map(|bucket| bucket[0]).collect();
+
+        assert_eq!(firsts.len(), sorted_vec.buckets.len());
+        assert!(firsts.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn par_iter_visits_every_element() {
+        let sorted_vec = SortedVec::from_vec((0..20_000).collect(), Default::default());
+        let sum = AtomicUsize::new(0);
+        sorted_vec.par_iter().for_each(|item| {
+            sum.fetch_add(*item, Ordering::Relaxed);
+        });
+
+        assert_eq!(sum.into_inner(), (0..20_000).sum());
+        let doubled: Vec<usize> = sorted_vec.into_par_iter().map(|item| item * 2).collect();
+        assert!(doubled.into_iter().eq((0..20_000).map(|item| item * 2)));
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn par_extend_merges_into_existing() {
+        let mut sorted_vec = SortedVec::from_vec((0..100).map(|i| i * 2).collect(), Default::default());
+        sorted_vec.par_extend((0..40_000).into_par_iter().rev());
+
+        assert_eq!(sorted_vec.size, 40_000);
+        assert!(sorted_vec.into_iter().eq(0..40_000));
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn from_par_iter_sorts_and_dedups() {
+        let sorted_vec: SortedVec<u64> = (0..30_000u64).into_par_iter().map(|i| i * 7919 % 10_007).collect();
+
+        assert_eq!(sorted_vec.size, 10_007);
+        assert!(sorted_vec.into_iter().eq(0..10_007));
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn from_vec_par_matches_from_vec() {
+        let data: Vec<u64> = (0..30_000u64).map(|i| i * 7919 % 10_007).collect();
+        let config = BucketConfiguration::builder().max_bucket_capacity(64).build();
+        let parallel = SortedVec::from_vec_par(data.clone(), config.clone());
+        let sequential = SortedVec::from_vec(data, config);
+
+        assert_eq!(parallel.size, sequential.size);
+        assert!(parallel.buckets.iter().all(|bucket| bucket.len() <= 64));
+        assert!(parallel.into_iter().eq(sequential));
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn from_vec_par_empty() {
+        let sorted_vec: SortedVec<i32> = SortedVec::from_vec_par(Vec::new(), Default::default());
+
+        assert_eq!(sorted_vec.size, 0);
+        assert_eq!(sorted_vec.buckets.len(), 1);
+    }
+}
@@ -0,0 +1,293 @@
+use std::cmp::Ordering;
+use std::sync::Arc;
+
+use crate::bucket::SharedItems;
+use crate::sorted_vec::{BucketConfiguration, DuplicatePolicy, TieBreak};
+
+/// A bucket shared between versions, and with the snapshots of a mutable
+/// [`SortedVec`](crate::sorted_vec::SortedVec) it was converted from.
+type SharedBucket<T> = Arc<SharedItems<Vec<T>>>;
+
+fn shared_bucket<T>(items: Vec<T>) -> SharedBucket<T> {
+    Arc::new(SharedItems::new(items))
+}
+
+/// An immutable sorted container whose `insert` and `remove` return a new
+/// version instead of mutating in place. Versions share every bucket the
+/// operation didn't touch, so keeping old versions around costs one bucket
+/// copy per change rather than a full clone.
+#[derive(Debug)]
+pub struct SortedVec<T: PartialOrd + Ord> {
+    buckets: Arc<[SharedBucket<T>]>,
+    configuration: BucketConfiguration,
+    size: usize,
+}
+
+impl<T: PartialOrd + Ord> Clone for SortedVec<T> {
+    fn clone(&self) -> Self {
+        SortedVec {
+            buckets: Arc::clone(&self.buckets),
+            configuration: self.configuration.clone(),
+            size: self.size,
+        }
+    }
+}
+
+impl<T: PartialOrd + Ord + Clone> SortedVec<T> {
+    pub fn new(configuration: BucketConfiguration) -> Self {
+        SortedVec {
+            buckets: Arc::new([shared_bucket(Vec::new())]),
+            configuration,
+            size: 0,
+        }
+    }
+
+    /// Returns a new version containing `item`. When the item is rejected as a
+    /// duplicate the new version shares everything with this one.
+    pub fn insert(&self, item: T) -> Self {
+        let order = self.configuration.order();
+        let lifo = self.configuration.duplicate_policy() == DuplicatePolicy::Allow
+            && self.configuration.tie_break() == TieBreak::Lifo;
+        let bucket_idx = if lifo { self.lower_bound_bucket_index(&item) } else { self.find_bucket_index(&item) };
+        let bucket = &self.buckets[bucket_idx];
+
+        let position = match (self.configuration.duplicate_policy(), self.configuration.tie_break()) {
+            (DuplicatePolicy::Reject, _) => match bucket.binary_search_by(|probe| order.compare(probe, &item)) {
+                Ok(_) => return self.clone(),
+                Err(position) => position,
+            },
+            (DuplicatePolicy::Allow, TieBreak::Fifo) => {
+                bucket.partition_point(|probe| order.compare(probe, &item) != Ordering::Greater)
+            }
+            (DuplicatePolicy::Allow, TieBreak::Lifo) => {
+                bucket.partition_point(|probe| order.compare(probe, &item) == Ordering::Less)
+            }
+        };
+
+        let mut data = Vec::with_capacity(bucket.len() + 1);
+        data.extend_from_slice(&bucket[..position]);
+        data.push(item);
+        data.extend_from_slice(&bucket[position..]);
+
+        let capacity = self.configuration.bucket_capacity(self.size + 1);
+        let replacement: Vec<SharedBucket<T>> = if data.len() > capacity {
+            let at = self.configuration.split_point(data.len(), position, capacity);
+            let upper = data.split_off(at);
+            vec![shared_bucket(data), shared_bucket(upper)]
+        } else {
+            vec![shared_bucket(data)]
+        };
+
+        self.replace_bucket(bucket_idx, replacement, self.size + 1)
+    }
+
+    /// Returns a new version without one element equal to `item`.
+    pub fn remove(&self, item: &T) -> Self {
+        let order = self.configuration.order();
+        let bucket_idx = self.find_bucket_index(item);
+        let bucket = &self.buckets[bucket_idx];
+
+        let Ok(position) = bucket.binary_search_by(|probe| order.compare(probe, item)) else {
+            return self.clone();
+        };
+
+        let mut data = Vec::with_capacity(bucket.len() - 1);
+        data.extend_from_slice(&bucket[..position]);
+        data.extend_from_slice(&bucket[position + 1..]);
+
+        let replacement = if data.is_empty() && self.buckets.len() > 1 {
+            Vec::new()
+        } else {
+            vec![shared_bucket(data)]
+        };
+
+        self.replace_bucket(bucket_idx, replacement, self.size - 1)
+    }
+
+    fn replace_bucket(&self, bucket_idx: usize, replacement: Vec<SharedBucket<T>>, size: usize) -> Self {
+        let buckets: Vec<SharedBucket<T>> = self.buckets[..bucket_idx]
+            .iter()
+            .cloned()
+            .chain(replacement)
+            .chain(self.buckets[bucket_idx + 1..].iter().cloned())
+            .collect();
+
+        SortedVec {
+            buckets: buckets.into(),
+            configuration: self.configuration.clone(),
+            size,
+        }
+    }
+}
+
+impl<T: PartialOrd + Ord> SortedVec<T> {
+    pub fn len(&self) -> usize {
+        self.size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    pub fn contains(&self, item: &T) -> bool {
+        let order = self.configuration.order();
+        self.buckets[self.find_bucket_index(item)]
+            .binary_search_by(|probe| order.compare(probe, item))
+            .is_ok()
+    }
+
+    pub fn at(&self, mut idx: usize) -> Option<&T> {
+        for bucket in self.buckets.iter() {
+            if idx < bucket.len() {
+                return Some(&bucket[idx]);
+            }
+
+            idx -= bucket.len();
+        }
+
+        None
+    }
+
+    pub fn first(&self) -> Option<&T> {
+        self.buckets.first()?.first()
+    }
+
+    pub fn last(&self) -> Option<&T> {
+        self.buckets.last()?.last()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.buckets.iter().flat_map(|bucket| bucket.iter())
+    }
+
+    /// The last bucket starting at or before `item`. Equal elements can only
+    /// live in that bucket, and new elements are appended after them.
+    fn find_bucket_index(&self, item: &T) -> usize {
+        let order = self.configuration.order();
+        self.buckets
+            .partition_point(|bucket| bucket.first().is_some_and(|first| order.compare(first, item) != Ordering::Greater))
+            .saturating_sub(1)
+    }
+
+    /// The last bucket starting before `item`, where a LIFO insert goes in
+    /// front of any equal elements.
+    fn lower_bound_bucket_index(&self, item: &T) -> usize {
+        let order = self.configuration.order();
+        self.buckets
+            .partition_point(|bucket| bucket.first().is_some_and(|first| order.compare(first, item) == Ordering::Less))
+            .saturating_sub(1)
+    }
+}
+
+impl<T: PartialOrd + Ord + Clone> From<&crate::sorted_vec::SortedVec<T>> for SortedVec<T> {
+    /// Shares the buckets of a [`snapshot`](crate::sorted_vec::SortedVec::snapshot).
+    fn from(sorted_vec: &crate::sorted_vec::SortedVec<T>) -> Self {
+        let buckets: Vec<SharedBucket<T>> = sorted_vec
+            .buckets
+            .iter()
+            .filter(|bucket| bucket.len() > 0)
+            .map(|bucket| bucket.data.share())
+            .collect();
+
+        let mut result = SortedVec::new(sorted_vec.configuration.clone());
+        if !buckets.is_empty() {
+            result.buckets = buckets.into();
+            result.size = sorted_vec.size;
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    #[test]
+    fn persistent_insert_keeps_old_versions() {
+        let empty = SortedVec::new(BucketConfiguration::default());
+        let one = empty.insert(5);
+        let two = one.insert(3);
+        let duplicate = two.insert(3);
+
+        assert!(empty.is_empty());
+        assert_eq!(one.iter().copied().collect::<Vec<_>>(), vec![5]);
+        assert_eq!(two.iter().copied().collect::<Vec<_>>(), vec![3, 5]);
+        assert_eq!(duplicate.len(), 2);
+        assert!(two.contains(&3));
+        assert!(!one.contains(&3));
+    }
+
+    #[test]
+    fn persistent_versions_share_untouched_buckets() {
+        let config = BucketConfiguration::builder().max_bucket_capacity(4).build();
+        let base = (0..16).fold(SortedVec::new(config), |version, item| version.insert(item));
+        let next = base.insert(100);
+
+        assert_eq!(next.len(), 17);
+        assert_eq!(next.last(), Some(&100));
+        let shared = base
+            .buckets
+            .iter()
+            .zip(next.buckets.iter())
+            .filter(|(a, b)| Arc::ptr_eq(a, b))
+            .count();
+        assert_eq!(shared, base.buckets.len() - 1);
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    struct Keyed(i32, u32);
+
+    impl PartialOrd for Keyed {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Keyed {
+        fn cmp(&self, other: &Self) -> Ordering {
+            self.0.cmp(&other.0)
+        }
+    }
+
+    #[test]
+    fn persistent_insert_honours_tie_break() {
+        for (tie_break, expected) in [(TieBreak::Fifo, vec![1, 2, 3, 4, 5, 6]), (TieBreak::Lifo, vec![6, 5, 4, 3, 2, 1])] {
+            let config = BucketConfiguration::builder()
+                .max_bucket_capacity(2)
+                .duplicate_policy(DuplicatePolicy::Allow)
+                .tie_break(tie_break)
+                .build();
+            let keyed = (1..=6).fold(SortedVec::new(config), |version, payload| version.insert(Keyed(0, payload)));
+            let keyed = keyed.insert(Keyed(-1, 0)).insert(Keyed(1, 0));
+
+            let payloads: Vec<_> = keyed.iter().filter(|item| item.0 == 0).map(|item| item.1).collect();
+            assert_eq!(payloads, expected);
+            assert_eq!(keyed.first(), Some(&Keyed(-1, 0)));
+            assert_eq!(keyed.last(), Some(&Keyed(1, 0)));
+        }
+    }
+
+    #[test]
+    fn persistent_remove() {
+        let config = BucketConfiguration::builder().max_bucket_capacity(2).build();
+        let base = (0..6).fold(SortedVec::new(config), |version, item| version.insert(item));
+        let removed = base.remove(&0).remove(&1).remove(&42);
+
+        assert_eq!(base.len(), 6);
+        assert_eq!(removed.iter().copied().collect::<Vec<_>>(), vec![2, 3, 4, 5]);
+        assert_eq!(removed.first(), Some(&2));
+        assert_eq!(removed.at(3), Some(&5));
+    }
+
+    #[test]
+    fn persistent_from_sorted_vec() {
+        let sorted_vec = crate::sorted_vec::SortedVec::from_vec(vec![3, 1, 2], Default::default());
+        let persistent = SortedVec::from(&sorted_vec).insert(0);
+
+        assert_eq!(persistent.iter().copied().collect::<Vec<_>>(), vec![0, 1, 2, 3]);
+        assert_eq!(sorted_vec.size, 3);
+    }
+}
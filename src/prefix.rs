@@ -0,0 +1,132 @@
+use std::borrow::Borrow;
+use std::ops::Bound;
+
+use crate::iter::SortedVecIter;
+use crate::map::SortedVecMap;
+use crate::sorted_vec::{Order, SortedVec};
+use crate::storage::BucketStorage;
+
+impl<T: PartialOrd + Ord + Borrow<str>, S: BucketStorage<T>> SortedVec<T, S> {
+    /// Every element starting with `prefix`, in order. The strings extending
+    /// `prefix` are exactly those from `prefix` up to its successor, so this
+    /// is a range lookup.
+    ///
+    /// Elements are compared as the `str` they borrow, so `T`'s `Ord` must
+    /// agree with `str`'s, as `Borrow` requires.
+    pub fn range_prefix(&self, prefix: &str) -> SortedVecIter<'_, T, S> {
+        let successor = prefix_successor(prefix);
+        self.borrowed_range(&prefix_range(prefix, successor.as_deref(), self.configuration.order()))
+    }
+}
+
+impl<K: PartialOrd + Ord + Borrow<str>, V> SortedVecMap<K, V> {
+    /// The pairs whose keys start with `prefix`, in order. Found like
+    /// [`SortedVec::range_prefix`].
+    pub fn range_prefix(&self, prefix: &str) -> impl DoubleEndedIterator<Item = (&K, &V)> {
+        let successor = prefix_successor(prefix);
+        let range = prefix_range(prefix, successor.as_deref(), self.sorted_vec.configuration.order());
+        let (start, end) = self.key_range_positions::<str, _>(&range);
+        SortedVecIter::between(&self.sorted_vec, start, end.max(start)).map(|pair| (&pair.key, &pair.value))
+    }
+}
+
+/// The strings from `prefix` up to `successor`, with the bounds swapped for
+/// a descending order.
+fn prefix_range<'a>(prefix: &'a str, successor: Option<&'a str>, order: Order) -> (Bound<&'a str>, Bound<&'a str>) {
+    let past = successor.map_or(Bound::Unbounded, Bound::Excluded);
+    match order {
+        Order::Ascending => (Bound::Included(prefix), past),
+        Order::Descending => (past, Bound::Included(prefix)),
+    }
+}
+
+/// The smallest string ordered after every string starting with `prefix`,
+/// or `None` when there is none (an empty prefix, or one made of
+/// `char::MAX`).
+fn prefix_successor(prefix: &str) -> Option<String> {
+    let mut successor = prefix.to_string();
+    while let Some(last) = successor.pop() {
+        let next = match last {
+            '\u{d7ff}' => Some('\u{e000}'),
+            _ => char::from_u32(last as u32 + 1),
+        };
+        if let Some(next) = next {
+            successor.push(next);
+            return Some(successor);
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::prefix_successor;
+    use crate::map::SortedVecMap;
+    use crate::sorted_vec::{BucketConfiguration, Order, SortedVec};
+
+    fn airports(order: Order) -> SortedVec<String> {
+        let configuration = BucketConfiguration::builder().max_bucket_capacity(2).order(order).build();
+        let codes = ["JFK", "LAX", "LA", "LAXA", "LBA", "LAS", "KIX", "L"];
+        SortedVec::from_vec(codes.iter().map(|code| code.to_string()).collect(), configuration)
+    }
+
+    #[test]
+    fn range_prefix_ascending() {
+        let sorted_vec = airports(Order::Ascending);
+
+        assert_eq!(sorted_vec.range_prefix("LA").collect::<Vec<_>>(), vec!["LA", "LAS", "LAX", "LAXA"]);
+        assert_eq!(sorted_vec.range_prefix("LAX").len(), 2);
+        assert_eq!(sorted_vec.range_prefix("LAX").next_back().map(String::as_str), Some("LAXA"));
+        assert_eq!(sorted_vec.range_prefix("Z").next(), None);
+        assert_eq!(sorted_vec.range_prefix("").count(), 8);
+    }
+
+    #[test]
+    fn range_prefix_descending() {
+        let sorted_vec = airports(Order::Descending);
+
+        assert_eq!(sorted_vec.range_prefix("LA").collect::<Vec<_>>(), vec!["LAXA", "LAX", "LAS", "LA"]);
+    }
+
+    #[test]
+    fn map_range_prefix() {
+        for order in [Order::Ascending, Order::Descending] {
+            let configuration = BucketConfiguration::builder().max_bucket_capacity(2).order(order).build();
+            let mut map = SortedVecMap::with_configuration(configuration);
+            for (idx, code) in ["JFK", "LAX", "LA", "LAXA", "LBA", "LAS", "KIX", "L"].into_iter().enumerate() {
+                map.insert(code.to_string(), idx);
+            }
+
+            let mut expected = vec![("LA", 2), ("LAS", 5), ("LAX", 1), ("LAXA", 3)];
+            if order == Order::Descending {
+                expected.reverse();
+            }
+            let found: Vec<_> = map.range_prefix("LA").map(|(key, value)| (key.as_str(), *value)).collect();
+            assert_eq!(found, expected);
+            assert_eq!(map.range_prefix("Z").next(), None);
+            assert_eq!(map.range_prefix("").count(), 8);
+        }
+    }
+
+    #[test]
+    fn range_prefix_without_successor() {
+        for order in [Order::Ascending, Order::Descending] {
+            let configuration = BucketConfiguration::builder().max_bucket_capacity(2).order(order).build();
+            let words = ["\u{10ffff}", "\u{10ffff}a", "\u{10fffe}", "z"];
+            let sorted_vec = SortedVec::from_vec(words.iter().map(|word| word.to_string()).collect(), configuration);
+
+            let mut found: Vec<_> = sorted_vec.range_prefix("\u{10ffff}").collect();
+            found.sort();
+            assert_eq!(found, vec!["\u{10ffff}", "\u{10ffff}a"]);
+        }
+    }
+
+    #[test]
+    fn prefix_successor_skips_what_cannot_be_incremented() {
+        assert_eq!(prefix_successor("LA").as_deref(), Some("LB"));
+        assert_eq!(prefix_successor("a\u{d7ff}").as_deref(), Some("a\u{e000}"));
+        assert_eq!(prefix_successor("a\u{10ffff}").as_deref(), Some("b"));
+        assert_eq!(prefix_successor(""), None);
+    }
+}
@@ -0,0 +1,68 @@
+//! [`proptest`](::proptest) strategies for containers and their
+//! configurations.
+
+use ::proptest::collection::{self, SizeRange};
+use ::proptest::prelude::*;
+
+use crate::sorted_vec::{BucketConfiguration, BucketSearch, DuplicatePolicy, Order, SortedVec, TieBreak};
+
+/// Configurations with small bucket capacities, both orders, both duplicate
+/// policies and both bucket searches, so splits and merges get exercised.
+pub fn configurations() -> impl Strategy<Value = BucketConfiguration> {
+    (
+        1..=64usize,
+        0..=8usize,
+        any::<bool>(),
+        any::<bool>(),
+        any::<bool>(),
+        any::<bool>(),
+        1..=99u8,
+        0..=4usize,
+    )
+        .prop_map(
+            |(capacity, initial_buckets, allow, lifo, descending, galloping, split_ratio, split_hysteresis)| {
+                BucketConfiguration::builder()
+                    .max_bucket_capacity(capacity)
+                    .initial_buckets(initial_buckets)
+                    .duplicate_policy(if allow { DuplicatePolicy::Allow } else { DuplicatePolicy::Reject })
+                    .tie_break(if lifo { TieBreak::Lifo } else { TieBreak::Fifo })
+                    .order(if descending { Order::Descending } else { Order::Ascending })
+                    .split_ratio(split_ratio)
+                    .split_hysteresis(split_hysteresis)
+                    .bucket_search(if galloping { BucketSearch::Galloping } else { BucketSearch::Binary })
+                    .build()
+            },
+        )
+}
+
+/// Containers built by inserting `size` elements drawn from `element` one
+/// by one, under a configuration from [`configurations`].
+pub fn sorted_vecs<E>(element: E, size: impl Into<SizeRange>) -> impl Strategy<Value = SortedVec<E::Value>>
+where
+    E: Strategy,
+    E::Value: PartialOrd + Ord,
+{
+    (configurations(), collection::vec(element, size)).prop_map(|(configuration, items)| {
+        let mut result = SortedVec::new(configuration);
+        for item in items {
+            result.insert(item);
+        }
+
+        result
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    proptest! {
+        #[test]
+        fn sorted_vecs_are_sorted(sorted_vec in sorted_vecs(any::<i16>(), 0..256)) {
+            let order = sorted_vec.configuration.order();
+            let items: Vec<_> = sorted_vec.iter().collect();
+            prop_assert_eq!(items.len(), sorted_vec.size);
+            prop_assert!(items.windows(2).all(|pair| order.compare(pair[0], pair[1]).is_le()));
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use std::collections::BTreeSet;
+
+use rand::Rng;
+
+use crate::sorted_vec::SortedVec;
+use crate::storage::BucketStorage;
+
+impl<T: PartialOrd + Ord, S: BucketStorage<T>> SortedVec<T, S> {
+    /// A uniformly random element, found through the bucket directory's
+    /// element counts.
+    pub fn sample(&self, rng: &mut impl Rng) -> Option<&T> {
+        if self.size == 0 {
+            return None;
+        }
+
+        self.at(rng.random_range(0..self.size))
+    }
+
+    /// A uniformly random subset of `n` elements (or all of them if there are
+    /// fewer), in sorted order.
+    pub fn sample_n(&self, rng: &mut impl Rng, n: usize) -> Vec<&T> {
+        let n = n.min(self.size);
+
+        // Floyd's algorithm: n distinct indices with exactly n draws.
+        let mut picked = BTreeSet::new();
+        for bound in self.size - n..self.size {
+            let candidate = rng.random_range(0..=bound);
+            if !picked.insert(candidate) {
+                picked.insert(bound);
+            }
+        }
+
+        picked.into_iter().filter_map(|idx| self.at(idx)).collect()
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::sorted_vec::BucketConfiguration;
+
+    #[test]
+    fn sorted_vec_sample_hits_every_element() {
+        let configuration = BucketConfiguration::builder().max_bucket_capacity(3).build();
+        let sorted_vec = SortedVec::from_vec((0..10).collect(), configuration);
+        let mut rng = StdRng::seed_from_u64(42);
+        let mut seen = BTreeSet::new();
+        for _ in 0..1000 {
+            seen.insert(*sorted_vec.sample(&mut rng).unwrap());
+        }
+
+        assert_eq!(seen.into_iter().collect::<Vec<_>>(), (0..10).collect::<Vec<_>>());
+        assert_eq!(SortedVec::<i32>::new(Default::default()).sample(&mut rng), None);
+    }
+
+    #[test]
+    fn sorted_vec_sample_n_is_sorted_and_distinct() {
+        let configuration = BucketConfiguration::builder().max_bucket_capacity(4).build();
+        let sorted_vec = SortedVec::from_vec((0..100).collect(), configuration);
+        let sample = sorted_vec.sample_n(&mut StdRng::seed_from_u64(7), 20);
+
+        assert_eq!(sample.len(), 20);
+        assert!(sample.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn sorted_vec_sample_n_larger_than_len() {
+        let sorted_vec = SortedVec::from_vec(vec![3, 1, 2], Default::default());
+        assert_eq!(sorted_vec.sample_n(&mut StdRng::seed_from_u64(3), 10), vec![&1, &2, &3]);
+    }
+}
@@ -0,0 +1,301 @@
+use std::cmp::Ordering;
+use std::collections::BTreeSet;
+use std::ops::Deref;
+
+use crate::diff::DiffEntry;
+use crate::iter::SortedVecIter;
+use crate::sorted_vec::{BucketConfiguration, DuplicatePolicy, SortedVec};
+
+/// A set with the method names and return values of `BTreeSet`, so code can
+/// switch between the two by changing the type. Duplicates are always
+/// rejected, whatever the configuration says. The rest of the `SortedVec`
+/// reads are available through `Deref`.
+#[derive(Debug)]
+pub struct SortedVecSet<T: PartialOrd + Ord> {
+    sorted_vec: SortedVec<T>,
+}
+
+impl<T: PartialOrd + Ord> SortedVecSet<T> {
+    pub fn new() -> Self {
+        Self::with_configuration(Default::default())
+    }
+
+    pub fn with_configuration(configuration: BucketConfiguration) -> Self {
+        SortedVecSet {
+            sorted_vec: SortedVec::new(configuration.with_duplicate_policy(DuplicatePolicy::Reject)),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.sorted_vec.size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sorted_vec.size == 0
+    }
+
+    pub fn contains(&self, item: &T) -> bool {
+        self.sorted_vec.find_index(item).is_some()
+    }
+
+    /// The stored element equal to `item`.
+    pub fn get(&self, item: &T) -> Option<&T> {
+        let found = self.sorted_vec.find_index(item)?;
+        self.sorted_vec.get_position((found.bucket_idx(), found.item_idx()))
+    }
+
+    /// Adds `item` and returns whether it was not already present. An equal
+    /// element already stored is left untouched.
+    pub fn insert(&mut self, item: T) -> bool {
+        self.sorted_vec.insert_position(item).is_some()
+    }
+
+    /// Adds `item`, replacing and returning an equal element.
+    pub fn replace(&mut self, item: T) -> Option<T> {
+        match self.sorted_vec.find_index(&item) {
+            Some(found) => Some(std::mem::replace(&mut self.sorted_vec.buckets[found.bucket_idx()].data[found.item_idx()], item)),
+            None => {
+                self.sorted_vec.insert_position(item);
+                None
+            },
+        }
+    }
+
+    /// Removes `item` and returns whether it was present.
+    pub fn remove(&mut self, item: &T) -> bool {
+        self.take(item).is_some()
+    }
+
+    /// Removes and returns the element equal to `item`.
+    pub fn take(&mut self, item: &T) -> Option<T> {
+        self.sorted_vec.remove_position(item).map(|(item, _)| item)
+    }
+
+    pub fn pop_first(&mut self) -> Option<T> {
+        let bucket_idx = self.sorted_vec.buckets.iter().position(|bucket| bucket.len() > 0)?;
+        Some(self.sorted_vec.remove_at((bucket_idx, 0)))
+    }
+
+    pub fn pop_last(&mut self) -> Option<T> {
+        self.sorted_vec.pop_last()
+    }
+
+    pub fn retain(&mut self, mut f: impl FnMut(&T) -> bool) {
+        self.sorted_vec.retain_map(|item| f(&item).then_some(item));
+    }
+
+    pub fn clear(&mut self) {
+        self.sorted_vec = SortedVec::new(self.sorted_vec.configuration.clone());
+    }
+
+    /// Moves all elements of `other` into `self`, leaving `other` empty.
+    pub fn append(&mut self, other: &mut Self) {
+        let configuration = other.sorted_vec.configuration.clone();
+        let taken = std::mem::replace(&mut other.sorted_vec, SortedVec::new(configuration));
+        self.sorted_vec.insert_sorted_batch(taken.into_iter().collect());
+    }
+
+    /// Splits the set in two at `item`. Returns everything from `item` on.
+    pub fn split_off(&mut self, item: &T) -> Self {
+        let order = self.sorted_vec.configuration.order();
+        let configuration = self.sorted_vec.configuration.clone();
+        let mut items: Vec<T> = std::mem::replace(&mut self.sorted_vec, SortedVec::new(configuration.clone()))
+            .into_iter()
+            .collect();
+        let at = items.partition_point(|probe| order.compare(probe, item) == Ordering::Less);
+        let tail = items.split_off(at);
+        self.sorted_vec.insert_sorted_batch(items);
+
+        let mut result = Self::with_configuration(configuration);
+        result.sorted_vec.insert_sorted_batch(tail);
+        result
+    }
+
+    /// Elements in `self` but not in `other`, in order.
+    pub fn difference<'a>(&'a self, other: &'a Self) -> impl Iterator<Item = &'a T> {
+        self.sorted_vec.diff(&other.sorted_vec).filter_map(|entry| match entry {
+            DiffEntry::Removed(item) => Some(item),
+            _ => None,
+        })
+    }
+
+    /// Elements in exactly one of the two sets, in order.
+    pub fn symmetric_difference<'a>(&'a self, other: &'a Self) -> impl Iterator<Item = &'a T> {
+        self.sorted_vec.diff(&other.sorted_vec).filter_map(|entry| match entry {
+            DiffEntry::Added(item) | DiffEntry::Removed(item) => Some(item),
+            DiffEntry::Unchanged(_) => None,
+        })
+    }
+
+    /// Elements in both sets, in order. Yields the ones from `self`.
+    pub fn intersection<'a>(&'a self, other: &'a Self) -> impl Iterator<Item = &'a T> {
+        self.sorted_vec.diff(&other.sorted_vec).filter_map(|entry| match entry {
+            DiffEntry::Unchanged(item) => Some(item),
+            _ => None,
+        })
+    }
+
+    /// Elements in either set, in order, each once.
+    pub fn union<'a>(&'a self, other: &'a Self) -> impl Iterator<Item = &'a T> {
+        self.sorted_vec.diff(&other.sorted_vec).map(|entry| match entry {
+            DiffEntry::Added(item) | DiffEntry::Removed(item) | DiffEntry::Unchanged(item) => item,
+        })
+    }
+
+    pub fn is_disjoint(&self, other: &Self) -> bool {
+        self.intersection(other).next().is_none()
+    }
+
+    pub fn is_subset(&self, other: &Self) -> bool {
+        self.len() <= other.len() && self.difference(other).next().is_none()
+    }
+
+    pub fn is_superset(&self, other: &Self) -> bool {
+        other.is_subset(self)
+    }
+
+    pub fn into_inner(self) -> SortedVec<T> {
+        self.sorted_vec
+    }
+}
+
+impl<T: PartialOrd + Ord> Default for SortedVecSet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: PartialOrd + Ord> Deref for SortedVecSet<T> {
+    type Target = SortedVec<T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.sorted_vec
+    }
+}
+
+impl<T: PartialOrd + Ord> PartialEq for SortedVecSet<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: PartialOrd + Ord> Eq for SortedVecSet<T> {}
+
+impl<T: PartialOrd + Ord> FromIterator<T> for SortedVecSet<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut result = Self::new();
+        result.extend(iter);
+        result
+    }
+}
+
+impl<T: PartialOrd + Ord> Extend<T> for SortedVecSet<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        self.sorted_vec.insert_many(iter);
+    }
+}
+
+impl<T: PartialOrd + Ord> IntoIterator for SortedVecSet<T> {
+    type Item = T;
+    type IntoIter = std::vec::IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.sorted_vec.into_iter()
+    }
+}
+
+impl<'a, T: PartialOrd + Ord> IntoIterator for &'a SortedVecSet<T> {
+    type Item = &'a T;
+    type IntoIter = SortedVecIter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T: PartialOrd + Ord> From<BTreeSet<T>> for SortedVecSet<T> {
+    fn from(set: BTreeSet<T>) -> Self {
+        SortedVecSet { sorted_vec: set.into() }
+    }
+}
+
+impl<T: PartialOrd + Ord> From<SortedVecSet<T>> for BTreeSet<T> {
+    fn from(set: SortedVecSet<T>) -> Self {
+        set.sorted_vec.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::container::tests::Lcg;
+
+    fn small_buckets(items: impl IntoIterator<Item = i32>) -> SortedVecSet<i32> {
+        let mut set = SortedVecSet::with_configuration(BucketConfiguration::builder().max_bucket_capacity(2).build());
+        set.extend(items);
+        set
+    }
+
+    #[test]
+    fn sorted_vec_set_matches_btree_set() {
+        let mut set = small_buckets([]);
+        let mut model = BTreeSet::new();
+        let mut rng = Lcg::new(7);
+        for step in 0..400 {
+            let item = rng.below(50) as i32;
+            match step % 4 {
+                0 | 1 => assert_eq!(set.insert(item), model.insert(item)),
+                2 => assert_eq!(set.remove(&item), model.remove(&item)),
+                _ => assert_eq!(set.pop_first(), model.pop_first()),
+            }
+            assert_eq!(set.len(), model.len());
+        }
+
+        assert!(set.iter().eq(model.iter()));
+        assert_eq!(set.pop_last(), model.pop_last());
+        assert_eq!(set.get(&model.first().copied().unwrap()), model.first());
+    }
+
+    #[test]
+    fn sorted_vec_set_set_operations() {
+        let a = small_buckets([1, 2, 3, 5, 8]);
+        let b = small_buckets([2, 3, 4, 8, 9]);
+
+        assert_eq!(a.difference(&b).copied().collect::<Vec<_>>(), vec![1, 5]);
+        assert_eq!(a.symmetric_difference(&b).copied().collect::<Vec<_>>(), vec![1, 4, 5, 9]);
+        assert_eq!(a.intersection(&b).copied().collect::<Vec<_>>(), vec![2, 3, 8]);
+        assert_eq!(a.union(&b).copied().collect::<Vec<_>>(), vec![1, 2, 3, 4, 5, 8, 9]);
+        assert!(small_buckets([2, 8]).is_subset(&a));
+        assert!(a.is_superset(&small_buckets([1, 5])));
+        assert!(a.is_disjoint(&small_buckets([4, 6])));
+        assert!(!a.is_disjoint(&b));
+    }
+
+    #[test]
+    fn sorted_vec_set_split_off_and_append() {
+        let mut set = small_buckets(0..10);
+        let mut tail = set.split_off(&6);
+
+        assert!(set.iter().copied().eq(0..6));
+        assert!(tail.iter().copied().eq(6..10));
+
+        set.append(&mut tail);
+        assert!(tail.is_empty());
+        assert_eq!(set, small_buckets(0..10));
+
+        set.retain(|item| item % 2 == 0);
+        assert_eq!(set.into_iter().collect::<Vec<_>>(), vec![0, 2, 4, 6, 8]);
+    }
+
+    #[test]
+    fn sorted_vec_set_forces_unique_elements() {
+        let configuration = BucketConfiguration::builder().duplicate_policy(DuplicatePolicy::Allow).build();
+        let mut set = SortedVecSet::with_configuration(configuration);
+
+        assert!(set.insert(1));
+        assert!(!set.insert(1));
+        assert_eq!(set.replace(1), Some(1));
+        assert_eq!(set.replace(2), None);
+        assert_eq!(set.len(), 2);
+    }
+}
@@ -0,0 +1,127 @@
+use std::cmp::Ordering;
+
+use crate::sorted_vec::{FindResult, SortedVec};
+use crate::storage::BucketStorage;
+use crate::total_order::OrderedF64;
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// Primitive keys the branchless search is used for. Equal keys of these
+/// types can't be told apart, so returning the first equal element instead
+/// of an arbitrary one makes no difference. Other types keep the binary
+/// search of [`SortedVec::find_index`].
+pub trait SimdKey: sealed::Sealed + Copy + Ord {}
+
+macro_rules! simd_keys {
+    ($($key:ty),*) => {
+        $(
+            impl sealed::Sealed for $key {}
+            impl SimdKey for $key {}
+        )*
+    };
+}
+
+simd_keys!(u32, u64, i32, i64, OrderedF64);
+
+impl<T: SimdKey, S: BucketStorage<T>> SortedVec<T, S> {
+    /// Same as [`find_index`](Self::find_index), searching the routed bucket
+    /// without branching on the comparisons. Returns the first equal element.
+    pub fn find_index_branchless(&self, item: &T) -> Option<FindResult> {
+        let order = self.configuration.order();
+        self.find_index_with(item, |bucket| {
+            let (front, back) = bucket.data.as_slices();
+            let compare = |probe: &T| order.compare(probe, item);
+            if front.last().is_none_or(|last| compare(last) == Ordering::Less) {
+                match branchless_search(back, compare) {
+                    Ok(idx) => Ok(front.len() + idx),
+                    Err(idx) => Err(front.len() + idx),
+                }
+            } else {
+                branchless_search(front, compare)
+            }
+        })
+    }
+
+    pub fn contains_branchless(&self, item: &T) -> bool {
+        self.find_index_branchless(item).is_some()
+    }
+}
+
+/// Windows at or below this size are finished with a linear scan.
+const LINEAR_SEARCH_LEN: usize = 16;
+
+/// Lower bound search whose loop doesn't branch on the comparison, so it
+/// compiles to conditional moves for primitive elements, finished by a linear
+/// count that vectorizes. Much faster than a branchy binary search on the
+/// small slices buckets hold.
+fn branchless_search<T>(slice: &[T], compare: impl Fn(&T) -> Ordering) -> Result<usize, usize> {
+    let mut base = 0;
+    let mut size = slice.len();
+    while size > LINEAR_SEARCH_LEN {
+        let half = size / 2;
+        let mid = base + half;
+        base = if compare(&slice[mid]) == Ordering::Less { mid } else { base };
+        size -= half;
+    }
+
+    let window = &slice[base..(base + size).min(slice.len())];
+    let idx = base + window.iter().filter(|probe| compare(probe) == Ordering::Less).count();
+    match slice.get(idx) {
+        Some(probe) if compare(probe) == Ordering::Equal => Ok(idx),
+        _ => Err(idx),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use super::*;
+    use crate::sorted_vec::{BucketConfiguration, DuplicatePolicy};
+    use crate::total_order::TotalOrd;
+
+    #[test]
+    fn branchless_search_matches_binary_search() {
+        for len in [0, 1, 2, 15, 16, 17, 100, 256] {
+            let items: Vec<u32> = (0..len).map(|i| i * 2 + 1).collect();
+            for probe in 0..len * 2 + 3 {
+                let expected = items.binary_search(&probe);
+                assert_eq!(branchless_search(&items, |item| item.cmp(&probe)), expected, "len {len}, probe {probe}");
+            }
+        }
+    }
+
+    #[test]
+    fn branchless_search_finds_first_duplicate() {
+        let items = [1, 3, 3, 3, 5];
+        assert_eq!(branchless_search(&items, |item| item.cmp(&3)), Ok(1));
+        assert_eq!(branchless_search(&items, |item| item.cmp(&4)), Err(4));
+    }
+
+    #[test]
+    fn sorted_vec_find_index_branchless_matches_find_index() {
+        let configuration = BucketConfiguration::builder()
+            .max_bucket_capacity(40)
+            .duplicate_policy(DuplicatePolicy::Allow)
+            .build();
+        let sorted_vec: SortedVec<i64> = SortedVec::from_vec((0..500).map(|item| item / 3 * 2).collect(), configuration);
+        for probe in -2..340 {
+            let found = sorted_vec.find_index_branchless(&probe).and_then(|found| sorted_vec.get_found(&found).copied());
+            assert_eq!(found, sorted_vec.find_index(&probe).and_then(|found| sorted_vec.get_found(&found).copied()));
+            assert_eq!(sorted_vec.contains_branchless(&probe), sorted_vec.contains(&probe));
+        }
+    }
+
+    #[test]
+    fn sorted_vec_find_index_branchless_on_wrapped_storage() {
+        let mut sorted_vec: SortedVec<OrderedF64, VecDeque<OrderedF64>> = SortedVec::default();
+        for item in [3.0, 1.0, 2.0, -0.0, f64::NAN] {
+            sorted_vec.insert(TotalOrd(item));
+        }
+        assert!(sorted_vec.contains_branchless(&TotalOrd(2.0)));
+        assert!(sorted_vec.contains_branchless(&TotalOrd(f64::NAN)));
+        assert!(!sorted_vec.contains_branchless(&TotalOrd(0.0)));
+    }
+}
@@ -0,0 +1,254 @@
+use std::fmt::{self, Debug, Formatter};
+use std::ops::{Bound, RangeBounds};
+
+use crate::container::SortedContainer;
+
+const MAX_LEVEL: usize = 16;
+
+struct Node<T> {
+    item: T,
+    /// The following node on each level this node takes part in.
+    next: Vec<Option<usize>>,
+}
+
+/// A skip list of unique elements, offered as an alternative
+/// [`SortedContainer`] backend to compare against `SortedVec`. Nodes live in
+/// one `Vec` and link to each other by index; removed slots are reused.
+pub struct SkipList<T> {
+    nodes: Vec<Option<Node<T>>>,
+    head: [Option<usize>; MAX_LEVEL],
+    free: Vec<usize>,
+    len: usize,
+    /// Xorshift state used to pick node heights.
+    state: u64,
+}
+
+impl<T: Ord> SkipList<T> {
+    pub fn new() -> Self {
+        SkipList {
+            nodes: Vec::new(),
+            head: [None; MAX_LEVEL],
+            free: Vec::new(),
+            len: 0,
+            state: 0x9E37_79B9_7F4A_7C15,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn contains(&self, item: &T) -> bool {
+        self.lower_bound(|probe| probe < item).is_some_and(|idx| self.item(idx) == item)
+    }
+
+    /// Adds `item` and returns whether it was not already present.
+    pub fn insert(&mut self, item: T) -> bool {
+        let update = self.predecessors(&item);
+        if self.next(update[0], 0).is_some_and(|idx| *self.item(idx) == item) {
+            return false;
+        }
+
+        let height = self.random_height();
+        let next = (0..height).map(|level| self.next(update[level], level)).collect();
+        let node = Some(Node { item, next });
+        let idx = match self.free.pop() {
+            Some(idx) => {
+                self.nodes[idx] = node;
+                idx
+            },
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            },
+        };
+        for (level, before) in update.into_iter().enumerate().take(height) {
+            self.set_next(before, level, Some(idx));
+        }
+        self.len += 1;
+
+        true
+    }
+
+    /// Removes and returns the element equal to `item`.
+    pub fn remove(&mut self, item: &T) -> Option<T> {
+        let update = self.predecessors(item);
+        let idx = self.next(update[0], 0).filter(|&idx| self.item(idx) == item)?;
+        let node = self.nodes[idx].take().expect("linked nodes are occupied");
+        for (level, next) in node.next.into_iter().enumerate() {
+            self.set_next(update[level], level, next);
+        }
+        self.free.push(idx);
+        self.len -= 1;
+
+        Some(node.item)
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        self.range(..)
+    }
+
+    /// The elements inside `range`, in order.
+    pub fn range<R: RangeBounds<T>>(&self, range: R) -> Iter<'_, T> {
+        let start = match range.start_bound() {
+            Bound::Included(start) => self.lower_bound(|probe| probe < start),
+            Bound::Excluded(start) => self.lower_bound(|probe| probe <= start),
+            Bound::Unbounded => self.head[0],
+        };
+        let end = match range.end_bound() {
+            Bound::Included(end) => self.lower_bound(|probe| probe <= end),
+            Bound::Excluded(end) => self.lower_bound(|probe| probe < end),
+            Bound::Unbounded => None,
+        };
+        // An inverted range puts `end` before `start`; the walk would never meet it.
+        let start = start.filter(|&start| end.is_none_or(|end| self.item(start) <= self.item(end)));
+
+        Iter { nodes: &self.nodes, next: start, end }
+    }
+
+    fn item(&self, idx: usize) -> &T {
+        &self.nodes[idx].as_ref().expect("linked nodes are occupied").item
+    }
+
+    fn next(&self, node: Option<usize>, level: usize) -> Option<usize> {
+        match node {
+            None => self.head[level],
+            Some(idx) => self.nodes[idx].as_ref().expect("linked nodes are occupied").next[level],
+        }
+    }
+
+    fn set_next(&mut self, node: Option<usize>, level: usize, next: Option<usize>) {
+        match node {
+            None => self.head[level] = next,
+            Some(idx) => self.nodes[idx].as_mut().expect("linked nodes are occupied").next[level] = next,
+        }
+    }
+
+    /// The last node on each level whose element is ordered before `item`,
+    /// `None` standing for the head.
+    fn predecessors(&self, item: &T) -> [Option<usize>; MAX_LEVEL] {
+        let mut update = [None; MAX_LEVEL];
+        let mut current = None;
+        for level in (0..MAX_LEVEL).rev() {
+            while let Some(next) = self.next(current, level).filter(|&next| self.item(next) < item) {
+                current = Some(next);
+            }
+            update[level] = current;
+        }
+
+        update
+    }
+
+    /// The first node whose element does not satisfy `before`, which must be
+    /// true for a prefix of the list.
+    fn lower_bound(&self, before: impl Fn(&T) -> bool) -> Option<usize> {
+        let mut current = None;
+        for level in (0..MAX_LEVEL).rev() {
+            while let Some(next) = self.next(current, level).filter(|&next| before(self.item(next))) {
+                current = Some(next);
+            }
+        }
+
+        self.next(current, 0)
+    }
+
+    /// A height where each extra level has half the chance of the one below.
+    fn random_height(&mut self) -> usize {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        (self.state.trailing_ones() as usize + 1).min(MAX_LEVEL)
+    }
+}
+
+impl<T: Ord> Default for SkipList<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Ord + Debug> Debug for SkipList<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<T: Ord> SortedContainer<T> for SkipList<T> {
+    type Range<'a>
+        = Iter<'a, T>
+    where
+        Self: 'a,
+        T: 'a;
+
+    fn insert(&mut self, item: T) -> bool {
+        SkipList::insert(self, item)
+    }
+
+    fn remove(&mut self, item: &T) -> bool {
+        SkipList::remove(self, item).is_some()
+    }
+
+    fn contains(&self, item: &T) -> bool {
+        SkipList::contains(self, item)
+    }
+
+    fn range<R: RangeBounds<T>>(&self, range: R) -> Self::Range<'_> {
+        SkipList::range(self, range)
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+}
+
+/// Walks the bottom level of a [`SkipList`] up to, not including, `end`.
+pub struct Iter<'a, T> {
+    nodes: &'a [Option<Node<T>>],
+    next: Option<usize>,
+    end: Option<usize>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let idx = self.next.filter(|&idx| Some(idx) != self.end)?;
+        let node = self.nodes[idx].as_ref().expect("linked nodes are occupied");
+        self.next = node.next[0];
+        Some(&node.item)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::container::tests::behaves_like_a_set;
+
+    #[test]
+    fn skip_list_is_a_sorted_container() {
+        behaves_like_a_set(SkipList::new());
+    }
+
+    #[test]
+    fn skip_list_reuses_removed_slots() {
+        let mut skip_list = SkipList::new();
+        for item in (0..100).rev() {
+            assert!(skip_list.insert(item));
+        }
+        for item in (0..100).step_by(2) {
+            assert_eq!(skip_list.remove(&item), Some(item));
+        }
+        for item in 100..150 {
+            skip_list.insert(item);
+        }
+
+        assert_eq!(skip_list.nodes.len(), 100);
+        assert!(skip_list.iter().copied().eq((1..100).step_by(2).chain(100..150)));
+        assert!(skip_list.range(10..=15).copied().eq([11, 13, 15]));
+        assert_eq!(skip_list.range((Bound::Included(50), Bound::Excluded(40))).count(), 0);
+    }
+}
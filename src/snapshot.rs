@@ -0,0 +1,143 @@
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use crate::bucket::SharedItems;
+use crate::storage::BucketStorage;
+
+/// An immutable view of a [`SortedVec`](crate::sorted_vec::SortedVec) taken
+/// with [`snapshot`](crate::sorted_vec::SortedVec::snapshot). It stays valid
+/// and unchanged while the container keeps being mutated, and can be sent to
+/// reader threads.
+#[derive(Debug)]
+pub struct Snapshot<T, S = Vec<T>> {
+    buckets: Vec<Arc<SharedItems<S>>>,
+    size: usize,
+    _elements: PhantomData<T>,
+}
+
+impl<T, S> Clone for Snapshot<T, S> {
+    fn clone(&self) -> Self {
+        Snapshot {
+            buckets: self.buckets.clone(),
+            size: self.size,
+            _elements: PhantomData,
+        }
+    }
+}
+
+impl<T, S: BucketStorage<T>> Snapshot<T, S> {
+    pub(crate) fn new(buckets: Vec<Arc<SharedItems<S>>>, size: usize) -> Self {
+        Snapshot {
+            buckets,
+            size,
+            _elements: PhantomData,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    pub fn at(&self, mut idx: usize) -> Option<&T> {
+        for bucket in &self.buckets {
+            if idx < bucket.len() {
+                return bucket.get(idx);
+            }
+
+            idx -= bucket.len();
+        }
+
+        None
+    }
+
+    pub fn first(&self) -> Option<&T> {
+        self.buckets.iter().find_map(|bucket| bucket.first())
+    }
+
+    pub fn last(&self) -> Option<&T> {
+        self.buckets.iter().rev().find_map(|bucket| bucket.last())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.buckets.iter().flat_map(|bucket| bucket.iter())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::sync::Arc;
+
+    use crate::sorted_vec::{BucketConfiguration, SortedVec};
+
+    #[test]
+    fn snapshot_is_unaffected_by_later_mutation() {
+        let mut sorted_vec = SortedVec::from_vec(vec![3, 1, 2], Default::default());
+        let snapshot = sorted_vec.snapshot();
+        sorted_vec.insert(0);
+        sorted_vec.remove(&3);
+
+        assert_eq!(snapshot.len(), 3);
+        assert_eq!(snapshot.iter().copied().collect::<Vec<_>>(), vec![1, 2, 3]);
+        assert_eq!(snapshot.first(), Some(&1));
+        assert_eq!(snapshot.last(), Some(&3));
+        assert_eq!(snapshot.at(1), Some(&2));
+        assert_eq!(sorted_vec.snapshot().iter().copied().collect::<Vec<_>>(), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn snapshot_shares_unmodified_buckets() {
+        let config = BucketConfiguration::builder().max_bucket_capacity(4).build();
+        let mut sorted_vec = SortedVec::from_vec((0..16).collect(), config);
+        let before = sorted_vec.snapshot();
+        sorted_vec.insert(100);
+        let after = sorted_vec.snapshot();
+
+        let shared = before
+            .buckets
+            .iter()
+            .zip(&after.buckets)
+            .filter(|(a, b)| Arc::ptr_eq(a, b))
+            .count();
+        assert_eq!(shared, before.buckets.len() - 1);
+    }
+
+    #[test]
+    fn snapshot_does_not_copy_buckets() {
+        let mut sorted_vec = SortedVec::from_vec(vec![3, 1, 2], Default::default());
+        let snapshot = sorted_vec.snapshot();
+        let bucket = sorted_vec.buckets.iter().next().unwrap();
+        assert!(Arc::ptr_eq(&snapshot.buckets[0], &bucket.data.share()));
+
+        sorted_vec.insert(4);
+        let bucket = sorted_vec.buckets.iter().next().unwrap();
+        assert!(!Arc::ptr_eq(&snapshot.buckets[0], &bucket.data.share()));
+        assert_eq!(snapshot.iter().copied().collect::<Vec<_>>(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn snapshot_of_vec_deque_storage() {
+        let mut sorted_vec: SortedVec<i32, VecDeque<i32>> = SortedVec::default();
+        sorted_vec.insert(2);
+        sorted_vec.insert(1);
+        let snapshot = sorted_vec.snapshot();
+        sorted_vec.insert(0);
+
+        assert_eq!(snapshot.iter().copied().collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(snapshot.at(1), Some(&2));
+    }
+
+    #[test]
+    fn snapshot_of_empty() {
+        let sorted_vec: SortedVec<i32> = SortedVec::new(Default::default());
+        let snapshot = sorted_vec.snapshot();
+
+        assert!(snapshot.is_empty());
+        assert_eq!(snapshot.first(), None);
+        assert_eq!(snapshot.iter().next(), None);
+    }
+}
@@ -1,13 +1,38 @@
-use std::cmp::min;
-use std::ops::Deref;
+use std::borrow::Borrow;
+use std::cmp::{min, Ordering};
+use std::collections::TryReserveError;
+use std::fmt::{self, Debug, Formatter};
+use std::iter::{Enumerate, Rev};
+use std::ops::{Bound, Deref, RangeBounds};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
 
 use crate::AddResult;
+use crate::batch::InsertSummary;
 use crate::bucket::Bucket;
-use crate::iter::SortedVecIter;
+#[cfg(feature = "bloom-filter")]
+use crate::bloom::BloomFilter;
+#[cfg(feature = "content-hash")]
+use crate::content_hash::ContentHash;
+use crate::directory::BucketDirectory;
+use crate::builder::SortedVecBuilder;
+use crate::error::SortedVecError;
+use crate::fence::{BoundaryKeys, FenceIndex};
+use crate::iter::{Buckets, GroupByEqual, SortedVecIter};
+pub use crate::merge::{merge_iter, MergeIter};
+#[cfg(feature = "metrics")]
+use crate::metrics::{Metrics, Stats};
+use crate::snapshot::Snapshot;
+use crate::storage::BucketStorage;
 
+/// Where [`SortedVec::find_index`] found an element. Only valid until the
+/// container is next modified; debug builds panic when a stale result is
+/// handed back to [`SortedVec::get_found`].
 pub struct FindResult {
     bucket_idx: usize,
     item_idx: usize,
+    /// The container generation it was found in, `None` when built by hand.
+    generation: Option<usize>,
 }
 
 impl FindResult {
@@ -15,6 +40,7 @@ impl FindResult {
         FindResult {
             bucket_idx,
             item_idx,
+            generation: None,
         }
     }
 
@@ -54,6 +80,13 @@ impl MaxBucketCapacity {
 
         MaxBucketCapacity(size)
     }
+
+    pub fn try_new(size: usize) -> Result<Self, SortedVecError> {
+        match size {
+            0 => Err(SortedVecError::InvalidCapacity(size)),
+            _ => Ok(MaxBucketCapacity(size)),
+        }
+    }
 }
 
 impl Default for MaxBucketCapacity {
@@ -62,10 +95,162 @@ impl Default for MaxBucketCapacity {
     }
 }
 
-#[derive(Default, Debug, Copy, Clone)]
+/// Decides how large a bucket may grow before it is split, given the number of
+/// elements currently stored in the whole container.
+pub trait CapacityPolicy: Debug + Send + Sync {
+    fn max_bucket_capacity(&self, total_len: usize) -> usize;
+}
+
+impl CapacityPolicy for MaxBucketCapacity {
+    fn max_bucket_capacity(&self, _total_len: usize) -> usize {
+        self.0
+    }
+}
+
+/// Grows the bucket capacity with the square root of the element count, which
+/// keeps roughly `sqrt(n)` buckets. Never goes below `min_capacity`.
+#[derive(Debug, Copy, Clone)]
+pub struct SqrtCapacity {
+    min_capacity: MaxBucketCapacity,
+}
+
+impl SqrtCapacity {
+    pub fn new(min_capacity: MaxBucketCapacity) -> Self {
+        SqrtCapacity { min_capacity }
+    }
+}
+
+impl Default for SqrtCapacity {
+    fn default() -> Self {
+        SqrtCapacity::new(MaxBucketCapacity::default())
+    }
+}
+
+impl CapacityPolicy for SqrtCapacity {
+    fn max_bucket_capacity(&self, total_len: usize) -> usize {
+        total_len.isqrt().max(*self.min_capacity)
+    }
+}
+
+/// Percentage of elements kept in the left bucket when an insert at a bucket's
+/// tail overflows it. Inserts at the head use the mirrored ratio and inserts
+/// anywhere else always split at the midpoint.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SplitRatio(u8);
+
+impl Deref for SplitRatio {
+    type Target = u8;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl SplitRatio {
+    pub fn new(percent: u8) -> Self {
+        if !(1..=99).contains(&percent) {
+            panic!("SplitRatio must be between 1 and 99");
+        }
+
+        SplitRatio(percent)
+    }
+
+    pub fn try_new(percent: u8) -> Result<Self, SortedVecError> {
+        match percent {
+            1..=99 => Ok(SplitRatio(percent)),
+            _ => Err(SortedVecError::InvalidSplitRatio(percent)),
+        }
+    }
+}
+
+impl Default for SplitRatio {
+    fn default() -> Self {
+        SplitRatio(50)
+    }
+}
+
+/// Which half of a split bucket is moved to a new allocation. The other half
+/// stays in the allocation of the bucket that overflowed.
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SplitSide {
+    Left,
+    #[default]
+    Right,
+}
+
+/// Decides how a bucket that overflowed on insert is split, replacing
+/// [`SplitRatio`] and the split hysteresis.
+pub trait SplitStrategy: Debug + Send + Sync {
+    /// Where to split a bucket of `len` elements whose last insert landed at
+    /// `inserted_at`. Clamped to `1..len`.
+    fn split_point(&self, len: usize, inserted_at: usize, capacity: usize) -> usize;
+
+    /// Which half, once split at `at`, gets the new allocation.
+    fn new_allocation(&self, _len: usize, _at: usize) -> SplitSide {
+        SplitSide::Right
+    }
+}
+
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DuplicatePolicy {
+    #[default]
+    Reject,
+    Allow,
+}
+
+/// Where an element goes among the equal elements already stored, when
+/// duplicates are allowed.
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TieBreak {
+    /// After its equals, so equal elements keep insertion order.
+    #[default]
+    Fifo,
+    /// Before its equals, so the newest equal element comes first.
+    Lifo,
+}
+
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Order {
+    #[default]
+    Ascending,
+    Descending,
+}
+
+impl Order {
+    #[inline]
+    pub fn compare<T: PartialOrd + Ord + ?Sized>(self, a: &T, b: &T) -> Ordering {
+        #[cfg(feature = "metrics")]
+        crate::metrics::count(|stats| stats.comparisons += 1);
+        match self {
+            Order::Ascending => a.cmp(b),
+            Order::Descending => b.cmp(a),
+        }
+    }
+}
+
+/// How `find_bucket_index` locates the bucket for an item.
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BucketSearch {
+    /// Plain binary search over all buckets.
+    #[default]
+    Binary,
+    /// Exponential search outward from the bucket hit by the previous lookup,
+    /// which needs far fewer comparisons when accesses are clustered.
+    Galloping,
+}
+
+#[derive(Default, Debug, Clone)]
 pub struct BucketConfiguration {
     max_bucket_capacity: MaxBucketCapacity,
+    capacity_policy: Option<Arc<dyn CapacityPolicy>>,
     initial_set_capacity: usize,
+    duplicate_policy: DuplicatePolicy,
+    tie_break: TieBreak,
+    order: Order,
+    split_ratio: SplitRatio,
+    split_hysteresis: usize,
+    split_strategy: Option<Arc<dyn SplitStrategy>>,
+    bucket_search: BucketSearch,
 }
 
 impl BucketConfiguration {
@@ -73,9 +258,14 @@ impl BucketConfiguration {
         Self {
             max_bucket_capacity,
             initial_set_capacity,
+            ..Default::default()
         }
     }
 
+    pub fn builder() -> BucketConfigurationBuilder {
+        BucketConfigurationBuilder::default()
+    }
+
     pub fn with_max_bucket_capacity(max_bucket_capacity: MaxBucketCapacity) -> Self {
         Self {
             max_bucket_capacity,
@@ -89,252 +279,1583 @@ impl BucketConfiguration {
             ..Default::default()
         }
     }
-}
 
-#[derive(Default, Debug)]
-pub struct SortedVec<T: PartialOrd + Ord> {
-    pub(crate) buckets: Vec<Bucket<T>>,
-    configuration: BucketConfiguration,
-    pub(crate) size: usize
-}
+    pub(crate) fn with_duplicate_policy(mut self, duplicate_policy: DuplicatePolicy) -> Self {
+        self.duplicate_policy = duplicate_policy;
+        self
+    }
 
-impl<T: PartialOrd + Ord> SortedVec<T> {
-    pub fn new(configuration: BucketConfiguration) -> Self {
-        let mut result = Self::empty(configuration);
-        result.buckets.push(Bucket::empty());
-        result
+    pub fn max_bucket_capacity(&self) -> MaxBucketCapacity {
+        self.max_bucket_capacity
     }
 
-    fn empty(configuration: BucketConfiguration) -> Self {
-        let buckets = Vec::with_capacity(configuration.initial_set_capacity);
+    /// The custom policy, if any. Without one, every bucket is limited by
+    /// [`max_bucket_capacity`](Self::max_bucket_capacity).
+    pub fn capacity_policy(&self) -> Option<&dyn CapacityPolicy> {
+        self.capacity_policy.as_deref()
+    }
 
-        SortedVec {
-            buckets,
-            configuration,
-            size: 0,
-        }
+    pub fn initial_set_capacity(&self) -> usize {
+        self.initial_set_capacity
     }
 
-    pub fn from_vec(data: Vec<T>, configuration: BucketConfiguration) -> Self {
-        let mut result = Self::new(configuration);
-        for item in data {
-            result.insert(item);
-        }
+    pub fn duplicate_policy(&self) -> DuplicatePolicy {
+        self.duplicate_policy
+    }
 
-        result
+    pub fn tie_break(&self) -> TieBreak {
+        self.tie_break
     }
 
-    pub fn insert(&mut self, item: T) {
-        let idx = self.find_bucket_index(&item);
-        let bucket = &mut self.buckets[idx];
+    pub fn order(&self) -> Order {
+        self.order
+    }
 
-        match bucket.add(item) {
-            AddResult::Added(_) => {
-                let bucket_len = bucket.len();
-                if bucket_len > *self.configuration.max_bucket_capacity {
-                    let new_bucket = bucket.split();
-                    self.buckets.insert(idx + 1, new_bucket);
-                }
+    pub fn split_ratio(&self) -> SplitRatio {
+        self.split_ratio
+    }
 
-                self.size += 1;
-            },
-            AddResult::Duplicated(_) => {}
+    pub fn split_hysteresis(&self) -> usize {
+        self.split_hysteresis
+    }
+
+    /// The custom split strategy, if any. Without one, buckets are split by
+    /// [`split_ratio`](Self::split_ratio) and
+    /// [`split_hysteresis`](Self::split_hysteresis).
+    pub fn split_strategy(&self) -> Option<&dyn SplitStrategy> {
+        self.split_strategy.as_deref()
+    }
+
+    pub fn bucket_search(&self) -> BucketSearch {
+        self.bucket_search
+    }
+
+    pub(crate) fn bucket_capacity(&self, total_len: usize) -> usize {
+        match &self.capacity_policy {
+            Some(policy) => policy.max_bucket_capacity(total_len).max(1),
+            None => *self.max_bucket_capacity,
         }
     }
 
-    #[inline]
-    fn find_bucket_index(&self, item: &T) -> usize {
-        match self
-            .buckets
-            .binary_search_by(|bucket| bucket.item_compare(item))
-        {
-            Ok(idx) => idx,
-            Err(idx) => {
-                min(idx, self.buckets.len() - 1)
-            },
+    /// Where to split an overflowing bucket of `len` elements whose last insert
+    /// landed at `inserted_at`. Both halves are kept at least `split_hysteresis`
+    /// slots below `capacity` whenever that is possible.
+    pub(crate) fn split_point(&self, len: usize, inserted_at: usize, capacity: usize) -> usize {
+        if let Some(strategy) = &self.split_strategy {
+            return strategy.split_point(len, inserted_at, capacity).clamp(1, len.saturating_sub(1).max(1));
         }
+
+        let percent = if inserted_at + 1 == len {
+            *self.split_ratio as usize
+        } else if inserted_at == 0 {
+            100 - *self.split_ratio as usize
+        } else {
+            50
+        };
+
+        let at = len * percent / 100;
+        let limit = capacity.saturating_sub(self.split_hysteresis);
+        let at = if limit > 0 && len <= limit * 2 {
+            at.clamp(len - limit, limit)
+        } else {
+            len / 2
+        };
+
+        at.clamp(1, len.saturating_sub(1).max(1))
     }
 
-    pub fn iter(&self) -> SortedVecIter<T> {
-        SortedVecIter::new(self)
+    pub(crate) fn split_side(&self, len: usize, at: usize) -> SplitSide {
+        self.split_strategy.as_ref().map_or(SplitSide::Right, |strategy| strategy.new_allocation(len, at))
     }
+}
 
-    pub fn at(&self, mut idx: usize) -> Option<&T> {
-        for bucket in &self.buckets {
-            if idx < bucket.len() {
-                return Some(&bucket.data[idx]);
-            }
+#[derive(Default, Debug, Clone)]
+pub struct BucketConfigurationBuilder {
+    configuration: BucketConfiguration,
+}
 
-            idx -= bucket.len();
-        }
+impl BucketConfigurationBuilder {
+    /// Panics if `max_bucket_capacity` is 0, like [`MaxBucketCapacity::new`].
+    pub fn max_bucket_capacity(mut self, max_bucket_capacity: usize) -> Self {
+        self.configuration.max_bucket_capacity = MaxBucketCapacity::new(max_bucket_capacity);
+        self
+    }
 
-        None
+    pub fn try_max_bucket_capacity(mut self, max_bucket_capacity: usize) -> Result<Self, SortedVecError> {
+        self.configuration.max_bucket_capacity = MaxBucketCapacity::try_new(max_bucket_capacity)?;
+        Ok(self)
     }
 
-    pub fn last(&self) -> Option<&T> {
-        let last_bucket = self.buckets.last()?;
-        last_bucket.data.last()
+    pub fn initial_buckets(mut self, initial_buckets: usize) -> Self {
+        self.configuration.initial_set_capacity = initial_buckets;
+        self
     }
 
-    pub fn first(&self) -> Option<&T> {
-        let first_bucket = self.buckets.first()?;
-        first_bucket.data.first()
+    pub fn duplicate_policy(mut self, duplicate_policy: DuplicatePolicy) -> Self {
+        self.configuration.duplicate_policy = duplicate_policy;
+        self
     }
 
-    pub fn remove(&mut self, item: &T) {
-        if let Some(FindResult { bucket_idx, item_idx }) = self.find_index(item) {
-            let bucket = &mut self.buckets[bucket_idx];
-            bucket.data.remove(item_idx);
-            if bucket.data.is_empty() {
-                self.buckets.remove(bucket_idx);
-            }
+    pub fn tie_break(mut self, tie_break: TieBreak) -> Self {
+        self.configuration.tie_break = tie_break;
+        self
+    }
 
-            self.size -= 1;
-        };
+    pub fn order(mut self, order: Order) -> Self {
+        self.configuration.order = order;
+        self
     }
 
-    pub fn slice(&self, start: usize, end: usize) -> Vec<&T> {
-        let mut result = Vec::new();
-        for i in start..end {
-            if let Some(item) = self.at(i) {
-                result.push(item);
-            }
-        }
+    pub fn capacity_policy(mut self, capacity_policy: impl CapacityPolicy + 'static) -> Self {
+        self.configuration.capacity_policy = Some(Arc::new(capacity_policy));
+        self
+    }
 
-        result
+    /// Panics if `percent` is not between 1 and 99, like [`SplitRatio::new`].
+    pub fn split_ratio(mut self, percent: u8) -> Self {
+        self.configuration.split_ratio = SplitRatio::new(percent);
+        self
     }
 
-    pub fn find_index(&self, item: &T) -> Option<FindResult> {
-        let bucket_idx = self.find_bucket_index(item);
-        let bucket = &self.buckets[bucket_idx];
+    pub fn try_split_ratio(mut self, percent: u8) -> Result<Self, SortedVecError> {
+        self.configuration.split_ratio = SplitRatio::try_new(percent)?;
+        Ok(self)
+    }
 
-        match bucket.data.binary_search(item) {
-            Ok(i) => Some(FindResult::new(bucket_idx, i)),
-            Err(_) => None,
-        }
+    pub fn split_hysteresis(mut self, split_hysteresis: usize) -> Self {
+        self.configuration.split_hysteresis = split_hysteresis;
+        self
     }
-}
 
-impl<T: PartialOrd + Ord> FromIterator<T> for SortedVec<T> {
-    fn from_iter<Iter: IntoIterator<Item = T>>(iter: Iter) -> Self {
-        let mut result = SortedVec::new(Default::default());
-        for item in iter {
-            result.insert(item);
-        }
+    pub fn split_strategy(mut self, split_strategy: impl SplitStrategy + 'static) -> Self {
+        self.configuration.split_strategy = Some(Arc::new(split_strategy));
+        self
+    }
 
-        result
+    pub fn bucket_search(mut self, bucket_search: BucketSearch) -> Self {
+        self.configuration.bucket_search = bucket_search;
+        self
     }
-}
 
-impl<T: PartialOrd + Ord> Extend<T> for SortedVec<T> {
-    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
-        for item in iter {
-            self.insert(item);
-        }
+    pub fn build(self) -> BucketConfiguration {
+        self.configuration
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use crate::sorted_vec::{BucketConfiguration, MaxBucketCapacity, SortedVec};
+pub struct SortedVec<T: PartialOrd + Ord, S = Vec<T>> {
+    pub(crate) buckets: BucketDirectory<Bucket<T, S>>,
+    pub(crate) configuration: BucketConfiguration,
+    pub(crate) size: usize,
+    last_hit: AtomicUsize,
+    pub(crate) fences: Option<FenceIndex<T>>,
+    pub(crate) boundaries: Option<BoundaryKeys<T>>,
+    /// Bumped whenever elements may have moved, to catch stale positions.
+    generation: usize,
+    #[cfg(feature = "content-hash")]
+    pub(crate) content_hash: Option<ContentHash<T>>,
+    #[cfg(feature = "bloom-filter")]
+    pub(crate) bloom: Option<BloomFilter<T>>,
+    #[cfg(feature = "metrics")]
+    pub(crate) metrics: Arc<Metrics>,
+}
 
-    #[test]
-    fn max_bucket_capacity_new_with_valid_size() {
-        let capacity = MaxBucketCapacity::new(10);
-        assert_eq!(*capacity, 10);
+impl<T: PartialOrd + Ord> SortedVec<T> {
+    pub fn new(configuration: BucketConfiguration) -> Self {
+        Self::with_storage(configuration)
     }
 
-    #[test]
-    #[should_panic(expected = "MaxBucketCapacity must be greater than 0")]
-    fn max_bucket_capacity_new_with_zero_size() {
-        MaxBucketCapacity::new(0);
+    /// An empty container sized for about `expected_elements`: the first
+    /// bucket is reserved up to the bucket capacity and the bucket table gets
+    /// slots for the buckets the rest will need, so bulk loading does not
+    /// keep reallocating.
+    ///
+    /// Panics if the room cannot be allocated, e.g. because
+    /// `expected_elements` overflows the bucket table. Use
+    /// [`try_with_capacity`](Self::try_with_capacity) to handle that.
+    pub fn with_capacity(expected_elements: usize, configuration: BucketConfiguration) -> Self {
+        match Self::try_with_capacity(expected_elements, configuration) {
+            Ok(result) => result,
+            Err(error) => panic!("cannot reserve room for {expected_elements} elements: {error}"),
+        }
     }
 
-    #[test]
-    fn max_bucket_capacity_default() {
-        let capacity = MaxBucketCapacity::default();
-        assert_eq!(*capacity, 200);
+    /// Like [`with_capacity`](Self::with_capacity), but returns an error
+    /// instead of panicking when the room cannot be allocated.
+    pub fn try_with_capacity(expected_elements: usize, configuration: BucketConfiguration) -> Result<Self, SortedVecError> {
+        let mut result = Self::new(configuration);
+        result.try_reserve(expected_elements).map_err(SortedVecError::AllocationFailed)?;
+
+        Ok(result)
     }
 
-    #[test]
-    fn bucket_configuration_new_with_valid_values() {
-        let config = BucketConfiguration::new(MaxBucketCapacity::new(10), 5);
-        assert_eq!(*config.max_bucket_capacity, 10);
-        assert_eq!(config.initial_set_capacity, 5);
+    pub fn from_vec(data: Vec<T>, configuration: BucketConfiguration) -> Self {
+        let mut result = Self::new(configuration);
+        for item in data {
+            result.insert(item);
+        }
+
+        result
     }
 
-    #[test]
-    fn sorted_vec_new_with_configuration() {
-        let config = BucketConfiguration::new(MaxBucketCapacity::new(10), 5);
-        let sorted_vec: SortedVec<i32> = SortedVec::new(config);
-        assert_eq!(sorted_vec.buckets.len(), 1);
-        assert_eq!(sorted_vec.size, 0);
+    /// Like [`from_vec`](Self::from_vec), but bulk loads the data with
+    /// [`insert_many`](Self::insert_many) and reports how many inputs were
+    /// dropped as duplicates.
+    pub fn from_vec_with_summary(data: Vec<T>, configuration: BucketConfiguration) -> (Self, InsertSummary) {
+        let mut result = Self::new(configuration);
+        let summary = result.insert_many(data);
+
+        (result, summary)
     }
 
-    #[test]
-    fn sorted_vec_insert_single_element() {
-        let config = BucketConfiguration::new(MaxBucketCapacity::new(10), 5);
-        let mut sorted_vec: SortedVec<i32> = SortedVec::new(config);
-        sorted_vec.insert(5);
-        assert_eq!(sorted_vec.size, 1);
-        assert_eq!(sorted_vec.at(0), Some(&5));
+    /// Like [`from_vec_with_summary`](Self::from_vec_with_summary), but hands
+    /// back the inputs rejected as duplicates, in order, instead of dropping
+    /// them. The first of each run of equal inputs is the one kept.
+    pub fn from_vec_with_rejects(data: Vec<T>, configuration: BucketConfiguration) -> (Self, Vec<T>) {
+        let mut result = Self::new(configuration);
+        let mut rejected = Vec::new();
+        result.insert_many_into(data, &mut rejected);
+
+        (result, rejected)
     }
 
-    #[test]
-    fn sorted_vec_insert_multiple_elements() {
-        let config = BucketConfiguration::new(MaxBucketCapacity::new(10), 5);
-        let mut sorted_vec: SortedVec<i32> = SortedVec::new(config);
-        sorted_vec.insert(5);
-        sorted_vec.insert(3);
-        sorted_vec.insert(8);
-        assert_eq!(sorted_vec.size, 3);
-        assert_eq!(sorted_vec.at(0), Some(&3));
-        assert_eq!(sorted_vec.at(1), Some(&5));
-        assert_eq!(sorted_vec.at(2), Some(&8));
+    /// Like [`from_vec`](Self::from_vec), but returns an error instead of
+    /// aborting when a bucket cannot be allocated. The input is sorted in
+    /// place, which may still need the sort's scratch space, and then cut
+    /// into full buckets, the first of them reusing the input's allocation.
+    pub fn try_from_vec(mut data: Vec<T>, configuration: BucketConfiguration) -> Result<Self, TryReserveError> {
+        let mut result = Self::new(configuration);
+        let order = result.configuration.order;
+        data.sort_by(|a, b| order.compare(a, b));
+        match (result.configuration.duplicate_policy, result.configuration.tie_break) {
+            (DuplicatePolicy::Reject, _) => data.dedup_by(|a, b| order.compare(a, b) == Ordering::Equal),
+            (DuplicatePolicy::Allow, TieBreak::Lifo) => {
+                data.chunk_by_mut(|a, b| order.compare(a, b) == Ordering::Equal).for_each(<[T]>::reverse);
+            },
+            (DuplicatePolicy::Allow, TieBreak::Fifo) => {},
+        }
+
+        let size = data.len();
+        let capacity = result.configuration.bucket_capacity(size);
+        let mut buckets = Vec::new();
+        buckets.try_reserve_exact(size.div_ceil(capacity).max(1))?;
+        while data.len() > capacity {
+            let at = (data.len() - 1) / capacity * capacity;
+            let mut bucket = Vec::new();
+            bucket.try_reserve_exact(data.len() - at)?;
+            bucket.extend(data.drain(at..));
+            buckets.push(Bucket::from_vec(bucket));
+        }
+        buckets.push(Bucket::from_vec(data));
+        buckets.reverse();
+
+        result.buckets = buckets.into();
+        result.size = size;
+        result.rebuild_indexes();
+        Ok(result)
     }
 
-    #[test]
-    fn sorted_vec_insert_duplicate_element() {
-        let config = BucketConfiguration::new(MaxBucketCapacity::new(10), 5);
-        let mut sorted_vec: SortedVec<i32> = SortedVec::new(config);
-        sorted_vec.insert(5);
-        sorted_vec.insert(5);
-        assert_eq!(sorted_vec.size, 1);
-        assert_eq!(sorted_vec.at(0), Some(&5));
+    /// Builds a container from elements already in the configured order,
+    /// without searching. Fails with the index of the first element that
+    /// sorts before its predecessor.
+    pub fn try_from_sorted(data: Vec<T>, configuration: BucketConfiguration) -> Result<Self, SortedVecError> {
+        let mut builder = SortedVecBuilder::new(configuration);
+        for (index, item) in data.into_iter().enumerate() {
+            builder.push(item).map_err(|_| SortedVecError::UnsortedInput { index })?;
+        }
+
+        Ok(builder.finish())
     }
 
-    #[test]
-    fn sorted_vec_insert_triggers_split() {
-        let config = BucketConfiguration::new(MaxBucketCapacity::new(1), 5);
-        let mut sorted_vec: SortedVec<i32> = SortedVec::new(config);
-        sorted_vec.insert(5);
-        sorted_vec.insert(3);
+    /// Makes room for `additional` more elements appended at the end: the
+    /// last bucket is topped up to the bucket capacity and the bucket table
+    /// gets slots for the buckets the rest will need.
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        let capacity = self.configuration.bucket_capacity(self.size.saturating_add(additional));
+        let mut rest = additional;
+        if let Some(last) = self.buckets.last_mut() {
+            let room = capacity.saturating_sub(last.len()).min(additional);
+            last.data.try_reserve(room)?;
+            rest -= room;
+        }
 
-        assert_eq!(sorted_vec.buckets.len(), 2);
-        assert_eq!(sorted_vec.size, 2);
-        assert_eq!(sorted_vec.at(0), Some(&3));
-        assert_eq!(sorted_vec.at(1), Some(&5));
+        self.buckets.try_reserve(rest.div_ceil(capacity))
     }
 
-    #[test]
-    fn sorted_vec_at_out_of_bounds() {
-        let config = BucketConfiguration::new(MaxBucketCapacity::new(10), 5);
-        let sorted_vec: SortedVec<i32> = SortedVec::new(config);
-        assert_eq!(sorted_vec.at(0), None);
+    /// A [`BucketView`](crate::iter::BucketView) of every non-empty bucket in
+    /// order, for scans that work bucket by bucket, e.g. skipping buckets by
+    /// their first and last element or handing whole slices to workers.
+    pub fn iter_buckets(&self) -> Buckets<'_, T> {
+        Buckets::new(self)
     }
+}
 
-    #[test]
-    fn sorted_vec_remove_single_element() {
-        let config = BucketConfiguration::new(MaxBucketCapacity::new(10), 5);
-        let mut sorted_vec: SortedVec<i32> = SortedVec::new(config);
-        sorted_vec.insert(5);
-        sorted_vec.remove(&5);
-        assert_eq!(sorted_vec.size, 0);
-        assert_eq!(sorted_vec.at(0), None);
+impl<T: PartialOrd + Ord, S: BucketStorage<T>> SortedVec<T, S> {
+    /// Like [`new`](SortedVec::new), but keeps the bucket elements in a
+    /// custom [`BucketStorage`].
+    pub fn with_storage(configuration: BucketConfiguration) -> Self {
+        let mut result = Self::empty(configuration);
+        result.buckets.push(Bucket::default());
+        result
     }
 
-    #[test]
-    fn sorted_vec_remove_multiple_elements() {
+    fn empty(configuration: BucketConfiguration) -> Self {
+        let buckets = BucketDirectory::with_capacity(configuration.initial_set_capacity);
+
+        SortedVec {
+            buckets,
+            configuration,
+            size: 0,
+            last_hit: AtomicUsize::new(0),
+            fences: None,
+            boundaries: None,
+            generation: 0,
+            #[cfg(feature = "content-hash")]
+            content_hash: None,
+            #[cfg(feature = "bloom-filter")]
+            bloom: None,
+            #[cfg(feature = "metrics")]
+            metrics: Arc::default(),
+        }
+    }
+
+    pub fn insert(&mut self, item: T) {
+        self.insert_position(item);
+    }
+
+    /// Like [`insert`](Self::insert), but checks the element against itself
+    /// and its new neighbours. If its `Ord` is inconsistent the element is
+    /// taken out again, leaving the container as it was, and an error is
+    /// returned. A rejected duplicate is not an error.
+    pub fn try_insert(&mut self, item: T) -> Result<(), SortedVecError> {
+        let order = self.configuration.order;
+        if order.compare(&item, &item) != Ordering::Equal {
+            return Err(SortedVecError::InconsistentOrder { index: self.insertion_index(&item) });
+        }
+        let Some(position) = self.insert_position(item) else {
+            return Ok(());
+        };
+
+        let strict = self.configuration.duplicate_policy == DuplicatePolicy::Reject;
+        let in_order = |a: &T, b: &T| {
+            let forward = order.compare(a, b);
+            forward == order.compare(b, a).reverse() && (forward == Ordering::Less || (!strict && forward == Ordering::Equal))
+        };
+        let item = self.get_position(position).expect("the inserted element is stored at its position");
+        let before = self.step_back(position).and_then(|before| self.get_position(before));
+        let after = self.step_forward(position).and_then(|after| self.get_position(after));
+        if before.is_none_or(|before| in_order(before, item)) && after.is_none_or(|after| in_order(item, after)) {
+            return Ok(());
+        }
+
+        let index = self.global_index(position);
+        self.remove_at(position);
+        Err(SortedVecError::InconsistentOrder { index })
+    }
+
+    /// Inserts `item` and returns where it ended up as `(bucket_idx, item_idx)`,
+    /// or `None` if it was rejected as a duplicate.
+    pub(crate) fn insert_position(&mut self, item: T) -> Option<(usize, usize)> {
+        self.insert_tracked(item).map(|(position, _)| position)
+    }
+
+    /// Like [`insert_position`](Self::insert_position), but also returns the
+    /// index of the bucket that split, if any. Its upper part became the
+    /// bucket right after it.
+    pub(crate) fn insert_tracked(&mut self, item: T) -> Option<((usize, usize), Option<usize>)> {
+        #[cfg(feature = "metrics")]
+        let _scope = self.metrics.scope();
+        let configuration = &self.configuration;
+        let idx = match (configuration.duplicate_policy, configuration.tie_break) {
+            (DuplicatePolicy::Allow, TieBreak::Lifo) => self.lower_bound_bucket_index(&item),
+            _ => self.find_bucket_index(&item),
+        };
+        let bucket = &mut self.buckets[idx];
+
+        match bucket.add(item, configuration.order, configuration.duplicate_policy, configuration.tie_break) {
+            AddResult::Added(inserted_at) => Some(self.finish_insert((idx, inserted_at))),
+            AddResult::Duplicated(_) => None,
+        }
+    }
+
+    /// Inserts `item` at `(bucket_idx, item_idx)` without searching. The
+    /// caller makes sure the position keeps the order and respects the
+    /// duplicate policy. Returns the same as
+    /// [`insert_tracked`](Self::insert_tracked).
+    pub(crate) fn insert_at(&mut self, (bucket_idx, item_idx): (usize, usize), item: T) -> ((usize, usize), Option<usize>) {
+        self.buckets[bucket_idx].data.insert(item_idx, item);
+        self.finish_insert((bucket_idx, item_idx))
+    }
+
+    /// Updates fences and size after an element landed at `(idx, inserted_at)`
+    /// and splits the bucket if it overflowed.
+    fn finish_insert(&mut self, (idx, inserted_at): (usize, usize)) -> ((usize, usize), Option<usize>) {
+        let bucket_len = self.buckets[idx].len();
+        let capacity = self.configuration.bucket_capacity(self.size + 1);
+        let mut position = (idx, inserted_at);
+        let mut split = None;
+        #[cfg(feature = "content-hash")]
+        self.hash_added(position);
+        #[cfg(feature = "bloom-filter")]
+        self.bloom_added(position);
+        if inserted_at == 0 {
+            self.refresh_fence(idx);
+        } else {
+            self.refresh_boundary(idx);
+        }
+        self.size += 1;
+        self.bump_generation();
+        if bucket_len > capacity {
+            // A user split strategy may panic, which leaves the overflowing
+            // bucket whole but already counted.
+            let at = self.configuration.split_point(bucket_len, inserted_at, capacity);
+            let side = self.configuration.split_side(bucket_len, at);
+            let bucket = &mut self.buckets[idx];
+            let new_bucket = match side {
+                SplitSide::Left => bucket.split_at_moving_lower(at),
+                SplitSide::Right => bucket.split_at(at),
+            };
+            self.buckets.insert(idx + 1, new_bucket);
+            self.insert_fence(idx + 1);
+            #[cfg(feature = "bloom-filter")]
+            self.rebuild_bucket_bloom(idx + 1);
+            self.refresh_boundary(idx);
+            #[cfg(feature = "metrics")]
+            crate::metrics::count(|stats| stats.splits += 1);
+            if inserted_at >= at {
+                position = (idx + 1, inserted_at - at);
+            }
+            split = Some(idx);
+            self.buckets.refresh_counts();
+        }
+
+        #[cfg(feature = "paranoid-checks")]
+        self.check_neighbourhood(position.0);
+        (position, split)
+    }
+
+    /// The bucket `item` belongs to: the last one whose first element is not
+    /// ordered after it. Items falling in the gap between two buckets go to
+    /// the left one, and equal runs spanning buckets grow at their end.
+    #[inline]
+    pub(crate) fn find_bucket_index(&self, item: &T) -> usize {
+        if let Some(idx) = self.find_edge_bucket_index(item) {
+            return idx;
+        }
+
+        if self.configuration.bucket_search == BucketSearch::Galloping {
+            let idx = self.gallop_bucket_index(item);
+            self.last_hit.store(idx, AtomicOrdering::Relaxed);
+            return idx;
+        }
+
+        if let Some(idx) = self.fence_bucket_index(item, false) {
+            return idx;
+        }
+
+        if let Some(idx) = self.boundary_bucket_index(item, false) {
+            return idx;
+        }
+
+        let order = self.configuration.order;
+        let idx = self.buckets.partition_point(|bucket| {
+            bucket.data.first().is_some_and(|first| order.compare(first, item) != Ordering::Greater)
+        });
+        idx.saturating_sub(1)
+    }
+
+    /// The bucket holding the first element not ordered before `item`, or the
+    /// one before the gap it falls in. Used to insert ahead of equal runs.
+    fn lower_bound_bucket_index(&self, item: &T) -> usize {
+        if let Some(idx) = self.fence_bucket_index(item, true) {
+            return idx;
+        }
+
+        if let Some(idx) = self.boundary_bucket_index(item, true) {
+            return idx;
+        }
+
+        let order = self.configuration.order;
+        let idx = self.buckets.partition_point(|bucket| {
+            bucket.data.first().is_some_and(|first| order.compare(first, item) == Ordering::Less)
+        });
+        idx.saturating_sub(1)
+    }
+
+    /// Doubles the probe distance from the last hit bucket until the item is
+    /// bracketed, then binary searches inside the bracket.
+    fn gallop_bucket_index(&self, item: &T) -> usize {
+        let len = self.buckets.len();
+        let start = min(self.last_hit.load(AtomicOrdering::Relaxed), len - 1);
+        let compare = |idx: usize| self.bucket_compare(idx, item);
+
+        let (lo, hi) = match compare(start) {
+            // Later buckets may still start with an element equal to the
+            // item, and equal runs grow at their end, so keep going right.
+            Ordering::Less | Ordering::Equal => {
+                let mut lo = start + 1;
+                let mut step = 1;
+                loop {
+                    let probe = start + step;
+                    if probe >= len {
+                        break (lo, len);
+                    }
+                    if compare(probe) == Ordering::Greater {
+                        break (lo, probe);
+                    }
+                    lo = probe + 1;
+                    step *= 2;
+                }
+            },
+            Ordering::Greater => {
+                let mut hi = start;
+                let mut step = 1;
+                loop {
+                    if step > start {
+                        break (0, hi);
+                    }
+                    let probe = start - step;
+                    if compare(probe) != Ordering::Greater {
+                        break (probe, hi);
+                    }
+                    hi = probe;
+                    step *= 2;
+                }
+            },
+        };
+
+        // The last bucket not after the item. A miss lands between two
+        // buckets and goes to the left one.
+        let (mut lo, mut hi) = (lo, hi);
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            if compare(mid) == Ordering::Greater {
+                hi = mid;
+            } else {
+                lo = mid + 1;
+            }
+        }
+        min(lo.saturating_sub(1), len - 1)
+    }
+
+    /// Like [`Bucket::item_compare`], going through the boundary keys when
+    /// they are enabled.
+    fn bucket_compare(&self, bucket_idx: usize, item: &T) -> Ordering {
+        if self.boundaries.is_none() {
+            return self.buckets[bucket_idx].item_compare(item, self.configuration.order);
+        }
+
+        match self.compare_bucket_end(bucket_idx, false, item) {
+            None => Ordering::Equal,
+            Some(Ordering::Greater) => Ordering::Greater,
+            Some(_) if self.compare_bucket_end(bucket_idx, true, item) == Some(Ordering::Less) => Ordering::Less,
+            Some(_) => Ordering::Equal,
+        }
+    }
+
+    /// O(1) shortcut for items that go after the last element or before the
+    /// first one, which is the common case when ingesting (nearly) sorted data.
+    #[inline]
+    fn find_edge_bucket_index(&self, item: &T) -> Option<usize> {
+        let last_idx = self.buckets.len() - 1;
+        if self.compare_bucket_end(last_idx, true, item) == Some(Ordering::Less) {
+            return Some(last_idx);
+        }
+
+        if self.compare_bucket_end(0, false, item) == Some(Ordering::Greater) {
+            return Some(0);
+        }
+
+        None
+    }
+
+    pub fn iter(&self) -> SortedVecIter<'_, T, S> {
+        SortedVecIter::new(self)
+    }
+
+    /// Iterates from the first element not ordered before `start`, e.g. to
+    /// resume a paginated scan from the last key seen. Seeking costs a
+    /// binary search, and the remaining length comes from the bucket
+    /// directory's element counts, so nothing is skipped element by element
+    /// or bucket by bucket.
+    pub fn iter_from(&self, start: &T) -> SortedVecIter<'_, T, S> {
+        let front = self.lower_bound_position(start);
+        let remaining = self.size - self.global_index(front);
+        SortedVecIter::between_counted(self, front, (self.buckets.len(), 0), remaining)
+    }
+
+    /// The elements inside `range`, in order. The iterator keeps a cursor at
+    /// each end, so it can also be walked backwards. Both ends are found by
+    /// binary search and ranked through the bucket directory, so seeking is
+    /// O(log n).
+    pub fn range<R: RangeBounds<T>>(&self, range: R) -> SortedVecIter<'_, T, S> {
+        self.borrowed_range::<T, R>(&range)
+    }
+
+    /// [`range`](Self::range) over the values the elements borrow as, like
+    /// `BTreeSet::range`.
+    pub(crate) fn borrowed_range<Q, R>(&self, range: &R) -> SortedVecIter<'_, T, S>
+    where
+        T: Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q> + ?Sized,
+    {
+        let (start, end) = self.range_positions(range);
+        if end <= start {
+            return SortedVecIter::between_counted(self, start, start, 0);
+        }
+
+        SortedVecIter::between(self, start, end)
+    }
+
+    /// The elements inside `range`, last first. Seeks like
+    /// [`range`](Self::range).
+    pub fn range_rev<R: RangeBounds<T>>(&self, range: R) -> Rev<SortedVecIter<'_, T, S>> {
+        self.range(range).rev()
+    }
+
+    /// Yields every element with its global index. The index is counted
+    /// along the bucket cursor, so there is no per-element lookup.
+    pub fn enumerate_global(&self) -> Enumerate<SortedVecIter<'_, T, S>> {
+        self.iter().enumerate()
+    }
+
+    /// Yields each distinct element with how many equal elements are stored,
+    /// in order. Runs spanning several buckets are reported once.
+    pub fn group_by_equal(&self) -> GroupByEqual<'_, T, S> {
+        GroupByEqual::new(self)
+    }
+
+    /// Global index of the first element matching `pred`. Scans the buckets
+    /// directly rather than looking up every index.
+    pub fn position(&self, mut pred: impl FnMut(&T) -> bool) -> Option<usize> {
+        let mut offset = 0;
+        for bucket in &self.buckets {
+            if let Some(idx) = bucket.data.iter().position(&mut pred) {
+                return Some(offset + idx);
+            }
+            offset += bucket.len();
+        }
+
+        None
+    }
+
+    /// First element matching `pred`.
+    pub fn find(&self, mut pred: impl FnMut(&T) -> bool) -> Option<&T> {
+        self.buckets.iter().find_map(|bucket| bucket.data.iter().find(|item| pred(item)))
+    }
+
+    pub fn any(&self, pred: impl FnMut(&T) -> bool) -> bool {
+        self.find(pred).is_some()
+    }
+
+    pub fn all(&self, mut pred: impl FnMut(&T) -> bool) -> bool {
+        self.find(|item| !pred(item)).is_none()
+    }
+
+    /// Like [`any`](Self::any) for a predicate that is monotone in the
+    /// container's order, i.e. flips at most once from one end to the other.
+    /// Such a predicate holds somewhere iff it holds at an end, so only the
+    /// first and last elements are checked.
+    pub fn any_monotone(&self, mut pred: impl FnMut(&T) -> bool) -> bool {
+        self.first().is_some_and(&mut pred) || self.last().is_some_and(pred)
+    }
+
+    /// Like [`all`](Self::all) for a monotone predicate: it holds everywhere
+    /// iff it holds at both ends.
+    pub fn all_monotone(&self, mut pred: impl FnMut(&T) -> bool) -> bool {
+        self.first().is_none_or(&mut pred) && self.last().is_none_or(pred)
+    }
+
+    pub fn try_at(&self, index: usize) -> Result<&T, SortedVecError> {
+        self.at(index).ok_or(SortedVecError::IndexOutOfBounds { index, len: self.size })
+    }
+
+    pub fn at(&self, idx: usize) -> Option<&T> {
+        let (bucket_idx, item_idx) = self.buckets.select(idx)?;
+        self.buckets[bucket_idx].data.get(item_idx)
+    }
+
+    pub fn last(&self) -> Option<&T> {
+        let last_bucket = self.buckets.last()?;
+        last_bucket.data.last()
+    }
+
+    pub fn first(&self) -> Option<&T> {
+        let first_bucket = self.buckets.first()?;
+        first_bucket.data.first()
+    }
+
+    /// The first `n` elements in order, found by walking only the buckets
+    /// that hold them.
+    pub fn peek_n(&self, n: usize) -> SortedVecIter<'_, T, S> {
+        let n = n.min(self.size);
+        let mut left = n;
+        let mut back = (self.buckets.len(), 0);
+        for (bucket_idx, bucket) in self.buckets.iter().enumerate() {
+            if left < bucket.len() {
+                back = (bucket_idx, left);
+                break;
+            }
+            left -= bucket.len();
+        }
+
+        SortedVecIter::between_counted(self, (0, 0), back, n)
+    }
+
+    /// The last `n` elements in order, found by walking back only the
+    /// buckets that hold them.
+    pub fn last_n(&self, n: usize) -> SortedVecIter<'_, T, S> {
+        let n = n.min(self.size);
+        let mut left = n;
+        let mut front = (self.buckets.len(), 0);
+        for (bucket_idx, bucket) in self.buckets.iter().enumerate().rev() {
+            if left == 0 {
+                break;
+            }
+            if left <= bucket.len() {
+                front = (bucket_idx, bucket.len() - left);
+                break;
+            }
+            left -= bucket.len();
+        }
+
+        SortedVecIter::between_counted(self, front, (self.buckets.len(), 0), n)
+    }
+
+    /// The smallest and largest element by `T`'s own `Ord`, whatever the
+    /// configured order, or `None` when empty.
+    pub fn min_max(&self) -> Option<(&T, &T)> {
+        let first = self.buckets.first()?.data.first()?;
+        let last = self.buckets.last()?.data.last()?;
+        match self.configuration.order {
+            Order::Ascending => Some((first, last)),
+            Order::Descending => Some((last, first)),
+        }
+    }
+
+    /// Folds the elements in order, walking each bucket's elements directly.
+    /// An empty container returns `init`.
+    pub fn fold_sorted<B>(&self, init: B, mut f: impl FnMut(B, &T) -> B) -> B {
+        self.buckets
+            .iter()
+            .fold(init, |acc, bucket| bucket.data.iter().fold(acc, &mut f))
+    }
+
+    pub fn remove(&mut self, item: &T) {
+        self.remove_position(item);
+    }
+
+    /// Removes and returns the last element.
+    pub(crate) fn pop_last(&mut self) -> Option<T> {
+        let bucket_idx = self.buckets.len() - 1;
+        let item_idx = self.buckets[bucket_idx].len().checked_sub(1)?;
+
+        Some(self.remove_at((bucket_idx, item_idx)))
+    }
+
+    /// Removes `item` and returns it together with the `(bucket_idx, item_idx)`
+    /// it was taken from.
+    pub(crate) fn remove_position(&mut self, item: &T) -> Option<(T, (usize, usize))> {
+        #[cfg(feature = "metrics")]
+        let _scope = self.metrics.scope();
+        let FindResult { bucket_idx, item_idx, .. } = self.find_index(item)?;
+
+        Some((self.remove_at((bucket_idx, item_idx)), (bucket_idx, item_idx)))
+    }
+
+    /// Removes the element at `(bucket_idx, item_idx)`, which must exist.
+    pub(crate) fn remove_at(&mut self, (bucket_idx, item_idx): (usize, usize)) -> T {
+        #[cfg(feature = "metrics")]
+        let _scope = self.metrics.scope();
+        #[cfg(feature = "metrics")]
+        crate::metrics::count(|stats| stats.shifts += (self.buckets[bucket_idx].len() - item_idx - 1) as u64);
+        let removed = self.buckets[bucket_idx].data.remove(item_idx);
+        #[cfg(feature = "content-hash")]
+        self.hash_removed(&removed);
+        #[cfg(feature = "bloom-filter")]
+        self.bloom_removed(bucket_idx);
+        if item_idx == 0 && !self.remove_bucket_if_empty(bucket_idx) {
+            self.refresh_fence(bucket_idx);
+        } else if item_idx > 0 {
+            self.refresh_boundary(bucket_idx);
+        }
+        self.size -= 1;
+        self.bump_generation();
+        #[cfg(feature = "paranoid-checks")]
+        self.check_neighbourhood(min(bucket_idx, self.buckets.len() - 1));
+
+        removed
+    }
+
+    /// Global index of a `(bucket_idx, item_idx)` position.
+    pub(crate) fn global_index(&self, (bucket_idx, item_idx): (usize, usize)) -> usize {
+        self.buckets.rank(bucket_idx) + item_idx
+    }
+
+    /// The position of the element at global index `idx`, or the end
+    /// position `(buckets.len(), 0)` past the last one.
+    pub(crate) fn position_of_rank(&self, idx: usize) -> (usize, usize) {
+        self.buckets.select(idx).unwrap_or((self.buckets.len(), 0))
+    }
+
+    /// Finds the element equal to `probe` and lets `f` modify it. If the
+    /// element still sorts between its neighbours it stays where it is,
+    /// otherwise it is moved to its new place (and dropped if that makes it
+    /// a rejected duplicate). Returns `false` if nothing matched `probe`.
+    pub fn update(&mut self, probe: &T, f: impl FnOnce(&mut T)) -> bool {
+        let Some(found) = self.find_index(probe) else {
+            return false;
+        };
+
+        self.repair_on_unwind(|this| this.update_at(found, f));
+        true
+    }
+
+    /// Inserts `item`, or if an equal element is already stored, hands both
+    /// to `merge` instead, e.g. to accumulate quantities. The merged element
+    /// is moved if `merge` changes where it sorts, like with
+    /// [`update`](Self::update). This applies to both duplicate policies.
+    pub fn insert_with_merge(&mut self, item: T, merge: impl FnOnce(&mut T, T)) {
+        match self.find_index(&item) {
+            Some(found) => self.repair_on_unwind(|this| this.update_at(found, |existing| merge(existing, item))),
+            None => self.insert(item),
+        }
+    }
+
+    fn update_at(&mut self, FindResult { bucket_idx, item_idx, .. }: FindResult, f: impl FnOnce(&mut T)) {
+        let order = self.configuration.order;
+        let strict = self.configuration.duplicate_policy == DuplicatePolicy::Reject;
+        let mut item = self.buckets[bucket_idx].data.remove(item_idx);
+        #[cfg(feature = "content-hash")]
+        self.hash_removed(&item);
+        #[cfg(feature = "bloom-filter")]
+        self.bloom_removed(bucket_idx);
+        f(&mut item);
+
+        let bucket = &self.buckets[bucket_idx];
+        let prev = match item_idx {
+            0 => bucket_idx.checked_sub(1).and_then(|prev| self.buckets[prev].data.last()),
+            _ => bucket.data.get(item_idx - 1),
+        };
+        let next = bucket
+            .data
+            .get(item_idx)
+            .or_else(|| self.buckets.get(bucket_idx + 1).and_then(|next| next.data.first()));
+        let fits = |neighbour: Option<&T>, expected: Ordering| {
+            neighbour.is_none_or(|neighbour| {
+                let ordering = order.compare(neighbour, &item);
+                ordering == expected || (!strict && ordering == Ordering::Equal)
+            })
+        };
+
+        if fits(prev, Ordering::Less) && fits(next, Ordering::Greater) {
+            self.buckets[bucket_idx].data.insert(item_idx, item);
+            #[cfg(feature = "content-hash")]
+            self.hash_added((bucket_idx, item_idx));
+            #[cfg(feature = "bloom-filter")]
+            self.bloom_added((bucket_idx, item_idx));
+            if item_idx == 0 {
+                self.refresh_fence(bucket_idx);
+            } else {
+                self.refresh_boundary(bucket_idx);
+            }
+        } else {
+            if !self.remove_bucket_if_empty(bucket_idx) {
+                self.refresh_fence(bucket_idx);
+            }
+            self.size -= 1;
+            self.insert(item);
+        }
+    }
+
+    /// Removes and returns some element inside `range`, without promising
+    /// which. Where the range covers the end of a bucket that last element is
+    /// taken, so nothing has to shift.
+    pub fn remove_any_in_range<R: RangeBounds<T>>(&mut self, range: R) -> Option<T> {
+        let (start, end) = self.range_positions(&range);
+        if start >= end {
+            return None;
+        }
+
+        let position = if end.0 > start.0 {
+            (start.0, self.buckets[start.0].len() - 1)
+        } else {
+            (end.0, end.1 - 1)
+        };
+        Some(self.remove_at(position))
+    }
+
+    /// Removes every element inside `range` and returns how many were removed.
+    /// Whole buckets inside the range are dropped without being visited and
+    /// only the two edge buckets are trimmed.
+    pub fn remove_range<R: RangeBounds<T>>(&mut self, range: R) -> usize {
+        #[cfg(feature = "metrics")]
+        let _scope = self.metrics.scope();
+        let (start, end) = self.range_positions(&range);
+        if start >= end {
+            return 0;
+        }
+
+        let (start_bucket, start_item) = start;
+        let (end_bucket, end_item) = end;
+        #[cfg(feature = "content-hash")]
+        self.hash_range_removed(start, end);
+        let removed = if start_bucket == end_bucket {
+            let removed = self.buckets[start_bucket].data.remove_range(start_item..end_item);
+            self.trimmed_edge(start_bucket);
+            removed
+        } else {
+            let interior = self.buckets.rank(end_bucket) - self.buckets.rank(start_bucket + 1);
+            let start_len = self.buckets[start_bucket].len();
+            let mut removed = interior + self.buckets[start_bucket].data.remove_range(start_item..start_len);
+            self.buckets.remove_range(start_bucket + 1..end_bucket);
+            self.remove_fences(start_bucket + 1..end_bucket);
+            if start_bucket + 1 < self.buckets.len() {
+                removed += self.buckets[start_bucket + 1].data.remove_range(0..end_item);
+                self.trimmed_edge(start_bucket + 1);
+            }
+            self.trimmed_edge(start_bucket);
+            removed
+        };
+        self.size -= removed;
+        self.bump_generation();
+
+        removed
+    }
+
+    /// Panics if the comparator disagrees with the layout of `bucket_idx` and
+    /// its neighbours, which means the element type's `Ord` is inconsistent.
+    #[cfg(feature = "paranoid-checks")]
+    fn check_neighbourhood(&self, bucket_idx: usize) {
+        let order = self.configuration.order;
+        let strict = self.configuration.duplicate_policy == DuplicatePolicy::Reject;
+        let buckets = bucket_idx.saturating_sub(1)..min(bucket_idx + 2, self.buckets.len());
+        let items: Vec<&T> = self.buckets.iter().skip(buckets.start).take(buckets.len()).flat_map(|bucket| bucket.data.iter()).collect();
+
+        for (idx, item) in items.iter().enumerate() {
+            assert!(
+                order.compare(*item, *item) == Ordering::Equal,
+                "paranoid-checks: Ord is not reflexive for element {idx} of buckets {buckets:?}"
+            );
+        }
+        for (idx, pair) in items.windows(2).enumerate() {
+            let forward = order.compare(pair[0], pair[1]);
+            assert!(
+                forward == order.compare(pair[1], pair[0]).reverse(),
+                "paranoid-checks: Ord is not antisymmetric for elements {idx} and {} of buckets {buckets:?}",
+                idx + 1
+            );
+            assert!(
+                forward == Ordering::Less || (!strict && forward == Ordering::Equal),
+                "paranoid-checks: elements {idx} and {} of buckets {buckets:?} are out of order, Ord is inconsistent",
+                idx + 1
+            );
+        }
+    }
+
+    /// Drops `bucket_idx` if a range removal emptied it, and otherwise brings
+    /// its fence and filter up to date with the elements it kept.
+    fn trimmed_edge(&mut self, bucket_idx: usize) {
+        if self.remove_bucket_if_empty(bucket_idx) || self.buckets[bucket_idx].data.is_empty() {
+            return;
+        }
+
+        self.refresh_fence(bucket_idx);
+        #[cfg(feature = "bloom-filter")]
+        self.rebuild_bucket_bloom(bucket_idx);
+    }
+
+    /// Drops an emptied bucket, always keeping at least one so inserts have
+    /// somewhere to go. Returns whether the bucket was dropped.
+    fn remove_bucket_if_empty(&mut self, bucket_idx: usize) -> bool {
+        if !self.buckets[bucket_idx].data.is_empty() {
+            return false;
+        }
+
+        if self.buckets.len() > 1 {
+            self.buckets.remove(bucket_idx);
+            self.remove_fence(bucket_idx);
+            return true;
+        }
+        self.rebuild_fences();
+        false
+    }
+
+    /// Position of the first element not ordered before `item`, as
+    /// `(bucket_idx, item_idx)`. Past the end it is `(buckets.len(), 0)`.
+    pub(crate) fn lower_bound_position<Q: Ord + ?Sized>(&self, item: &Q) -> (usize, usize)
+    where
+        T: Borrow<Q>,
+    {
+        self.partition_position(|probe| self.configuration.order.compare(probe.borrow(), item) == Ordering::Less)
+    }
+
+    /// Position of the first element ordered after `item`.
+    pub(crate) fn upper_bound_position<Q: Ord + ?Sized>(&self, item: &Q) -> (usize, usize)
+    where
+        T: Borrow<Q>,
+    {
+        self.partition_position(|probe| self.configuration.order.compare(probe.borrow(), item) != Ordering::Greater)
+    }
+
+    /// Position of the first element for which `pred` is false, assuming the
+    /// elements are partitioned by `pred` like `slice::partition_point`.
+    pub(crate) fn partition_position(&self, mut pred: impl FnMut(&T) -> bool) -> (usize, usize) {
+        #[cfg(feature = "metrics")]
+        let _scope = self.metrics.scope();
+        let bucket_idx = self
+            .buckets
+            .partition_point(|bucket| bucket.data.last().is_some_and(&mut pred));
+        match self.buckets.get(bucket_idx) {
+            Some(bucket) => (bucket_idx, bucket.data.partition_point(pred)),
+            None => (bucket_idx, 0),
+        }
+    }
+
+    /// Smallest element not ordered before `item`.
+    pub fn ge(&self, item: &T) -> Option<&T> {
+        self.get_position(self.lower_bound_position(item))
+    }
+
+    /// Smallest element ordered after `item`.
+    pub fn gt(&self, item: &T) -> Option<&T> {
+        self.get_position(self.upper_bound_position(item))
+    }
+
+    /// Largest element not ordered after `item`.
+    pub fn le(&self, item: &T) -> Option<&T> {
+        self.get_before_position(self.upper_bound_position(item))
+    }
+
+    /// Largest element ordered before `item`.
+    pub fn lt(&self, item: &T) -> Option<&T> {
+        self.get_before_position(self.lower_bound_position(item))
+    }
+
+    pub(crate) fn get_position(&self, (bucket_idx, item_idx): (usize, usize)) -> Option<&T> {
+        self.buckets.get(bucket_idx)?.data.get(item_idx)
+    }
+
+    fn get_before_position(&self, (bucket_idx, item_idx): (usize, usize)) -> Option<&T> {
+        match item_idx {
+            0 => self.buckets.get(bucket_idx.checked_sub(1)?)?.data.last(),
+            _ => self.buckets[bucket_idx].data.get(item_idx - 1),
+        }
+    }
+
+    /// Start (inclusive) and end (exclusive) positions covered by `range`.
+    pub(crate) fn range_positions<Q, R>(&self, range: &R) -> ((usize, usize), (usize, usize))
+    where
+        T: Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q> + ?Sized,
+    {
+        let start = match range.start_bound() {
+            Bound::Included(item) => self.lower_bound_position(item),
+            Bound::Excluded(item) => self.upper_bound_position(item),
+            Bound::Unbounded => (0, 0),
+        };
+        let end = match range.end_bound() {
+            Bound::Included(item) => self.upper_bound_position(item),
+            Bound::Excluded(item) => self.lower_bound_position(item),
+            Bound::Unbounded => (self.buckets.len(), 0),
+        };
+
+        (start, end)
+    }
+
+    /// Takes a cheap immutable view of the current contents. The view shares
+    /// the bucket storage with the container without copying it; a bucket is
+    /// copied only when the container writes to it while a snapshot holds it.
+    pub fn snapshot(&self) -> Snapshot<T, S>
+    where
+        T: Clone,
+    {
+        Snapshot::new(self.buckets.iter().map(|bucket| bucket.data.share()).collect(), self.size)
+    }
+
+    /// Operation counts since creation or the last [`reset_stats`](Self::reset_stats).
+    #[cfg(feature = "metrics")]
+    pub fn stats(&self) -> Stats {
+        self.metrics.stats()
+    }
+
+    #[cfg(feature = "metrics")]
+    pub fn reset_stats(&self) {
+        self.metrics.reset();
+    }
+
+    /// Debug view that shows how the elements are split into buckets.
+    pub fn debug_buckets(&self) -> DebugBuckets<'_, T, S> {
+        DebugBuckets(self)
+    }
+
+    pub fn slice(&self, start: usize, end: usize) -> Vec<&T> {
+        let mut result = Vec::new();
+        for i in start..end {
+            if let Some(item) = self.at(i) {
+                result.push(item);
+            }
+        }
+
+        result
+    }
+
+    /// Global position `item` would be inserted at, without inserting it. With
+    /// [`DuplicatePolicy::Reject`] an equal element already sits there; with
+    /// [`DuplicatePolicy::Allow`] the item would go after its equals under
+    /// [`TieBreak::Fifo`] and before them under [`TieBreak::Lifo`].
+    pub fn insertion_index(&self, item: &T) -> usize {
+        let position = match (self.configuration.duplicate_policy, self.configuration.tie_break) {
+            (DuplicatePolicy::Reject, _) | (DuplicatePolicy::Allow, TieBreak::Lifo) => self.lower_bound_position(item),
+            (DuplicatePolicy::Allow, TieBreak::Fifo) => self.upper_bound_position(item),
+        };
+
+        self.global_index(position)
+    }
+
+    /// Like `slice::binary_search_by` over the whole container, returning
+    /// global positions. `f` tells how an element compares to the target and
+    /// must agree with the container's order. On a match the first matching
+    /// element is returned.
+    pub fn binary_search_by(&self, mut f: impl FnMut(&T) -> Ordering) -> Result<usize, usize> {
+        let position = self.partition_position(|item| f(item) == Ordering::Less);
+        let idx = self.global_index(position);
+        match self.get_position(position).map(f) {
+            Some(Ordering::Equal) => Ok(idx),
+            _ => Err(idx),
+        }
+    }
+
+    /// Like `slice::binary_search_by_key`: searches by the key `f` extracts,
+    /// which must be ordered like the elements.
+    pub fn binary_search_by_key<B: Ord>(&self, key: &B, mut f: impl FnMut(&T) -> B) -> Result<usize, usize> {
+        let order = self.configuration.order;
+        self.binary_search_by(|item| order.compare(&f(item), key))
+    }
+
+    /// Every element for which `f` returns `Equal`, e.g. all flights from one
+    /// origin when ordered by `(origin, price)`. Like with
+    /// [`binary_search_by`](Self::binary_search_by), `f` must agree with the
+    /// container's order, which makes the matches contiguous.
+    pub fn find_range_by(&self, mut f: impl FnMut(&T) -> Ordering) -> SortedVecIter<'_, T, S> {
+        let front = self.partition_position(|item| f(item) == Ordering::Less);
+        let back = self.partition_position(|item| f(item) != Ordering::Greater);
+
+        SortedVecIter::between(self, front, back)
+    }
+
+    /// Equal runs spanning buckets need no neighbour lookups: the routed
+    /// bucket is the last one starting at or before `item`, so if an equal
+    /// element exists anywhere, that bucket holds one too.
+    pub fn find_index(&self, item: &T) -> Option<FindResult> {
+        self.find_index_with(item, |bucket| bucket.search(item, self.configuration.order))
+    }
+
+    /// [`find_index`](Self::find_index) with the search inside the routed
+    /// bucket supplied by the caller.
+    pub(crate) fn find_index_with(
+        &self,
+        item: &T,
+        search: impl FnOnce(&Bucket<T, S>) -> Result<usize, usize>,
+    ) -> Option<FindResult> {
+        #[cfg(feature = "metrics")]
+        let _scope = self.metrics.scope();
+        let bucket_idx = self.find_bucket_index(item);
+        #[cfg(feature = "bloom-filter")]
+        if !self.bucket_might_contain(bucket_idx, item) {
+            return None;
+        }
+
+        match search(&self.buckets[bucket_idx]) {
+            Ok(item_idx) => Some(FindResult {
+                bucket_idx,
+                item_idx,
+                generation: Some(self.generation),
+            }),
+            Err(_) => None,
+        }
+    }
+
+    pub fn contains(&self, item: &T) -> bool {
+        self.find_index(item).is_some()
+    }
+
+    /// The element a [`FindResult`] points at. Debug builds panic if the
+    /// container changed since the result was obtained.
+    pub fn get_found(&self, found: &FindResult) -> Option<&T> {
+        debug_assert!(
+            found.generation.is_none_or(|generation| generation == self.generation),
+            "FindResult used after the SortedVec was modified"
+        );
+        self.get_position((found.bucket_idx, found.item_idx))
+    }
+
+    /// Changes whenever elements may have moved, so positions obtained
+    /// earlier can no longer be trusted.
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    pub(crate) fn bump_generation(&mut self) {
+        self.generation = self.generation.wrapping_add(1);
+        self.buckets.refresh_counts();
+    }
+
+    /// Recomputes every index kept next to the buckets, after bulk
+    /// operations that change which elements are stored: the fences and
+    /// boundary keys, the content hash and the bloom filter.
+    pub(crate) fn rebuild_indexes(&mut self) {
+        self.rebuild_fences();
+        #[cfg(feature = "content-hash")]
+        self.rehash();
+        #[cfg(feature = "bloom-filter")]
+        self.rebuild_bloom();
+    }
+
+    /// [`rebuild_indexes`](Self::rebuild_indexes) for bulk operations that
+    /// dropped the bloom filters of the buckets they changed, so only those
+    /// are rebuilt.
+    pub(crate) fn refresh_indexes(&mut self) {
+        self.rebuild_fences();
+        #[cfg(feature = "content-hash")]
+        self.rehash();
+        #[cfg(feature = "bloom-filter")]
+        self.rebuild_stale_blooms();
+    }
+}
+
+/// Same as [`SortedVec::with_storage`] with the default configuration, so it
+/// starts with the one empty bucket every container keeps.
+impl<T: PartialOrd + Ord, S: BucketStorage<T>> Default for SortedVec<T, S> {
+    fn default() -> Self {
+        SortedVec::with_storage(Default::default())
+    }
+}
+
+/// Formats the elements in order, like a `Vec`. Use
+/// [`debug_buckets`](SortedVec::debug_buckets) to see the bucket layout.
+impl<T: PartialOrd + Ord + Debug, S: BucketStorage<T>> Debug for SortedVec<T, S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.buckets.iter().flat_map(|bucket| bucket.data.iter())).finish()
+    }
+}
+
+/// Formats every bucket as its own list. Returned by
+/// [`SortedVec::debug_buckets`].
+pub struct DebugBuckets<'a, T: PartialOrd + Ord, S>(&'a SortedVec<T, S>);
+
+impl<T: PartialOrd + Ord + Debug, S: BucketStorage<T>> Debug for DebugBuckets<'_, T, S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.0.buckets.iter().map(|bucket| DebugBucket(bucket.data.as_slices())))
+            .finish()
+    }
+}
+
+struct DebugBucket<'a, T>((&'a [T], &'a [T]));
+
+impl<T: Debug> Debug for DebugBucket<'_, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let (front, back) = self.0;
+        f.debug_list().entries(front.iter().chain(back)).finish()
+    }
+}
+
+impl<T: PartialOrd + Ord, S: BucketStorage<T>> FromIterator<T> for SortedVec<T, S> {
+    fn from_iter<Iter: IntoIterator<Item = T>>(iter: Iter) -> Self {
+        let mut result = SortedVec::with_storage(Default::default());
+        for item in iter {
+            result.insert(item);
+        }
+
+        result
+    }
+}
+
+impl<T: PartialOrd + Ord, S: BucketStorage<T>> Extend<T> for SortedVec<T, S> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for item in iter {
+            self.insert(item);
+        }
+    }
+}
+
+/// Copies the elements in and merges them with
+/// [`insert_many`](SortedVec::insert_many).
+impl<'a, T: PartialOrd + Ord + Copy + 'a, S: BucketStorage<T>> Extend<&'a T> for SortedVec<T, S> {
+    fn extend<I: IntoIterator<Item = &'a T>>(&mut self, iter: I) {
+        self.insert_many(iter.into_iter().copied());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::panic::{self, AssertUnwindSafe};
+
+    use crate::batch::InsertSummary;
+    use crate::error::SortedVecError;
+    use crate::sorted_vec::{
+        BucketConfiguration, BucketSearch, CapacityPolicy, DuplicatePolicy, MaxBucketCapacity, Order, SortedVec, SplitRatio,
+        SplitSide, SplitStrategy, SqrtCapacity, TieBreak,
+    };
+
+    #[test]
+    fn max_bucket_capacity_new_with_valid_size() {
+        let capacity = MaxBucketCapacity::new(10);
+        assert_eq!(*capacity, 10);
+    }
+
+    #[test]
+    #[should_panic(expected = "MaxBucketCapacity must be greater than 0")]
+    fn max_bucket_capacity_new_with_zero_size() {
+        MaxBucketCapacity::new(0);
+    }
+
+    #[test]
+    fn max_bucket_capacity_default() {
+        let capacity = MaxBucketCapacity::default();
+        assert_eq!(*capacity, 200);
+    }
+
+    #[test]
+    fn sqrt_capacity_scales_with_total_len() {
+        let policy = SqrtCapacity::new(MaxBucketCapacity::new(4));
+        assert_eq!(policy.max_bucket_capacity(0), 4);
+        assert_eq!(policy.max_bucket_capacity(100), 10);
+        assert_eq!(policy.max_bucket_capacity(1_000_000), 1000);
+    }
+
+    #[test]
+    fn bucket_configuration_uses_capacity_policy() {
+        let config = BucketConfiguration::builder()
+            .max_bucket_capacity(10)
+            .capacity_policy(SqrtCapacity::new(MaxBucketCapacity::new(2)))
+            .build();
+        assert!(config.capacity_policy().is_some());
+        assert_eq!(config.bucket_capacity(400), 20);
+        assert_eq!(BucketConfiguration::default().bucket_capacity(400), 200);
+    }
+
+    #[test]
+    #[should_panic(expected = "SplitRatio must be between 1 and 99")]
+    fn split_ratio_new_out_of_range() {
+        SplitRatio::new(100);
+    }
+
+    #[test]
+    fn split_point_uses_ratio_on_tail_insert() {
+        let config = BucketConfiguration::builder().max_bucket_capacity(10).split_ratio(90).build();
+        assert_eq!(config.split_point(11, 10, 10), 9);
+        assert_eq!(config.split_point(11, 0, 10), 1);
+        assert_eq!(config.split_point(11, 4, 10), 5);
+    }
+
+    #[test]
+    fn split_point_respects_hysteresis() {
+        let config = BucketConfiguration::builder()
+            .max_bucket_capacity(10)
+            .split_ratio(90)
+            .split_hysteresis(3)
+            .build();
+        assert_eq!(config.split_point(11, 10, 10), 7);
+        assert_eq!(config.split_point(11, 0, 10), 4);
+    }
+
+    #[test]
+    fn split_point_ignores_unsatisfiable_hysteresis() {
+        let config = BucketConfiguration::builder()
+            .max_bucket_capacity(10)
+            .split_ratio(90)
+            .split_hysteresis(8)
+            .build();
+        assert_eq!(config.split_point(11, 10, 10), 5);
+    }
+
+    /// Fills the lower bucket up to the capacity, as suits appends.
+    #[derive(Debug)]
+    struct FillLower(SplitSide);
+
+    impl SplitStrategy for FillLower {
+        fn split_point(&self, _len: usize, _inserted_at: usize, capacity: usize) -> usize {
+            capacity
+        }
+
+        fn new_allocation(&self, _len: usize, _at: usize) -> SplitSide {
+            self.0
+        }
+    }
+
+    #[test]
+    fn split_strategy_replaces_split_ratio() {
+        let config = BucketConfiguration::builder().max_bucket_capacity(4).split_ratio(10).split_strategy(FillLower(SplitSide::Right)).build();
+        assert!(config.split_strategy().is_some());
+        assert_eq!(config.split_point(5, 4, 4), 4);
+        assert_eq!(config.split_point(5, 0, 9), 4);
+
+        let mut sorted_vec = SortedVec::new(config);
+        (0..10).for_each(|item| {
+            sorted_vec.insert(item);
+        });
+        let lens: Vec<_> = sorted_vec.buckets.iter().map(|bucket| bucket.len()).collect();
+        assert_eq!(lens, vec![4, 4, 2]);
+    }
+
+    #[test]
+    fn split_strategy_picks_the_half_with_the_new_allocation() {
+        for side in [SplitSide::Left, SplitSide::Right] {
+            let config = BucketConfiguration::builder().max_bucket_capacity(4).split_strategy(FillLower(side)).build();
+            let mut sorted_vec = SortedVec::new(config);
+            (0..5).for_each(|item| {
+                sorted_vec.insert(item);
+            });
+
+            let (lower, upper) = (sorted_vec.buckets[0].data.capacity(), sorted_vec.buckets[1].data.capacity());
+            match side {
+                SplitSide::Left => assert!(lower == 4 && upper >= 5),
+                SplitSide::Right => assert!(lower >= 5 && upper == 1),
+            }
+            assert!(sorted_vec.iter().copied().eq(0..5));
+            assert_eq!(sorted_vec.check_integrity(), Ok(()));
+        }
+    }
+
+    #[derive(Debug)]
+    struct PanickingSplit;
+
+    impl SplitStrategy for PanickingSplit {
+        fn split_point(&self, _len: usize, _inserted_at: usize, _capacity: usize) -> usize {
+            panic!("split strategy failed")
+        }
+    }
+
+    #[test]
+    fn split_strategy_panic_keeps_the_insert_counted() {
+        let config = BucketConfiguration::builder().max_bucket_capacity(4).split_strategy(PanickingSplit).build();
+        let mut sorted_vec = SortedVec::new(config);
+        (0..4).for_each(|item| {
+            sorted_vec.insert(item);
+        });
+        let generation = sorted_vec.generation();
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| sorted_vec.insert(4)));
+        assert!(result.is_err());
+        assert_eq!(sorted_vec.size, 5);
+        assert_ne!(sorted_vec.generation(), generation);
+        assert!(sorted_vec.iter().copied().eq(0..5));
+        assert_eq!(sorted_vec.at(4), Some(&4));
+        assert_eq!(sorted_vec.check_integrity(), Ok(()));
+    }
+
+    #[test]
+    fn bucket_configuration_new_with_valid_values() {
+        let config = BucketConfiguration::new(MaxBucketCapacity::new(10), 5);
+        assert_eq!(*config.max_bucket_capacity, 10);
+        assert_eq!(config.initial_set_capacity, 5);
+    }
+
+    #[test]
+    fn bucket_configuration_builder_defaults() {
+        let config = BucketConfiguration::builder().build();
+        assert_eq!(*config.max_bucket_capacity(), 200);
+        assert_eq!(config.initial_set_capacity(), 0);
+        assert_eq!(config.duplicate_policy(), DuplicatePolicy::Reject);
+        assert_eq!(config.order(), Order::Ascending);
+    }
+
+    #[test]
+    fn bucket_configuration_builder_with_all_values() {
+        let config = BucketConfiguration::builder()
+            .max_bucket_capacity(256)
+            .initial_buckets(16)
+            .duplicate_policy(DuplicatePolicy::Allow)
+            .order(Order::Descending)
+            .build();
+        assert_eq!(*config.max_bucket_capacity(), 256);
+        assert_eq!(config.initial_set_capacity(), 16);
+        assert_eq!(config.duplicate_policy(), DuplicatePolicy::Allow);
+        assert_eq!(config.order(), Order::Descending);
+    }
+
+    #[test]
+    #[should_panic(expected = "MaxBucketCapacity must be greater than 0")]
+    fn bucket_configuration_builder_with_zero_capacity() {
+        BucketConfiguration::builder().max_bucket_capacity(0);
+    }
+
+    #[test]
+    fn sorted_vec_new_with_configuration() {
+        let config = BucketConfiguration::new(MaxBucketCapacity::new(10), 5);
+        let sorted_vec: SortedVec<i32> = SortedVec::new(config);
+        assert_eq!(sorted_vec.buckets.len(), 1);
+        assert_eq!(sorted_vec.size, 0);
+    }
+
+    #[test]
+    fn sorted_vec_insert_single_element() {
+        let config = BucketConfiguration::new(MaxBucketCapacity::new(10), 5);
+        let mut sorted_vec: SortedVec<i32> = SortedVec::new(config);
+        sorted_vec.insert(5);
+        assert_eq!(sorted_vec.size, 1);
+        assert_eq!(sorted_vec.at(0), Some(&5));
+    }
+
+    #[test]
+    fn sorted_vec_insert_multiple_elements() {
+        let config = BucketConfiguration::new(MaxBucketCapacity::new(10), 5);
+        let mut sorted_vec: SortedVec<i32> = SortedVec::new(config);
+        sorted_vec.insert(5);
+        sorted_vec.insert(3);
+        sorted_vec.insert(8);
+        assert_eq!(sorted_vec.size, 3);
+        assert_eq!(sorted_vec.at(0), Some(&3));
+        assert_eq!(sorted_vec.at(1), Some(&5));
+        assert_eq!(sorted_vec.at(2), Some(&8));
+    }
+
+    #[test]
+    fn sorted_vec_insert_duplicate_element() {
+        let config = BucketConfiguration::new(MaxBucketCapacity::new(10), 5);
+        let mut sorted_vec: SortedVec<i32> = SortedVec::new(config);
+        sorted_vec.insert(5);
+        sorted_vec.insert(5);
+        assert_eq!(sorted_vec.size, 1);
+        assert_eq!(sorted_vec.at(0), Some(&5));
+    }
+
+    #[test]
+    fn sorted_vec_insert_triggers_split() {
+        let config = BucketConfiguration::new(MaxBucketCapacity::new(1), 5);
+        let mut sorted_vec: SortedVec<i32> = SortedVec::new(config);
+        sorted_vec.insert(5);
+        sorted_vec.insert(3);
+
+        assert_eq!(sorted_vec.buckets.len(), 2);
+        assert_eq!(sorted_vec.size, 2);
+        assert_eq!(sorted_vec.at(0), Some(&3));
+        assert_eq!(sorted_vec.at(1), Some(&5));
+    }
+
+    #[test]
+    fn sorted_vec_at_out_of_bounds() {
+        let config = BucketConfiguration::new(MaxBucketCapacity::new(10), 5);
+        let sorted_vec: SortedVec<i32> = SortedVec::new(config);
+        assert_eq!(sorted_vec.at(0), None);
+    }
+
+    #[test]
+    fn sorted_vec_remove_single_element() {
+        let config = BucketConfiguration::new(MaxBucketCapacity::new(10), 5);
+        let mut sorted_vec: SortedVec<i32> = SortedVec::new(config);
+        sorted_vec.insert(5);
+        sorted_vec.remove(&5);
+        assert_eq!(sorted_vec.size, 0);
+        assert_eq!(sorted_vec.at(0), None);
+    }
+
+    #[test]
+    fn sorted_vec_remove_multiple_elements() {
         let config = BucketConfiguration::new(MaxBucketCapacity::new(10), 5);
         let mut sorted_vec: SortedVec<i32> = SortedVec::new(config);
         sorted_vec.insert(5);
@@ -345,4 +1866,730 @@ mod tests {
         assert_eq!(sorted_vec.size, 1);
         assert_eq!(sorted_vec.at(0), Some(&5));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn sorted_vec_insert_duplicates_when_allowed() {
+        let config = BucketConfiguration::builder()
+            .max_bucket_capacity(2)
+            .duplicate_policy(DuplicatePolicy::Allow)
+            .build();
+        let mut sorted_vec: SortedVec<i32> = SortedVec::new(config);
+        sorted_vec.insert(5);
+        sorted_vec.insert(5);
+        sorted_vec.insert(3);
+        sorted_vec.insert(5);
+        assert_eq!(sorted_vec.size, 4);
+        assert_eq!(sorted_vec.iter().collect::<Vec<_>>(), vec![&3, &5, &5, &5]);
+
+        sorted_vec.remove(&5);
+        assert_eq!(sorted_vec.size, 3);
+        assert_eq!(sorted_vec.iter().collect::<Vec<_>>(), vec![&3, &5, &5]);
+    }
+
+    #[test]
+    fn sorted_vec_descending_order() {
+        let config = BucketConfiguration::builder()
+            .max_bucket_capacity(2)
+            .order(Order::Descending)
+            .build();
+        let sorted_vec = SortedVec::from_vec(vec![3, 1, 4, 5, 2], config);
+        assert_eq!(sorted_vec.iter().collect::<Vec<_>>(), vec![&5, &4, &3, &2, &1]);
+        assert_eq!(sorted_vec.first(), Some(&5));
+        assert_eq!(sorted_vec.last(), Some(&1));
+        assert!(sorted_vec.find_index(&4).is_some());
+    }
+
+    #[test]
+    fn sorted_vec_append_split_keeps_left_bucket_full() {
+        let config = BucketConfiguration::builder().max_bucket_capacity(10).split_ratio(90).build();
+        let sorted_vec = SortedVec::from_vec((0..11).collect(), config);
+        assert_eq!(sorted_vec.buckets.len(), 2);
+        assert_eq!(sorted_vec.buckets[0].len(), 9);
+        assert_eq!(sorted_vec.buckets[1].len(), 2);
+    }
+
+    #[test]
+    fn sorted_vec_capacity_policy_limits_bucket_count() {
+        let config = BucketConfiguration::builder()
+            .capacity_policy(SqrtCapacity::new(MaxBucketCapacity::new(1)))
+            .build();
+        let sorted_vec = SortedVec::from_vec((0..10_000).rev().collect(), config);
+        assert_eq!(sorted_vec.size, 10_000);
+        assert!(sorted_vec.buckets.len() < 400);
+        assert_eq!(sorted_vec.iter().copied().collect::<Vec<_>>(), (0..10_000).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn sorted_vec_insert_ascending_sequence() {
+        let config = BucketConfiguration::new(MaxBucketCapacity::new(4), 5);
+        let mut sorted_vec: SortedVec<i32> = SortedVec::new(config);
+        for i in 0..20 {
+            sorted_vec.insert(i);
+        }
+        sorted_vec.insert(19);
+
+        assert_eq!(sorted_vec.size, 20);
+        assert_eq!(sorted_vec.iter().copied().collect::<Vec<_>>(), (0..20).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn sorted_vec_insert_descending_sequence() {
+        let config = BucketConfiguration::new(MaxBucketCapacity::new(4), 5);
+        let mut sorted_vec: SortedVec<i32> = SortedVec::new(config);
+        for i in (0..20).rev() {
+            sorted_vec.insert(i);
+        }
+        sorted_vec.insert(0);
+
+        assert_eq!(sorted_vec.size, 20);
+        assert_eq!(sorted_vec.iter().copied().collect::<Vec<_>>(), (0..20).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn sorted_vec_galloping_search_matches_binary_search() {
+        let galloping = BucketConfiguration::builder()
+            .max_bucket_capacity(3)
+            .bucket_search(BucketSearch::Galloping)
+            .build();
+        let binary = BucketConfiguration::builder().max_bucket_capacity(3).build();
+        let data: Vec<i32> = (0..200).map(|i| (i * 37) % 101 * 2).collect();
+        let mut galloping = SortedVec::from_vec(data.clone(), galloping);
+        let binary = SortedVec::from_vec(data, binary);
+
+        assert_eq!(galloping.iter().collect::<Vec<_>>(), binary.iter().collect::<Vec<_>>());
+        for probe in [150, 151, 3, 4, 190, 0, 202, -1, 100, 101] {
+            assert_eq!(galloping.find_bucket_index(&probe), binary.find_bucket_index(&probe));
+            assert_eq!(galloping.find_index(&probe).is_some(), binary.find_index(&probe).is_some());
+        }
+
+        galloping.remove(&200);
+        galloping.remove(&0);
+        assert!(galloping.find_index(&100).is_some());
+    }
+
+    #[test]
+    fn sorted_vec_gap_items_go_to_left_bucket() {
+        for bucket_search in [BucketSearch::Binary, BucketSearch::Galloping] {
+            let configuration = BucketConfiguration::builder()
+                .max_bucket_capacity(4)
+                .bucket_search(bucket_search)
+                .build();
+            let mut sorted_vec = SortedVec::new(configuration);
+            sorted_vec.insert_many([10, 20, 30, 40, 50, 60]);
+            assert_eq!(format!("{:?}", sorted_vec.debug_buckets()), "[[10, 20, 30], [40, 50, 60]]");
+
+            sorted_vec.insert(35);
+            assert_eq!(format!("{:?}", sorted_vec.debug_buckets()), "[[10, 20, 30, 35], [40, 50, 60]]");
+        }
+    }
+
+    #[test]
+    fn sorted_vec_duplicates_spanning_buckets_stay_fifo() {
+        let configuration = BucketConfiguration::builder()
+            .max_bucket_capacity(2)
+            .duplicate_policy(DuplicatePolicy::Allow)
+            .build();
+        let mut sorted_vec = SortedVec::new(configuration);
+        for id in 0..6 {
+            sorted_vec.insert(Keyed { key: 1, payload: ["a", "b", "c", "d", "e", "f"][id] });
+        }
+
+        assert!(sorted_vec.buckets.len() > 1);
+        let payloads: Vec<_> = sorted_vec.iter().map(|item| item.payload).collect();
+        assert_eq!(payloads, vec!["a", "b", "c", "d", "e", "f"]);
+        for _ in 0..6 {
+            assert!(sorted_vec.find_index(&keyed(1)).is_some());
+            sorted_vec.remove(&keyed(1));
+        }
+        assert!(sorted_vec.find_index(&keyed(1)).is_none());
+    }
+
+    #[test]
+    fn sorted_vec_lifo_tie_break() {
+        let configuration = BucketConfiguration::builder()
+            .max_bucket_capacity(2)
+            .duplicate_policy(DuplicatePolicy::Allow)
+            .tie_break(TieBreak::Lifo)
+            .build();
+        let mut sorted_vec = SortedVec::new(configuration);
+        sorted_vec.insert(keyed(0));
+        sorted_vec.insert(keyed(2));
+        for payload in ["a", "b", "c", "d", "e"] {
+            sorted_vec.insert(Keyed { key: 1, payload });
+        }
+
+        assert!(sorted_vec.buckets.len() > 1);
+        let payloads: Vec<_> = sorted_vec.iter().map(|item| item.payload).collect();
+        assert_eq!(payloads, vec!["", "e", "d", "c", "b", "a", ""]);
+    }
+
+    #[test]
+    fn sorted_vec_lifo_tie_break_insert_many() {
+        let configuration = BucketConfiguration::builder()
+            .max_bucket_capacity(2)
+            .duplicate_policy(DuplicatePolicy::Allow)
+            .tie_break(TieBreak::Lifo)
+            .build();
+        let mut sorted_vec = SortedVec::new(configuration);
+        sorted_vec.insert_many(["a", "b", "c"].map(|payload| Keyed { key: 1, payload }));
+        sorted_vec.insert_many(["d", "e"].map(|payload| Keyed { key: 1, payload }));
+
+        let payloads: Vec<_> = sorted_vec.iter().map(|item| item.payload).collect();
+        assert_eq!(payloads, vec!["e", "d", "c", "b", "a"]);
+    }
+
+    #[test]
+    fn sorted_vec_remove_keeps_one_bucket() {
+        let config = BucketConfiguration::new(MaxBucketCapacity::new(10), 5);
+        let mut sorted_vec: SortedVec<i32> = SortedVec::new(config);
+        sorted_vec.insert(5);
+        sorted_vec.remove(&5);
+        sorted_vec.insert(3);
+        assert_eq!(sorted_vec.buckets.len(), 1);
+        assert_eq!(sorted_vec.at(0), Some(&3));
+    }
+
+    #[test]
+    fn sorted_vec_remove_range_within_bucket() {
+        let mut sorted_vec = SortedVec::from_vec((0..10).collect(), Default::default());
+        assert_eq!(sorted_vec.remove_range(3..6), 3);
+        assert_eq!(sorted_vec.size, 7);
+        assert_eq!(sorted_vec.iter().copied().collect::<Vec<_>>(), vec![0, 1, 2, 6, 7, 8, 9]);
+    }
+
+    #[test]
+    fn sorted_vec_remove_range_across_buckets() {
+        let config = BucketConfiguration::new(MaxBucketCapacity::new(3), 5);
+        let mut sorted_vec = SortedVec::from_vec((0..30).collect(), config);
+        assert_eq!(sorted_vec.remove_range(4..=25), 22);
+        assert_eq!(sorted_vec.size, 8);
+        assert!(sorted_vec.buckets.iter().all(|bucket| bucket.len() > 0));
+        assert_eq!(sorted_vec.iter().copied().collect::<Vec<_>>(), vec![0, 1, 2, 3, 26, 27, 28, 29]);
+    }
+
+    #[test]
+    fn sorted_vec_remove_range_unbounded() {
+        let config = BucketConfiguration::new(MaxBucketCapacity::new(3), 5);
+        let mut sorted_vec = SortedVec::from_vec((0..30).collect(), config);
+        assert_eq!(sorted_vec.remove_range(..10), 10);
+        assert_eq!(sorted_vec.first(), Some(&10));
+        assert_eq!(sorted_vec.remove_range(20..), 10);
+        assert_eq!(sorted_vec.last(), Some(&19));
+        assert_eq!(sorted_vec.remove_range(..), 10);
+        assert_eq!(sorted_vec.size, 0);
+        assert_eq!(sorted_vec.buckets.len(), 1);
+        assert_eq!(sorted_vec.remove_range(..), 0);
+    }
+
+    #[test]
+    fn sorted_vec_insertion_index() {
+        let configuration = BucketConfiguration::builder().max_bucket_capacity(2).build();
+        let sorted_vec = SortedVec::from_vec(vec![10, 20, 30, 40, 50], configuration);
+
+        assert_eq!(sorted_vec.insertion_index(&5), 0);
+        assert_eq!(sorted_vec.insertion_index(&30), 2);
+        assert_eq!(sorted_vec.insertion_index(&35), 3);
+        assert_eq!(sorted_vec.insertion_index(&60), 5);
+    }
+
+    #[test]
+    fn sorted_vec_insertion_index_goes_after_duplicates() {
+        let configuration = BucketConfiguration::builder()
+            .max_bucket_capacity(2)
+            .duplicate_policy(DuplicatePolicy::Allow)
+            .build();
+        let sorted_vec = SortedVec::from_vec(vec![1, 2, 2, 3], configuration);
+
+        assert_eq!(sorted_vec.insertion_index(&2), 3);
+        assert_eq!(sorted_vec.insertion_index(&1), 1);
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    struct Keyed {
+        key: i32,
+        payload: &'static str,
+    }
+
+    impl PartialOrd for Keyed {
+        fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Keyed {
+        fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+            self.key.cmp(&other.key)
+        }
+    }
+
+    fn keyed(key: i32) -> Keyed {
+        Keyed { key, payload: "" }
+    }
+
+    #[test]
+    fn sorted_vec_insertion_index_matches_insert() {
+        for tie_break in [TieBreak::Fifo, TieBreak::Lifo] {
+            let configuration = BucketConfiguration::builder()
+                .max_bucket_capacity(2)
+                .duplicate_policy(DuplicatePolicy::Allow)
+                .tie_break(tie_break)
+                .build();
+            let mut sorted_vec = SortedVec::from_vec([1, 5, 5, 9].map(keyed).to_vec(), configuration);
+            let expected = if tie_break == TieBreak::Lifo { 1 } else { 3 };
+            assert_eq!(sorted_vec.insertion_index(&keyed(5)), expected);
+
+            let index = sorted_vec.insertion_index(&keyed(5));
+            sorted_vec.insert(Keyed { key: 5, payload: "new" });
+            assert_eq!(sorted_vec.iter().position(|item| item.payload == "new"), Some(index));
+        }
+    }
+
+    #[test]
+    fn sorted_vec_galloping_keeps_fifo_runs_across_buckets() {
+        let labels = ["0", "1", "2", "3", "4", "5", "6", "7", "8", "9", "99", "100"];
+        let configuration = BucketConfiguration::builder()
+            .max_bucket_capacity(4)
+            .duplicate_policy(DuplicatePolicy::Allow)
+            .bucket_search(BucketSearch::Galloping)
+            .build();
+        let mut sorted_vec = SortedVec::new(configuration);
+        sorted_vec.insert(Keyed { key: 0, payload: labels[0] });
+        for label in &labels[1..10] {
+            sorted_vec.insert(Keyed { key: 1, payload: label });
+        }
+        sorted_vec.insert(Keyed { key: 2, payload: "99" });
+        sorted_vec.insert(Keyed { key: 0, payload: "1" });
+        sorted_vec.insert(Keyed { key: 1, payload: "100" });
+
+        assert!(sorted_vec.buckets.len() >= 3);
+        let run: Vec<_> = sorted_vec.iter().filter(|item| item.key == 1).map(|item| item.payload).collect();
+        assert_eq!(run, ["1", "2", "3", "4", "5", "6", "7", "8", "9", "100"]);
+    }
+
+    #[test]
+    fn sorted_vec_galloping_places_equal_elements_like_binary_search() {
+        let labels = ["a", "b", "c", "d", "e", "f", "g"];
+        for tie_break in [TieBreak::Fifo, TieBreak::Lifo] {
+            let sequences: Vec<Vec<(i32, &str)>> = [BucketSearch::Binary, BucketSearch::Galloping]
+                .into_iter()
+                .map(|bucket_search| {
+                    let configuration = BucketConfiguration::builder()
+                        .max_bucket_capacity(3)
+                        .duplicate_policy(DuplicatePolicy::Allow)
+                        .tie_break(tie_break)
+                        .bucket_search(bucket_search)
+                        .build();
+                    let mut sorted_vec = SortedVec::new(configuration);
+                    for i in 0..300 {
+                        // Long runs of 1s, with inserts at both ends moving
+                        // the galloping start around.
+                        let key = match i % 6 {
+                            0 => 0,
+                            5 => 2,
+                            _ => 1,
+                        };
+                        sorted_vec.insert(Keyed { key, payload: labels[i as usize % labels.len()] });
+                        if i % 11 == 0 {
+                            sorted_vec.remove(&keyed(i % 3));
+                        }
+                    }
+                    sorted_vec.iter().map(|item| (item.key, item.payload)).collect()
+                })
+                .collect();
+
+            assert_eq!(sequences[0], sequences[1]);
+        }
+    }
+
+    #[test]
+    fn sorted_vec_update_payload_in_place() {
+        let configuration = BucketConfiguration::builder().max_bucket_capacity(2).build();
+        let mut sorted_vec = SortedVec::from_vec((0..6).map(keyed).collect(), configuration);
+
+        assert!(sorted_vec.update(&keyed(3), |item| item.payload = "updated"));
+        assert!(!sorted_vec.update(&keyed(9), |item| item.payload = "missing"));
+        assert_eq!(sorted_vec.at(3).map(|item| item.payload), Some("updated"));
+        assert_eq!(sorted_vec.size, 6);
+    }
+
+    #[test]
+    fn sorted_vec_update_moves_changed_key() {
+        let configuration = BucketConfiguration::builder().max_bucket_capacity(2).build();
+        let mut sorted_vec = SortedVec::from_vec(vec![1, 2, 3, 4, 5], configuration);
+
+        assert!(sorted_vec.update(&2, |item| *item = 10));
+        assert_eq!(sorted_vec.iter().copied().collect::<Vec<_>>(), vec![1, 3, 4, 5, 10]);
+
+        assert!(sorted_vec.update(&10, |item| *item = 4));
+        assert_eq!(sorted_vec.iter().copied().collect::<Vec<_>>(), vec![1, 3, 4, 5]);
+        assert_eq!(sorted_vec.size, 4);
+    }
+
+    #[test]
+    fn sorted_vec_insert_with_merge() {
+        let configuration = BucketConfiguration::builder().max_bucket_capacity(2).build();
+        let mut sorted_vec = SortedVec::from_vec((0..6).map(keyed).collect(), configuration);
+        let merge = |existing: &mut Keyed, incoming: Keyed| existing.payload = incoming.payload;
+
+        sorted_vec.insert_with_merge(Keyed { key: 3, payload: "merged" }, merge);
+        sorted_vec.insert_with_merge(Keyed { key: 7, payload: "new" }, merge);
+
+        assert_eq!(sorted_vec.size, 7);
+        assert_eq!(sorted_vec.at(3).map(|item| item.payload), Some("merged"));
+        assert_eq!(sorted_vec.last().map(|item| item.payload), Some("new"));
+    }
+
+    #[test]
+    fn sorted_vec_binary_search_by_projection() {
+        let configuration = BucketConfiguration::builder().max_bucket_capacity(2).build();
+        let sorted_vec = SortedVec::from_vec((0..6).map(|key| keyed(key * 10)).collect(), configuration);
+
+        assert_eq!(sorted_vec.binary_search_by(|item| item.key.cmp(&30)), Ok(3));
+        assert_eq!(sorted_vec.binary_search_by(|item| item.key.cmp(&35)), Err(4));
+        assert_eq!(sorted_vec.binary_search_by_key(&0, |item| item.key), Ok(0));
+        assert_eq!(sorted_vec.binary_search_by_key(&-5, |item| item.key), Err(0));
+        assert_eq!(sorted_vec.binary_search_by_key(&99, |item| item.key), Err(6));
+    }
+
+    #[test]
+    fn sorted_vec_binary_search_by_key_descending() {
+        let configuration = BucketConfiguration::builder().max_bucket_capacity(2).order(Order::Descending).build();
+        let sorted_vec = SortedVec::from_vec(vec![(1, 'a'), (5, 'b'), (3, 'c'), (9, 'd')], configuration);
+
+        assert_eq!(sorted_vec.binary_search_by_key(&5, |item| item.0), Ok(1));
+        assert_eq!(sorted_vec.binary_search_by_key(&4, |item| item.0), Err(2));
+    }
+
+    #[test]
+    fn sorted_vec_find_range_by_key_prefix() {
+        let configuration = BucketConfiguration::builder().max_bucket_capacity(2).build();
+        let flights = vec![("LAX", 300), ("JFK", 100), ("LAX", 100), ("SFO", 50), ("LAX", 200), ("JFK", 400)];
+        let sorted_vec = SortedVec::from_vec(flights, configuration);
+
+        let from_lax: Vec<_> = sorted_vec.find_range_by(|flight| flight.0.cmp("LAX")).map(|flight| flight.1).collect();
+        assert_eq!(from_lax, vec![100, 200, 300]);
+        assert_eq!(sorted_vec.find_range_by(|flight| flight.0.cmp("JFK")).len(), 2);
+        assert_eq!(sorted_vec.find_range_by(|flight| flight.0.cmp("BOS")).next(), None);
+    }
+
+    #[test]
+    fn sorted_vec_try_from_vec_matches_from_vec() {
+        for (duplicate_policy, tie_break) in [
+            (DuplicatePolicy::Reject, TieBreak::Fifo),
+            (DuplicatePolicy::Allow, TieBreak::Fifo),
+            (DuplicatePolicy::Allow, TieBreak::Lifo),
+        ] {
+            let configuration = BucketConfiguration::builder()
+                .max_bucket_capacity(3)
+                .duplicate_policy(duplicate_policy)
+                .tie_break(tie_break)
+                .build();
+            let items: Vec<_> = (0..20).map(|idx| Keyed { key: idx % 7, payload: ["a", "b", "c"][idx as usize % 3] }).collect();
+
+            let built = SortedVec::try_from_vec(items.clone(), configuration.clone()).unwrap();
+            let inserted = SortedVec::from_vec(items, configuration);
+            assert!(built.buckets.iter().all(|bucket| bucket.len() <= 3));
+            assert_eq!(built.size, inserted.size);
+            assert!(built.iter().map(|item| (item.key, item.payload)).eq(inserted.iter().map(|item| (item.key, item.payload))));
+        }
+    }
+
+    #[test]
+    fn sorted_vec_get_found() {
+        let mut sorted_vec = SortedVec::from_vec(vec![1, 2, 3], Default::default());
+        let generation = sorted_vec.generation();
+        let found = sorted_vec.find_index(&2).unwrap();
+
+        assert_eq!(sorted_vec.get_found(&found), Some(&2));
+        sorted_vec.insert(2);
+        assert_eq!(sorted_vec.generation(), generation);
+        sorted_vec.insert(0);
+        assert_ne!(sorted_vec.generation(), generation);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "FindResult used after the SortedVec was modified")]
+    fn sorted_vec_get_found_after_mutation_panics() {
+        let mut sorted_vec = SortedVec::from_vec(vec![1, 2, 3], Default::default());
+        let found = sorted_vec.find_index(&2).unwrap();
+        sorted_vec.remove(&1);
+        sorted_vec.get_found(&found);
+    }
+
+    #[test]
+    fn sorted_vec_remove_any_in_range() {
+        let configuration = BucketConfiguration::builder().max_bucket_capacity(4).build();
+        let mut sorted_vec = SortedVec::from_vec((0..20).collect(), configuration);
+        let mut taken = Vec::new();
+        while let Some(item) = sorted_vec.remove_any_in_range(5..15) {
+            taken.push(item);
+        }
+        taken.sort();
+
+        assert_eq!(taken, (5..15).collect::<Vec<_>>());
+        assert!(sorted_vec.iter().copied().eq((0..5).chain(15..20)));
+        assert_eq!(sorted_vec.remove_any_in_range(7..=9), None);
+        assert_eq!(sorted_vec.remove_any_in_range(19..), Some(19));
+        assert_eq!(sorted_vec.size, 9);
+    }
+
+    #[test]
+    fn sorted_vec_from_vec_with_summary() {
+        let configuration = BucketConfiguration::builder().max_bucket_capacity(3).build();
+        let (sorted_vec, summary) = SortedVec::from_vec_with_summary(vec![4, 1, 4, 2, 1, 4, 3], configuration);
+
+        assert_eq!(summary, InsertSummary { added: 4, duplicates: 3 });
+        assert!(sorted_vec.iter().copied().eq(1..5));
+    }
+
+    #[test]
+    fn sorted_vec_min_max_and_fold_sorted() {
+        let configuration = BucketConfiguration::builder().max_bucket_capacity(2).build();
+        let sorted_vec = SortedVec::from_vec(vec![4, 9, 1, 7], configuration);
+        assert_eq!(sorted_vec.min_max(), Some((&1, &9)));
+        assert_eq!(sorted_vec.fold_sorted(Vec::new(), |mut seen, item| {
+            seen.push(*item);
+            seen
+        }), vec![1, 4, 7, 9]);
+
+        let configuration = BucketConfiguration::builder().order(Order::Descending).build();
+        let sorted_vec = SortedVec::from_vec(vec![4, 9, 1, 7], configuration);
+        assert_eq!(sorted_vec.min_max(), Some((&1, &9)));
+
+        let empty: SortedVec<i32> = SortedVec::new(Default::default());
+        assert_eq!(empty.min_max(), None);
+        assert_eq!(empty.fold_sorted(0, |sum, item| sum + item), 0);
+    }
+
+    #[test]
+    fn sorted_vec_peek_n_and_last_n() {
+        let configuration = BucketConfiguration::builder().max_bucket_capacity(3).build();
+        let sorted_vec = SortedVec::from_vec((0..20).collect(), configuration);
+
+        for n in [0, 1, 3, 4, 6, 19, 20, 25] {
+            let first = sorted_vec.peek_n(n);
+            assert_eq!(first.len(), n.min(20));
+            assert!(first.copied().eq(0..n.min(20) as i32));
+            assert!(sorted_vec.peek_n(n).rev().copied().eq((0..n.min(20) as i32).rev()));
+
+            let last = sorted_vec.last_n(n);
+            assert_eq!(last.len(), n.min(20));
+            assert!(last.copied().eq(20 - n.min(20) as i32..20));
+            assert!(sorted_vec.last_n(n).rev().copied().eq((20 - n.min(20) as i32..20).rev()));
+        }
+
+        let empty: SortedVec<i32> = SortedVec::new(Default::default());
+        assert_eq!(empty.peek_n(3).next(), None);
+        assert_eq!(empty.last_n(3).next_back(), None);
+    }
+
+    #[test]
+    fn sorted_vec_default_matches_new() {
+        let mut sorted_vec: SortedVec<i32> = SortedVec::default();
+        assert_eq!(sorted_vec.buckets.len(), 1);
+        assert_eq!(sorted_vec.size, 0);
+        assert_eq!(sorted_vec.first(), None);
+        assert_eq!(sorted_vec.check_integrity(), Ok(()));
+
+        for item in [3, 1, 2, 3] {
+            sorted_vec.insert(item);
+        }
+        sorted_vec.remove(&2);
+        assert!(sorted_vec.iter().copied().eq([1, 3]));
+        assert_eq!(sorted_vec.check_integrity(), Ok(()));
+
+        let mut taken = std::mem::take(&mut sorted_vec);
+        assert_eq!(taken.size, 2);
+        assert_eq!(sorted_vec.size, 0);
+        sorted_vec.insert(5);
+        taken.insert(0);
+        assert!(sorted_vec.iter().copied().eq([5]));
+        assert!(taken.iter().copied().eq([0, 1, 3]));
+
+        let mut deque: SortedVec<u8, VecDeque<u8>> = SortedVec::default();
+        deque.insert(4);
+        deque.insert(2);
+        assert!(deque.iter().copied().eq([2, 4]));
+    }
+
+    #[test]
+    fn sorted_vec_from_vec_with_rejects() {
+        let configuration = BucketConfiguration::builder().max_bucket_capacity(3).build();
+        let (sorted_vec, rejected) = SortedVec::from_vec_with_rejects(vec![4, 1, 4, 2, 1, 4, 3], configuration);
+
+        assert!(sorted_vec.iter().copied().eq(1..5));
+        assert_eq!(rejected, vec![1, 4, 4]);
+
+        let configuration = BucketConfiguration::builder().duplicate_policy(DuplicatePolicy::Allow).build();
+        let (sorted_vec, rejected) = SortedVec::from_vec_with_rejects(vec![2, 2, 1], configuration);
+        assert_eq!(sorted_vec.size, 3);
+        assert!(rejected.is_empty());
+    }
+
+    #[test]
+    fn sorted_vec_with_capacity_reserves_buckets() {
+        let configuration = BucketConfiguration::builder().max_bucket_capacity(16).build();
+        let mut sorted_vec = SortedVec::with_capacity(100, configuration);
+
+        assert_eq!(sorted_vec.size, 0);
+        assert!(sorted_vec.buckets[0].data.capacity() >= 16);
+        assert!(sorted_vec.buckets.capacity() >= 6);
+        sorted_vec.insert_many(0..100);
+        assert!(sorted_vec.iter().copied().eq(0..100));
+    }
+
+    #[test]
+    fn sorted_vec_try_reserve() {
+        let configuration = BucketConfiguration::builder().max_bucket_capacity(4).build();
+        let mut sorted_vec = SortedVec::from_vec(vec![1, 2], configuration);
+
+        assert!(sorted_vec.try_reserve(10).is_ok());
+        assert!(sorted_vec.buckets.capacity() >= 4);
+        assert!(sorted_vec.try_reserve(usize::MAX).is_err());
+    }
+
+    // paranoid-checks panics on the inconsistency before try_insert sees it.
+    #[test]
+    #[cfg(not(feature = "paranoid-checks"))]
+    fn sorted_vec_try_insert_detects_inconsistent_order() {
+        /// Claims every distinct value is smaller than every other one.
+        #[derive(Debug, PartialEq, Eq)]
+        struct AlwaysLess(i32);
+
+        impl PartialOrd for AlwaysLess {
+            fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+
+        impl Ord for AlwaysLess {
+            fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+                match self.0 == other.0 {
+                    true => std::cmp::Ordering::Equal,
+                    false => std::cmp::Ordering::Less,
+                }
+            }
+        }
+
+        let mut sorted_vec = SortedVec::new(Default::default());
+        assert_eq!(sorted_vec.try_insert(AlwaysLess(1)), Ok(()));
+        assert_eq!(sorted_vec.try_insert(AlwaysLess(1)), Ok(()));
+        assert!(matches!(sorted_vec.try_insert(AlwaysLess(2)), Err(SortedVecError::InconsistentOrder { .. })));
+        assert_eq!(sorted_vec.size, 1);
+    }
+
+    #[test]
+    fn sorted_vec_try_variants() {
+        assert_eq!(MaxBucketCapacity::try_new(0).err(), Some(SortedVecError::InvalidCapacity(0)));
+        assert!(BucketConfiguration::builder().try_split_ratio(100).is_err());
+        let configuration = BucketConfiguration::builder().try_max_bucket_capacity(2).unwrap().build();
+
+        let unsorted = SortedVec::try_from_sorted(vec![1, 2, 4, 3], configuration.clone());
+        assert_eq!(unsorted.err(), Some(SortedVecError::UnsortedInput { index: 3 }));
+
+        let sorted_vec = SortedVec::try_from_sorted(vec![1, 2, 3, 4, 5], configuration).unwrap();
+        assert_eq!(sorted_vec.try_at(4), Ok(&5));
+        assert_eq!(sorted_vec.try_at(5), Err(SortedVecError::IndexOutOfBounds { index: 5, len: 5 }));
+        assert_eq!(SortedVecError::IndexOutOfBounds { index: 5, len: 5 }.to_string(), "index 5 out of bounds for length 5");
+
+        let configuration = BucketConfiguration::builder().max_bucket_capacity(16).build();
+        assert!(SortedVec::<u64>::try_with_capacity(100, configuration.clone()).is_ok());
+        let overflowing = SortedVec::<u64>::try_with_capacity(usize::MAX, configuration);
+        assert!(matches!(overflowing, Err(SortedVecError::AllocationFailed(_))));
+    }
+
+    #[test]
+    fn sorted_vec_short_circuit_scans() {
+        let configuration = BucketConfiguration::builder().max_bucket_capacity(2).build();
+        let sorted_vec = SortedVec::from_vec(vec![3, 8, 1, 6, 4, 9, 2], configuration);
+
+        assert_eq!(sorted_vec.position(|item| item % 3 == 0), Some(2));
+        assert_eq!(sorted_vec.position(|item| *item > 9), None);
+        assert_eq!(sorted_vec.find(|item| *item > 4), Some(&6));
+        assert!(sorted_vec.any(|item| *item == 8));
+        assert!(!sorted_vec.all(|item| *item < 9));
+        assert!(sorted_vec.any_monotone(|item| *item >= 9));
+        assert!(!sorted_vec.any_monotone(|item| *item > 9));
+        assert!(sorted_vec.all_monotone(|item| *item < 10));
+        assert!(!sorted_vec.all_monotone(|item| *item > 1));
+    }
+
+    #[test]
+    fn sorted_vec_floor_and_ceiling() {
+        let configuration = BucketConfiguration::builder().max_bucket_capacity(2).build();
+        let sorted_vec = SortedVec::from_vec(vec![10, 20, 30, 40, 50], configuration);
+
+        assert_eq!(sorted_vec.le(&30), Some(&30));
+        assert_eq!(sorted_vec.lt(&30), Some(&20));
+        assert_eq!(sorted_vec.ge(&30), Some(&30));
+        assert_eq!(sorted_vec.gt(&30), Some(&40));
+        assert_eq!(sorted_vec.le(&25), Some(&20));
+        assert_eq!(sorted_vec.ge(&25), Some(&30));
+        assert_eq!(sorted_vec.lt(&10), None);
+        assert_eq!(sorted_vec.le(&5), None);
+        assert_eq!(sorted_vec.gt(&50), None);
+        assert_eq!(sorted_vec.ge(&55), None);
+        assert_eq!(sorted_vec.le(&55), Some(&50));
+    }
+
+    #[test]
+    fn sorted_vec_floor_and_ceiling_follow_order() {
+        let configuration = BucketConfiguration::builder().order(Order::Descending).build();
+        let sorted_vec = SortedVec::from_vec(vec![1, 3, 5], configuration);
+
+        assert_eq!(sorted_vec.lt(&3), Some(&5));
+        assert_eq!(sorted_vec.gt(&3), Some(&1));
+    }
+
+    #[test]
+    fn sorted_vec_floor_and_ceiling_on_empty() {
+        let sorted_vec = SortedVec::<i32>::new(Default::default());
+        assert_eq!(sorted_vec.le(&1), None);
+        assert_eq!(sorted_vec.ge(&1), None);
+    }
+
+    #[test]
+    fn sorted_vec_debug_shows_elements() {
+        let configuration = BucketConfiguration::builder().max_bucket_capacity(2).build();
+        let sorted_vec = SortedVec::from_vec(vec![3, 1, 2], configuration);
+
+        assert_eq!(format!("{:?}", sorted_vec), "[1, 2, 3]");
+        assert_eq!(format!("{:?}", sorted_vec.debug_buckets()), "[[1], [2, 3]]");
+        assert_eq!(format!("{:?}", SortedVec::<i32>::new(Default::default())), "[]");
+    }
+
+    #[cfg(feature = "paranoid-checks")]
+    #[test]
+    #[should_panic(expected = "paranoid-checks")]
+    fn sorted_vec_paranoid_checks_catch_inconsistent_ord() {
+        #[derive(Debug, PartialEq, Eq)]
+        struct Unordered(i32);
+
+        impl PartialOrd for Unordered {
+            fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+
+        impl Ord for Unordered {
+            fn cmp(&self, _other: &Self) -> std::cmp::Ordering {
+                std::cmp::Ordering::Less
+            }
+        }
+
+        let mut sorted_vec = SortedVec::new(Default::default());
+        sorted_vec.insert(Unordered(1));
+        sorted_vec.insert(Unordered(2));
+    }
+
+    #[test]
+    fn sorted_vec_remove_range_empty_or_inverted() {
+        let mut sorted_vec = SortedVec::from_vec(vec![1, 5, 9], Default::default());
+        assert_eq!(sorted_vec.remove_range(2..5), 0);
+        #[allow(clippy::reversed_empty_ranges)]
+        let removed = sorted_vec.remove_range(9..1);
+        assert_eq!(removed, 0);
+        assert_eq!(sorted_vec.size, 3);
+    }
+}
@@ -1,40 +1,218 @@
-use crate::sorted_vec::SortedVec;
+use std::cmp::Ordering;
+use std::iter::FusedIterator;
 
-pub struct SortedVecIter<'a, T: PartialOrd + Ord> {
-    sorted_vec: &'a SortedVec<T>,
-    index: usize,
+use crate::bucket::Bucket;
+use crate::directory;
+use crate::sorted_vec::{Order, SortedVec};
+use crate::storage::BucketStorage;
+
+/// Walks the buckets with a cursor at each end, so every step is O(1)
+/// instead of locating the element from the start.
+pub struct SortedVecIter<'a, T: PartialOrd + Ord, S = Vec<T>> {
+    sorted_vec: &'a SortedVec<T, S>,
+    /// Next position to yield from the front, as `(bucket_idx, item_idx)`.
+    front: (usize, usize),
+    /// Position just past the next element to yield from the back.
+    back: (usize, usize),
+    remaining: usize,
 }
 
-impl<'a, T: PartialOrd + Ord> SortedVecIter<'a, T> {
-    pub fn new(sorted_vec: &'a SortedVec<T>) -> Self {
+impl<'a, T: PartialOrd + Ord, S: BucketStorage<T>> SortedVecIter<'a, T, S> {
+    pub fn new(sorted_vec: &'a SortedVec<T, S>) -> Self {
         SortedVecIter {
             sorted_vec,
-            index: 0,
+            front: (0, 0),
+            back: (sorted_vec.buckets.len(), 0),
+            remaining: sorted_vec.size,
         }
     }
+
+    /// Iterates from position `front` up to, but excluding, position `back`.
+    /// The length takes two rank queries on the bucket directory.
+    pub(crate) fn between(sorted_vec: &'a SortedVec<T, S>, front: (usize, usize), back: (usize, usize)) -> Self {
+        let remaining = sorted_vec.global_index(back).saturating_sub(sorted_vec.global_index(front));
+        SortedVecIter {
+            sorted_vec,
+            front,
+            back,
+            remaining,
+        }
+    }
+
+    /// Like [`between`](Self::between) when the caller already knows how
+    /// many elements lie between the positions.
+    pub(crate) fn between_counted(
+        sorted_vec: &'a SortedVec<T, S>,
+        front: (usize, usize),
+        back: (usize, usize),
+        remaining: usize,
+    ) -> Self {
+        SortedVecIter {
+            sorted_vec,
+            front,
+            back,
+            remaining,
+        }
+    }
+}
+
+impl<T: PartialOrd + Ord, S> Clone for SortedVecIter<'_, T, S> {
+    fn clone(&self) -> Self {
+        SortedVecIter { ..*self }
+    }
 }
 
-impl<'a, T: PartialOrd + Ord> ExactSizeIterator for SortedVecIter<'a, T> {
+impl<T: PartialOrd + Ord, S: BucketStorage<T>> ExactSizeIterator for SortedVecIter<'_, T, S> {
     fn len(&self) -> usize {
-        self.sorted_vec.size
+        self.remaining
     }
 }
 
-impl<'a, T: PartialOrd + Ord> Iterator for SortedVecIter<'a, T> {
+impl<T: PartialOrd + Ord, S: BucketStorage<T>> FusedIterator for SortedVecIter<'_, T, S> {}
+
+impl<'a, T: PartialOrd + Ord, S: BucketStorage<T>> Iterator for SortedVecIter<'a, T, S> {
     type Item = &'a T;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.index < self.sorted_vec.size {
-            let item = self.sorted_vec.at(self.index);
-            self.index += 1;
-            item
-        } else {
-            None
+        if self.remaining == 0 {
+            return None;
+        }
+
+        loop {
+            let (bucket_idx, item_idx) = self.front;
+            match self.sorted_vec.buckets[bucket_idx].data.get(item_idx) {
+                Some(item) => {
+                    self.front.1 += 1;
+                    self.remaining -= 1;
+                    return Some(item);
+                },
+                None => self.front = (bucket_idx + 1, 0),
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<T: PartialOrd + Ord, S: BucketStorage<T>> DoubleEndedIterator for SortedVecIter<'_, T, S> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        while self.back.1 == 0 {
+            let bucket_idx = self.back.0 - 1;
+            self.back = (bucket_idx, self.sorted_vec.buckets[bucket_idx].len());
+        }
+        self.back.1 -= 1;
+        self.remaining -= 1;
+        self.sorted_vec.buckets[self.back.0].data.get(self.back.1)
+    }
+}
+
+/// Yields each distinct element once with the length of its run of equal
+/// elements. Returned by [`SortedVec::group_by_equal`].
+pub struct GroupByEqual<'a, T: PartialOrd + Ord, S = Vec<T>> {
+    order: Order,
+    inner: SortedVecIter<'a, T, S>,
+}
+
+impl<'a, T: PartialOrd + Ord, S: BucketStorage<T>> GroupByEqual<'a, T, S> {
+    pub(crate) fn new(sorted_vec: &'a SortedVec<T, S>) -> Self {
+        GroupByEqual {
+            order: sorted_vec.configuration.order(),
+            inner: sorted_vec.iter(),
+        }
+    }
+}
+
+impl<T: PartialOrd + Ord, S: BucketStorage<T>> FusedIterator for GroupByEqual<'_, T, S> {}
+
+impl<'a, T: PartialOrd + Ord, S: BucketStorage<T>> Iterator for GroupByEqual<'a, T, S> {
+    type Item = (&'a T, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let first = self.inner.next()?;
+        let mut count = 1;
+        loop {
+            let mut ahead = self.inner.clone();
+            match ahead.next() {
+                Some(item) if self.order.compare(item, first) == Ordering::Equal => {
+                    self.inner = ahead;
+                    count += 1;
+                },
+                _ => break,
+            }
+        }
+
+        Some((first, count))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.inner.len();
+        (remaining.min(1), Some(remaining))
+    }
+}
+
+/// One bucket of a container: its elements in order plus the first and
+/// last of them, so a scan can skip buckets outside the keys it wants.
+#[derive(Debug, Clone, Copy)]
+pub struct BucketView<'a, T> {
+    /// The first element in the container's order.
+    pub min: &'a T,
+    /// The last element in the container's order.
+    pub max: &'a T,
+    pub len: usize,
+    pub slice: &'a [T],
+}
+
+/// Yields a [`BucketView`] of every non-empty bucket in order. Returned by
+/// [`SortedVec::iter_buckets`].
+pub struct Buckets<'a, T: PartialOrd + Ord> {
+    inner: directory::Iter<'a, Bucket<T>>,
+}
+
+impl<'a, T: PartialOrd + Ord> Buckets<'a, T> {
+    pub(crate) fn new(sorted_vec: &'a SortedVec<T>) -> Self {
+        Buckets {
+            inner: sorted_vec.buckets.iter(),
         }
     }
+
+    fn view(bucket: &'a Bucket<T>) -> Option<BucketView<'a, T>> {
+        let slice: &'a [T] = &bucket.data;
+        Some(BucketView {
+            min: slice.first()?,
+            max: slice.last()?,
+            len: slice.len(),
+            slice,
+        })
+    }
 }
 
-impl <T: PartialOrd + Ord> IntoIterator for SortedVec<T> {
+impl<'a, T: PartialOrd + Ord> Iterator for Buckets<'a, T> {
+    type Item = BucketView<'a, T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.by_ref().find_map(Self::view)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.inner.len()))
+    }
+}
+
+impl<T: PartialOrd + Ord> DoubleEndedIterator for Buckets<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.by_ref().rev().find_map(Self::view)
+    }
+}
+
+impl<T: PartialOrd + Ord> FusedIterator for Buckets<'_, T> {}
+
+impl <T: PartialOrd + Ord, S: BucketStorage<T>> IntoIterator for SortedVec<T, S> {
     type Item = T;
     type IntoIter = std::vec::IntoIter<T>;
 
@@ -49,7 +227,26 @@ impl <T: PartialOrd + Ord> IntoIterator for SortedVec<T> {
 
 #[cfg(test)]
 mod tests {
+    use std::ops::Bound;
+
     use super::*;
+    use crate::sorted_vec::{BucketConfiguration, DuplicatePolicy};
+
+    #[test]
+    fn iter_buckets_views_every_bucket() {
+        let configuration = BucketConfiguration::builder().max_bucket_capacity(3).order(Order::Descending).build();
+        let sorted_vec = SortedVec::from_vec((0..10).collect(), configuration);
+        let views: Vec<BucketView<'_, i32>> = sorted_vec.iter_buckets().collect();
+
+        assert_eq!(views.len(), sorted_vec.buckets.len());
+        assert!(views.iter().flat_map(|view| view.slice).copied().eq((0..10).rev()));
+        assert!(views.iter().all(|view| view.len == view.slice.len() && view.min >= view.max));
+        assert_eq!(*views[0].min, 9);
+        assert_eq!(sorted_vec.iter_buckets().next_back().map(|view| *view.max), Some(0));
+
+        let empty: SortedVec<i32> = SortedVec::new(Default::default());
+        assert_eq!(empty.iter_buckets().count(), 0);
+    }
 
     #[test]
     fn test_sorted_vec_iter() {
@@ -76,6 +273,108 @@ mod tests {
         assert_eq!(iter.next(), None);
     }
 
+    #[test]
+    fn sorted_vec_iter_across_buckets_from_both_ends() {
+        let configuration = BucketConfiguration::builder().max_bucket_capacity(2).build();
+        let sorted_vec = SortedVec::from_vec((0..7).collect(), configuration);
+        let mut iter = sorted_vec.iter();
+
+        assert_eq!(iter.len(), 7);
+        assert_eq!(iter.next(), Some(&0));
+        assert_eq!(iter.next_back(), Some(&6));
+        assert_eq!(iter.len(), 5);
+        assert_eq!(iter.clone().rev().copied().collect::<Vec<_>>(), vec![5, 4, 3, 2, 1]);
+        assert_eq!(iter.copied().collect::<Vec<_>>(), vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn sorted_vec_iter_from_resumes_at_cursor() {
+        let configuration = BucketConfiguration::builder().max_bucket_capacity(2).build();
+        let sorted_vec = SortedVec::from_vec((0..10).map(|item| item * 10).collect(), configuration);
+
+        assert_eq!(sorted_vec.iter_from(&35).copied().collect::<Vec<_>>(), vec![40, 50, 60, 70, 80, 90]);
+        assert_eq!(sorted_vec.iter_from(&40).len(), 6);
+        assert_eq!(sorted_vec.iter_from(&-1).len(), 10);
+        assert_eq!(sorted_vec.iter_from(&91).next(), None);
+        assert_eq!(sorted_vec.iter_from(&50).next_back(), Some(&90));
+    }
+
+    #[test]
+    fn sorted_vec_iter_from_counts_after_updates() {
+        let configuration = BucketConfiguration::builder().max_bucket_capacity(4).build();
+        let mut sorted_vec = SortedVec::from_vec((0..2_000).collect(), configuration);
+        for item in (0..2_000).step_by(3) {
+            sorted_vec.remove(&item);
+        }
+        for item in 2_000..2_500 {
+            sorted_vec.insert(item);
+        }
+
+        let items: Vec<_> = sorted_vec.iter().copied().collect();
+        for start in (0..2_600).step_by(37) {
+            let skipped = items.partition_point(|&item| item < start);
+            assert_eq!(sorted_vec.iter_from(&start).len(), items.len() - skipped);
+            assert!(sorted_vec.iter_from(&start).eq(&items[skipped..]));
+        }
+    }
+
+    #[test]
+    fn sorted_vec_range_both_directions() {
+        let configuration = BucketConfiguration::builder().max_bucket_capacity(2).build();
+        let prices = SortedVec::from_vec(vec![120, 450, 380, 90, 399, 400, 250], configuration);
+
+        assert_eq!(prices.range(100..400).copied().collect::<Vec<_>>(), vec![120, 250, 380, 399]);
+        assert_eq!(prices.range_rev(..400).copied().collect::<Vec<_>>(), vec![399, 380, 250, 120, 90]);
+        assert_eq!(prices.range(400..=400).len(), 1);
+        assert_eq!(prices.range((Bound::Included(300), Bound::Excluded(200))).next(), None);
+
+        let mut range = prices.range(100..);
+        assert_eq!(range.next_back(), Some(&450));
+        assert_eq!(range.next(), Some(&120));
+        assert_eq!(range.len(), 4);
+    }
+
+    #[test]
+    fn sorted_vec_range_counts_after_updates() {
+        let configuration = BucketConfiguration::builder().max_bucket_capacity(4).build();
+        let mut sorted_vec = SortedVec::from_vec((0..2_000).collect(), configuration);
+        for item in (0..2_000).step_by(5) {
+            sorted_vec.remove(&item);
+        }
+
+        let items: Vec<_> = sorted_vec.iter().copied().collect();
+        for start in (0..2_000).step_by(97) {
+            let end = start + 313;
+            let expected: Vec<_> = items.iter().copied().filter(|item| (start..end).contains(item)).collect();
+            assert_eq!(sorted_vec.range(start..end).len(), expected.len());
+            assert!(sorted_vec.range_rev(start..end).eq(expected.iter().rev()));
+            assert_eq!(sorted_vec.range(end..start).len(), 0);
+        }
+    }
+
+    #[test]
+    fn sorted_vec_enumerate_global() {
+        let configuration = BucketConfiguration::builder().max_bucket_capacity(2).build();
+        let sorted_vec = SortedVec::from_vec(vec![30, 10, 20, 40, 50], configuration);
+
+        let ranked: Vec<_> = sorted_vec.enumerate_global().collect();
+        assert_eq!(ranked, vec![(0, &10), (1, &20), (2, &30), (3, &40), (4, &50)]);
+        assert_eq!(sorted_vec.enumerate_global().next_back(), Some((4, &50)));
+    }
+
+    #[test]
+    fn sorted_vec_group_by_equal_across_buckets() {
+        let configuration = BucketConfiguration::builder()
+            .max_bucket_capacity(2)
+            .duplicate_policy(DuplicatePolicy::Allow)
+            .build();
+        let sorted_vec = SortedVec::from_vec(vec![3, 1, 3, 2, 3, 1, 3, 5], configuration);
+
+        let groups: Vec<_> = sorted_vec.group_by_equal().collect();
+        assert_eq!(groups, vec![(&1, 2), (&2, 1), (&3, 4), (&5, 1)]);
+        assert_eq!(SortedVec::<i32>::new(Default::default()).group_by_equal().next(), None);
+    }
+
     #[test]
     fn test_sorted_vec_into_iter() {
         let mut sorted_vec = SortedVec::new(Default::default());
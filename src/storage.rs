@@ -0,0 +1,313 @@
+#[cfg(feature = "arena")]
+mod arena;
+mod delta_packed;
+mod gap_buffer;
+
+#[cfg(feature = "arena")]
+pub use arena::{Arena, ArenaVec};
+pub use delta_packed::{DeltaPacked, PackedInt};
+pub use gap_buffer::GapBuffer;
+
+use std::collections::VecDeque;
+use std::iter::Chain;
+use std::ops::Range;
+use std::slice::Iter;
+
+/// Where a bucket keeps its elements. The search, split and iteration logic
+/// only goes through this trait, so other layouts can be plugged in by
+/// implementing it. Elements are always kept in sorted order; the container
+/// decides where they go.
+pub trait BucketStorage<T>: Default {
+    fn from_vec(items: Vec<T>) -> Self;
+
+    /// Like [`from_vec`](Self::from_vec), but the new storage draws from the
+    /// same resources as `self`, such as the same arena.
+    fn new_like(&self, items: Vec<T>) -> Self {
+        Self::from_vec(items)
+    }
+
+    fn into_vec(self) -> Vec<T>;
+
+    fn len(&self) -> usize;
+
+    /// How many elements fit without reallocating, used to report memory.
+    fn capacity(&self) -> usize {
+        self.len()
+    }
+
+    /// The elements in order, as at most two contiguous slices. Contiguous
+    /// storages return an empty second slice.
+    fn as_slices(&self) -> (&[T], &[T]);
+
+    fn insert(&mut self, idx: usize, item: T);
+
+    fn remove(&mut self, idx: usize) -> T;
+
+    /// Moves the elements from `at` onwards into a new storage.
+    fn split_off(&mut self, at: usize) -> Self;
+
+    /// Drops the elements in `range` and returns how many were dropped.
+    fn remove_range(&mut self, range: Range<usize>) -> usize;
+
+    fn retain(&mut self, f: impl FnMut(&T) -> bool);
+
+    fn push(&mut self, item: T) {
+        self.insert(self.len(), item);
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn get(&self, idx: usize) -> Option<&T> {
+        let (front, back) = self.as_slices();
+        match front.get(idx) {
+            Some(item) => Some(item),
+            None => back.get(idx - front.len()),
+        }
+    }
+
+    fn first(&self) -> Option<&T> {
+        self.get(0)
+    }
+
+    fn last(&self) -> Option<&T> {
+        self.get(self.len().checked_sub(1)?)
+    }
+
+    fn iter(&self) -> Chain<Iter<'_, T>, Iter<'_, T>> {
+        let (front, back) = self.as_slices();
+        front.iter().chain(back.iter())
+    }
+
+    /// Like `slice::partition_point` across both slices.
+    fn partition_point(&self, mut pred: impl FnMut(&T) -> bool) -> usize {
+        let (front, back) = self.as_slices();
+        if front.last().is_none_or(&mut pred) {
+            front.len() + back.partition_point(pred)
+        } else {
+            front.partition_point(pred)
+        }
+    }
+
+    /// Like `slice::binary_search_by` across both slices.
+    fn binary_search_by(&self, mut f: impl FnMut(&T) -> std::cmp::Ordering) -> Result<usize, usize> {
+        let (front, back) = self.as_slices();
+        if front.last().is_none_or(|last| f(last) == std::cmp::Ordering::Less) {
+            match back.binary_search_by(f) {
+                Ok(idx) => Ok(front.len() + idx),
+                Err(idx) => Err(front.len() + idx),
+            }
+        } else {
+            front.binary_search_by(f)
+        }
+    }
+}
+
+impl<T> BucketStorage<T> for Vec<T> {
+    fn from_vec(items: Vec<T>) -> Self {
+        items
+    }
+
+    fn into_vec(self) -> Vec<T> {
+        self
+    }
+
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+
+    fn capacity(&self) -> usize {
+        Vec::capacity(self)
+    }
+
+    fn as_slices(&self) -> (&[T], &[T]) {
+        (self.as_slice(), &[])
+    }
+
+    fn insert(&mut self, idx: usize, item: T) {
+        Vec::insert(self, idx, item)
+    }
+
+    fn remove(&mut self, idx: usize) -> T {
+        Vec::remove(self, idx)
+    }
+
+    fn split_off(&mut self, at: usize) -> Self {
+        Vec::split_off(self, at)
+    }
+
+    fn remove_range(&mut self, range: Range<usize>) -> usize {
+        self.drain(range).len()
+    }
+
+    fn retain(&mut self, f: impl FnMut(&T) -> bool) {
+        Vec::retain(self, f)
+    }
+
+    fn push(&mut self, item: T) {
+        Vec::push(self, item)
+    }
+}
+
+/// Ring buffer buckets: inserts shift whichever side of the bucket is shorter,
+/// so inserting at either end is O(1).
+impl<T> BucketStorage<T> for VecDeque<T> {
+    fn from_vec(items: Vec<T>) -> Self {
+        VecDeque::from(items)
+    }
+
+    fn into_vec(self) -> Vec<T> {
+        Vec::from(self)
+    }
+
+    fn len(&self) -> usize {
+        VecDeque::len(self)
+    }
+
+    fn capacity(&self) -> usize {
+        VecDeque::capacity(self)
+    }
+
+    fn as_slices(&self) -> (&[T], &[T]) {
+        VecDeque::as_slices(self)
+    }
+
+    fn insert(&mut self, idx: usize, item: T) {
+        VecDeque::insert(self, idx, item)
+    }
+
+    fn remove(&mut self, idx: usize) -> T {
+        VecDeque::remove(self, idx).expect("index out of bounds")
+    }
+
+    fn split_off(&mut self, at: usize) -> Self {
+        VecDeque::split_off(self, at)
+    }
+
+    fn remove_range(&mut self, range: Range<usize>) -> usize {
+        self.drain(range).len()
+    }
+
+    fn retain(&mut self, f: impl FnMut(&T) -> bool) {
+        VecDeque::retain(self, f)
+    }
+
+    fn push(&mut self, item: T) {
+        self.push_back(item)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sorted_vec::{BucketConfiguration, SortedVec};
+
+    /// Keeps the elements split in two halves to exercise the two-slice paths.
+    #[derive(Default, Debug)]
+    struct Halves(Vec<i32>, Vec<i32>);
+
+    impl BucketStorage<i32> for Halves {
+        fn from_vec(mut items: Vec<i32>) -> Self {
+            let back = items.split_off(items.len() / 2);
+            Halves(items, back)
+        }
+
+        fn into_vec(mut self) -> Vec<i32> {
+            self.0.append(&mut self.1);
+            self.0
+        }
+
+        fn len(&self) -> usize {
+            self.0.len() + self.1.len()
+        }
+
+        fn as_slices(&self) -> (&[i32], &[i32]) {
+            (&self.0, &self.1)
+        }
+
+        fn insert(&mut self, idx: usize, item: i32) {
+            let mut items = std::mem::take(self).into_vec();
+            items.insert(idx, item);
+            *self = Self::from_vec(items);
+        }
+
+        fn remove(&mut self, idx: usize) -> i32 {
+            let mut items = std::mem::take(self).into_vec();
+            let item = items.remove(idx);
+            *self = Self::from_vec(items);
+            item
+        }
+
+        fn split_off(&mut self, at: usize) -> Self {
+            let mut items = std::mem::take(self).into_vec();
+            let other = items.split_off(at);
+            *self = Self::from_vec(items);
+            Self::from_vec(other)
+        }
+
+        fn remove_range(&mut self, range: Range<usize>) -> usize {
+            let mut items = std::mem::take(self).into_vec();
+            let removed = items.drain(range).len();
+            *self = Self::from_vec(items);
+            removed
+        }
+
+        fn retain(&mut self, f: impl FnMut(&i32) -> bool) {
+            let mut items = std::mem::take(self).into_vec();
+            items.retain(f);
+            *self = Self::from_vec(items);
+        }
+    }
+
+    #[test]
+    fn storage_provided_methods_span_both_slices() {
+        let storage = Halves::from_vec(vec![1, 3, 5, 7, 9]);
+
+        assert_eq!(storage.as_slices(), (&[1, 3][..], &[5, 7, 9][..]));
+        assert_eq!(storage.get(3), Some(&7));
+        assert_eq!(storage.first(), Some(&1));
+        assert_eq!(storage.last(), Some(&9));
+        assert_eq!(storage.partition_point(|item| *item < 6), 3);
+        assert_eq!(storage.binary_search_by(|item| item.cmp(&7)), Ok(3));
+        assert_eq!(storage.binary_search_by(|item| item.cmp(&2)), Err(1));
+        assert_eq!(storage.binary_search_by(|item| item.cmp(&10)), Err(5));
+
+        let storage = Halves::from_vec(vec![4]);
+        assert_eq!(storage.as_slices(), (&[][..], &[4][..]));
+        assert_eq!(storage.binary_search_by(|item| item.cmp(&4)), Ok(0));
+        assert_eq!(storage.partition_point(|item| *item < 5), 1);
+    }
+
+    #[test]
+    fn sorted_vec_with_custom_storage() {
+        let config = BucketConfiguration::builder().max_bucket_capacity(4).build();
+        let mut sorted_vec: SortedVec<i32, Halves> = SortedVec::with_storage(config);
+        for item in [5, 1, 9, 3, 7, 2, 8, 4, 6, 0] {
+            sorted_vec.insert(item);
+        }
+        sorted_vec.remove(&4);
+        sorted_vec.remove_range(7..);
+        sorted_vec.insert_many(vec![11, 10]);
+
+        assert_eq!(sorted_vec.at(3), Some(&3));
+        assert!(sorted_vec.find_index(&6).is_some());
+        assert_eq!(sorted_vec.into_iter().collect::<Vec<_>>(), vec![0, 1, 2, 3, 5, 6, 10, 11]);
+    }
+
+    #[test]
+    fn sorted_vec_with_vec_deque_storage() {
+        let config = BucketConfiguration::builder().max_bucket_capacity(8).build();
+        let mut sorted_vec: SortedVec<i32, VecDeque<i32>> = SortedVec::with_storage(config);
+        for item in (0..20).rev() {
+            sorted_vec.insert(item);
+        }
+        sorted_vec.remove(&10);
+        sorted_vec.remove_range(..3);
+
+        assert_eq!(sorted_vec.find_index(&11).map(|found| found.bucket_idx()), sorted_vec.find_index(&12).map(|found| found.bucket_idx()));
+        assert_eq!(sorted_vec.at(7), Some(&11));
+        assert_eq!(sorted_vec.last(), Some(&19));
+        assert_eq!(sorted_vec.into_iter().collect::<Vec<_>>(), (3..20).filter(|item| *item != 10).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,311 @@
+use std::cell::{Cell, RefCell};
+use std::fmt::{self, Debug, Formatter};
+use std::mem::MaybeUninit;
+use std::ops::Range;
+use std::slice;
+
+use super::BucketStorage;
+use crate::sorted_vec::{BucketConfiguration, SortedVec};
+
+/// A bump allocator handing out slots for [`ArenaVec`] buckets. Nothing is
+/// freed until the arena itself is dropped, which releases every chunk at
+/// once without visiting the elements.
+pub struct Arena<T> {
+    /// Chunks obtained from `Box::into_raw`, freed in `Drop`.
+    chunks: RefCell<Vec<*mut [MaybeUninit<T>]>>,
+    /// Slots already handed out from the last chunk.
+    used: Cell<usize>,
+    chunk_capacity: usize,
+}
+
+impl<T> Arena<T> {
+    pub fn new() -> Self {
+        Self::with_chunk_capacity(1024)
+    }
+
+    /// An arena that allocates its memory `chunk_capacity` elements at a time.
+    pub fn with_chunk_capacity(chunk_capacity: usize) -> Self {
+        Arena {
+            chunks: RefCell::new(Vec::new()),
+            used: Cell::new(0),
+            chunk_capacity: chunk_capacity.max(1),
+        }
+    }
+
+    /// How many element slots the arena has reserved so far.
+    pub fn allocated(&self) -> usize {
+        self.chunks.borrow().iter().map(|chunk| chunk.len()).sum()
+    }
+
+    // Every call hands out a fresh range that is never given out again, so
+    // the returned slices never alias.
+    #[allow(clippy::mut_from_ref)]
+    fn alloc(&self, len: usize) -> &mut [MaybeUninit<T>] {
+        if len == 0 {
+            return &mut [];
+        }
+
+        let mut chunks = self.chunks.borrow_mut();
+        let fits = chunks.last().is_some_and(|chunk| chunk.len() - self.used.get() >= len);
+        if !fits {
+            let chunk: Box<[MaybeUninit<T>]> = (0..len.max(self.chunk_capacity)).map(|_| MaybeUninit::uninit()).collect();
+            chunks.push(Box::into_raw(chunk));
+            self.used.set(0);
+        }
+
+        let start = self.used.get();
+        self.used.set(start + len);
+        let base = *chunks.last().expect("a chunk was just ensured") as *mut MaybeUninit<T>;
+        // SAFETY: `start..start + len` lies inside the chunk, which stays
+        // allocated until the arena is dropped, and no other slice covers it.
+        unsafe { slice::from_raw_parts_mut(base.add(start), len) }
+    }
+}
+
+impl<T> Default for Arena<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for Arena<T> {
+    fn drop(&mut self) {
+        for chunk in self.chunks.get_mut().drain(..) {
+            // SAFETY: the chunk came from `Box::into_raw` and no slice handed
+            // out from it can outlive the arena.
+            drop(unsafe { Box::from_raw(chunk) });
+        }
+    }
+}
+
+impl<T> Debug for Arena<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Arena").field("allocated", &self.allocated()).finish()
+    }
+}
+
+enum Slots<'a, T> {
+    Arena(&'a mut [MaybeUninit<T>]),
+    Heap(Vec<MaybeUninit<T>>),
+}
+
+/// Bucket storage for `Copy` elements living in an [`Arena`]. Growing a bucket
+/// moves it to a larger block of the arena and leaves the old one behind.
+/// Storages created without an arena, e.g. through `Default`, use the heap,
+/// and storages built from an arena-backed one use the same arena.
+pub struct ArenaVec<'a, T> {
+    arena: Option<&'a Arena<T>>,
+    /// The first `len` slots are initialized.
+    slots: Slots<'a, T>,
+    len: usize,
+}
+
+impl<'a, T: Copy> ArenaVec<'a, T> {
+    pub fn new_in(arena: &'a Arena<T>) -> Self {
+        ArenaVec {
+            arena: Some(arena),
+            slots: Slots::Arena(&mut []),
+            len: 0,
+        }
+    }
+
+    fn with_capacity_like(&self, capacity: usize) -> Self {
+        let slots = match self.arena {
+            Some(arena) => Slots::Arena(arena.alloc(capacity)),
+            None => Slots::Heap(vec![MaybeUninit::uninit(); capacity]),
+        };
+
+        ArenaVec {
+            arena: self.arena,
+            slots,
+            len: 0,
+        }
+    }
+
+    fn slots(&self) -> &[MaybeUninit<T>] {
+        match &self.slots {
+            Slots::Arena(slots) => slots,
+            Slots::Heap(slots) => slots,
+        }
+    }
+
+    fn slots_mut(&mut self) -> &mut [MaybeUninit<T>] {
+        match &mut self.slots {
+            Slots::Arena(slots) => slots,
+            Slots::Heap(slots) => slots,
+        }
+    }
+
+    fn extend_from(&mut self, items: &[T]) {
+        let len = self.len;
+        for (slot, item) in self.slots_mut()[len..len + items.len()].iter_mut().zip(items) {
+            *slot = MaybeUninit::new(*item);
+        }
+        self.len += items.len();
+    }
+
+    fn grow(&mut self) {
+        let mut grown = self.with_capacity_like((self.slots().len() * 2).max(4));
+        grown.extend_from(self.as_slices().0);
+        self.slots = grown.slots;
+    }
+}
+
+impl<T: Copy> Default for ArenaVec<'_, T> {
+    fn default() -> Self {
+        ArenaVec {
+            arena: None,
+            slots: Slots::Heap(Vec::new()),
+            len: 0,
+        }
+    }
+}
+
+impl<T: Copy> BucketStorage<T> for ArenaVec<'_, T> {
+    fn from_vec(items: Vec<T>) -> Self {
+        let len = items.len();
+        ArenaVec {
+            arena: None,
+            slots: Slots::Heap(items.into_iter().map(MaybeUninit::new).collect()),
+            len,
+        }
+    }
+
+    fn new_like(&self, items: Vec<T>) -> Self {
+        let mut result = self.with_capacity_like(items.len());
+        result.extend_from(&items);
+        result
+    }
+
+    fn into_vec(self) -> Vec<T> {
+        self.as_slices().0.to_vec()
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn as_slices(&self) -> (&[T], &[T]) {
+        let base = self.slots().as_ptr() as *const T;
+        // SAFETY: the first `len` slots are initialized.
+        (unsafe { slice::from_raw_parts(base, self.len) }, &[])
+    }
+
+    fn insert(&mut self, idx: usize, item: T) {
+        assert!(idx <= self.len, "insertion index {idx} out of bounds");
+        if self.len == self.slots().len() {
+            self.grow();
+        }
+
+        let len = self.len;
+        let slots = self.slots_mut();
+        slots.copy_within(idx..len, idx + 1);
+        slots[idx] = MaybeUninit::new(item);
+        self.len += 1;
+    }
+
+    fn remove(&mut self, idx: usize) -> T {
+        assert!(idx < self.len, "removal index {idx} out of bounds");
+        let item = self.as_slices().0[idx];
+        let len = self.len;
+        self.slots_mut().copy_within(idx + 1..len, idx);
+        self.len -= 1;
+
+        item
+    }
+
+    fn split_off(&mut self, at: usize) -> Self {
+        assert!(at <= self.len, "split index {at} out of bounds");
+        let mut other = self.with_capacity_like(self.len - at);
+        other.extend_from(&self.as_slices().0[at..]);
+        self.len = at;
+
+        other
+    }
+
+    fn remove_range(&mut self, range: Range<usize>) -> usize {
+        assert!(range.start <= range.end && range.end <= self.len, "range {range:?} out of bounds");
+        let len = self.len;
+        self.slots_mut().copy_within(range.end..len, range.start);
+        self.len -= range.len();
+
+        range.len()
+    }
+
+    fn retain(&mut self, mut f: impl FnMut(&T) -> bool) {
+        let mut kept = 0;
+        for idx in 0..self.len {
+            let item = self.as_slices().0[idx];
+            if f(&item) {
+                self.slots_mut()[kept] = MaybeUninit::new(item);
+                kept += 1;
+            }
+        }
+        self.len = kept;
+    }
+}
+
+impl<T: Copy + Debug> Debug for ArenaVec<'_, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<'a, T: PartialOrd + Ord + Copy> SortedVec<T, ArenaVec<'a, T>> {
+    /// An empty container whose buckets are allocated from `arena`. Dropping
+    /// the arena afterwards frees all of them at once.
+    pub fn new_in(arena: &'a Arena<T>, configuration: BucketConfiguration) -> Self {
+        let mut result = Self::with_storage(configuration);
+        *result.buckets[0].data = ArenaVec::new_in(arena);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::container::tests::{vec_steps, VecStep};
+
+    #[test]
+    fn arena_vec_matches_vec() {
+        let arena = Arena::with_chunk_capacity(16);
+        let mut model = Vec::new();
+        let mut arena_vec = ArenaVec::new_in(&arena);
+        for step in vec_steps(17, 300, 3) {
+            match step {
+                VecStep::Insert(at, item) => {
+                    arena_vec.insert(at, item);
+                    model.insert(at, item);
+                },
+                VecStep::Remove(at) => assert_eq!(arena_vec.remove(at), model.remove(at)),
+            }
+        }
+
+        let tail = arena_vec.split_off(model.len() / 2);
+        let model_tail = model.split_off(model.len() / 2);
+        assert_eq!(tail.into_vec(), model_tail);
+        assert_eq!(arena_vec.remove_range(2..5), model.drain(2..5).len());
+        arena_vec.retain(|item| item % 2 == 0);
+        model.retain(|item| item % 2 == 0);
+        assert_eq!(arena_vec.into_vec(), model);
+        assert!(arena.allocated() >= 300);
+    }
+
+    #[test]
+    fn sorted_vec_in_arena() {
+        let arena = Arena::new();
+        let config = BucketConfiguration::builder().max_bucket_capacity(32).build();
+        let mut sorted_vec = SortedVec::new_in(&arena, config);
+        for item in (0..500u32).map(|i| (i * 37) % 500) {
+            sorted_vec.insert(item);
+        }
+        sorted_vec.insert_many(500..600);
+        for item in (0..600).step_by(3) {
+            sorted_vec.remove(&item);
+        }
+
+        assert!(sorted_vec.iter().copied().eq((0..600).filter(|item| item % 3 != 0)));
+        assert!(sorted_vec.buckets.len() > 1);
+        assert!(sorted_vec.buckets.iter().all(|bucket| bucket.data.arena.is_some()));
+    }
+}
@@ -0,0 +1,265 @@
+use std::fmt::{self, Debug, Formatter};
+use std::ops::Range;
+use std::sync::OnceLock;
+
+use super::BucketStorage;
+use crate::sorted_vec::SortedVec;
+
+/// Integers that [`DeltaPacked`] can compress.
+pub trait PackedInt: Copy {
+    fn to_bits(self) -> u64;
+
+    fn from_bits(bits: u64) -> Self;
+}
+
+impl PackedInt for u32 {
+    fn to_bits(self) -> u64 {
+        self as u64
+    }
+
+    fn from_bits(bits: u64) -> Self {
+        bits as u32
+    }
+}
+
+impl PackedInt for u64 {
+    fn to_bits(self) -> u64 {
+        self
+    }
+
+    fn from_bits(bits: u64) -> Self {
+        bits
+    }
+}
+
+/// Bucket storage keeping integers as bit-packed deltas between neighbours,
+/// so dense sorted ids take a few bits each. The first and last elements are
+/// kept unpacked, which lets the fence index skip a bucket without decoding
+/// it. A bucket is decoded the first time its elements are read and the
+/// decoded copy is kept until [`release`](DeltaPacked::release) is called.
+pub struct DeltaPacked<T> {
+    len: usize,
+    bounds: Option<(T, T)>,
+    /// Bits per delta; deltas are zigzag encoded so descending order packs
+    /// just as well.
+    width: u32,
+    words: Vec<u64>,
+    decoded: OnceLock<Vec<T>>,
+}
+
+impl<T: PackedInt> DeltaPacked<T> {
+    /// Heap bytes used by the packed deltas, not counting a decoded copy.
+    pub fn packed_bytes(&self) -> usize {
+        self.words.len() * size_of::<u64>()
+    }
+
+    /// Drops the decoded copy of the bucket, if any.
+    pub fn release(&mut self) {
+        self.decoded = OnceLock::new();
+    }
+
+    fn encode(items: Vec<T>) -> Self {
+        let deltas: Vec<u64> = items
+            .windows(2)
+            .map(|pair| zigzag(pair[1].to_bits().wrapping_sub(pair[0].to_bits())))
+            .collect();
+        let width = deltas.iter().map(|delta| u64::BITS - delta.leading_zeros()).max().unwrap_or(0);
+        let mut words = vec![0; (deltas.len() * width as usize).div_ceil(64)];
+        for (idx, delta) in deltas.into_iter().enumerate() {
+            let bit = idx * width as usize;
+            let (word, offset) = (bit / 64, (bit % 64) as u32);
+            words[word] |= delta << offset;
+            if offset + width > 64 {
+                words[word + 1] |= delta >> (64 - offset);
+            }
+        }
+
+        DeltaPacked {
+            len: items.len(),
+            bounds: items.first().zip(items.last()).map(|(first, last)| (*first, *last)),
+            width,
+            words,
+            decoded: OnceLock::from(items),
+        }
+    }
+
+    fn decode(&self) -> Vec<T> {
+        let Some((first, _)) = self.bounds else {
+            return Vec::new();
+        };
+
+        let mask = if self.width == 64 { u64::MAX } else { (1 << self.width) - 1 };
+        let mut current = first.to_bits();
+        let mut items = Vec::with_capacity(self.len);
+        items.push(first);
+        for idx in 0..self.len - 1 {
+            let bit = idx * self.width as usize;
+            let (word, offset) = (bit / 64, (bit % 64) as u32);
+            let mut delta = self.words.get(word).map_or(0, |word| word >> offset);
+            if offset + self.width > 64 {
+                delta |= self.words[word + 1] << (64 - offset);
+            }
+            current = current.wrapping_add(unzigzag(delta & mask));
+            items.push(T::from_bits(current));
+        }
+
+        items
+    }
+
+    fn decoded(&self) -> &[T] {
+        self.decoded.get_or_init(|| self.decode())
+    }
+
+    /// Decodes the bucket, applies `f` and packs the result again.
+    fn edit<R>(&mut self, f: impl FnOnce(&mut Vec<T>) -> R) -> R {
+        let mut items = self.decoded.take().unwrap_or_else(|| self.decode());
+        let result = f(&mut items);
+        *self = Self::encode(items);
+
+        result
+    }
+}
+
+fn zigzag(delta: u64) -> u64 {
+    (delta << 1) ^ ((delta as i64 >> 63) as u64)
+}
+
+fn unzigzag(bits: u64) -> u64 {
+    (bits >> 1) ^ (bits & 1).wrapping_neg()
+}
+
+impl<T: PackedInt> Default for DeltaPacked<T> {
+    fn default() -> Self {
+        Self::encode(Vec::new())
+    }
+}
+
+impl<T: PackedInt> BucketStorage<T> for DeltaPacked<T> {
+    fn from_vec(items: Vec<T>) -> Self {
+        let mut result = Self::encode(items);
+        result.release();
+        result
+    }
+
+    fn into_vec(mut self) -> Vec<T> {
+        self.decoded.take().unwrap_or_else(|| self.decode())
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn as_slices(&self) -> (&[T], &[T]) {
+        (self.decoded(), &[])
+    }
+
+    fn insert(&mut self, idx: usize, item: T) {
+        self.edit(|items| items.insert(idx, item))
+    }
+
+    fn remove(&mut self, idx: usize) -> T {
+        self.edit(|items| items.remove(idx))
+    }
+
+    fn split_off(&mut self, at: usize) -> Self {
+        let tail = self.edit(|items| items.split_off(at));
+        Self::encode(tail)
+    }
+
+    fn remove_range(&mut self, range: Range<usize>) -> usize {
+        self.edit(|items| items.drain(range).len())
+    }
+
+    fn retain(&mut self, f: impl FnMut(&T) -> bool) {
+        self.edit(|items| items.retain(f))
+    }
+
+    fn first(&self) -> Option<&T> {
+        self.bounds.as_ref().map(|(first, _)| first)
+    }
+
+    fn last(&self) -> Option<&T> {
+        self.bounds.as_ref().map(|(_, last)| last)
+    }
+}
+
+impl<T: PackedInt + Debug> Debug for DeltaPacked<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: PackedInt + PartialOrd + Ord> SortedVec<T, DeltaPacked<T>> {
+    /// Drops the decoded copies of all buckets, leaving only the packed form.
+    pub fn release_decoded(&mut self) {
+        for bucket in &mut self.buckets {
+            bucket.data.release();
+        }
+    }
+
+    /// Heap bytes used by the packed buckets.
+    pub fn packed_bytes(&self) -> usize {
+        self.buckets.iter().map(|bucket| bucket.data.packed_bytes()).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sorted_vec::{BucketConfiguration, Order};
+
+    #[test]
+    fn delta_packed_round_trips() {
+        let cases: Vec<Vec<u64>> = vec![
+            vec![],
+            vec![7],
+            vec![1, 2, 3, 10, 1_000, 1_000, 5_000_000],
+            vec![0, u64::MAX],
+            vec![u64::MAX, 3, 2, 0],
+        ];
+        for items in cases {
+            let packed = DeltaPacked::from_vec(items.clone());
+            assert_eq!(packed.first(), items.first());
+            assert_eq!(packed.last(), items.last());
+            assert_eq!(packed.into_vec(), items);
+        }
+    }
+
+    #[test]
+    fn delta_packed_edits() {
+        let mut packed = DeltaPacked::from_vec(vec![10u32, 20, 30, 40, 50]);
+        packed.insert(2, 25);
+        assert_eq!(packed.remove(0), 10);
+        let tail = packed.split_off(3);
+        packed.retain(|item| *item != 25);
+
+        assert_eq!(packed.into_vec(), vec![20, 30]);
+        assert_eq!(tail.into_vec(), vec![40, 50]);
+    }
+
+    #[test]
+    fn sorted_vec_with_delta_packed_storage() {
+        let config = BucketConfiguration::builder().max_bucket_capacity(128).build();
+        let mut sorted_vec: SortedVec<u32, DeltaPacked<u32>> = SortedVec::with_storage(config);
+        sorted_vec.insert_many((0..2_000).map(|i| i * 3));
+        sorted_vec.remove(&300);
+        sorted_vec.release_decoded();
+
+        assert!(sorted_vec.packed_bytes() < 2_000);
+        assert_eq!(sorted_vec.size, 1_999);
+        assert!(sorted_vec.find_index(&303).is_some());
+        assert!(sorted_vec.find_index(&300).is_none());
+        assert!(sorted_vec.iter().copied().eq((0..2_000).map(|i| i * 3).filter(|item| *item != 300)));
+    }
+
+    #[test]
+    fn sorted_vec_descending_delta_packed() {
+        let config = BucketConfiguration::builder().order(Order::Descending).build();
+        let mut sorted_vec: SortedVec<u64, DeltaPacked<u64>> = SortedVec::with_storage(config);
+        for item in [5, 1, u64::MAX, 9] {
+            sorted_vec.insert(item);
+        }
+
+        assert_eq!(sorted_vec.iter().copied().collect::<Vec<_>>(), vec![u64::MAX, 9, 5, 1]);
+    }
+}
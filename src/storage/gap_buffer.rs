@@ -0,0 +1,232 @@
+use std::fmt::{self, Debug, Formatter};
+use std::mem::{self, MaybeUninit};
+use std::ops::Range;
+use std::{ptr, slice};
+
+use super::BucketStorage;
+
+/// Bucket storage with a movable gap of free slots. Inserts and removals
+/// happen at the gap, which is first moved to the target position, so the
+/// cost is proportional to the distance from the previous edit rather than
+/// to the elements after the position. Clustered inserts into large buckets
+/// become close to O(1).
+pub struct GapBuffer<T> {
+    /// Slots `[0, gap_start)` and `[gap_end, buf.len())` are initialized.
+    buf: Vec<MaybeUninit<T>>,
+    gap_start: usize,
+    gap_end: usize,
+}
+
+impl<T> GapBuffer<T> {
+    fn gap_len(&self) -> usize {
+        self.gap_end - self.gap_start
+    }
+
+    /// Moves the gap so that it starts at element `to`.
+    fn move_gap(&mut self, to: usize) {
+        let base = self.buf.as_mut_ptr();
+        // SAFETY: both ranges lie inside `buf` and `ptr::copy` allows overlap.
+        // Afterwards the moved-from slots are treated as part of the gap.
+        unsafe {
+            if to < self.gap_start {
+                let count = self.gap_start - to;
+                ptr::copy(base.add(to), base.add(self.gap_end - count), count);
+                self.gap_start -= count;
+                self.gap_end -= count;
+            } else if to > self.gap_start {
+                let count = to - self.gap_start;
+                ptr::copy(base.add(self.gap_end), base.add(self.gap_start), count);
+                self.gap_start += count;
+                self.gap_end += count;
+            }
+        }
+    }
+
+    fn grow(&mut self) {
+        let len = self.len();
+        self.move_gap(len);
+        let capacity = (self.buf.len() * 2).max(4);
+        self.buf.resize_with(capacity, MaybeUninit::uninit);
+        self.gap_end = capacity;
+    }
+}
+
+impl<T> Default for GapBuffer<T> {
+    fn default() -> Self {
+        GapBuffer {
+            buf: Vec::new(),
+            gap_start: 0,
+            gap_end: 0,
+        }
+    }
+}
+
+impl<T> BucketStorage<T> for GapBuffer<T> {
+    fn from_vec(items: Vec<T>) -> Self {
+        let len = items.len();
+        GapBuffer {
+            buf: items.into_iter().map(MaybeUninit::new).collect(),
+            gap_start: len,
+            gap_end: len,
+        }
+    }
+
+    fn into_vec(mut self) -> Vec<T> {
+        let len = self.len();
+        self.move_gap(len);
+        let mut buf = mem::take(&mut self.buf);
+        self.gap_start = 0;
+        self.gap_end = 0;
+        buf.truncate(len);
+        // SAFETY: after moving the gap to the end the first `len` slots are
+        // the initialized elements, and `self` no longer owns them.
+        buf.into_iter().map(|item| unsafe { item.assume_init() }).collect()
+    }
+
+    fn len(&self) -> usize {
+        self.buf.len() - self.gap_len()
+    }
+
+    fn as_slices(&self) -> (&[T], &[T]) {
+        let base = self.buf.as_ptr() as *const T;
+        // SAFETY: both ranges hold initialized elements.
+        unsafe {
+            (
+                slice::from_raw_parts(base, self.gap_start),
+                slice::from_raw_parts(base.add(self.gap_end), self.buf.len() - self.gap_end),
+            )
+        }
+    }
+
+    fn insert(&mut self, idx: usize, item: T) {
+        assert!(idx <= self.len(), "insertion index {idx} out of bounds");
+        if self.gap_len() == 0 {
+            self.grow();
+        }
+
+        self.move_gap(idx);
+        self.buf[self.gap_start].write(item);
+        self.gap_start += 1;
+    }
+
+    fn remove(&mut self, idx: usize) -> T {
+        assert!(idx < self.len(), "removal index {idx} out of bounds");
+        self.move_gap(idx + 1);
+        self.gap_start -= 1;
+        // SAFETY: the slot was initialized and is now part of the gap.
+        unsafe { self.buf[self.gap_start].assume_init_read() }
+    }
+
+    fn split_off(&mut self, at: usize) -> Self {
+        let len = self.len();
+        assert!(at <= len, "split index {at} out of bounds");
+        self.move_gap(len);
+        let tail = (at..len)
+            // SAFETY: the slots hold initialized elements which become part
+            // of the gap once `gap_start` is lowered below.
+            .map(|idx| unsafe { self.buf[idx].assume_init_read() })
+            .collect();
+        self.gap_start = at;
+
+        Self::from_vec(tail)
+    }
+
+    fn remove_range(&mut self, range: Range<usize>) -> usize {
+        assert!(range.start <= range.end && range.end <= self.len(), "range {range:?} out of bounds");
+        self.move_gap(range.end);
+        self.gap_start = range.start;
+        for slot in &mut self.buf[range.clone()] {
+            // SAFETY: the slots were initialized and already belong to the gap,
+            // so a panicking destructor leaks the rest instead of double dropping.
+            unsafe { slot.assume_init_drop() };
+        }
+
+        range.len()
+    }
+
+    fn retain(&mut self, f: impl FnMut(&T) -> bool) {
+        let mut items = mem::take(self).into_vec();
+        items.retain(f);
+        *self = Self::from_vec(items);
+    }
+}
+
+impl<T> Drop for GapBuffer<T> {
+    fn drop(&mut self) {
+        let base = self.buf.as_mut_ptr() as *mut T;
+        // SAFETY: both ranges hold initialized elements, dropped exactly once.
+        unsafe {
+            ptr::drop_in_place(ptr::slice_from_raw_parts_mut(base, self.gap_start));
+            ptr::drop_in_place(ptr::slice_from_raw_parts_mut(base.add(self.gap_end), self.buf.len() - self.gap_end));
+        }
+    }
+}
+
+impl<T: Debug> Debug for GapBuffer<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::container::tests::{vec_steps, VecStep};
+    use crate::sorted_vec::{BucketConfiguration, SortedVec};
+
+    #[test]
+    fn gap_buffer_matches_vec() {
+        let mut model = Vec::new();
+        let mut gap_buffer = GapBuffer::default();
+        for step in vec_steps(17, 500, 3) {
+            match step {
+                VecStep::Insert(at, item) => {
+                    gap_buffer.insert(at, item);
+                    model.insert(at, item);
+                },
+                VecStep::Remove(at) => assert_eq!(gap_buffer.remove(at), model.remove(at)),
+            }
+            assert!(gap_buffer.iter().eq(model.iter()));
+        }
+
+        let tail = gap_buffer.split_off(model.len() / 2);
+        let model_tail = model.split_off(model.len() / 2);
+        assert_eq!(tail.into_vec(), model_tail);
+        assert_eq!(gap_buffer.remove_range(2..5), model.drain(2..5).len());
+        assert_eq!(gap_buffer.into_vec(), model);
+    }
+
+    #[test]
+    fn gap_buffer_drops_every_element_once() {
+        let counter = Rc::new(());
+        let mut gap_buffer = GapBuffer::from_vec(vec![counter.clone(); 4]);
+        gap_buffer.insert(2, counter.clone());
+        gap_buffer.insert(0, counter.clone());
+        drop(gap_buffer.remove(3));
+        gap_buffer.remove_range(0..2);
+        let tail = gap_buffer.split_off(1);
+        assert_eq!(Rc::strong_count(&counter), 4);
+
+        drop(tail);
+        drop(gap_buffer);
+        assert_eq!(Rc::strong_count(&counter), 1);
+    }
+
+    #[test]
+    fn sorted_vec_with_gap_buffer_storage() {
+        let config = BucketConfiguration::builder().max_bucket_capacity(64).build();
+        let mut sorted_vec: SortedVec<i32, GapBuffer<i32>> = SortedVec::with_storage(config);
+        for item in (0..300).map(|i| (i * 37) % 300) {
+            sorted_vec.insert(item);
+        }
+        for item in (0..300).step_by(3) {
+            sorted_vec.remove(&item);
+        }
+
+        assert_eq!(sorted_vec.size, 200);
+        assert!(sorted_vec.iter().copied().eq((0..300).filter(|item| item % 3 != 0)));
+        assert!(sorted_vec.find_index(&151).is_some());
+    }
+}
@@ -0,0 +1,119 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_core::Stream;
+
+use crate::sorted_vec::{BucketConfiguration, SortedVec};
+
+const DEFAULT_BATCH_SIZE: usize = 1024;
+
+impl<T: PartialOrd + Ord> SortedVec<T> {
+    /// Collects `stream` into a new container. Items are gathered into
+    /// batches that are merged with [`insert_many`](SortedVec::insert_many),
+    /// so the executor is never held up by one insert per item.
+    pub fn from_stream<St: Stream<Item = T> + Unpin>(stream: St, configuration: BucketConfiguration) -> FromStream<T, St> {
+        FromStream {
+            stream,
+            batch: Vec::new(),
+            batch_size: DEFAULT_BATCH_SIZE,
+            sorted_vec: Some(SortedVec::new(configuration)),
+        }
+    }
+}
+
+/// Future returned by [`SortedVec::from_stream`].
+pub struct FromStream<T: PartialOrd + Ord, St> {
+    stream: St,
+    batch: Vec<T>,
+    batch_size: usize,
+    sorted_vec: Option<SortedVec<T>>,
+}
+
+impl<T: PartialOrd + Ord, St> FromStream<T, St> {
+    /// How many items are buffered before they are merged in. Defaults to
+    /// `1024`.
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    fn flush(&mut self) {
+        if let Some(sorted_vec) = &mut self.sorted_vec {
+            sorted_vec.insert_many(self.batch.drain(..));
+        }
+    }
+}
+
+impl<T: PartialOrd + Ord, St: Stream<Item = T> + Unpin> Future for FromStream<T, St> {
+    type Output = SortedVec<T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        loop {
+            match Pin::new(&mut this.stream).poll_next(cx) {
+                Poll::Ready(Some(item)) => {
+                    this.batch.push(item);
+                    if this.batch.len() >= this.batch_size {
+                        this.flush();
+                    }
+                },
+                Poll::Ready(None) => {
+                    this.flush();
+                    let sorted_vec = this.sorted_vec.take().expect("FromStream polled after completion");
+                    return Poll::Ready(sorted_vec);
+                },
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+// Nothing is pinned structurally: the elements live on the heap.
+impl<T: PartialOrd + Ord, St: Unpin> Unpin for FromStream<T, St> {}
+
+#[cfg(test)]
+mod tests {
+    use std::task::Waker;
+
+    use super::*;
+
+    /// Yields the items, returning `Pending` before every other one.
+    struct Stuttering {
+        items: std::vec::IntoIter<i32>,
+        ready: bool,
+    }
+
+    impl Stream for Stuttering {
+        type Item = i32;
+
+        fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<i32>> {
+            self.ready = !self.ready;
+            if !self.ready {
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+
+            Poll::Ready(self.items.next())
+        }
+    }
+
+    fn block_on<F: Future + Unpin>(mut future: F) -> F::Output {
+        let mut cx = Context::from_waker(Waker::noop());
+        loop {
+            if let Poll::Ready(output) = Pin::new(&mut future).poll(&mut cx) {
+                return output;
+            }
+        }
+    }
+
+    #[test]
+    fn from_stream_merges_in_batches() {
+        let items: Vec<i32> = (0..50).map(|item| (item * 17) % 50).collect();
+        let stream = Stuttering { items: items.into_iter(), ready: false };
+        let configuration = BucketConfiguration::builder().max_bucket_capacity(8).build();
+
+        let sorted_vec = block_on(SortedVec::from_stream(stream, configuration).batch_size(7));
+        assert_eq!(sorted_vec.iter().copied().collect::<Vec<_>>(), (0..50).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,237 @@
+use std::cmp::Ordering;
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+
+use crate::bucket::Bucket;
+use crate::sorted_vec::{BucketConfiguration, DuplicatePolicy, SortedVec};
+
+/// An element plus its tombstone flag. Ordering only looks at the element.
+#[derive(Debug)]
+pub(crate) struct Entry<T> {
+    item: T,
+    dead: AtomicBool,
+}
+
+impl<T> Entry<T> {
+    fn new(item: T) -> Self {
+        Entry {
+            item,
+            dead: AtomicBool::new(false),
+        }
+    }
+
+    fn is_dead(&self) -> bool {
+        self.dead.load(AtomicOrdering::Relaxed)
+    }
+}
+
+impl<T: PartialEq> PartialEq for Entry<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.item == other.item
+    }
+}
+
+impl<T: Eq> Eq for Entry<T> {}
+
+impl<T: Ord> PartialOrd for Entry<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: Ord> Ord for Entry<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.item.cmp(&other.item)
+    }
+}
+
+/// A sorted container for delete-heavy workloads. `remove` only marks the
+/// element dead, which costs a lookup but never shifts a bucket; queries skip
+/// dead elements and [`compact`](Self::compact) reclaims them in bulk.
+#[derive(Debug)]
+pub struct TombstoneSortedVec<T: PartialOrd + Ord> {
+    entries: SortedVec<Entry<T>>,
+    tombstones: usize,
+    compaction_ratio: f64,
+}
+
+impl<T: PartialOrd + Ord> TombstoneSortedVec<T> {
+    pub fn new(configuration: BucketConfiguration) -> Self {
+        TombstoneSortedVec {
+            entries: SortedVec::new(configuration),
+            tombstones: 0,
+            compaction_ratio: 0.5,
+        }
+    }
+
+    /// Buckets whose share of dead elements exceeds `ratio` are compacted, and
+    /// once the share across the whole container exceeds it, `remove`
+    /// compacts automatically. Defaults to `0.5`.
+    pub fn with_compaction_ratio(mut self, ratio: f64) -> Self {
+        self.compaction_ratio = ratio;
+        self
+    }
+
+    pub fn insert(&mut self, item: T) {
+        let entry = Entry::new(item);
+        if self.entries.configuration.duplicate_policy() == DuplicatePolicy::Reject {
+            if let Some(position) = self.entries.find_index(&entry) {
+                let (bucket_idx, item_idx) = (position.bucket_idx(), position.item_idx());
+                // A dead equal element gives up its slot to the new one.
+                let data = &mut self.entries.buckets[bucket_idx].data;
+                if data.get(item_idx).is_some_and(Entry::is_dead) {
+                    data.remove(item_idx);
+                    data.insert(item_idx, entry);
+                    self.tombstones -= 1;
+                }
+                return;
+            }
+        }
+
+        self.entries.insert(entry);
+    }
+
+    /// Marks one element equal to `item` as dead. Returns whether a live one
+    /// was found.
+    pub fn remove(&mut self, item: &T) -> bool {
+        let Some(entry) = self.find_live(item) else {
+            return false;
+        };
+
+        entry.dead.store(true, AtomicOrdering::Relaxed);
+        self.tombstones += 1;
+        if self.tombstones as f64 > self.entries.size as f64 * self.compaction_ratio {
+            self.compact();
+        }
+
+        true
+    }
+
+    pub fn contains(&self, item: &T) -> bool {
+        self.find_live(item).is_some()
+    }
+
+    /// Walks the run of elements equal to `item` until a live one is found.
+    fn find_live(&self, item: &T) -> Option<&Entry<T>> {
+        let order = self.entries.configuration.order();
+        let mut position = Some(self.entries.partition_position(|entry| order.compare(&entry.item, item) == Ordering::Less));
+        while let Some(entry) = position.and_then(|position| self.entries.get_position(position)) {
+            if order.compare(&entry.item, item) != Ordering::Equal {
+                return None;
+            }
+            if !entry.is_dead() {
+                return Some(entry);
+            }
+            position = position.and_then(|position| self.entries.step_forward(position));
+        }
+
+        None
+    }
+
+    /// Live elements.
+    pub fn len(&self) -> usize {
+        self.entries.size - self.tombstones
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Dead elements still taking up space.
+    pub fn tombstones(&self) -> usize {
+        self.tombstones
+    }
+
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &T> {
+        self.entries.iter().filter(|entry| !entry.is_dead()).map(|entry| &entry.item)
+    }
+
+    pub fn first(&self) -> Option<&T> {
+        self.iter().next()
+    }
+
+    pub fn last(&self) -> Option<&T> {
+        self.iter().next_back()
+    }
+
+    /// Drops the dead elements of every bucket where they make up at least
+    /// the compaction ratio.
+    pub fn compact(&mut self) {
+        let ratio = self.compaction_ratio;
+        let mut reclaimed = 0;
+        for bucket in &mut self.entries.buckets {
+            let dead = bucket.data.iter().filter(|entry| entry.is_dead()).count();
+            if dead > 0 && dead as f64 >= bucket.len() as f64 * ratio {
+                bucket.data.retain(|entry| !entry.is_dead());
+                bucket.drop_bloom();
+                reclaimed += dead;
+            }
+        }
+
+        self.entries.buckets.retain(|bucket| bucket.len() > 0);
+        if self.entries.buckets.is_empty() {
+            self.entries.buckets.push(Bucket::default());
+        }
+        self.entries.refresh_indexes();
+        self.entries.size -= reclaimed;
+        self.entries.bump_generation();
+        self.tombstones -= reclaimed;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contents(sorted_vec: &TombstoneSortedVec<i32>) -> Vec<i32> {
+        sorted_vec.iter().copied().collect()
+    }
+
+    #[test]
+    fn tombstone_remove_marks_without_shifting() {
+        let configuration = BucketConfiguration::builder().max_bucket_capacity(4).build();
+        let mut sorted_vec = TombstoneSortedVec::new(configuration).with_compaction_ratio(0.9);
+        for item in 0..10 {
+            sorted_vec.insert(item);
+        }
+
+        assert!(sorted_vec.remove(&3));
+        assert!(!sorted_vec.remove(&3));
+        assert!(sorted_vec.remove(&0));
+        assert_eq!(sorted_vec.len(), 8);
+        assert_eq!(sorted_vec.tombstones(), 2);
+        assert_eq!(sorted_vec.entries.size, 10);
+        assert!(!sorted_vec.contains(&3));
+        assert_eq!(sorted_vec.first(), Some(&1));
+        assert_eq!(contents(&sorted_vec), vec![1, 2, 4, 5, 6, 7, 8, 9]);
+    }
+
+    #[test]
+    fn tombstone_compact_reclaims_dead_buckets() {
+        let configuration = BucketConfiguration::builder().max_bucket_capacity(4).build();
+        let mut sorted_vec = TombstoneSortedVec::new(configuration).with_compaction_ratio(0.5);
+        for item in 0..12 {
+            sorted_vec.insert(item);
+        }
+        for item in [0, 1, 2, 3, 11] {
+            sorted_vec.remove(&item);
+        }
+        sorted_vec.compact();
+
+        assert_eq!(sorted_vec.tombstones(), 1);
+        assert_eq!(sorted_vec.entries.size, 8);
+        assert_eq!(contents(&sorted_vec), vec![4, 5, 6, 7, 8, 9, 10]);
+    }
+
+    #[test]
+    fn tombstone_reinsert_revives_slot() {
+        let mut sorted_vec = TombstoneSortedVec::new(Default::default()).with_compaction_ratio(1.0);
+        sorted_vec.insert(1);
+        sorted_vec.insert(2);
+        sorted_vec.remove(&2);
+        sorted_vec.insert(2);
+        sorted_vec.insert(2);
+
+        assert_eq!(contents(&sorted_vec), vec![1, 2]);
+        assert_eq!(sorted_vec.len(), 2);
+    }
+}
@@ -0,0 +1,125 @@
+use std::cmp::Ordering;
+use std::fmt::{self, Debug, Display, Formatter};
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+
+/// Types with an IEEE 754 total order, such as the floating point types.
+pub trait TotalOrder: Copy {
+    fn total_cmp(&self, other: &Self) -> Ordering;
+
+    /// Bits that are equal exactly when `total_cmp` says `Equal`.
+    fn total_bits(&self) -> u64;
+}
+
+impl TotalOrder for f32 {
+    fn total_cmp(&self, other: &Self) -> Ordering {
+        f32::total_cmp(self, other)
+    }
+
+    fn total_bits(&self) -> u64 {
+        self.to_bits() as u64
+    }
+}
+
+impl TotalOrder for f64 {
+    fn total_cmp(&self, other: &Self) -> Ordering {
+        f64::total_cmp(self, other)
+    }
+
+    fn total_bits(&self) -> u64 {
+        self.to_bits()
+    }
+}
+
+/// Makes floating point values usable as elements by ordering them with
+/// `total_cmp`: `-NaN < -inf < ... < -0.0 < 0.0 < ... < inf < NaN`.
+#[derive(Default, Clone, Copy)]
+pub struct TotalOrd<T>(pub T);
+
+pub type OrderedF32 = TotalOrd<f32>;
+pub type OrderedF64 = TotalOrd<f64>;
+
+impl<T> TotalOrd<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T: TotalOrder> PartialEq for TotalOrd<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.total_cmp(&other.0) == Ordering::Equal
+    }
+}
+
+impl<T: TotalOrder> Eq for TotalOrd<T> {}
+
+impl<T: TotalOrder> PartialOrd for TotalOrd<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: TotalOrder> Ord for TotalOrd<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+impl<T: TotalOrder> Hash for TotalOrd<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.total_bits().hash(state);
+    }
+}
+
+impl<T> Deref for TotalOrd<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> From<T> for TotalOrd<T> {
+    fn from(value: T) -> Self {
+        TotalOrd(value)
+    }
+}
+
+impl<T: Debug> Debug for TotalOrd<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl<T: Display> Display for TotalOrd<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sorted_vec::{BucketConfiguration, SortedVec};
+
+    #[test]
+    fn total_ord_sorts_floats() {
+        let configuration = BucketConfiguration::builder().max_bucket_capacity(2).build();
+        let items = [2.5, -1.0, f64::NAN, 0.0, -0.0, f64::INFINITY, 2.5];
+        let sorted_vec = SortedVec::from_vec(items.into_iter().map(OrderedF64::from).collect(), configuration);
+
+        let sorted: Vec<f64> = sorted_vec.iter().map(|item| item.into_inner()).collect();
+        assert_eq!(sorted.len(), 6);
+        assert_eq!(&sorted[..5], &[-1.0, -0.0, 0.0, 2.5, f64::INFINITY]);
+        assert!(sorted[5].is_nan());
+        assert!(sorted[1].is_sign_negative());
+    }
+
+    #[test]
+    fn total_ord_finds_nan() {
+        let sorted_vec = SortedVec::from_vec(vec![TotalOrd(1.0f32), TotalOrd(f32::NAN)], Default::default());
+        assert!(sorted_vec.find_index(&TotalOrd(f32::NAN)).is_some());
+        assert_eq!(TotalOrd(0.0f64), TotalOrd(0.0));
+        assert_ne!(TotalOrd(0.0f64), TotalOrd(-0.0));
+    }
+}
@@ -0,0 +1,167 @@
+use std::panic::{self, AssertUnwindSafe};
+
+use crate::bucket::Bucket;
+use crate::sorted_vec::SortedVec;
+use crate::storage::BucketStorage;
+
+impl<T: PartialOrd + Ord, S: BucketStorage<T>> SortedVec<T, S> {
+    /// Runs `f`, and if it panics, e.g. because `T::cmp` or a user closure
+    /// did, repairs the bookkeeping before the panic continues: empty buckets
+    /// are dropped, the length is recounted and the indexes are rebuilt.
+    /// Elements the operation had taken out of their bucket at that moment
+    /// are dropped, but what stays is in order and usable. `f` must never
+    /// leave elements out of order inside the buckets.
+    pub(crate) fn repair_on_unwind<R>(&mut self, f: impl FnOnce(&mut Self) -> R) -> R {
+        match panic::catch_unwind(AssertUnwindSafe(|| f(self))) {
+            Ok(result) => result,
+            Err(payload) => {
+                self.repair();
+                panic::resume_unwind(payload)
+            },
+        }
+    }
+
+    /// Like [`repair_on_unwind`](Self::repair_on_unwind), for steps that may
+    /// leave elements out of order: a panic empties the container instead.
+    pub(crate) fn clear_on_unwind<R>(&mut self, f: impl FnOnce(&mut Self) -> R) -> R {
+        match panic::catch_unwind(AssertUnwindSafe(|| f(self))) {
+            Ok(result) => result,
+            Err(payload) => {
+                self.clear_after_panic();
+                panic::resume_unwind(payload)
+            },
+        }
+    }
+
+    /// Empties the container without calling into `T`, for when a panic left
+    /// its elements in an unknown order.
+    pub(crate) fn clear_after_panic(&mut self) {
+        self.buckets.clear();
+        self.repair();
+    }
+
+    fn repair(&mut self) {
+        self.buckets.retain(|bucket| bucket.len() > 0);
+        if self.buckets.is_empty() {
+            self.buckets.push(Bucket::default());
+        }
+        self.size = self.buckets.iter().map(|bucket| bucket.len()).sum();
+        self.bump_generation();
+        self.rebuild_indexes();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::cmp::Ordering;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    use crate::batch::BatchOperation;
+    use crate::sorted_vec::{BucketConfiguration, DuplicatePolicy, SortedVec};
+
+    thread_local! {
+        /// Comparisons left before `Fragile::cmp` panics.
+        static FUSE: Cell<usize> = const { Cell::new(usize::MAX) };
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    struct Fragile(u32);
+
+    impl PartialOrd for Fragile {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Fragile {
+        fn cmp(&self, other: &Self) -> Ordering {
+            FUSE.with(|fuse| {
+                if fuse.get() == 0 {
+                    panic!("comparator bug");
+                }
+                fuse.set(fuse.get() - 1);
+            });
+            self.0.cmp(&other.0)
+        }
+    }
+
+    fn container() -> SortedVec<Fragile> {
+        let configuration = BucketConfiguration::builder()
+            .max_bucket_capacity(4)
+            .duplicate_policy(DuplicatePolicy::Allow)
+            .build();
+        SortedVec::from_vec((0..40).map(|item| Fragile(item * 2)).collect(), configuration)
+    }
+
+    /// Runs `f` with the comparator panicking after each possible number of
+    /// comparisons, checking that every panic leaves a consistent container.
+    fn survives_panics(f: impl Fn(&mut SortedVec<Fragile>)) {
+        for fuse in 0..200 {
+            let mut sorted_vec = container();
+            FUSE.with(|cell| cell.set(fuse));
+            let result = catch_unwind(AssertUnwindSafe(|| f(&mut sorted_vec)));
+            FUSE.with(|cell| cell.set(usize::MAX));
+
+            assert_eq!(sorted_vec.check_integrity(), Ok(()), "after {fuse} comparisons");
+            assert_eq!(sorted_vec.iter().count(), sorted_vec.size);
+            if result.is_ok() {
+                return;
+            }
+            sorted_vec.insert(Fragile(1));
+            assert_eq!(sorted_vec.check_integrity(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn insert_and_remove_survive_a_panicking_comparator() {
+        survives_panics(|sorted_vec| sorted_vec.insert(Fragile(33)));
+        survives_panics(|sorted_vec| sorted_vec.remove(&Fragile(30)));
+    }
+
+    #[test]
+    fn update_survives_a_panicking_comparator() {
+        survives_panics(|sorted_vec| {
+            sorted_vec.update(&Fragile(10), |item| item.0 = 71);
+        });
+        survives_panics(|sorted_vec| sorted_vec.insert_with_merge(Fragile(12), |existing, item| existing.0 += item.0));
+    }
+
+    #[test]
+    fn bulk_operations_survive_a_panicking_comparator() {
+        survives_panics(|sorted_vec| {
+            sorted_vec.insert_many((0..10).map(|item| Fragile(item * 7)));
+        });
+        survives_panics(|sorted_vec| {
+            sorted_vec.apply_batch([BatchOperation::Insert(Fragile(5)), BatchOperation::Remove(Fragile(20))]);
+        });
+        survives_panics(|sorted_vec| {
+            sorted_vec.remove_all(&[Fragile(4), Fragile(40), Fragile(41)]);
+        });
+        survives_panics(|sorted_vec| sorted_vec.transform(|item| Fragile(100 - item.0)));
+    }
+
+    #[test]
+    fn user_closures_may_panic() {
+        let mut sorted_vec = container();
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            sorted_vec.retain_map(|item| {
+                assert!(item.0 < 50, "mapping bug");
+                Some(item)
+            })
+        }));
+
+        assert!(result.is_err());
+        assert_eq!(sorted_vec.check_integrity(), Ok(()));
+        assert_eq!(sorted_vec.size, 0, "mapped elements may be out of order");
+
+        let mut sorted_vec = container();
+        let mut removed = Vec::new();
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            sorted_vec.retain_into(|item| item.0 % 4 == 0 || panic!("predicate bug"), &mut removed)
+        }));
+        assert!(result.is_err());
+        assert_eq!(sorted_vec.check_integrity(), Ok(()));
+        assert!(sorted_vec.size < 40);
+    }
+}